@@ -0,0 +1,70 @@
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::get_test_storage;
+use starknet_api::block::{BlockHeader, BlockNumber};
+use starknet_api::core::{ChainId, ContractAddress, EntryPointSelector, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StateDiff;
+use starknet_api::transaction::Calldata;
+use starknet_api::{patricia_key, stark_felt};
+
+use crate::{estimate_fee, execute_call, ExecutionConfig, ExecutionError};
+
+fn get_test_execution_config() -> ExecutionConfig {
+    ExecutionConfig {
+        fee_contract_address: ContractAddress(patricia_key!("0x1001")),
+        invoke_tx_max_n_steps: 1_000_000,
+        validate_tx_max_n_steps: 1_000_000,
+        max_recursion_depth: 50,
+    }
+}
+
+#[test]
+fn call_undeployed_contract() {
+    let (reader, mut writer) = get_test_storage();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let contract_address = ContractAddress(patricia_key!("0x1"));
+    let err = execute_call(
+        &reader.begin_ro_txn().unwrap(),
+        &ChainId("SN_GOERLI".to_owned()),
+        BlockNumber(0),
+        &contract_address,
+        EntryPointSelector(stark_felt!("0x2")),
+        Calldata::default(),
+        &get_test_execution_config(),
+    )
+    .unwrap_err();
+    assert_matches!(
+        err,
+        ExecutionError::ContractNotFound { contract_address: address, .. }
+        if address == contract_address
+    );
+}
+
+#[test]
+fn estimate_fee_missing_block() {
+    let (reader, _writer) = get_test_storage();
+    let err = estimate_fee(
+        &reader.begin_ro_txn().unwrap(),
+        &ChainId("SN_GOERLI".to_owned()),
+        BlockNumber(0),
+        vec![],
+        &get_test_execution_config(),
+    )
+    .unwrap_err();
+    assert_matches!(
+        err,
+        ExecutionError::BlockNotFound { block_number } if block_number == BlockNumber(0)
+    );
+}
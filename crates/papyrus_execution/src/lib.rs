@@ -0,0 +1,376 @@
+#[cfg(test)]
+#[path = "execution_test.rs"]
+mod execution_test;
+
+mod state_reader;
+
+use std::collections::HashMap;
+
+use blockifier::abi::constants::INITIAL_GAS_COST;
+use blockifier::block_context::BlockContext;
+use blockifier::execution::contract_class::{
+    ContractClass as BlockifierContractClass, ContractClassV0, ContractClassV1,
+};
+use blockifier::execution::entry_point::{
+    CallEntryPoint, CallInfo, CallType as BlockifierCallType, EntryPointExecutionContext,
+};
+use blockifier::execution::errors::EntryPointExecutionError;
+use blockifier::state::cached_state::CachedState;
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::StateReader as BlockifierStateReader;
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::errors::TransactionExecutionError;
+use blockifier::transaction::objects::{
+    AccountTransactionContext, TransactionExecutionInfo as BlockifierTransactionExecutionInfo,
+};
+use blockifier::transaction::transactions::{
+    DeclareTransaction as BlockifierDeclareTransaction, ExecutableTransaction,
+};
+use cairo_lang_starknet::casm_contract_class::StarknetSierraCompilationError;
+use cairo_vm::types::errors::program_errors::ProgramError;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+use papyrus_storage::db::RO;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::trace::{
+    CallType, FunctionInvocation, OrderedEvent, OrderedL2ToL1Message, TransactionTrace,
+};
+use papyrus_storage::{StorageError, StorageTxn};
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockNumber, GasPrice};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, EntryPointSelector};
+use starknet_api::deprecated_contract_class::{
+    ContractClass as DeprecatedContractClass, EntryPointType,
+};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::{ContractClass, StateNumber};
+use starknet_api::transaction::{
+    Calldata, DeclareTransaction, DeclareTransactionV0V1, DeclareTransactionV2,
+    DeployAccountTransaction, Fee, InvokeTransaction, TransactionSignature,
+};
+
+pub use crate::state_reader::{compile_class, ExecutionStateReader};
+
+pub type ExecutionResult<T> = Result<T, ExecutionError>;
+
+/// The parameters of the executor that aren't part of the blocks.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ExecutionConfig {
+    /// The address of the contract of the token the fees are paid in.
+    pub fee_contract_address: ContractAddress,
+    pub invoke_tx_max_n_steps: u32,
+    pub validate_tx_max_n_steps: u32,
+    pub max_recursion_depth: usize,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExecutionError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("Block {block_number} wasn't found.")]
+    BlockNotFound { block_number: BlockNumber },
+    #[error("Contract {contract_address:?} wasn't found at {state_number:?}.")]
+    ContractNotFound { contract_address: ContractAddress, state_number: StateNumber },
+    #[error(transparent)]
+    ContractError(#[from] EntryPointExecutionError),
+    #[error("Transaction {transaction_index} failed: {execution_error}")]
+    TransactionExecutionError { transaction_index: usize, execution_error: String },
+    #[error("Transaction {transaction_index} was reverted: {revert_error}")]
+    TransactionReverted { transaction_index: usize, revert_error: String },
+    #[error(transparent)]
+    State(#[from] StateError),
+    #[error(transparent)]
+    Program(#[from] ProgramError),
+    #[error(transparent)]
+    SierraCompilation(#[from] StarknetSierraCompilationError),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+}
+
+/// A transaction to execute, with the class definition a declare transaction declares.
+#[derive(Debug, Clone)]
+pub enum ExecutableTransactionInput {
+    Invoke(InvokeTransaction),
+    DeclareV1(DeclareTransactionV0V1, DeprecatedContractClass),
+    DeclareV2(DeclareTransactionV2, ContractClass),
+    DeployAccount(DeployAccountTransaction),
+}
+
+impl ExecutableTransactionInput {
+    fn signature(&self) -> TransactionSignature {
+        match self {
+            ExecutableTransactionInput::Invoke(InvokeTransaction::V0(tx)) => tx.signature.clone(),
+            ExecutableTransactionInput::Invoke(InvokeTransaction::V1(tx)) => tx.signature.clone(),
+            ExecutableTransactionInput::DeclareV1(tx, _) => tx.signature.clone(),
+            ExecutableTransactionInput::DeclareV2(tx, _) => tx.signature.clone(),
+            ExecutableTransactionInput::DeployAccount(tx) => tx.signature.clone(),
+        }
+    }
+}
+
+/// The fee of a transaction, at the gas price of the block it was executed on top of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeEstimation {
+    pub gas_price: GasPrice,
+    pub overall_fee: Fee,
+}
+
+/// The trace and the fee of a simulated transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSimulationOutput {
+    pub trace: TransactionTrace,
+    pub fee_estimation: FeeEstimation,
+    /// The reason the transaction was reverted, if it was.
+    pub revert_error: Option<String>,
+}
+
+/// Calls a view function of a contract on top of the state after `block_number`, and returns its
+/// result.
+pub fn execute_call(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    block_number: BlockNumber,
+    contract_address: &ContractAddress,
+    entry_point_selector: EntryPointSelector,
+    calldata: Calldata,
+    config: &ExecutionConfig,
+) -> ExecutionResult<Vec<StarkFelt>> {
+    let state_number = StateNumber::right_after_block(block_number);
+    let mut cached_state = CachedState::new(ExecutionStateReader::new(txn, state_number)?);
+    if cached_state.get_class_hash_at(*contract_address)? == ClassHash::default() {
+        return Err(ExecutionError::ContractNotFound {
+            contract_address: *contract_address,
+            state_number,
+        });
+    }
+
+    let call_entry_point = CallEntryPoint {
+        class_hash: None,
+        code_address: Some(*contract_address),
+        entry_point_type: EntryPointType::External,
+        entry_point_selector,
+        calldata,
+        storage_address: *contract_address,
+        caller_address: ContractAddress::default(),
+        call_type: BlockifierCallType::Call,
+        initial_gas: INITIAL_GAS_COST,
+    };
+    let block_context = create_block_context(txn, chain_id, block_number, config)?;
+    let mut context = EntryPointExecutionContext::new(
+        block_context,
+        AccountTransactionContext::default(),
+        config.invoke_tx_max_n_steps as usize,
+    );
+    let call_info = call_entry_point.execute(
+        &mut cached_state,
+        &mut ExecutionResources::default(),
+        &mut context,
+    )?;
+    Ok(call_info.execution.retdata.0)
+}
+
+/// Estimates the fees of transactions that are executed one after the other on top of the state
+/// after `block_number`. Fails if any of the transactions fails or is reverted.
+pub fn estimate_fee(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    block_number: BlockNumber,
+    transactions: Vec<ExecutableTransactionInput>,
+    config: &ExecutionConfig,
+) -> ExecutionResult<Vec<FeeEstimation>> {
+    let (gas_price, outputs) =
+        execute_transactions(txn, chain_id, block_number, transactions, config, false, true)?;
+    outputs
+        .into_iter()
+        .enumerate()
+        .map(|(transaction_index, (execution_info, _signature))| {
+            if let Some(revert_error) = execution_info.revert_error {
+                return Err(ExecutionError::TransactionReverted {
+                    transaction_index,
+                    revert_error,
+                });
+            }
+            Ok(FeeEstimation { gas_price, overall_fee: execution_info.actual_fee })
+        })
+        .collect()
+}
+
+/// Executes transactions one after the other on top of the state after `block_number`, and
+/// returns their traces and fees. Unlike [`estimate_fee`], reverted transactions are returned with
+/// their revert reason.
+pub fn simulate_transactions(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    block_number: BlockNumber,
+    transactions: Vec<ExecutableTransactionInput>,
+    config: &ExecutionConfig,
+    charge_fee: bool,
+    validate: bool,
+) -> ExecutionResult<Vec<TransactionSimulationOutput>> {
+    let (gas_price, outputs) = execute_transactions(
+        txn,
+        chain_id,
+        block_number,
+        transactions,
+        config,
+        charge_fee,
+        validate,
+    )?;
+    Ok(outputs
+        .into_iter()
+        .map(|(execution_info, signature)| TransactionSimulationOutput {
+            fee_estimation: FeeEstimation { gas_price, overall_fee: execution_info.actual_fee },
+            revert_error: execution_info.revert_error,
+            trace: TransactionTrace {
+                validate_invocation: execution_info.validate_call_info.map(to_invocation),
+                function_invocation: execution_info.execute_call_info.map(to_invocation),
+                fee_transfer_invocation: execution_info.fee_transfer_call_info.map(to_invocation),
+                signature,
+            },
+        })
+        .collect())
+}
+
+// Executes the transactions over the same state, so that each transaction sees the changes of the
+// ones before it. Returns the gas price they were executed with.
+fn execute_transactions(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    block_number: BlockNumber,
+    transactions: Vec<ExecutableTransactionInput>,
+    config: &ExecutionConfig,
+    charge_fee: bool,
+    validate: bool,
+) -> ExecutionResult<(GasPrice, Vec<(BlockifierTransactionExecutionInfo, TransactionSignature)>)> {
+    let state_number = StateNumber::right_after_block(block_number);
+    let mut cached_state = CachedState::new(ExecutionStateReader::new(txn, state_number)?);
+    let block_context = create_block_context(txn, chain_id, block_number, config)?;
+
+    let outputs = transactions
+        .into_iter()
+        .enumerate()
+        .map(|(transaction_index, transaction)| {
+            let signature = transaction.signature();
+            let execution_info = to_account_transaction(transaction, transaction_index)?
+                .execute(&mut cached_state, &block_context, charge_fee, validate)
+                .map_err(|err: TransactionExecutionError| {
+                    ExecutionError::TransactionExecutionError {
+                        transaction_index,
+                        execution_error: err.to_string(),
+                    }
+                })?;
+            Ok((execution_info, signature))
+        })
+        .collect::<ExecutionResult<_>>()?;
+    Ok((GasPrice(block_context.gas_price), outputs))
+}
+
+// The executed calls and transactions belong to the block after `block_number`, so the context is
+// taken from the last block that was created.
+fn create_block_context(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    block_number: BlockNumber,
+    config: &ExecutionConfig,
+) -> ExecutionResult<BlockContext> {
+    let header = txn
+        .get_block_header(block_number)?
+        .ok_or(ExecutionError::BlockNotFound { block_number })?;
+    Ok(BlockContext {
+        chain_id: chain_id.clone(),
+        block_number: block_number.next(),
+        block_timestamp: header.timestamp,
+        sequencer_address: header.sequencer,
+        fee_token_address: config.fee_contract_address,
+        vm_resource_fee_cost: vm_resource_fee_costs(),
+        gas_price: header.gas_price.0,
+        invoke_tx_max_n_steps: config.invoke_tx_max_n_steps,
+        validate_max_n_steps: config.validate_tx_max_n_steps,
+        max_recursion_depth: config.max_recursion_depth,
+    })
+}
+
+// The fee, in gas, of a single use of each resource of the Cairo VM, as of Starknet 0.12.
+fn vm_resource_fee_costs() -> HashMap<String, f64> {
+    HashMap::from([
+        ("n_steps".to_owned(), 0.01),
+        ("output_builtin".to_owned(), 0.0),
+        ("pedersen_builtin".to_owned(), 0.32),
+        ("range_check_builtin".to_owned(), 0.16),
+        ("ecdsa_builtin".to_owned(), 20.48),
+        ("bitwise_builtin".to_owned(), 0.64),
+        ("ec_op_builtin".to_owned(), 10.24),
+        ("poseidon_builtin".to_owned(), 0.32),
+        ("keccak_builtin".to_owned(), 20.48),
+    ])
+}
+
+fn to_account_transaction(
+    transaction: ExecutableTransactionInput,
+    transaction_index: usize,
+) -> ExecutionResult<AccountTransaction> {
+    Ok(match transaction {
+        ExecutableTransactionInput::Invoke(tx) => AccountTransaction::Invoke(tx),
+        ExecutableTransactionInput::DeployAccount(tx) => AccountTransaction::DeployAccount(tx),
+        ExecutableTransactionInput::DeclareV1(tx, class) => {
+            let class = BlockifierContractClass::V0(ContractClassV0::try_from(class)?);
+            let tx = DeclareTransaction::V1(tx);
+            AccountTransaction::Declare(declare_transaction(tx, class, transaction_index)?)
+        }
+        ExecutableTransactionInput::DeclareV2(tx, class) => {
+            let casm = state_reader::compile_class(class)?;
+            let class = BlockifierContractClass::V1(ContractClassV1::try_from(casm)?);
+            let tx = DeclareTransaction::V2(tx);
+            AccountTransaction::Declare(declare_transaction(tx, class, transaction_index)?)
+        }
+    })
+}
+
+fn declare_transaction(
+    tx: DeclareTransaction,
+    class: BlockifierContractClass,
+    transaction_index: usize,
+) -> ExecutionResult<BlockifierDeclareTransaction> {
+    BlockifierDeclareTransaction::new(tx, class).map_err(|err| {
+        ExecutionError::TransactionExecutionError {
+            transaction_index,
+            execution_error: err.to_string(),
+        }
+    })
+}
+
+fn to_invocation(call_info: CallInfo) -> FunctionInvocation {
+    FunctionInvocation {
+        caller_address: call_info.call.caller_address,
+        contract_address: call_info.call.storage_address,
+        calldata: call_info.call.calldata,
+        call_type: Some(match call_info.call.call_type {
+            BlockifierCallType::Call => CallType::Call,
+            BlockifierCallType::Delegate => CallType::Delegate,
+        }),
+        class_hash: call_info.call.class_hash,
+        selector: Some(call_info.call.entry_point_selector),
+        entry_point_type: Some(call_info.call.entry_point_type),
+        result: call_info.execution.retdata.0,
+        internal_calls: call_info.inner_calls.into_iter().map(to_invocation).collect(),
+        events: call_info
+            .execution
+            .events
+            .into_iter()
+            .map(|event| OrderedEvent {
+                order: event.order,
+                keys: event.event.keys,
+                data: event.event.data,
+            })
+            .collect(),
+        messages: call_info
+            .execution
+            .l2_to_l1_messages
+            .into_iter()
+            .map(|message| OrderedL2ToL1Message {
+                order: message.order,
+                to_address: message.message.to_address,
+                payload: message.message.payload,
+            })
+            .collect(),
+    }
+}
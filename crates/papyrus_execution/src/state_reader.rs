@@ -0,0 +1,129 @@
+#[cfg(test)]
+#[path = "state_reader_test.rs"]
+mod state_reader_test;
+
+use blockifier::execution::contract_class::{ContractClass, ContractClassV0, ContractClassV1};
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{StateReader as BlockifierStateReader, StateResult};
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use papyrus_storage::compiled_class::CasmStorageReader;
+use papyrus_storage::db::RO;
+use papyrus_storage::state::{StateReader, StateStorageReader};
+use papyrus_storage::{StorageError, StorageResult, StorageTxn};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::{StateNumber, StorageKey};
+
+use crate::ExecutionResult;
+
+// The version of the Sierra classes in the storage, which doesn't keep it.
+const SIERRA_CONTRACT_CLASS_VERSION: &str = "0.1.0";
+
+/// Reads the state of the storage as it was at a given state number, for the executor.
+pub struct ExecutionStateReader<'env> {
+    txn: &'env StorageTxn<'env, RO>,
+    reader: StateReader<'env, RO>,
+    state_number: StateNumber,
+}
+
+impl<'env> ExecutionStateReader<'env> {
+    pub fn new(txn: &'env StorageTxn<'env, RO>, state_number: StateNumber) -> StorageResult<Self> {
+        Ok(Self { txn, reader: txn.get_state_reader()?, state_number })
+    }
+}
+
+impl BlockifierStateReader for ExecutionStateReader<'_> {
+    fn get_storage_at(
+        &mut self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.reader
+            .get_storage_at(self.state_number, &contract_address, &key)
+            .map_err(storage_error)
+    }
+
+    fn get_nonce_at(&mut self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        Ok(self
+            .reader
+            .get_nonce_at(self.state_number, &contract_address)
+            .map_err(storage_error)?
+            .unwrap_or_default())
+    }
+
+    // Undeployed contracts have the default class hash, as the executor expects.
+    fn get_class_hash_at(&mut self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        Ok(self
+            .reader
+            .get_class_hash_at(self.state_number, &contract_address)
+            .map_err(storage_error)?
+            .unwrap_or_default())
+    }
+
+    // The compiled classes that were synced from the central are the ones the sequencer executes,
+    // so the classes are compiled only if their compiled classes weren't synced.
+    fn get_compiled_contract_class(
+        &mut self,
+        class_hash: &ClassHash,
+    ) -> StateResult<ContractClass> {
+        let is_declared = self
+            .reader
+            .get_compiled_class_hash_at(self.state_number, class_hash)
+            .map_err(storage_error)?
+            .is_some();
+        if is_declared {
+            if let Some(casm) = self.txn.get_casm(class_hash).map_err(storage_error)? {
+                return Ok(ContractClass::V1(
+                    ContractClassV1::try_from(casm).map_err(StateError::ProgramError)?,
+                ));
+            }
+        }
+        if let Some(class) = self
+            .reader
+            .get_class_definition_at(self.state_number, class_hash)
+            .map_err(storage_error)?
+        {
+            let casm =
+                compile_class(class).map_err(|err| StateError::StateReadError(err.to_string()))?;
+            return Ok(ContractClass::V1(
+                ContractClassV1::try_from(casm).map_err(StateError::ProgramError)?,
+            ));
+        }
+        if let Some(class) = self
+            .reader
+            .get_deprecated_class_definition_at(self.state_number, class_hash)
+            .map_err(storage_error)?
+        {
+            return Ok(ContractClass::V0(
+                ContractClassV0::try_from(class).map_err(StateError::ProgramError)?,
+            ));
+        }
+        Err(StateError::UndeclaredClassHash(*class_hash))
+    }
+
+    // Undeclared classes have the default compiled class hash, as the executor expects.
+    fn get_compiled_class_hash(&mut self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        Ok(self
+            .reader
+            .get_compiled_class_hash_at(self.state_number, &class_hash)
+            .map_err(storage_error)?
+            .unwrap_or_default())
+    }
+}
+
+fn storage_error(err: StorageError) -> StateError {
+    StateError::StateReadError(err.to_string())
+}
+
+/// Compiles a Sierra class to CASM, for the classes whose compiled classes weren't synced and the
+/// classes of the simulated declare transactions.
+pub fn compile_class(
+    class: starknet_api::state::ContractClass,
+) -> ExecutionResult<CasmContractClass> {
+    let sierra_class = serde_json::from_value(serde_json::json!({
+        "sierra_program": class.sierra_program,
+        "contract_class_version": SIERRA_CONTRACT_CLASS_VERSION,
+        "entry_points_by_type": class.entry_point_by_type,
+    }))?;
+    Ok(CasmContractClass::from_contract_class(sierra_class, true)?)
+}
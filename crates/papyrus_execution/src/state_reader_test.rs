@@ -0,0 +1,93 @@
+use assert_matches::assert_matches;
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::StateReader as BlockifierStateReader;
+use indexmap::IndexMap;
+use papyrus_storage::compiled_class::CasmStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::{get_test_casm, get_test_storage};
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, StateDiff, StateNumber, StorageKey};
+use starknet_api::{patricia_key, stark_felt};
+
+use crate::ExecutionStateReader;
+
+#[test]
+fn read_state() {
+    let contract_address = ContractAddress(patricia_key!("0x1"));
+    let class_hash = ClassHash(stark_felt!("0x2"));
+    let storage_key = StorageKey(patricia_key!("0x3"));
+    let storage_value = stark_felt!("0x4");
+    let nonce = Nonce(stark_felt!("0x5"));
+    let declared_class_hash = ClassHash(stark_felt!("0x6"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x7"));
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(contract_address, class_hash)]),
+        storage_diffs: IndexMap::from([(
+            contract_address,
+            IndexMap::from([(storage_key, storage_value)]),
+        )]),
+        nonces: IndexMap::from([(contract_address, nonce)]),
+        declared_classes: IndexMap::from([(
+            declared_class_hash,
+            (compiled_class_hash, ContractClass::default()),
+        )]),
+        ..StateDiff::default()
+    };
+
+    let (reader, mut writer) = get_test_storage();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(BlockNumber(0), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .append_state_diff(BlockNumber(1), state_diff, IndexMap::new())
+        .unwrap()
+        .append_casms(BlockNumber(0), IndexMap::new())
+        .unwrap()
+        .append_casms(
+            BlockNumber(1),
+            IndexMap::from([(declared_class_hash, get_test_casm("1.0.0"))]),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+
+    // Before the state diff, the contract has the default values.
+    let mut state_reader =
+        ExecutionStateReader::new(&txn, StateNumber::right_after_block(BlockNumber(0))).unwrap();
+    assert_eq!(state_reader.get_class_hash_at(contract_address).unwrap(), ClassHash::default());
+    assert_eq!(state_reader.get_nonce_at(contract_address).unwrap(), Nonce::default());
+    assert_eq!(
+        state_reader.get_storage_at(contract_address, storage_key).unwrap(),
+        StarkFelt::default()
+    );
+    assert_eq!(
+        state_reader.get_compiled_class_hash(declared_class_hash).unwrap(),
+        CompiledClassHash::default()
+    );
+
+    let mut state_reader =
+        ExecutionStateReader::new(&txn, StateNumber::right_after_block(BlockNumber(1))).unwrap();
+    assert_eq!(state_reader.get_class_hash_at(contract_address).unwrap(), class_hash);
+    assert_eq!(state_reader.get_nonce_at(contract_address).unwrap(), nonce);
+    assert_eq!(state_reader.get_storage_at(contract_address, storage_key).unwrap(), storage_value);
+    assert_eq!(
+        state_reader.get_compiled_class_hash(declared_class_hash).unwrap(),
+        compiled_class_hash
+    );
+
+    // The declared class is executed by its synced compiled class, since its Sierra definition
+    // can't be compiled.
+    assert_matches!(
+        state_reader.get_compiled_contract_class(&declared_class_hash).unwrap(),
+        BlockifierContractClass::V1(_)
+    );
+
+    // The class of the contract wasn't declared.
+    let err = state_reader.get_compiled_contract_class(&class_hash).unwrap_err();
+    assert_matches!(err, StateError::UndeclaredClassHash(hash) if hash == class_hash);
+}
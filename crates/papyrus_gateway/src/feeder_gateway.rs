@@ -0,0 +1,440 @@
+#[cfg(test)]
+#[path = "feeder_gateway_test.rs"]
+mod feeder_gateway_test;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Query};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use papyrus_storage::body::events::{ThinTransactionOutput, TransactionExecutionStatus};
+use papyrus_storage::body::{BodyStorageReader, TransactionIndex};
+use papyrus_storage::checkpoint::CheckpointStorageReader;
+use papyrus_storage::compiled_class::CasmStorageReader;
+use papyrus_storage::db::RO;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::{StorageError, StorageReader, StorageTxn};
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+use starknet_api::state::StateNumber;
+use starknet_api::transaction::{
+    EthAddress, Event, L1HandlerTransaction, L1ToL2Payload, Transaction, TransactionOffsetInBlock,
+};
+use starknet_client::{
+    Block, BlockStatus, BuiltinInstanceCounter, ContractClass, DeprecatedContractClass,
+    ExecutionResources, L1ToL2Message, L1ToL2Nonce, ResourcePrice, StarknetError,
+    StarknetErrorCode, StateDiff, StateUpdate, TransactionExecutionStatus as ClientExecutionStatus,
+    TransactionReceipt,
+};
+use tracing::{debug, instrument};
+use web3::types::H160;
+
+use crate::limits::{LimitError, RequestLimiter};
+use crate::API_KEY_HEADER;
+
+const BLOCK_NUMBER_QUERY: &str = "blockNumber";
+const CLASS_HASH_QUERY: &str = "classHash";
+const LATEST_BLOCK: &str = "latest";
+
+#[derive(thiserror::Error, Debug)]
+enum FeederGatewayError {
+    #[error(transparent)]
+    Starknet(#[from] StarknetError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("The compiled class of {0:?} wasn't synced.")]
+    MissingCompiledClass(ClassHash),
+    #[error("The state root before block {0} is unknown, as its parent isn't stored.")]
+    MissingParentHeader(BlockNumber),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+}
+
+type FeederGatewayResult<T> = Result<T, FeederGatewayError>;
+
+fn starknet_error(code: StarknetErrorCode, message: String) -> FeederGatewayError {
+    FeederGatewayError::Starknet(StarknetError { code, message })
+}
+
+fn block_not_found(block_number: BlockNumber) -> FeederGatewayError {
+    starknet_error(StarknetErrorCode::BlockNotFound, format!("Block {block_number} not found."))
+}
+
+fn undeclared_class(class_hash: ClassHash) -> FeederGatewayError {
+    starknet_error(
+        StarknetErrorCode::UndeclaredClass,
+        format!("Class with hash {class_hash:?} is not declared."),
+    )
+}
+
+// The endpoints of the feeder gateway that are served.
+#[derive(Clone, Copy, Debug)]
+enum Endpoint {
+    GetBlock,
+    GetStateUpdate,
+    GetClassByHash,
+    GetCompiledClassByClassHash,
+}
+
+impl Endpoint {
+    const ALL: [Endpoint; 4] = [
+        Endpoint::GetBlock,
+        Endpoint::GetStateUpdate,
+        Endpoint::GetClassByHash,
+        Endpoint::GetCompiledClassByClassHash,
+    ];
+
+    fn path(&self) -> &'static str {
+        match self {
+            Endpoint::GetBlock => "/feeder_gateway/get_block",
+            Endpoint::GetStateUpdate => "/feeder_gateway/get_state_update",
+            Endpoint::GetClassByHash => "/feeder_gateway/get_class_by_hash",
+            Endpoint::GetCompiledClassByClassHash => {
+                "/feeder_gateway/get_compiled_class_by_class_hash"
+            }
+        }
+    }
+}
+
+/// Routes of the endpoints of the feeder gateway that other nodes sync from, served from the
+/// storage in the JSON shapes of `starknet_client`. The requests count against the same limits as
+/// the JSON-RPC requests.
+pub(crate) fn routes(storage_reader: StorageReader, limiter: Arc<RequestLimiter>) -> Router {
+    let mut router = Router::new();
+    for endpoint in Endpoint::ALL {
+        let storage_reader = storage_reader.clone();
+        let limiter = limiter.clone();
+        let handler = move |ConnectInfo(client_address): ConnectInfo<SocketAddr>,
+                            headers: HeaderMap,
+                            Query(query): Query<HashMap<String, String>>| {
+            handle_request(
+                endpoint,
+                storage_reader.clone(),
+                limiter.clone(),
+                client_address,
+                headers,
+                query,
+            )
+        };
+        router = router.route(endpoint.path(), get(handler));
+    }
+    router
+}
+
+async fn handle_request(
+    endpoint: Endpoint,
+    storage_reader: StorageReader,
+    limiter: Arc<RequestLimiter>,
+    client_address: SocketAddr,
+    headers: HeaderMap,
+    query: HashMap<String, String>,
+) -> Response {
+    // The slot is released once the response is ready.
+    let _slot = match limiter.acquire_slot() {
+        Ok(slot) => slot,
+        Err(err) => return limit_error_response(err),
+    };
+    let api_key = headers.get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+    if let Err(err) = limiter.check_rate(&limiter.client(client_address.ip(), api_key)) {
+        return limit_error_response(err);
+    }
+
+    // Reading a block or a class may take a while, so it doesn't block the runtime.
+    let result =
+        tokio::task::spawn_blocking(move || serve_endpoint(endpoint, &storage_reader, &query))
+            .await;
+    match result {
+        Ok(Ok(body)) => {
+            (StatusCode::OK, [(CONTENT_TYPE, "application/json")], body).into_response()
+        }
+        // The feeder gateway reports its errors with this status, and the clients parse them.
+        Ok(Err(FeederGatewayError::Starknet(err))) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [(CONTENT_TYPE, "application/json")],
+            serde_json::to_string(&err).unwrap_or_default(),
+        )
+            .into_response(),
+        Ok(Err(err)) => {
+            debug!("Failed to serve {}: {err}.", endpoint.path());
+            (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    }
+}
+
+// The feeder gateway throttles with these statuses, which the clients retry.
+fn limit_error_response(err: LimitError) -> Response {
+    let status = match err {
+        LimitError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, err.to_string()).into_response()
+}
+
+#[instrument(skip(storage_reader), level = "debug", err)]
+fn serve_endpoint(
+    endpoint: Endpoint,
+    storage_reader: &StorageReader,
+    query: &HashMap<String, String>,
+) -> FeederGatewayResult<String> {
+    let txn = storage_reader.begin_ro_txn()?;
+    match endpoint {
+        Endpoint::GetBlock => {
+            let block_number = parse_block_number(&txn, query)?;
+            Ok(serde_json::to_string(&get_block(&txn, block_number)?)?)
+        }
+        Endpoint::GetStateUpdate => {
+            let block_number = parse_block_number(&txn, query)?;
+            Ok(serde_json::to_string(&get_state_update(&txn, block_number)?)?)
+        }
+        Endpoint::GetClassByHash => {
+            let class_hash = parse_class_hash(query)?;
+            get_class_by_hash(&txn, class_hash)
+        }
+        Endpoint::GetCompiledClassByClassHash => {
+            let class_hash = parse_class_hash(query)?;
+            get_compiled_class_by_class_hash(&txn, class_hash)
+        }
+    }
+}
+
+// Blocks are served once their body, state diff and classes are all stored, so that a node that
+// syncs from this one can sync them completely.
+fn get_latest_block_number(txn: &StorageTxn<'_, RO>) -> FeederGatewayResult<Option<BlockNumber>> {
+    let marker = [txn.get_body_marker()?, txn.get_state_marker()?, txn.get_class_marker()?]
+        .into_iter()
+        .min()
+        .expect("There should be markers.");
+    let checkpoint = txn.get_checkpoint()?;
+    Ok(marker.prev().filter(|block_number| Some(*block_number) >= checkpoint))
+}
+
+// The block of the query, or the latest block if the query doesn't have one.
+fn parse_block_number(
+    txn: &StorageTxn<'_, RO>,
+    query: &HashMap<String, String>,
+) -> FeederGatewayResult<BlockNumber> {
+    let latest_block_number = get_latest_block_number(txn)?;
+    match query.get(BLOCK_NUMBER_QUERY).map(String::as_str) {
+        None | Some(LATEST_BLOCK) => latest_block_number.ok_or_else(|| {
+            starknet_error(StarknetErrorCode::BlockNotFound, "There are no blocks.".to_owned())
+        }),
+        Some(block_number) => {
+            let block_number = block_number.parse().map(BlockNumber).map_err(|_| {
+                starknet_error(
+                    StarknetErrorCode::MalformedRequest,
+                    format!("Invalid block number: {block_number}."),
+                )
+            })?;
+            if latest_block_number.map_or(true, |latest| block_number > latest) {
+                return Err(block_not_found(block_number));
+            }
+            Ok(block_number)
+        }
+    }
+}
+
+fn parse_class_hash(query: &HashMap<String, String>) -> FeederGatewayResult<ClassHash> {
+    let class_hash = query.get(CLASS_HASH_QUERY).ok_or_else(|| {
+        starknet_error(StarknetErrorCode::MalformedRequest, "Missing class hash.".to_owned())
+    })?;
+    serde_json::from_value(serde_json::Value::String(class_hash.clone())).map_err(|_| {
+        starknet_error(
+            StarknetErrorCode::MalformedRequest,
+            format!("Invalid class hash: {class_hash}."),
+        )
+    })
+}
+
+fn get_block(txn: &StorageTxn<'_, RO>, block_number: BlockNumber) -> FeederGatewayResult<Block> {
+    let header =
+        txn.get_block_header(block_number)?.ok_or_else(|| block_not_found(block_number))?;
+    let transactions =
+        txn.get_block_transactions(block_number)?.ok_or_else(|| block_not_found(block_number))?;
+    let transaction_outputs = txn
+        .get_block_transaction_outputs(block_number)?
+        .ok_or_else(|| block_not_found(block_number))?;
+
+    let mut transaction_receipts = Vec::with_capacity(transactions.len());
+    for (offset, (transaction, output)) in transactions.iter().zip(transaction_outputs).enumerate()
+    {
+        let transaction_offset = TransactionOffsetInBlock(offset);
+        let events = txn
+            .get_transaction_events(TransactionIndex(block_number, transaction_offset))?
+            .unwrap_or_default();
+        transaction_receipts.push(get_receipt(transaction_offset, transaction, output, events));
+    }
+
+    Ok(Block {
+        block_hash: header.block_hash,
+        block_number,
+        gas_price: header.gas_price,
+        // Newer versions of the gateway serve the price in l1_gas_price and older ones in
+        // gas_price, so it is served in both for the clients of either.
+        l1_gas_price: Some(ResourcePrice { price_in_wei: header.gas_price }),
+        parent_block_hash: header.parent_hash,
+        sequencer_address: header.sequencer,
+        state_root: header.state_root.into(),
+        // Only blocks that were accepted on L2 are synced.
+        status: BlockStatus::AcceptedOnL2,
+        timestamp: header.timestamp,
+        transactions: transactions.into_iter().map(Into::into).collect(),
+        transaction_receipts,
+        starknet_version: txn.get_starknet_version(block_number)?.map(|version| version.0),
+    })
+}
+
+fn get_receipt(
+    transaction_index: TransactionOffsetInBlock,
+    transaction: &Transaction,
+    output: ThinTransactionOutput,
+    events: Vec<Event>,
+) -> TransactionReceipt {
+    let (actual_fee, messages_sent, execution_info) = match output {
+        ThinTransactionOutput::Declare(output) => {
+            (output.actual_fee, output.messages_sent, output.execution_info)
+        }
+        ThinTransactionOutput::Deploy(output) => {
+            (output.actual_fee, output.messages_sent, output.execution_info)
+        }
+        ThinTransactionOutput::DeployAccount(output) => {
+            (output.actual_fee, output.messages_sent, output.execution_info)
+        }
+        ThinTransactionOutput::Invoke(output) => {
+            (output.actual_fee, output.messages_sent, output.execution_info)
+        }
+        ThinTransactionOutput::L1Handler(output) => {
+            (output.actual_fee, output.messages_sent, output.execution_info)
+        }
+    };
+    let (execution_status, revert_error, execution_resources) = match execution_info {
+        Some(info) => {
+            let (execution_status, revert_error) = match info.execution_status {
+                TransactionExecutionStatus::Succeeded => (ClientExecutionStatus::Succeeded, None),
+                TransactionExecutionStatus::Reverted(error) => {
+                    (ClientExecutionStatus::Reverted, Some(error))
+                }
+            };
+            let resources = info.execution_resources;
+            let builtin_instance_counter = if resources.builtin_instance_counter.is_empty() {
+                BuiltinInstanceCounter::default()
+            } else {
+                BuiltinInstanceCounter::NonEmpty(
+                    resources.builtin_instance_counter.into_iter().collect(),
+                )
+            };
+            let execution_resources = ExecutionResources {
+                n_steps: resources.n_steps,
+                builtin_instance_counter,
+                n_memory_holes: resources.n_memory_holes,
+            };
+            (Some(execution_status), revert_error, Some(execution_resources))
+        }
+        // The execution info of transactions that were synced without it is left out rather than
+        // made up.
+        None => (None, None, None),
+    };
+
+    // Only L1 handlers consume a message, the receipts of other transactions have an empty one.
+    let l1_to_l2_consumed_message = match transaction {
+        Transaction::L1Handler(transaction) => consumed_message(transaction),
+        _ => L1ToL2Message::default(),
+    };
+
+    TransactionReceipt {
+        transaction_index,
+        transaction_hash: transaction.transaction_hash(),
+        l1_to_l2_consumed_message,
+        l2_to_l1_messages: messages_sent.into_iter().map(Into::into).collect(),
+        events,
+        execution_resources,
+        actual_fee,
+        execution_status,
+        revert_error,
+    }
+}
+
+// The message an L1 handler consumes isn't stored, but its calldata is the L1 sender followed by
+// the payload of the message.
+fn consumed_message(transaction: &L1HandlerTransaction) -> L1ToL2Message {
+    let (from_address, payload) = match transaction.calldata.0.split_first() {
+        Some((from_address, payload)) => {
+            (EthAddress(H160::from_slice(&from_address.bytes()[12..])), payload.to_vec())
+        }
+        None => (EthAddress::default(), vec![]),
+    };
+    L1ToL2Message {
+        from_address,
+        to_address: transaction.contract_address,
+        selector: transaction.entry_point_selector,
+        payload: L1ToL2Payload(payload),
+        nonce: L1ToL2Nonce(transaction.nonce.0),
+    }
+}
+
+fn get_state_update(
+    txn: &StorageTxn<'_, RO>,
+    block_number: BlockNumber,
+) -> FeederGatewayResult<StateUpdate> {
+    let header =
+        txn.get_block_header(block_number)?.ok_or_else(|| block_not_found(block_number))?;
+    let state_diff =
+        txn.get_state_diff(block_number)?.ok_or_else(|| block_not_found(block_number))?;
+    // The root before the genesis block is zero. The parent of the first block after a checkpoint
+    // isn't stored, so the root before it is unknown.
+    let old_root = match block_number.prev() {
+        Some(parent) => {
+            txn.get_block_header(parent)?
+                .ok_or(FeederGatewayError::MissingParentHeader(block_number))?
+                .state_root
+        }
+        None => Default::default(),
+    };
+    Ok(StateUpdate {
+        block_hash: header.block_hash,
+        new_root: header.state_root.into(),
+        old_root: old_root.into(),
+        state_diff: StateDiff::from(state_diff),
+    })
+}
+
+// Classes are served if they were declared up to the latest block.
+fn latest_state_number(txn: &StorageTxn<'_, RO>) -> FeederGatewayResult<Option<StateNumber>> {
+    Ok(get_latest_block_number(txn)?.map(StateNumber::right_after_block))
+}
+
+fn get_class_by_hash(
+    txn: &StorageTxn<'_, RO>,
+    class_hash: ClassHash,
+) -> FeederGatewayResult<String> {
+    let state_number = latest_state_number(txn)?.ok_or_else(|| undeclared_class(class_hash))?;
+    let state_reader = txn.get_state_reader()?;
+    if let Some(class) = state_reader.get_class_definition_at(state_number, &class_hash)? {
+        return Ok(serde_json::to_string(&ContractClass::from(class))?);
+    }
+    let class = state_reader
+        .get_deprecated_class_definition_at(state_number, &class_hash)?
+        .ok_or_else(|| undeclared_class(class_hash))?;
+    Ok(serde_json::to_string(&DeprecatedContractClass::from(class))?)
+}
+
+// Only the compiled classes that were synced from the central are served, since the compiler of
+// the node may compile a class differently than the sequencer did.
+fn get_compiled_class_by_class_hash(
+    txn: &StorageTxn<'_, RO>,
+    class_hash: ClassHash,
+) -> FeederGatewayResult<String> {
+    let state_number = latest_state_number(txn)?.ok_or_else(|| undeclared_class(class_hash))?;
+    txn.get_state_reader()?
+        .get_compiled_class_hash_at(state_number, &class_hash)?
+        .ok_or_else(|| undeclared_class(class_hash))?;
+    let casm =
+        txn.get_casm(&class_hash)?.ok_or(FeederGatewayError::MissingCompiledClass(class_hash))?;
+    Ok(serde_json::to_string(&casm)?)
+}
@@ -0,0 +1,177 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use hyper::StatusCode;
+use indexmap::IndexMap;
+use papyrus_storage::body::{BlockExecutionInfo, BodyStorageWriter, StarknetVersion};
+use papyrus_storage::checkpoint::CheckpointStorageWriter;
+use papyrus_storage::compiled_class::CasmStorageWriter;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::{get_test_casm, get_test_storage};
+use starknet_api::block::{BlockHeader, BlockNumber, GasPrice};
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use starknet_api::transaction::{
+    Calldata, EthAddress, L1HandlerTransaction, L1HandlerTransactionOutput, L1ToL2Payload,
+    TransactionOutput,
+};
+use starknet_client::{
+    ContractClass, DeprecatedContractClass, GenericContractClass, L1ToL2Message, L1ToL2Nonce,
+    ResourcePrice, RetryConfig, StarknetClient, StarknetClientTrait, Transaction,
+};
+use test_utils::{get_rng, get_test_body, get_test_state_diff, GetTestInstance};
+use web3::types::H160;
+
+use crate::test_utils::get_test_gateway_config;
+use crate::{run_server, GatewayConfig, GatewayMonitor};
+
+#[tokio::test]
+async fn serve_feeder_gateway_to_starknet_client() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let header = BlockHeader { gas_price: GasPrice(0x3b9aca00), ..BlockHeader::default() };
+    let mut body = get_test_body(None, 2, Some(1), None, None);
+    // An L1 handler that consumes a message from 0x10 with the payload [0x2].
+    let l1_handler = L1HandlerTransaction {
+        calldata: Calldata(Arc::new(vec![stark_felt!("0x10"), stark_felt!("0x2")])),
+        ..L1HandlerTransaction::get_test_instance(&mut get_rng(None))
+    };
+    body.transactions[1] = starknet_api::transaction::Transaction::L1Handler(l1_handler.clone());
+    body.transaction_outputs[1] =
+        TransactionOutput::L1Handler(L1HandlerTransactionOutput::default());
+    let diff = get_test_state_diff();
+    let casms = diff
+        .declared_classes
+        .keys()
+        .map(|class_hash| (*class_hash, get_test_casm("1.0.0")))
+        .collect();
+    // The Starknet version of a block that has an L1 gas price.
+    let execution_info = BlockExecutionInfo {
+        starknet_version: Some(StarknetVersion("0.12.1".to_owned())),
+        transaction_execution_infos: None,
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_body_with_execution_info(header.block_number, body.clone(), execution_info)
+        .unwrap()
+        .append_state_diff(header.block_number, diff.clone(), IndexMap::new())
+        .unwrap()
+        .append_casms(header.block_number, casms)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let config = GatewayConfig { serve_feeder_gateway: true, ..get_test_gateway_config() };
+    let (addr, _handle) =
+        run_server(&config, storage_reader, GatewayMonitor::default()).await.unwrap();
+    let retry_config =
+        RetryConfig { retry_base_millis: 1, retry_max_delay_millis: 1, max_retries: 0 };
+    let client =
+        StarknetClient::new(&format!("http://{addr:?}"), None, "test", retry_config).unwrap();
+
+    assert_eq!(client.block_number().await.unwrap(), Some(header.block_number));
+    let block = client.block(header.block_number).await.unwrap().unwrap();
+    assert_eq!(block.block_hash, header.block_hash);
+    assert_eq!(block.starknet_version, Some("0.12.1".to_owned()));
+    assert_eq!(block.l1_gas_price, Some(ResourcePrice { price_in_wei: header.gas_price }));
+    assert_eq!(
+        block.transactions,
+        body.transactions.into_iter().map(Transaction::from).collect::<Vec<_>>()
+    );
+    assert_eq!(block.transaction_receipts.len(), 2);
+    for (receipt, output) in block.transaction_receipts.iter().zip(body.transaction_outputs) {
+        assert_eq!(receipt.events, output.events());
+        // The block was stored without the execution info of its transactions.
+        assert_eq!(receipt.execution_status, None);
+        assert_eq!(receipt.execution_resources, None);
+    }
+    let expected_message = L1ToL2Message {
+        from_address: EthAddress(H160::from_low_u64_be(0x10)),
+        to_address: l1_handler.contract_address,
+        selector: l1_handler.entry_point_selector,
+        payload: L1ToL2Payload(vec![stark_felt!("0x2")]),
+        nonce: L1ToL2Nonce(l1_handler.nonce.0),
+    };
+    assert_eq!(block.transaction_receipts[1].l1_to_l2_consumed_message, expected_message);
+    assert!(client.block(BlockNumber(1)).await.unwrap().is_none());
+
+    let state_update = client.state_update(header.block_number).await.unwrap().unwrap();
+    assert_eq!(state_update.block_hash, header.block_hash);
+    assert_eq!(state_update.state_diff.deployed_contracts.len(), diff.deployed_contracts.len());
+    assert!(client.state_update(BlockNumber(1)).await.unwrap().is_none());
+
+    let (class_hash, (_, class)) = diff.declared_classes.first().unwrap();
+    let served_class = client.class_by_hash(*class_hash).await.unwrap().unwrap();
+    assert_matches!(
+        served_class,
+        GenericContractClass::Cairo1ContractClass(served_class)
+            if served_class == ContractClass::from(class.clone())
+    );
+    let served_casm = client.compiled_class_by_hash(*class_hash).await.unwrap().unwrap();
+    assert_eq!(served_casm, get_test_casm("1.0.0"));
+    let (class_hash, class) = diff.deprecated_declared_classes.first().unwrap();
+    let served_class = client.class_by_hash(*class_hash).await.unwrap().unwrap();
+    assert_matches!(
+        served_class,
+        GenericContractClass::Cairo0ContractClass(served_class)
+            if served_class == DeprecatedContractClass::from(class.clone())
+    );
+
+    let unknown_class_hash = ClassHash(stark_felt!("0x1234"));
+    assert!(client.class_by_hash(unknown_class_hash).await.unwrap().is_none());
+    assert!(client.compiled_class_by_hash(unknown_class_hash).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn serve_feeder_gateway_only_if_configured() {
+    let (storage_reader, _) = get_test_storage();
+    let (addr, _handle) =
+        run_server(&get_test_gateway_config(), storage_reader, GatewayMonitor::default())
+            .await
+            .unwrap();
+    assert_eq!(get_status(addr, "/feeder_gateway/get_block").await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn malformed_feeder_gateway_request() {
+    let (storage_reader, _) = get_test_storage();
+    let config = GatewayConfig { serve_feeder_gateway: true, ..get_test_gateway_config() };
+    let (addr, _handle) =
+        run_server(&config, storage_reader, GatewayMonitor::default()).await.unwrap();
+    // The feeder gateway reports its errors, including malformed requests, with this status.
+    let status = get_status(addr, "/feeder_gateway/get_block?blockNumber=abc").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn state_update_without_parent_header() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let block_number = BlockNumber(1);
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .set_checkpoint(block_number, None)
+        .unwrap()
+        .append_header(block_number, &BlockHeader { block_number, ..BlockHeader::default() })
+        .unwrap()
+        .append_state_diff(block_number, get_test_state_diff(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let config = GatewayConfig { serve_feeder_gateway: true, ..get_test_gateway_config() };
+    let (addr, _handle) =
+        run_server(&config, storage_reader, GatewayMonitor::default()).await.unwrap();
+    // The root before the block isn't made up.
+    let status = get_status(addr, "/feeder_gateway/get_state_update?blockNumber=1").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+async fn get_status(addr: SocketAddr, path: &str) -> StatusCode {
+    let uri = format!("http://{addr:?}{path}").parse().unwrap();
+    hyper::Client::new().get(uri).await.unwrap().status()
+}
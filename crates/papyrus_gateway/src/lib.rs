@@ -1,48 +1,48 @@
 mod api;
-mod block;
-mod deprecated_contract_class;
+mod feeder_gateway;
 #[cfg(test)]
 mod gateway_test;
-mod state;
+mod limits;
+mod papyrus;
+mod reader;
 #[cfg(test)]
 mod test_utils;
-mod transaction;
+mod v0_3;
+mod v0_4;
 
 use std::fmt::Display;
 use std::net::SocketAddr;
-
-use api::GatewayContractClass;
-use jsonrpsee::core::{async_trait, Error};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::rejection::StringRejection;
+use axum::extract::{ConnectInfo, DefaultBodyLimit};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use futures_util::{future, stream, StreamExt};
+use jsonrpsee::core::server::rpc_module::Methods;
+use jsonrpsee::core::Error;
 use jsonrpsee::http_server::types::error::CallError;
-use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
-use jsonrpsee::types::error::ErrorCode::InternalError;
+use jsonrpsee::http_server::RpcModule;
+use jsonrpsee::types::error::ErrorCode::{self, InternalError};
 use jsonrpsee::types::error::{ErrorObject, INTERNAL_ERROR_MSG};
-use papyrus_storage::body::events::{EventIndex, EventsReader};
-use papyrus_storage::body::{BodyStorageReader, TransactionIndex};
-use papyrus_storage::db::TransactionKind;
-use papyrus_storage::header::HeaderStorageReader;
-use papyrus_storage::state::StateStorageReader;
-use papyrus_storage::{StorageReader, StorageTxn};
+use papyrus_execution::{ExecutionConfig, ExecutionError};
+use papyrus_storage::StorageReader;
 use serde::{Deserialize, Serialize};
-use starknet_api::block::{BlockNumber, BlockStatus};
-use starknet_api::core::{ChainId, ClassHash, ContractAddress, GlobalRoot, Nonce};
-use starknet_api::hash::{StarkFelt, StarkHash, GENESIS_HASH};
-use starknet_api::state::{StateNumber, StorageKey};
-use starknet_api::transaction::{
-    EventIndexInTransactionOutput, TransactionHash, TransactionOffsetInBlock,
-};
+use starknet_api::core::ChainId;
+use starknet_api::hash::StarkFelt;
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, instrument};
 
-use crate::api::{
-    BlockHashAndNumber, BlockHashOrNumber, BlockId, ContinuationToken, EventFilter, EventsChunk,
-    JsonRpcError, JsonRpcServer, Tag,
-};
-use crate::block::{Block, BlockHeader};
-use crate::state::StateUpdate;
-use crate::transaction::{
-    Event, Transaction, TransactionOutput, TransactionReceipt, TransactionReceiptWithStatus,
-    TransactionStatus, TransactionWithType, Transactions,
-};
+use crate::api::JsonRpcError;
+use crate::limits::{Client, LimitError, RequestLimiter};
+pub use crate::limits::{GatewayMonitor, LimitsConfig, LimitsStats, API_KEY_HEADER};
+use crate::papyrus::JsonRpcServerPapyrusImpl;
+use crate::v0_3::JsonRpcServerV0_3Impl;
+use crate::v0_4::JsonRpcServerV0_4Impl;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GatewayConfig {
@@ -50,14 +50,50 @@ pub struct GatewayConfig {
     pub server_address: String,
     pub max_events_chunk_size: usize,
     pub max_events_keys: usize,
+    /// The maximal number of events a getEvents request examines. When it is reached, the request
+    /// returns the events found so far with a continuation token.
+    pub max_events_scanned: usize,
+    /// The maximal time in milliseconds a getEvents request examines events, handled the same.
+    pub max_events_scan_millis: u64,
+    pub execution_config: ExecutionConfig,
+    /// The version of the API that is served under `/`.
+    pub default_version: ApiVersion,
+    pub limits: LimitsConfig,
+    /// Whether to serve the endpoints of the feeder gateway that nodes sync from, under
+    /// `/feeder_gateway/`.
+    pub serve_feeder_gateway: bool,
+    /// The secret the continuation tokens of getEvents are authenticated with. If it isn't set, a
+    /// random secret is used, and the tokens aren't valid after the node restarts.
+    pub continuation_token_secret: Option<String>,
 }
 
-/// Rpc server.
-struct JsonRpcServerImpl {
-    chain_id: ChainId,
-    storage_reader: StorageReader,
-    max_events_chunk_size: usize,
-    max_events_keys: usize,
+/// A version of the JSON-RPC specification. Each version is served under its own path, with its
+/// own request and response types.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ApiVersion {
+    #[serde(rename = "v0_3")]
+    V0_3,
+    #[serde(rename = "v0_4")]
+    V0_4,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V0_3, ApiVersion::V0_4];
+
+    /// The HTTP path the version is served under.
+    pub fn path(&self) -> &'static str {
+        match self {
+            ApiVersion::V0_3 => "/rpc/v0_3",
+            ApiVersion::V0_4 => "/rpc/v0_4",
+        }
+    }
+}
+
+/// A version of the rpc server. All the versions read the same storage.
+trait JsonRpcServerImpl: Sized + Send + Sync + 'static {
+    fn new(config: &GatewayConfig, storage_reader: StorageReader) -> Self;
+
+    fn into_rpc_module(self) -> RpcModule<Self>;
 }
 
 impl From<JsonRpcError> for Error {
@@ -75,479 +111,207 @@ fn internal_server_error(err: impl Display) -> Error {
     )))
 }
 
-fn get_block_number<Mode: TransactionKind>(
-    txn: &StorageTxn<'_, Mode>,
-    block_id: BlockId,
-) -> Result<BlockNumber, Error> {
-    Ok(match block_id {
-        BlockId::HashOrNumber(BlockHashOrNumber::Hash(block_hash)) => txn
-            .get_block_number_by_hash(&block_hash)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?,
-        BlockId::HashOrNumber(BlockHashOrNumber::Number(block_number)) => {
-            // Check that the block exists.
-            let last_block_number = get_latest_block_number(txn)?
-                .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
-            if block_number > last_block_number {
-                return Err(Error::from(JsonRpcError::BlockNotFound));
-            }
-            block_number
-        }
-        BlockId::Tag(Tag::Latest) => {
-            get_latest_block_number(txn)?.ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?
+fn execution_error(err: ExecutionError) -> Error {
+    match err {
+        ExecutionError::BlockNotFound { .. } => Error::from(JsonRpcError::BlockNotFound),
+        ExecutionError::ContractNotFound { .. } => Error::from(JsonRpcError::ContractNotFound),
+        ExecutionError::ContractError(_)
+        | ExecutionError::TransactionExecutionError { .. }
+        | ExecutionError::TransactionReverted { .. } => {
+            debug!("Execution failed: {}", err);
+            Error::from(JsonRpcError::ContractError)
         }
-        BlockId::Tag(Tag::Pending) => {
-            todo!("Pending tag is not supported yet.")
-        }
-    })
-}
-
-fn get_latest_block_number<Mode: TransactionKind>(
-    txn: &StorageTxn<'_, Mode>,
-) -> Result<Option<BlockNumber>, Error> {
-    Ok(txn.get_header_marker().map_err(internal_server_error)?.prev())
+        _ => internal_server_error(err),
+    }
 }
 
-fn get_block_header_by_number<Mode: TransactionKind>(
-    txn: &StorageTxn<'_, Mode>,
-    block_number: BlockNumber,
-) -> Result<BlockHeader, Error> {
-    let header = txn
-        .get_block_header(block_number)
-        .map_err(internal_server_error)?
-        .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
-
-    Ok(BlockHeader::from(header))
+// Numbers that the specification gives as hex strings, like fees and gas prices.
+fn u128_as_stark_felt(value: u128) -> StarkFelt {
+    let mut bytes = [0_u8; 32];
+    bytes[16..].copy_from_slice(&value.to_be_bytes());
+    StarkFelt::new(bytes).expect("A u128 should fit in a StarkFelt.")
 }
 
-fn get_block_txs_by_number<Mode: TransactionKind>(
-    txn: &StorageTxn<'_, Mode>,
-    block_number: BlockNumber,
-) -> Result<Vec<Transaction>, Error> {
-    let transactions = txn
-        .get_block_transactions(block_number)
-        .map_err(internal_server_error)?
-        .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
-
-    Ok(transactions.into_iter().map(Transaction::from).collect())
+/// Runs the JSON-RPC server. Each version of the API is served under its path, and the default
+/// version is also served under `/`. The requests that exceed the limits of the config are counted
+/// in `monitor`.
+// The config isn't recorded since it has the continuation token secret.
+#[instrument(
+    skip(config, storage_reader, monitor),
+    fields(server_address = %config.server_address),
+    level = "debug",
+    err
+)]
+pub async fn run_server(
+    config: &GatewayConfig,
+    storage_reader: StorageReader,
+    monitor: GatewayMonitor,
+) -> anyhow::Result<(SocketAddr, JoinHandle<Result<(), hyper::Error>>)> {
+    debug!("Starting gateway.");
+    let server_address = SocketAddr::from_str(&config.server_address)?;
+    let server = axum::Server::try_bind(&server_address)?.serve(
+        app(config, storage_reader, monitor).into_make_service_with_connect_info::<SocketAddr>(),
+    );
+    let addr = server.local_addr();
+    let handle = tokio::spawn(server);
+    info!(local_address = %addr, "Gateway is running.");
+    Ok((addr, handle))
 }
 
-struct ContinuationTokenAsStruct(EventIndex);
-
-impl ContinuationToken {
-    fn parse(&self) -> Result<ContinuationTokenAsStruct, Error> {
-        let ct = serde_json::from_str(&self.0)
-            .map_err(|_| Error::from(JsonRpcError::InvalidContinuationToken))?;
-
-        Ok(ContinuationTokenAsStruct(ct))
+fn app(config: &GatewayConfig, storage_reader: StorageReader, monitor: GatewayMonitor) -> Router {
+    // All the versions share the limits.
+    let limiter = Arc::new(RequestLimiter::new(config.limits.clone(), monitor));
+    tokio::spawn(limits::remove_idle_clients_periodically(Arc::downgrade(&limiter)));
+    let mut router = Router::new();
+    for version in ApiVersion::ALL {
+        let methods = get_methods(version, config, storage_reader.clone());
+        let limiter = limiter.clone();
+        let handler = move |ConnectInfo(client_address): ConnectInfo<SocketAddr>,
+                            headers: HeaderMap,
+                            body: Result<String, StringRejection>| {
+            handle_request(methods.clone(), limiter.clone(), client_address, headers, body)
+        };
+        if version == config.default_version {
+            router = router.route("/", post(handler.clone()));
+        }
+        router = router.route(version.path(), post(handler));
     }
-
-    fn new(ct: ContinuationTokenAsStruct) -> Result<Self, Error> {
-        Ok(Self(serde_json::to_string(&ct.0).map_err(internal_server_error)?))
+    if config.serve_feeder_gateway {
+        router = router.merge(feeder_gateway::routes(storage_reader, limiter));
     }
+    router.layer(DefaultBodyLimit::max(config.limits.max_request_body_size as usize))
 }
 
-#[async_trait]
-impl JsonRpcServer for JsonRpcServerImpl {
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn block_number(&self) -> Result<BlockNumber, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        get_latest_block_number(&txn)?.ok_or_else(|| Error::from(JsonRpcError::NoBlocks))
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn block_hash_and_number(&self) -> Result<BlockHashAndNumber, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let block_number =
-            get_latest_block_number(&txn)?.ok_or_else(|| Error::from(JsonRpcError::NoBlocks))?;
-        let header = get_block_header_by_number(&txn, block_number)?;
-
-        Ok(BlockHashAndNumber { block_hash: header.block_hash, block_number })
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_block_w_transaction_hashes(&self, block_id: BlockId) -> Result<Block, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let block_number = get_block_number(&txn, block_id)?;
-        let header = get_block_header_by_number(&txn, block_number)?;
-        let transactions = get_block_txs_by_number(&txn, block_number)?;
-        let transaction_hashes: Vec<TransactionHash> =
-            transactions.iter().map(|transaction| transaction.transaction_hash()).collect();
-
-        Ok(Block {
-            status: BlockStatus::AcceptedOnL2,
-            header,
-            transactions: Transactions::Hashes(transaction_hashes),
-        })
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<Block, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let block_number = get_block_number(&txn, block_id)?;
-        let header = get_block_header_by_number(&txn, block_number)?;
-        let transactions = get_block_txs_by_number(&txn, block_number)?;
-
-        Ok(Block {
-            status: BlockStatus::AcceptedOnL2,
-            header,
-            transactions: Transactions::Full(
-                transactions.into_iter().map(TransactionWithType::from).collect(),
-            ),
-        })
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_storage_at(
-        &self,
-        contract_address: ContractAddress,
-        key: StorageKey,
-        block_id: BlockId,
-    ) -> Result<StarkFelt, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        // Check that the block is valid and get the state number.
-        let block_number = get_block_number(&txn, block_id)?;
-        let state = StateNumber::right_after_block(block_number);
-        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
-
-        // Check that the contract exists.
-        state_reader
-            .get_class_hash_at(state, &contract_address)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
-
-        state_reader.get_storage_at(state, &contract_address, &key).map_err(internal_server_error)
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_transaction_by_hash(
-        &self,
-        transaction_hash: TransactionHash,
-    ) -> Result<TransactionWithType, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        let transaction_index = txn
-            .get_transaction_idx_by_hash(&transaction_hash)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
-
-        let transaction = txn
-            .get_transaction(transaction_index)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
-
-        Ok(TransactionWithType::from(transaction))
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_transaction_by_block_id_and_index(
-        &self,
-        block_id: BlockId,
-        index: TransactionOffsetInBlock,
-    ) -> Result<TransactionWithType, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let block_number = get_block_number(&txn, block_id)?;
-
-        let transaction = txn
-            .get_transaction(TransactionIndex(block_number, index))
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::InvalidTransactionIndex))?;
-
-        Ok(TransactionWithType::from(transaction))
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_block_transaction_count(&self, block_id: BlockId) -> Result<usize, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let block_number = get_block_number(&txn, block_id)?;
-        let transactions = get_block_txs_by_number(&txn, block_number)?;
-
-        Ok(transactions.len())
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_state_update(&self, block_id: BlockId) -> Result<StateUpdate, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        // Get the block header for the block hash and state root.
-        let block_number = get_block_number(&txn, block_id)?;
-        let header = get_block_header_by_number(&txn, block_number)?;
-
-        // Get the old root.
-        let parent_block_number = get_block_number(
-            &txn,
-            BlockId::HashOrNumber(BlockHashOrNumber::Hash(header.parent_hash)),
-        );
-        let mut old_root =
-            GlobalRoot(StarkHash::try_from(GENESIS_HASH).map_err(internal_server_error)?);
-        if parent_block_number.is_ok() {
-            let parent_header = get_block_header_by_number(&txn, parent_block_number.unwrap())?;
-            old_root = parent_header.new_root;
+fn get_methods(
+    version: ApiVersion,
+    config: &GatewayConfig,
+    storage_reader: StorageReader,
+) -> Methods {
+    let mut methods: Methods = match version {
+        ApiVersion::V0_3 => {
+            JsonRpcServerV0_3Impl::new(config, storage_reader.clone()).into_rpc_module().into()
         }
-
-        // Get the block state diff.
-        let thin_state_diff = txn
-            .get_state_diff(block_number)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
-
-        Ok(StateUpdate {
-            block_hash: header.block_hash,
-            new_root: header.new_root,
-            old_root,
-            state_diff: thin_state_diff.into(),
-        })
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_transaction_receipt(
-        &self,
-        transaction_hash: TransactionHash,
-    ) -> Result<TransactionReceiptWithStatus, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        let transaction_index = txn
-            .get_transaction_idx_by_hash(&transaction_hash)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
-
-        let block_number = transaction_index.0;
-        let header =
-            get_block_header_by_number(&txn, block_number).map_err(internal_server_error)?;
-
-        let transaction = txn
-            .get_transaction(transaction_index)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
-
-        let thin_tx_output = txn
-            .get_transaction_output(transaction_index)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
-
-        let events = txn
-            .get_transaction_events(transaction_index)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
-
-        let output = TransactionOutput::from_thin_transaction_output(thin_tx_output, events);
-
-        Ok(TransactionReceiptWithStatus {
-            receipt: TransactionReceipt::from_transaction_output(
-                output,
-                &transaction,
-                header.block_hash,
-                block_number,
-            ),
-            status: TransactionStatus::default(),
-        })
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_class(
-        &self,
-        block_id: BlockId,
-        class_hash: ClassHash,
-    ) -> Result<GatewayContractClass, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        let block_number = get_block_number(&txn, block_id)?;
-        let state_number = StateNumber::right_after_block(block_number);
-        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
-
-        // The class might be a deprecated class. Search it first in the declared classes and if not
-        // found, search in the deprecated classes.
-        if let Some(class) = state_reader
-            .get_class_definition_at(state_number, &class_hash)
-            .map_err(internal_server_error)?
-        {
-            Ok(GatewayContractClass::Sierra(class.try_into().map_err(internal_server_error)?))
-        } else {
-            let class = state_reader
-                .get_deprecated_class_definition_at(state_number, &class_hash)
-                .map_err(internal_server_error)?
-                .ok_or_else(|| Error::from(JsonRpcError::ClassHashNotFound))?;
-            Ok(GatewayContractClass::Cairo0(class.try_into().map_err(internal_server_error)?))
+        ApiVersion::V0_4 => {
+            JsonRpcServerV0_4Impl::new(config, storage_reader.clone()).into_rpc_module().into()
         }
-    }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_class_at(
-        &self,
-        block_id: BlockId,
-        contract_address: ContractAddress,
-    ) -> Result<GatewayContractClass, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        let block_number = get_block_number(&txn, block_id)?;
-        let state_number = StateNumber::right_after_block(block_number);
-        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
-
-        let class_hash = state_reader
-            .get_class_hash_at(state_number, &contract_address)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
+    };
+    // The methods of the node are served with every version.
+    methods
+        .merge(JsonRpcServerPapyrusImpl::new(config, storage_reader).into_rpc_module())
+        .expect("The namespaces of the methods should be distinct.");
+    methods
+}
 
-        if let Some(class) = state_reader
-            .get_class_definition_at(state_number, &class_hash)
-            .map_err(internal_server_error)?
-        {
-            Ok(GatewayContractClass::Sierra(class.try_into().map_err(internal_server_error)?))
-        } else {
-            let class = state_reader
-                .get_deprecated_class_definition_at(state_number, &class_hash)
-                .map_err(internal_server_error)?
-                .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
-            Ok(GatewayContractClass::Cairo0(class.try_into().map_err(internal_server_error)?))
+// Serves a request, or a batch of requests, with the methods of a single version of the API.
+async fn handle_request(
+    methods: Methods,
+    limiter: Arc<RequestLimiter>,
+    client_address: SocketAddr,
+    headers: HeaderMap,
+    body: Result<String, StringRejection>,
+) -> Response {
+    let response = match body {
+        Ok(body) => {
+            let api_key = headers.get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+            let client = limiter.client(client_address.ip(), api_key);
+            serve_body(&methods, &limiter, &client, &body).await
         }
+        Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            Some(limiter.reject(LimitError::OversizedRequest).response(serde_json::Value::Null))
+        }
+        Err(_) => Some(error_response(ErrorCode::ParseError)),
+    };
+    match response {
+        Some(response) => ([(CONTENT_TYPE, "application/json")], response).into_response(),
+        // A request with only notifications has no response.
+        None => StatusCode::NO_CONTENT.into_response(),
     }
+}
 
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_class_hash_at(
-        &self,
-        block_id: BlockId,
-        contract_address: ContractAddress,
-    ) -> Result<ClassHash, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        let block_number = get_block_number(&txn, block_id)?;
-        let state = StateNumber::right_after_block(block_number);
-        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
-
-        state_reader
-            .get_class_hash_at(state, &contract_address)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))
+// Returns the response to the body, or None if it has only notifications.
+async fn serve_body(
+    methods: &Methods,
+    limiter: &RequestLimiter,
+    client: &Client,
+    body: &str,
+) -> Option<String> {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Array(requests)) if !requests.is_empty() => {
+            let responses: Vec<String> = stream::iter(requests)
+                .map(|request| call_method(methods, limiter, client, request))
+                .buffered(limiter.max_concurrent_batch_calls())
+                .filter_map(future::ready)
+                .collect()
+                .await;
+            if responses.is_empty() {
+                return None;
+            }
+            let response = format!("[{}]", responses.join(","));
+            match limiter.check_response_size(&response) {
+                Ok(()) => Some(response),
+                Err(err) => Some(err.response(serde_json::Value::Null)),
+            }
+        }
+        Ok(serde_json::Value::Array(_)) => Some(error_response(ErrorCode::InvalidRequest)),
+        Ok(request) => call_method(methods, limiter, client, request).await,
+        Err(_) => Some(error_response(ErrorCode::ParseError)),
     }
+}
 
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_nonce(
-        &self,
-        block_id: BlockId,
-        contract_address: ContractAddress,
-    ) -> Result<Nonce, Error> {
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-
-        let block_number = get_block_number(&txn, block_id)?;
-        let state = StateNumber::right_after_block(block_number);
-        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
-
-        state_reader
-            .get_nonce_at(state, &contract_address)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))
+// Returns the response to the request, or None if it is a notification.
+async fn call_method(
+    methods: &Methods,
+    limiter: &RequestLimiter,
+    client: &Client,
+    request: serde_json::Value,
+) -> Option<String> {
+    // Notifications are requests without an id. They are served, but their responses and errors
+    // are dropped.
+    match request {
+        serde_json::Value::Object(mut notification) if !notification.contains_key("id") => {
+            // The methods expect an id.
+            notification.insert("id".to_owned(), serde_json::Value::Null);
+            serve_call(methods, limiter, client, &notification.into()).await;
+            None
+        }
+        request => Some(serve_call(methods, limiter, client, &request).await),
     }
+}
 
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn chain_id(&self) -> Result<String, Error> {
-        Ok(self.chain_id.as_hex())
+async fn serve_call(
+    methods: &Methods,
+    limiter: &RequestLimiter,
+    client: &Client,
+    request: &serde_json::Value,
+) -> String {
+    let id = request.get("id").cloned().unwrap_or_default();
+    // Each call takes a slot, which is released once its response is ready.
+    let _slot = match limiter.acquire_slot() {
+        Ok(slot) => slot,
+        Err(err) => return err.response(id),
+    };
+    // Requests without a method are rejected by the methods themselves.
+    let method = request.get("method").and_then(|method| method.as_str());
+    let checked = method
+        .map_or(Ok(()), |method| limiter.check_method(method))
+        .and_then(|()| limiter.check_rate(client));
+    if let Err(err) = checked {
+        return err.response(id);
     }
-
-    #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error> {
-        // Check the chunk size.
-        if filter.chunk_size > self.max_events_chunk_size {
-            return Err(Error::from(JsonRpcError::PageSizeTooBig));
-        }
-        // Check the number of keys.
-        if filter.keys.len() > self.max_events_keys {
-            return Err(Error::from(JsonRpcError::TooManyKeysInFilter));
-        }
-
-        // Get the requested block numbers.
-        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let from_block_number = filter
-            .from_block
-            .map_or(Ok(BlockNumber(0)), |block_id| get_block_number(&txn, block_id))?;
-        let maybe_to_block_number =
-            filter.to_block.map_or(get_latest_block_number(&txn), |block_id| {
-                get_block_number(&txn, block_id).map(Some)
-            })?;
-        if maybe_to_block_number.is_none() {
-            // There are no blocks.
-            return Ok(EventsChunk { events: vec![], continuation_token: None });
-        }
-        let to_block_number = maybe_to_block_number.unwrap();
-        if from_block_number > to_block_number {
-            return Ok(EventsChunk { events: vec![], continuation_token: None });
-        }
-
-        // Get the event index. If there's a continuation token we take the event index from there.
-        // Otherwise, we take the first index in the from_block_number.
-        let event_index = match filter.continuation_token {
-            Some(token) => token.parse()?.0,
-            None => EventIndex(
-                TransactionIndex(from_block_number, TransactionOffsetInBlock(0)),
-                EventIndexInTransactionOutput(0),
-            ),
-        };
-
-        // Collect the requested events.
-        // Once we collected enough events, we continue to check if there are any more events
-        // corresponding to the requested filter. If there are, we return a continuation token
-        // pointing to the next relevant event. Otherwise, we return a continuation token None.
-        let mut filtered_events = vec![];
-        for ((from_address, event_index), content) in txn
-            .iter_events(filter.address, event_index, to_block_number)
-            .map_err(internal_server_error)?
-        {
-            let block_number = (event_index.0).0;
-            if block_number > to_block_number {
-                break;
-            }
-            if filter.address.is_some() && from_address != filter.address.unwrap() {
-                break;
-            }
-            // TODO: Consider changing empty sets in the filer keys to None.
-            if filter.keys.iter().enumerate().all(|(i, keys)| {
-                content.keys.len() > i && (keys.is_empty() || keys.contains(&content.keys[i]))
-            }) {
-                if filtered_events.len() == filter.chunk_size {
-                    return Ok(EventsChunk {
-                        events: filtered_events,
-                        continuation_token: Some(ContinuationToken::new(
-                            ContinuationTokenAsStruct(event_index),
-                        )?),
-                    });
-                }
-                let header = get_block_header_by_number(&txn, block_number)
-                    .map_err(internal_server_error)?;
-                let transaction = txn
-                    .get_transaction(event_index.0)
-                    .map_err(internal_server_error)?
-                    .ok_or_else(|| internal_server_error("Unknown internal error."))?;
-                let emitted_event = Event {
-                    block_hash: header.block_hash,
-                    block_number,
-                    transaction_hash: transaction.transaction_hash(),
-                    event: starknet_api::transaction::Event { from_address, content },
-                };
-                filtered_events.push(emitted_event);
-            }
-        }
-
-        Ok(EventsChunk { events: filtered_events, continuation_token: None })
+    match methods.raw_json_request(&request.to_string()).await {
+        Ok((response, _)) => match limiter.check_response_size(&response) {
+            Ok(()) => response,
+            Err(err) => err.response(id),
+        },
+        Err(_) => error_response(ErrorCode::InvalidRequest),
     }
 }
 
-#[instrument(skip(storage_reader), level = "debug", err)]
-pub async fn run_server(
-    config: &GatewayConfig,
-    storage_reader: StorageReader,
-) -> anyhow::Result<(SocketAddr, HttpServerHandle)> {
-    debug!("Starting gateway.");
-    let server = HttpServerBuilder::default().build(&config.server_address).await?;
-    let addr = server.local_addr()?;
-    let handle = server.start(
-        JsonRpcServerImpl {
-            chain_id: config.chain_id.clone(),
-            storage_reader,
-            max_events_chunk_size: config.max_events_chunk_size,
-            max_events_keys: config.max_events_keys,
-        }
-        .into_rpc(),
-    )?;
-    info!(local_address = %addr, "Gateway is running.");
-    Ok((addr, handle))
+fn error_response(code: ErrorCode) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {"code": code.code(), "message": code.message()},
+        "id": null,
+    })
+    .to_string()
 }
@@ -0,0 +1,346 @@
+#[cfg(test)]
+#[path = "limits_test.rs"]
+mod limits_test;
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use jsonrpsee::types::error::ErrorCode;
+use serde::{Deserialize, Serialize};
+use starknet_client::{RateLimitConfig, TokenBucket};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+/// The header that carries the API key of a request.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// The codes jsonrpsee's own server uses for exceeding its limits.
+const OVERSIZED_REQUEST_CODE: i32 = -32701;
+const OVERSIZED_RESPONSE_CODE: i32 = -32702;
+const SERVER_IS_BUSY_CODE: i32 = -32604;
+// The code of EIP-1474 for exceeding a request limit.
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+// Above this number of clients, the rate limit state of the least recently seen client is dropped.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+// The rate limit state of clients that were idle for this long is dropped, checked periodically.
+const IDLE_CLIENT_TIMEOUT: Duration = Duration::from_secs(600);
+const IDLE_CLIENTS_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A configuration for the limits of the requests to the gateway.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LimitsConfig {
+    /// The maximal size in bytes of the body of a request.
+    pub max_request_body_size: u32,
+    /// The maximal size in bytes of a response. Bigger responses are replaced by an error.
+    pub max_response_body_size: u32,
+    /// The maximal number of calls that are served at once, over all the connections. Calls above
+    /// it are rejected. Each call of a batch is counted.
+    pub max_concurrent_calls: u32,
+    /// The maximal number of the calls of a single batch that are served at once.
+    pub max_concurrent_batch_calls: u32,
+    /// The rate limit of the calls from each IP address, for calls without a known API key.
+    pub ip_rate_limit: Option<RateLimitConfig>,
+    /// The rate limits of the calls with each of the API keys in the `x-api-key` header.
+    pub api_key_rate_limits: HashMap<String, RateLimitConfig>,
+    /// If set, only these methods are served.
+    pub method_allowlist: Option<Vec<String>>,
+    /// Methods that are not served.
+    pub method_denylist: Vec<String>,
+}
+
+/// A request that exceeded one of the limits of the gateway.
+#[derive(thiserror::Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LimitError {
+    #[error("Request is too big")]
+    OversizedRequest,
+    #[error("Response is too big")]
+    OversizedResponse,
+    #[error("Server is busy, try again later")]
+    ServerIsBusy,
+    #[error("Rate limit exceeded")]
+    RateLimited,
+    #[error("Method not found")]
+    MethodNotAllowed,
+}
+
+impl LimitError {
+    fn code(&self) -> i32 {
+        match self {
+            LimitError::OversizedRequest => OVERSIZED_REQUEST_CODE,
+            LimitError::OversizedResponse => OVERSIZED_RESPONSE_CODE,
+            LimitError::ServerIsBusy => SERVER_IS_BUSY_CODE,
+            LimitError::RateLimited => LIMIT_EXCEEDED_CODE,
+            // Methods that aren't served look like methods that don't exist.
+            LimitError::MethodNotAllowed => ErrorCode::MethodNotFound.code(),
+        }
+    }
+
+    /// The JSON-RPC response to the request with the given id.
+    pub(crate) fn response(&self, id: serde_json::Value) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": {"code": self.code(), "message": self.to_string()},
+            "id": id,
+        })
+        .to_string()
+    }
+}
+
+/// The number of requests that were rejected for exceeding each of the limits of the gateway.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LimitsStats {
+    pub oversized_requests: u64,
+    pub oversized_responses: u64,
+    pub busy_rejections: u64,
+    pub rate_limited_calls: u64,
+    pub disallowed_methods: u64,
+}
+
+#[derive(Default)]
+struct LimitsCounters {
+    oversized_requests: AtomicU64,
+    oversized_responses: AtomicU64,
+    busy_rejections: AtomicU64,
+    rate_limited_calls: AtomicU64,
+    disallowed_methods: AtomicU64,
+    in_flight_requests: AtomicU64,
+}
+
+/// A handle to the counters of the requests the gateway rejected. Clones share the counters.
+#[derive(Clone, Default)]
+pub struct GatewayMonitor {
+    counters: Arc<LimitsCounters>,
+}
+
+impl GatewayMonitor {
+    /// Returns the number of rejected requests by the limit they exceeded.
+    pub fn limits_stats(&self) -> LimitsStats {
+        let counters = &self.counters;
+        LimitsStats {
+            oversized_requests: counters.oversized_requests.load(Ordering::Relaxed),
+            oversized_responses: counters.oversized_responses.load(Ordering::Relaxed),
+            busy_rejections: counters.busy_rejections.load(Ordering::Relaxed),
+            rate_limited_calls: counters.rate_limited_calls.load(Ordering::Relaxed),
+            disallowed_methods: counters.disallowed_methods.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the number of requests that are being served.
+    pub fn in_flight_requests(&self) -> u64 {
+        self.counters.in_flight_requests.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, err: LimitError) {
+        let counter = match err {
+            LimitError::OversizedRequest => &self.counters.oversized_requests,
+            LimitError::OversizedResponse => &self.counters.oversized_responses,
+            LimitError::ServerIsBusy => &self.counters.busy_rejections,
+            LimitError::RateLimited => &self.counters.rate_limited_calls,
+            LimitError::MethodNotAllowed => &self.counters.disallowed_methods,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The client a call is counted against for rate limiting.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Client {
+    Ip(IpAddr),
+    ApiKey(String),
+}
+
+// The token buckets of the clients, holding at most `MAX_TRACKED_CLIENTS` of them by dropping the
+// least recently used.
+#[derive(Default)]
+struct ClientBuckets {
+    // The bucket of each client and the order of its last use.
+    buckets: HashMap<Client, (TokenBucket, u64)>,
+    // The clients by the order of their last use.
+    by_last_use: BTreeMap<u64, Client>,
+    uses: u64,
+}
+
+impl ClientBuckets {
+    // Returns the bucket of the client, marked as the most recently used.
+    fn get_or_insert_with(
+        &mut self,
+        client: &Client,
+        new_bucket: impl FnOnce() -> TokenBucket,
+    ) -> &mut TokenBucket {
+        self.uses += 1;
+        let use_order = self.uses;
+        if let Some((_, last_use)) = self.buckets.get_mut(client) {
+            self.by_last_use.remove(last_use);
+            *last_use = use_order;
+        } else {
+            if self.buckets.len() >= MAX_TRACKED_CLIENTS {
+                if let Some((_, least_recent)) = self.by_last_use.pop_first() {
+                    self.buckets.remove(&least_recent);
+                }
+            }
+            self.buckets.insert(client.clone(), (new_bucket(), use_order));
+        }
+        self.by_last_use.insert(use_order, client.clone());
+        &mut self.buckets.get_mut(client).expect("The bucket was just inserted.").0
+    }
+
+    // Drops the buckets of the clients that weren't seen since `now - IDLE_CLIENT_TIMEOUT`.
+    fn remove_idle(&mut self, now: Instant) {
+        while let Some(entry) = self.by_last_use.first_entry() {
+            let (bucket, _) = &self.buckets[entry.get()];
+            if now.saturating_duration_since(bucket.last_refill()) < IDLE_CLIENT_TIMEOUT {
+                break;
+            }
+            let client = entry.remove();
+            self.buckets.remove(&client);
+        }
+    }
+}
+
+/// A slot of a request that is being served, counted as in flight until it is dropped.
+pub(crate) struct RequestSlot {
+    _permit: OwnedSemaphorePermit,
+    counters: Arc<LimitsCounters>,
+}
+
+impl Drop for RequestSlot {
+    fn drop(&mut self) {
+        self.counters.in_flight_requests.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Checks the requests to the gateway against its limits and counts the rejected ones.
+pub(crate) struct RequestLimiter {
+    config: LimitsConfig,
+    concurrent_calls: Arc<Semaphore>,
+    buckets: Mutex<ClientBuckets>,
+    monitor: GatewayMonitor,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(config: LimitsConfig, monitor: GatewayMonitor) -> Self {
+        RequestLimiter {
+            concurrent_calls: Arc::new(Semaphore::new(config.max_concurrent_calls as usize)),
+            config,
+            buckets: Mutex::new(ClientBuckets::default()),
+            monitor,
+        }
+    }
+
+    /// Counts a request that exceeded a limit, and returns the error to respond with.
+    pub(crate) fn reject(&self, err: LimitError) -> LimitError {
+        debug!("Rejecting a request: {err}.");
+        self.monitor.record(err);
+        err
+    }
+
+    /// Takes a slot for serving a request, which is released when the slot is dropped.
+    pub(crate) fn acquire_slot(&self) -> Result<RequestSlot, LimitError> {
+        let permit = self
+            .concurrent_calls
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| self.reject(LimitError::ServerIsBusy))?;
+        self.monitor.counters.in_flight_requests.fetch_add(1, Ordering::Relaxed);
+        Ok(RequestSlot { _permit: permit, counters: self.monitor.counters.clone() })
+    }
+
+    /// The number of the calls of a batch that are served at once.
+    pub(crate) fn max_concurrent_batch_calls(&self) -> usize {
+        // A batch is served even if the config allows no calls of it at once.
+        (self.config.max_concurrent_batch_calls as usize).max(1)
+    }
+
+    /// The client the calls of a request are counted against. Calls with an unknown API key are
+    /// counted against their IP address, and the addresses of an IPv6 /64 subnet, which is usually
+    /// given to a single host, are counted together.
+    pub(crate) fn client(&self, ip: IpAddr, api_key: Option<&str>) -> Client {
+        match api_key {
+            Some(api_key) if self.config.api_key_rate_limits.contains_key(api_key) => {
+                Client::ApiKey(api_key.to_owned())
+            }
+            _ => Client::Ip(rate_limited_address(ip)),
+        }
+    }
+
+    /// Checks that the method is served.
+    pub(crate) fn check_method(&self, method: &str) -> Result<(), LimitError> {
+        let allowed = self
+            .config
+            .method_allowlist
+            .as_ref()
+            .map_or(true, |allowlist| allowlist.iter().any(|allowed| allowed == method));
+        if !allowed || self.config.method_denylist.iter().any(|denied| denied == method) {
+            return Err(self.reject(LimitError::MethodNotAllowed));
+        }
+        Ok(())
+    }
+
+    /// Checks that the client may make another call now.
+    pub(crate) fn check_rate(&self, client: &Client) -> Result<(), LimitError> {
+        if !self.try_acquire_token(client, Instant::now()) {
+            return Err(self.reject(LimitError::RateLimited));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_response_size(&self, response: &str) -> Result<(), LimitError> {
+        if response.len() > self.config.max_response_body_size as usize {
+            return Err(self.reject(LimitError::OversizedResponse));
+        }
+        Ok(())
+    }
+
+    // Takes a token of the client if it may make a call at `now`.
+    fn try_acquire_token(&self, client: &Client, now: Instant) -> bool {
+        let rate_limit = match client {
+            Client::Ip(_) => self.config.ip_rate_limit,
+            Client::ApiKey(api_key) => self.config.api_key_rate_limits.get(api_key).copied(),
+        };
+        let Some(rate_limit) = rate_limit else {
+            return true;
+        };
+
+        let mut buckets = self.buckets.lock().expect("Rate limiter lock should not be poisoned.");
+        let bucket = buckets.get_or_insert_with(client, || TokenBucket::new(rate_limit, now));
+        bucket.try_acquire(now).is_none()
+    }
+
+    // Drops the rate limit state of the clients that were idle at `now`.
+    fn remove_idle_clients(&self, now: Instant) {
+        self.buckets.lock().expect("Rate limiter lock should not be poisoned.").remove_idle(now);
+    }
+
+    #[cfg(test)]
+    fn tracked_clients(&self) -> usize {
+        self.buckets.lock().expect("Rate limiter lock should not be poisoned.").buckets.len()
+    }
+}
+
+/// Periodically drops the rate limit state of idle clients, until the limiter is dropped.
+pub(crate) async fn remove_idle_clients_periodically(limiter: Weak<RequestLimiter>) {
+    let mut interval = tokio::time::interval(IDLE_CLIENTS_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(limiter) = limiter.upgrade() else {
+            return;
+        };
+        limiter.remove_idle_clients(Instant::now());
+    }
+}
+
+// The address the calls of `ip` are counted against: the /64 subnet of an IPv6 address, and the
+// address itself otherwise.
+fn rate_limited_address(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !u128::from(u64::MAX))),
+        },
+        ip => ip,
+    }
+}
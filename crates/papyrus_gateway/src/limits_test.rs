@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+use assert_matches::assert_matches;
+use starknet_client::RateLimitConfig;
+
+use crate::limits::{
+    Client, GatewayMonitor, LimitError, LimitsConfig, LimitsStats, RequestLimiter,
+    IDLE_CLIENT_TIMEOUT, MAX_TRACKED_CLIENTS,
+};
+use crate::test_utils::get_test_gateway_config;
+
+const IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const OTHER_IP: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+fn limiter(config: LimitsConfig) -> (RequestLimiter, GatewayMonitor) {
+    let monitor = GatewayMonitor::default();
+    (RequestLimiter::new(config, monitor.clone()), monitor)
+}
+
+#[test]
+fn rate_limit_per_ip() {
+    let config = LimitsConfig {
+        ip_rate_limit: Some(RateLimitConfig { requests_per_second: 2, burst: 2 }),
+        ..get_test_gateway_config().limits
+    };
+    let (limiter, monitor) = limiter(config);
+    let client = limiter.client(IP, None);
+    let now = Instant::now();
+
+    // The burst is allowed at once, and then a token is added every half a second.
+    assert!(limiter.try_acquire_token(&client, now));
+    assert!(limiter.try_acquire_token(&client, now));
+    assert!(!limiter.try_acquire_token(&client, now));
+    assert!(!limiter.try_acquire_token(&client, now + Duration::from_millis(400)));
+    assert!(limiter.try_acquire_token(&client, now + Duration::from_millis(500)));
+
+    // Other addresses have their own tokens.
+    assert!(limiter.try_acquire_token(&limiter.client(OTHER_IP, None), now));
+
+    assert_matches!(limiter.check_rate(&client), Err(LimitError::RateLimited));
+    assert_eq!(monitor.limits_stats(), LimitsStats { rate_limited_calls: 1, ..Default::default() });
+}
+
+#[test]
+fn rate_limit_per_ipv6_subnet() {
+    let (limiter, _) = limiter(get_test_gateway_config().limits);
+    let ip = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 1, 2, 3, 4));
+    let same_subnet_ip = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 5, 6, 7, 8));
+    let other_subnet_ip = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 2, 1, 2, 3, 4));
+    assert_eq!(limiter.client(ip, None), limiter.client(same_subnet_ip, None));
+    assert_ne!(limiter.client(ip, None), limiter.client(other_subnet_ip, None));
+    // IPv4 addresses mapped to IPv6 are counted as IPv4 addresses.
+    assert_eq!(
+        limiter.client(IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped()), None),
+        Client::Ip(IP)
+    );
+}
+
+#[test]
+fn tracked_clients_are_bounded() {
+    let config = LimitsConfig {
+        ip_rate_limit: Some(RateLimitConfig { requests_per_second: 1, burst: 1 }),
+        ..get_test_gateway_config().limits
+    };
+    let (limiter, _) = limiter(config);
+    let now = Instant::now();
+    assert!(limiter.try_acquire_token(&Client::Ip(IP), now));
+    for i in 0..MAX_TRACKED_CLIENTS {
+        let client = Client::Ip(IpAddr::V4(Ipv4Addr::from(i as u32 + 1)));
+        // The first client is kept since it's seen again.
+        assert!(!limiter.try_acquire_token(&Client::Ip(IP), now));
+        assert!(limiter.try_acquire_token(&client, now));
+    }
+    assert_eq!(limiter.tracked_clients(), MAX_TRACKED_CLIENTS);
+    assert!(!limiter.try_acquire_token(&Client::Ip(IP), now));
+    // The least recently seen client was dropped, so it gets a new bucket.
+    assert!(limiter.try_acquire_token(&Client::Ip(IpAddr::V4(Ipv4Addr::from(1_u32))), now));
+
+    limiter.remove_idle_clients(now + IDLE_CLIENT_TIMEOUT);
+    assert_eq!(limiter.tracked_clients(), 0);
+}
+
+#[test]
+fn rate_limit_per_api_key() {
+    let config = LimitsConfig {
+        ip_rate_limit: Some(RateLimitConfig { requests_per_second: 1, burst: 1 }),
+        api_key_rate_limits: HashMap::from([(
+            "key".to_owned(),
+            RateLimitConfig { requests_per_second: 10, burst: 10 },
+        )]),
+        ..get_test_gateway_config().limits
+    };
+    let (limiter, _) = limiter(config);
+
+    // Unknown keys are counted against the IP address.
+    assert_eq!(limiter.client(IP, Some("unknown")), Client::Ip(IP));
+    let client = limiter.client(IP, Some("key"));
+    assert_eq!(client, Client::ApiKey("key".to_owned()));
+
+    let now = Instant::now();
+    for _ in 0..10 {
+        assert!(limiter.try_acquire_token(&client, now));
+    }
+    assert!(!limiter.try_acquire_token(&client, now));
+    // The key's calls aren't counted against the IP address.
+    assert!(limiter.try_acquire_token(&Client::Ip(IP), now));
+}
+
+#[test]
+fn method_allowlist_and_denylist() {
+    let config = LimitsConfig {
+        method_allowlist: Some(vec![
+            "starknet_blockNumber".to_owned(),
+            "starknet_getEvents".to_owned(),
+        ]),
+        method_denylist: vec!["starknet_getEvents".to_owned()],
+        ..get_test_gateway_config().limits
+    };
+    let (limiter, monitor) = limiter(config);
+
+    assert!(limiter.check_method("starknet_blockNumber").is_ok());
+    assert_matches!(limiter.check_method("starknet_getEvents"), Err(LimitError::MethodNotAllowed));
+    assert_matches!(limiter.check_method("starknet_chainId"), Err(LimitError::MethodNotAllowed));
+    assert_eq!(monitor.limits_stats(), LimitsStats { disallowed_methods: 2, ..Default::default() });
+}
+
+#[test]
+fn concurrent_requests() {
+    let config = LimitsConfig { max_concurrent_calls: 1, ..get_test_gateway_config().limits };
+    let (limiter, monitor) = limiter(config);
+
+    let slot = limiter.acquire_slot().unwrap();
+    assert_eq!(monitor.in_flight_requests(), 1);
+    assert_matches!(limiter.acquire_slot(), Err(LimitError::ServerIsBusy));
+    drop(slot);
+    assert_eq!(monitor.in_flight_requests(), 0);
+    assert!(limiter.acquire_slot().is_ok());
+    assert_eq!(monitor.limits_stats(), LimitsStats { busy_rejections: 1, ..Default::default() });
+}
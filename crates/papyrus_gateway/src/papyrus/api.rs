@@ -0,0 +1,106 @@
+use jsonrpsee::core::Error;
+use jsonrpsee::proc_macros::rpc;
+use papyrus_storage::body::messages::L1ToL2MessageHash;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::transaction::{EthAddress, MessageToL1, TransactionHash};
+
+use crate::api::{ContinuationToken, EventFilter};
+use crate::papyrus::decoding::{DecodedEventContent, DecodedFunctionCall};
+
+/// An event with its keys and data decoded by the ABI of the class of the contract that emitted
+/// it. `decoded` is None if the ABI isn't available or doesn't describe the event.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DecodedEvent {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub transaction_hash: TransactionHash,
+    #[serde(flatten)]
+    pub event: starknet_api::transaction::Event,
+    pub decoded: Option<DecodedEventContent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DecodedEventsChunk {
+    pub events: Vec<DecodedEvent>,
+    pub continuation_token: Option<ContinuationToken>,
+}
+
+/// What happened on L2 to a message sent from L1.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MessageStatus {
+    pub message_hash: L1ToL2MessageHash,
+    /// The L1 handler transaction that consumed the message, None if no synced block has one.
+    pub consumed_by: Option<ConsumingTransaction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ConsumingTransaction {
+    pub transaction_hash: TransactionHash,
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    /// None for blocks synced before the execution status was stored.
+    pub execution_status: Option<ExecutionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ExecutionStatus {
+    #[serde(rename = "SUCCEEDED")]
+    Succeeded,
+    #[serde(rename = "REVERTED")]
+    Reverted,
+}
+
+/// A message from L2 to L1 with the transaction that sent it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SentMessageToL1 {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub transaction_hash: TransactionHash,
+    #[serde(flatten)]
+    pub message: MessageToL1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MessagesToL1Chunk {
+    pub messages: Vec<SentMessageToL1>,
+    pub continuation_token: Option<ContinuationToken>,
+}
+
+/// Methods of the node that are not part of the specification, served along with every version
+/// of it.
+#[rpc(server, client, namespace = "papyrus")]
+pub trait JsonRpcPapyrus {
+    /// Gets the events that match the filter, like starknet_getEvents, decoded by the ABIs of the
+    /// emitting contracts.
+    #[method(name = "getDecodedEvents")]
+    fn get_decoded_events(&self, filter: EventFilter) -> Result<DecodedEventsChunk, Error>;
+
+    /// Gets the calldata of an invoke transaction decoded by the ABI of the called function, which
+    /// is `__execute__` of the account for transactions of version 1. Returns null if the
+    /// calldata can't be decoded.
+    #[method(name = "getDecodedCalldata")]
+    fn get_decoded_calldata(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<Option<DecodedFunctionCall>, Error>;
+
+    /// Gets the L1 handler transactions that consumed the messages from L1, by the hashes the
+    /// Starknet core contract computed for the messages when they were sent.
+    #[method(name = "getMessagesStatus")]
+    fn get_messages_status(
+        &self,
+        message_hashes: Vec<L1ToL2MessageHash>,
+    ) -> Result<Vec<MessageStatus>, Error>;
+
+    /// Gets the messages sent from L2 to an L1 address, by the order they were sent in.
+    #[method(name = "getL2ToL1Messages")]
+    fn get_l2_to_l1_messages(
+        &self,
+        to_address: EthAddress,
+        chunk_size: usize,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<MessagesToL1Chunk, Error>;
+}
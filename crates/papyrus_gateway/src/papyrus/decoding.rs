@@ -0,0 +1,310 @@
+#[cfg(test)]
+#[path = "decoding_test.rs"]
+mod decoding_test;
+
+use std::collections::HashMap;
+use std::slice::Iter;
+
+use blockifier::abi::abi_utils::selector_from_name;
+use serde::{Deserialize, Serialize};
+use starknet_api::core::EntryPointSelector;
+use starknet_api::deprecated_contract_class::{ContractClassAbiEntry, TypedParameter};
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::EventContent;
+
+// Nested types beyond this depth are not decoded, which guards against recursive types in ABIs.
+const MAX_DECODING_DEPTH: usize = 32;
+
+/// A value decoded according to its type in an ABI.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DecodedValue {
+    Felt(StarkFelt),
+    Array(Vec<DecodedValue>),
+    Struct(Vec<DecodedField>),
+    Enum { variant: String, value: Box<DecodedValue> },
+}
+
+/// A named and typed value, such as a member of an event or an input of a function.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DecodedField {
+    pub name: String,
+    pub r#type: String,
+    pub value: DecodedValue,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DecodedEventContent {
+    pub name: String,
+    pub keys: Vec<DecodedField>,
+    pub data: Vec<DecodedField>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DecodedFunctionCall {
+    pub name: String,
+    pub inputs: Vec<DecodedField>,
+}
+
+// Cairo 0 passes arrays as a length followed by a pointer, while Cairo 1 serializes the length as
+// part of the array, and has enums.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum AbiKind {
+    Cairo0,
+    Sierra,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+struct AbiParam {
+    name: String,
+    #[serde(alias = "ty")]
+    r#type: String,
+}
+
+impl From<&TypedParameter> for AbiParam {
+    fn from(param: &TypedParameter) -> Self {
+        AbiParam { name: param.name.clone(), r#type: param.r#type.clone() }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct AbiEvent {
+    name: String,
+    keys: Vec<AbiParam>,
+    data: Vec<AbiParam>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct AbiFunction {
+    name: String,
+    inputs: Vec<AbiParam>,
+}
+
+// The entries of a Sierra ABI that are used for decoding.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SierraAbiEntry {
+    Function { name: String, inputs: Vec<AbiParam> },
+    Event { name: String, inputs: Vec<AbiParam> },
+    Struct { name: String, members: Vec<AbiParam> },
+    Enum { name: String, variants: Vec<AbiParam> },
+}
+
+/// The ABI of a contract class, indexed for decoding events and calldata.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractAbi {
+    kind: AbiKind,
+    // By the selector of their name, which is the first key of the events.
+    events: HashMap<StarkFelt, AbiEvent>,
+    functions: HashMap<EntryPointSelector, AbiFunction>,
+    structs: HashMap<String, Vec<AbiParam>>,
+    enums: HashMap<String, Vec<AbiParam>>,
+}
+
+impl ContractAbi {
+    /// Indexes the ABI of a Cairo 0 class.
+    pub fn from_deprecated(abi: &[ContractClassAbiEntry]) -> Self {
+        let mut contract_abi = ContractAbi::new(AbiKind::Cairo0);
+        for entry in abi {
+            match entry {
+                ContractClassAbiEntry::Event(event) => {
+                    contract_abi.add_event(
+                        &event.name,
+                        event.keys.iter().map(AbiParam::from).collect(),
+                        event.data.iter().map(AbiParam::from).collect(),
+                    );
+                }
+                ContractClassAbiEntry::Function(function) => {
+                    contract_abi.add_function(
+                        &function.entry.name,
+                        function.entry.inputs.iter().map(AbiParam::from).collect(),
+                    );
+                }
+                ContractClassAbiEntry::Struct(entry) => {
+                    let mut members = entry.members.iter().collect::<Vec<_>>();
+                    members.sort_by_key(|member| member.offset);
+                    contract_abi.structs.insert(
+                        entry.name.clone(),
+                        members.into_iter().map(|member| AbiParam::from(&member.param)).collect(),
+                    );
+                }
+            }
+        }
+        contract_abi
+    }
+
+    /// Indexes the JSON ABI of a Sierra class. Entries that can't be used for decoding are
+    /// skipped, and an ABI that isn't a JSON list gives None.
+    pub fn from_sierra(abi: &str) -> Option<Self> {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(abi).ok()?;
+        let mut contract_abi = ContractAbi::new(AbiKind::Sierra);
+        for entry in entries {
+            match serde_json::from_value(entry) {
+                Ok(SierraAbiEntry::Function { name, inputs }) => {
+                    contract_abi.add_function(&name, inputs)
+                }
+                Ok(SierraAbiEntry::Event { name, inputs }) => {
+                    contract_abi.add_event(&name, vec![], inputs)
+                }
+                Ok(SierraAbiEntry::Struct { name, members }) => {
+                    contract_abi.structs.insert(name, members);
+                }
+                Ok(SierraAbiEntry::Enum { name, variants }) => {
+                    contract_abi.enums.insert(name, variants);
+                }
+                Err(_) => {}
+            }
+        }
+        Some(contract_abi)
+    }
+
+    fn new(kind: AbiKind) -> Self {
+        ContractAbi {
+            kind,
+            events: HashMap::new(),
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+        }
+    }
+
+    fn add_event(&mut self, name: &str, keys: Vec<AbiParam>, data: Vec<AbiParam>) {
+        self.events
+            .insert(selector_from_name(name).0, AbiEvent { name: name.to_owned(), keys, data });
+    }
+
+    fn add_function(&mut self, name: &str, inputs: Vec<AbiParam>) {
+        self.functions
+            .insert(selector_from_name(name), AbiFunction { name: name.to_owned(), inputs });
+    }
+
+    /// Decodes an event that was emitted by a contract of this class. Returns None if the event
+    /// isn't in the ABI or doesn't match its types.
+    pub fn decode_event(&self, content: &EventContent) -> Option<DecodedEventContent> {
+        let (selector, keys) = content.keys.split_first()?;
+        let event = self.events.get(&selector.0)?;
+        let keys = keys.iter().map(|key| key.0).collect::<Vec<_>>();
+        Some(DecodedEventContent {
+            name: event.name.clone(),
+            keys: self.decode_params(&event.keys, &keys)?,
+            data: self.decode_params(&event.data, &content.data.0)?,
+        })
+    }
+
+    /// Decodes the calldata of a call to a function of this class. Returns None if the function
+    /// isn't in the ABI or the calldata doesn't match its inputs.
+    pub fn decode_call(
+        &self,
+        selector: &EntryPointSelector,
+        calldata: &[StarkFelt],
+    ) -> Option<DecodedFunctionCall> {
+        let function = self.functions.get(selector)?;
+        Some(DecodedFunctionCall {
+            name: function.name.clone(),
+            inputs: self.decode_params(&function.inputs, calldata)?,
+        })
+    }
+
+    // Decodes all the felts into the params.
+    fn decode_params(&self, params: &[AbiParam], felts: &[StarkFelt]) -> Option<Vec<DecodedField>> {
+        let mut felts = felts.iter();
+        let fields = self.decode_fields(params, &mut felts, 0)?;
+        felts.next().is_none().then_some(fields)
+    }
+
+    fn decode_fields(
+        &self,
+        params: &[AbiParam],
+        felts: &mut Iter<'_, StarkFelt>,
+        depth: usize,
+    ) -> Option<Vec<DecodedField>> {
+        let mut fields: Vec<DecodedField> = Vec::with_capacity(params.len());
+        for param in params {
+            let value = match param.r#type.strip_suffix('*') {
+                // A Cairo 0 pointer, whose length is the field before it.
+                Some(element_type) if self.kind == AbiKind::Cairo0 => {
+                    let Some(DecodedField { value: DecodedValue::Felt(len), .. }) = fields.last()
+                    else {
+                        return None;
+                    };
+                    let len = felt_as_len(len, felts.len())?;
+                    self.decode_array(element_type, len, felts, depth)?
+                }
+                _ => self.decode_value(&param.r#type, felts, depth)?,
+            };
+            fields.push(DecodedField {
+                name: param.name.clone(),
+                r#type: param.r#type.clone(),
+                value,
+            });
+        }
+        Some(fields)
+    }
+
+    fn decode_value(
+        &self,
+        r#type: &str,
+        felts: &mut Iter<'_, StarkFelt>,
+        depth: usize,
+    ) -> Option<DecodedValue> {
+        if depth > MAX_DECODING_DEPTH {
+            return None;
+        }
+        if let Some(members) = self.structs.get(r#type) {
+            return Some(DecodedValue::Struct(self.decode_fields(members, felts, depth + 1)?));
+        }
+        if let Some(variants) = self.enums.get(r#type) {
+            let variant = variants.get(felt_as_len(felts.next()?, variants.len())?)?;
+            return Some(DecodedValue::Enum {
+                variant: variant.name.clone(),
+                value: Box::new(self.decode_value(&variant.r#type, felts, depth + 1)?),
+            });
+        }
+        let felt = match self.kind {
+            AbiKind::Cairo0 => (r#type == "felt").then(|| felts.next().copied()).flatten(),
+            AbiKind::Sierra => {
+                if r#type == "()" {
+                    return Some(DecodedValue::Struct(vec![]));
+                }
+                let element_type = r#type
+                    .strip_prefix("core::array::Array::<")
+                    .or_else(|| r#type.strip_prefix("core::array::Span::<"))
+                    .and_then(|element_type| element_type.strip_suffix('>'));
+                if let Some(element_type) = element_type {
+                    let len = felt_as_len(felts.next()?, felts.len())?;
+                    return self.decode_array(element_type, len, felts, depth);
+                }
+                // Tuples and other generic types aren't decoded.
+                if r#type.contains(['<', '(']) {
+                    return None;
+                }
+                // The rest of the types, like integers and addresses, are serialized as a felt.
+                felts.next().copied()
+            }
+        };
+        felt.map(DecodedValue::Felt)
+    }
+
+    fn decode_array(
+        &self,
+        element_type: &str,
+        len: usize,
+        felts: &mut Iter<'_, StarkFelt>,
+        depth: usize,
+    ) -> Option<DecodedValue> {
+        let elements = (0..len)
+            .map(|_| self.decode_value(element_type, felts, depth + 1))
+            .collect::<Option<_>>()?;
+        Some(DecodedValue::Array(elements))
+    }
+}
+
+// Returns the felt as a length, if it is at most `max`.
+fn felt_as_len(felt: &StarkFelt, max: usize) -> Option<usize> {
+    let (high, low) = felt.bytes().split_at(24);
+    if high.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    let len = usize::try_from(u64::from_be_bytes(low.try_into().ok()?)).ok()?;
+    (len <= max).then_some(len)
+}
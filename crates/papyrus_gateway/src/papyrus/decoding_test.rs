@@ -0,0 +1,205 @@
+use blockifier::abi::abi_utils::selector_from_name;
+use starknet_api::deprecated_contract_class::{
+    ContractClassAbiEntry, EventAbiEntry, FunctionAbiEntry, FunctionAbiEntryType,
+    FunctionAbiEntryWithType, StructAbiEntry, StructMember, TypedParameter,
+};
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use starknet_api::transaction::{EventContent, EventData, EventKey};
+
+use super::{ContractAbi, DecodedEventContent, DecodedField, DecodedFunctionCall, DecodedValue};
+
+fn param(name: &str, r#type: &str) -> TypedParameter {
+    TypedParameter { name: name.to_owned(), r#type: r#type.to_owned() }
+}
+
+fn field(name: &str, r#type: &str, value: DecodedValue) -> DecodedField {
+    DecodedField { name: name.to_owned(), r#type: r#type.to_owned(), value }
+}
+
+fn felt(value: &str) -> DecodedValue {
+    DecodedValue::Felt(stark_felt!(value))
+}
+
+fn deprecated_abi() -> ContractAbi {
+    ContractAbi::from_deprecated(&[
+        // The members are decoded by their offsets, not by their order in the ABI.
+        ContractClassAbiEntry::Struct(StructAbiEntry {
+            name: "Uint256".to_owned(),
+            size: 2,
+            members: vec![
+                StructMember { param: param("high", "felt"), offset: 1 },
+                StructMember { param: param("low", "felt"), offset: 0 },
+            ],
+        }),
+        ContractClassAbiEntry::Event(EventAbiEntry {
+            name: "Transfer".to_owned(),
+            keys: vec![],
+            data: vec![param("from_", "felt"), param("to", "felt"), param("value", "Uint256")],
+        }),
+        ContractClassAbiEntry::Function(FunctionAbiEntryWithType {
+            r#type: FunctionAbiEntryType::Regular,
+            entry: FunctionAbiEntry {
+                name: "transfer_many".to_owned(),
+                inputs: vec![
+                    param("recipients_len", "felt"),
+                    param("recipients", "felt*"),
+                    param("amounts_len", "felt"),
+                    param("amounts", "Uint256*"),
+                ],
+                outputs: vec![],
+            },
+        }),
+    ])
+}
+
+fn transfer_event(data: Vec<StarkFelt>) -> EventContent {
+    EventContent { keys: vec![EventKey(selector_from_name("Transfer").0)], data: EventData(data) }
+}
+
+#[test]
+fn decode_deprecated_event() {
+    let abi = deprecated_abi();
+    let event = transfer_event(vec![
+        stark_felt!("0x1"),
+        stark_felt!("0x2"),
+        stark_felt!("0x3"),
+        stark_felt!("0x4"),
+    ]);
+
+    let expected = DecodedEventContent {
+        name: "Transfer".to_owned(),
+        keys: vec![],
+        data: vec![
+            field("from_", "felt", felt("0x1")),
+            field("to", "felt", felt("0x2")),
+            field(
+                "value",
+                "Uint256",
+                DecodedValue::Struct(vec![
+                    field("low", "felt", felt("0x3")),
+                    field("high", "felt", felt("0x4")),
+                ]),
+            ),
+        ],
+    };
+    assert_eq!(abi.decode_event(&event), Some(expected));
+
+    // Events whose data doesn't match the ABI, or that aren't in it, aren't decoded.
+    assert_eq!(abi.decode_event(&transfer_event(vec![stark_felt!("0x1")])), None);
+    let too_long = transfer_event(vec![stark_felt!("0x1"); 5]);
+    assert_eq!(abi.decode_event(&too_long), None);
+    let unknown = EventContent {
+        keys: vec![EventKey(selector_from_name("Approval").0)],
+        ..transfer_event(vec![stark_felt!("0x1"); 4])
+    };
+    assert_eq!(abi.decode_event(&unknown), None);
+}
+
+#[test]
+fn decode_deprecated_calldata_with_pointers() {
+    let abi = deprecated_abi();
+    let selector = selector_from_name("transfer_many");
+    let calldata = ["0x2", "0xa", "0xb", "0x1", "0x5", "0x0"].map(|felt| stark_felt!(felt));
+
+    let expected = DecodedFunctionCall {
+        name: "transfer_many".to_owned(),
+        inputs: vec![
+            field("recipients_len", "felt", felt("0x2")),
+            field("recipients", "felt*", DecodedValue::Array(vec![felt("0xa"), felt("0xb")])),
+            field("amounts_len", "felt", felt("0x1")),
+            field(
+                "amounts",
+                "Uint256*",
+                DecodedValue::Array(vec![DecodedValue::Struct(vec![
+                    field("low", "felt", felt("0x5")),
+                    field("high", "felt", felt("0x0")),
+                ])]),
+            ),
+        ],
+    };
+    assert_eq!(abi.decode_call(&selector, &calldata), Some(expected));
+
+    // A length that is longer than the calldata.
+    let calldata = ["0x3", "0xa", "0xb", "0x0"].map(|felt| stark_felt!(felt));
+    assert_eq!(abi.decode_call(&selector, &calldata), None);
+    assert_eq!(abi.decode_call(&selector_from_name("transfer"), &[]), None);
+}
+
+#[test]
+fn decode_sierra_abi() {
+    let abi = ContractAbi::from_sierra(
+        r#"[
+            {"type": "struct", "name": "core::integer::u256", "members": [
+                {"name": "low", "ty": "core::integer::u128"},
+                {"name": "high", "ty": "core::integer::u128"}
+            ]},
+            {"type": "enum", "name": "core::option::Option::<core::felt252>", "variants": [
+                {"name": "Some", "ty": "core::felt252"},
+                {"name": "None", "ty": "()"}
+            ]},
+            {"type": "function", "name": "transfer", "inputs": [
+                {"name": "recipients", "ty": "core::array::Array::<core::starknet::contract_address::ContractAddress>"},
+                {"name": "amount", "ty": "core::integer::u256"},
+                {"name": "memo", "ty": "core::option::Option::<core::felt252>"}
+            ], "outputs": [], "state_mutability": "external"},
+            {"type": "event", "name": "Transfer", "inputs": [
+                {"name": "to", "ty": "core::starknet::contract_address::ContractAddress"},
+                {"name": "memo", "ty": "core::option::Option::<core::felt252>"}
+            ]},
+            {"type": "impl", "name": "ERC20Impl", "interface_name": "IERC20"}
+        ]"#,
+    )
+    .unwrap();
+
+    let calldata = ["0x2", "0xa", "0xb", "0x5", "0x0", "0x0", "0x7"].map(|felt| stark_felt!(felt));
+    let expected = DecodedFunctionCall {
+        name: "transfer".to_owned(),
+        inputs: vec![
+            field(
+                "recipients",
+                "core::array::Array::<core::starknet::contract_address::ContractAddress>",
+                DecodedValue::Array(vec![felt("0xa"), felt("0xb")]),
+            ),
+            field(
+                "amount",
+                "core::integer::u256",
+                DecodedValue::Struct(vec![
+                    field("low", "core::integer::u128", felt("0x5")),
+                    field("high", "core::integer::u128", felt("0x0")),
+                ]),
+            ),
+            field(
+                "memo",
+                "core::option::Option::<core::felt252>",
+                DecodedValue::Enum { variant: "Some".to_owned(), value: Box::new(felt("0x7")) },
+            ),
+        ],
+    };
+    assert_eq!(abi.decode_call(&selector_from_name("transfer"), &calldata), Some(expected));
+
+    let decoded = abi.decode_event(&transfer_event(vec![stark_felt!("0x1"), stark_felt!("0x1")]));
+    let expected = DecodedEventContent {
+        name: "Transfer".to_owned(),
+        keys: vec![],
+        data: vec![
+            field("to", "core::starknet::contract_address::ContractAddress", felt("0x1")),
+            field(
+                "memo",
+                "core::option::Option::<core::felt252>",
+                DecodedValue::Enum {
+                    variant: "None".to_owned(),
+                    value: Box::new(DecodedValue::Struct(vec![])),
+                },
+            ),
+        ],
+    };
+    assert_eq!(decoded, Some(expected));
+
+    // An unknown variant.
+    assert_eq!(
+        abi.decode_event(&transfer_event(vec![stark_felt!("0x1"), stark_felt!("0x2")])),
+        None
+    );
+    assert_eq!(ContractAbi::from_sierra("not an abi"), None);
+}
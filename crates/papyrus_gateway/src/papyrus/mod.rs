@@ -0,0 +1,301 @@
+mod api;
+mod decoding;
+#[cfg(test)]
+#[path = "papyrus_test.rs"]
+mod papyrus_test;
+
+use std::collections::HashMap;
+
+use blockifier::abi::abi_utils::selector_from_name;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_server::RpcModule;
+use papyrus_storage::body::events::TransactionExecutionStatus;
+use papyrus_storage::body::messages::{L1ToL2MessageHash, MessageToL1Index, MessagesReader};
+use papyrus_storage::body::{BodyStorageReader, TransactionIndex};
+use papyrus_storage::db::TransactionKind;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::{StateReader, StateStorageReader};
+use papyrus_storage::{StorageReader, StorageResult, StorageTxn};
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_api::state::StateNumber;
+use starknet_api::transaction::{
+    EthAddress, InvokeTransaction, Transaction, TransactionHash, TransactionOffsetInBlock,
+};
+use tracing::instrument;
+
+use crate::api::{ContinuationToken, EventFilter, JsonRpcError};
+use crate::papyrus::api::{
+    ConsumingTransaction, DecodedEvent, DecodedEventsChunk, ExecutionStatus, JsonRpcPapyrusServer,
+    MessageStatus, MessagesToL1Chunk, SentMessageToL1,
+};
+use crate::papyrus::decoding::{ContractAbi, DecodedFunctionCall};
+use crate::reader::{filter_events, ContinuationTokenKey, EventsScanBudget};
+use crate::{internal_server_error, GatewayConfig, JsonRpcServerImpl};
+
+/// Rpc server of the methods of the node that are not part of the specification.
+pub(crate) struct JsonRpcServerPapyrusImpl {
+    storage_reader: StorageReader,
+    max_events_chunk_size: usize,
+    max_events_keys: usize,
+    events_scan_budget: EventsScanBudget,
+    continuation_token_key: ContinuationTokenKey,
+}
+
+impl JsonRpcServerImpl for JsonRpcServerPapyrusImpl {
+    fn new(config: &GatewayConfig, storage_reader: StorageReader) -> Self {
+        Self {
+            storage_reader,
+            max_events_chunk_size: config.max_events_chunk_size,
+            max_events_keys: config.max_events_keys,
+            events_scan_budget: EventsScanBudget::new(config),
+            continuation_token_key: ContinuationTokenKey::new(config),
+        }
+    }
+
+    fn into_rpc_module(self) -> RpcModule<Self> {
+        self.into_rpc()
+    }
+}
+
+impl JsonRpcPapyrusServer for JsonRpcServerPapyrusImpl {
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_decoded_events(&self, filter: EventFilter) -> Result<DecodedEventsChunk, Error> {
+        // The events and the ABIs are read in one transaction, so a revert between the reads can't
+        // decode the events by the ABIs of another chain.
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let (events, continuation_token) = filter_events(
+            &txn,
+            filter,
+            self.max_events_chunk_size,
+            self.max_events_keys,
+            self.events_scan_budget,
+            &self.continuation_token_key,
+        )?;
+
+        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+        // The events of a chunk are often emitted by a few classes.
+        let mut abis = HashMap::new();
+        let mut decoded_events = Vec::with_capacity(events.len());
+        for event in events {
+            let abi = get_contract_abi(
+                &state_reader,
+                event.block_number,
+                &event.event.from_address,
+                &mut abis,
+            )
+            .map_err(internal_server_error)?;
+            decoded_events.push(DecodedEvent {
+                decoded: abi.and_then(|abi| abi.decode_event(&event.event.content)),
+                block_hash: event.block_hash,
+                block_number: event.block_number,
+                transaction_hash: event.transaction_hash,
+                event: event.event,
+            });
+        }
+        Ok(DecodedEventsChunk { events: decoded_events, continuation_token })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_decoded_calldata(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<Option<DecodedFunctionCall>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+
+        let transaction_index = txn
+            .get_transaction_idx_by_hash(&transaction_hash)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+
+        let transaction = txn
+            .get_transaction(transaction_index)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+
+        let (address, selector, calldata) = match transaction {
+            Transaction::Invoke(InvokeTransaction::V0(tx)) => {
+                (tx.sender_address, tx.entry_point_selector, tx.calldata)
+            }
+            Transaction::Invoke(InvokeTransaction::V1(tx)) => {
+                (tx.sender_address, selector_from_name("__execute__"), tx.calldata)
+            }
+            _ => return Ok(None),
+        };
+
+        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+        let abi =
+            get_contract_abi(&state_reader, transaction_index.0, &address, &mut HashMap::new())
+                .map_err(internal_server_error)?;
+        Ok(abi.and_then(|abi| abi.decode_call(&selector, &calldata.0)))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_messages_status(
+        &self,
+        message_hashes: Vec<L1ToL2MessageHash>,
+    ) -> Result<Vec<MessageStatus>, Error> {
+        if message_hashes.len() > self.max_events_chunk_size {
+            return Err(JsonRpcError::PageSizeTooBig.into());
+        }
+
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        message_hashes
+            .into_iter()
+            .map(|message_hash| {
+                let consumed_by = match txn
+                    .get_l1_handler_by_message_hash(&message_hash)
+                    .map_err(internal_server_error)?
+                {
+                    Some(transaction_index) => {
+                        Some(get_consuming_transaction(&txn, transaction_index)?)
+                    }
+                    None => None,
+                };
+                Ok(MessageStatus { message_hash, consumed_by })
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_l2_to_l1_messages(
+        &self,
+        to_address: EthAddress,
+        chunk_size: usize,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<MessagesToL1Chunk, Error> {
+        if chunk_size > self.max_events_chunk_size {
+            return Err(JsonRpcError::PageSizeTooBig.into());
+        }
+        let from_index = match continuation_token {
+            Some(token) => parse_messages_token(&token, to_address)?,
+            None => {
+                MessageToL1Index(TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0)), 0)
+            }
+        };
+
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        // The message after the chunk, if there is one, is where the next chunk starts.
+        let mut messages = txn
+            .get_messages_to_l1(to_address, from_index, chunk_size + 1)
+            .map_err(internal_server_error)?;
+        let continuation_token = if messages.len() > chunk_size {
+            Some(messages_token(to_address, messages[chunk_size].0)?)
+        } else {
+            None
+        };
+        messages.truncate(chunk_size);
+
+        // Consecutive messages are often sent by the same transaction.
+        let mut last_transaction = None;
+        let mut sent_messages = Vec::with_capacity(messages.len());
+        for (MessageToL1Index(transaction_index, _), message) in messages {
+            let (block_hash, transaction_hash) = match last_transaction {
+                Some((index, hashes)) if index == transaction_index => hashes,
+                _ => get_transaction_hashes(&txn, transaction_index)?,
+            };
+            last_transaction = Some((transaction_index, (block_hash, transaction_hash)));
+            sent_messages.push(SentMessageToL1 {
+                block_hash,
+                block_number: transaction_index.0,
+                transaction_hash,
+                message,
+            });
+        }
+        Ok(MessagesToL1Chunk { messages: sent_messages, continuation_token })
+    }
+}
+
+// Returns the hashes of the block and of the transaction, which are stored along with the indexes
+// of the messages of the transaction.
+fn get_transaction_hashes<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_index: TransactionIndex,
+) -> Result<(BlockHash, TransactionHash), Error> {
+    let block_hash = txn
+        .get_block_header(transaction_index.0)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| internal_server_error("A message of a block without a header."))?
+        .block_hash;
+    let transaction_hash = txn
+        .get_transaction(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| internal_server_error("A message of a missing transaction."))?
+        .transaction_hash();
+    Ok((block_hash, transaction_hash))
+}
+
+fn get_consuming_transaction<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_index: TransactionIndex,
+) -> Result<ConsumingTransaction, Error> {
+    let (block_hash, transaction_hash) = get_transaction_hashes(txn, transaction_index)?;
+    let execution_status = txn
+        .get_transaction_output(transaction_index)
+        .map_err(internal_server_error)?
+        .and_then(|output| output.execution_info().map(|info| info.execution_status.clone()));
+    let (execution_status, revert_reason) = match execution_status {
+        Some(TransactionExecutionStatus::Succeeded) => (Some(ExecutionStatus::Succeeded), None),
+        Some(TransactionExecutionStatus::Reverted(reason)) => {
+            (Some(ExecutionStatus::Reverted), Some(reason))
+        }
+        None => (None, None),
+    };
+    Ok(ConsumingTransaction {
+        transaction_hash,
+        block_hash,
+        block_number: transaction_index.0,
+        execution_status,
+        revert_reason,
+    })
+}
+
+// Tokens of chunks of messages are the URL-safe base64 encoding of the JSON of the recipient and
+// the index of the first message of the next chunk.
+fn parse_messages_token(
+    token: &ContinuationToken,
+    to_address: EthAddress,
+) -> Result<MessageToL1Index, Error> {
+    let invalid_token = || Error::from(JsonRpcError::InvalidContinuationToken);
+    let bytes =
+        base64::decode_config(&token.0, base64::URL_SAFE_NO_PAD).map_err(|_| invalid_token())?;
+    let (token_address, index): (EthAddress, MessageToL1Index) =
+        serde_json::from_slice(&bytes).map_err(|_| invalid_token())?;
+    if token_address != to_address {
+        return Err(invalid_token());
+    }
+    Ok(index)
+}
+
+fn messages_token(
+    to_address: EthAddress,
+    index: MessageToL1Index,
+) -> Result<ContinuationToken, Error> {
+    let bytes = serde_json::to_vec(&(to_address, index)).map_err(internal_server_error)?;
+    Ok(ContinuationToken(base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)))
+}
+
+// Returns the ABI of the class of the contract right after the block, or None if the contract, its
+// class or the ABI of the class aren't available. The ABIs are cached by their class.
+fn get_contract_abi<'cache, Mode: TransactionKind>(
+    state_reader: &StateReader<'_, Mode>,
+    block_number: BlockNumber,
+    address: &ContractAddress,
+    cache: &'cache mut HashMap<ClassHash, Option<ContractAbi>>,
+) -> StorageResult<Option<&'cache ContractAbi>> {
+    let state_number = StateNumber::right_after_block(block_number);
+    let Some(class_hash) = state_reader.get_class_hash_at(state_number, address)? else {
+        return Ok(None);
+    };
+    if !cache.contains_key(&class_hash) {
+        let abi = match state_reader.get_class_definition_at(state_number, &class_hash)? {
+            Some(class) => ContractAbi::from_sierra(&class.abi),
+            None => state_reader
+                .get_deprecated_class_definition_at(state_number, &class_hash)?
+                .and_then(|class| class.abi)
+                .map(|abi| ContractAbi::from_deprecated(&abi)),
+        };
+        cache.insert(class_hash, abi);
+    }
+    Ok(cache[&class_hash].as_ref())
+}
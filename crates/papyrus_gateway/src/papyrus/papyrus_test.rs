@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use blockifier::abi::abi_utils::selector_from_name;
+use indexmap::IndexMap;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::types::error::ErrorObject;
+use papyrus_storage::body::events::{TransactionExecutionInfo, TransactionExecutionStatus};
+use papyrus_storage::body::messages::L1ToL2MessageHash;
+use papyrus_storage::body::{BlockExecutionInfo, BodyStorageWriter};
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use starknet_api::block::BlockHeader;
+use starknet_api::core::{ContractAddress, PatriciaKey};
+use starknet_api::deprecated_contract_class::{
+    ContractClassAbiEntry, EventAbiEntry, FunctionAbiEntry, FunctionAbiEntryType,
+    FunctionAbiEntryWithType, TypedParameter,
+};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::transaction::{
+    Calldata, EthAddress, Event, EventContent, EventData, EventKey, Fee, InvokeTransaction,
+    InvokeTransactionOutput, InvokeTransactionV1, L1HandlerTransaction, L1HandlerTransactionOutput,
+    L2ToL1Payload, MessageToL1, Transaction, TransactionHash, TransactionOutput,
+};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::{get_rng, get_test_body, get_test_state_diff, GetTestInstance};
+use web3::types::{H160, H256};
+
+use crate::api::{ContinuationToken, EventFilter, JsonRpcError};
+use crate::papyrus::api::{
+    ConsumingTransaction, DecodedEventsChunk, ExecutionStatus, MessageStatus, MessagesToL1Chunk,
+};
+use crate::papyrus::decoding::{DecodedField, DecodedFunctionCall, DecodedValue};
+use crate::papyrus::JsonRpcServerPapyrusImpl;
+use crate::test_utils::get_test_rpc_server_and_storage_writer;
+
+fn param(name: &str) -> TypedParameter {
+    TypedParameter { name: name.to_owned(), r#type: "felt".to_owned() }
+}
+
+fn felt_field(name: &str, value: StarkFelt) -> DecodedField {
+    DecodedField {
+        name: name.to_owned(),
+        r#type: "felt".to_owned(),
+        value: DecodedValue::Felt(value),
+    }
+}
+
+#[tokio::test]
+async fn get_decoded_events_and_calldata() {
+    let (module, mut storage_writer) =
+        get_test_rpc_server_and_storage_writer::<JsonRpcServerPapyrusImpl>();
+
+    // A contract whose class has an ABI, and a contract that isn't deployed.
+    let mut diff = get_test_state_diff();
+    let (address, class_hash) = diff.deployed_contracts.get_index(0).unwrap();
+    let (address, class_hash) = (*address, *class_hash);
+    diff.deprecated_declared_classes.get_mut(&class_hash).unwrap().abi = Some(vec![
+        ContractClassAbiEntry::Event(EventAbiEntry {
+            name: "Transfer".to_owned(),
+            keys: vec![],
+            data: vec![param("from_"), param("to")],
+        }),
+        ContractClassAbiEntry::Function(FunctionAbiEntryWithType {
+            r#type: FunctionAbiEntryType::Regular,
+            entry: FunctionAbiEntry {
+                name: "__execute__".to_owned(),
+                inputs: vec![param("to"), param("selector")],
+                outputs: vec![],
+            },
+        }),
+    ]);
+    let unknown_address = ContractAddress(patricia_key!("0x1234"));
+
+    let content = EventContent {
+        keys: vec![EventKey(selector_from_name("Transfer").0)],
+        data: EventData(vec![stark_felt!("0x1"), stark_felt!("0x2")]),
+    };
+    let events = vec![
+        Event { from_address: address, content: content.clone() },
+        Event { from_address: unknown_address, content },
+    ];
+    let transaction = InvokeTransactionV1 {
+        sender_address: address,
+        calldata: Calldata(Arc::new(vec![stark_felt!("0x3"), stark_felt!("0x4")])),
+        ..InvokeTransactionV1::get_test_instance(&mut get_rng(None))
+    };
+    let transaction_hash = transaction.transaction_hash;
+    let mut body = get_test_body(None, 1, None, None, None);
+    body.transactions[0] = Transaction::Invoke(InvokeTransaction::V1(transaction));
+    body.transaction_outputs[0] = TransactionOutput::Invoke(InvokeTransactionOutput {
+        actual_fee: Fee::default(),
+        messages_sent: vec![],
+        events: events.clone(),
+    });
+
+    let header = BlockHeader::default();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_body(header.block_number, body)
+        .unwrap()
+        .append_state_diff(header.block_number, diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let filter = EventFilter {
+        from_block: None,
+        to_block: None,
+        continuation_token: None,
+        chunk_size: 10,
+        address: None,
+        keys: vec![],
+    };
+    let res =
+        module.call::<_, DecodedEventsChunk>("papyrus_getDecodedEvents", [filter]).await.unwrap();
+    assert_eq!(res.continuation_token, None);
+    assert_eq!(res.events.len(), 2);
+    for (decoded_event, event) in res.events.iter().zip(events) {
+        assert_eq!(decoded_event.block_number, header.block_number);
+        assert_eq!(decoded_event.transaction_hash, transaction_hash);
+        assert_eq!(decoded_event.event, event);
+    }
+    let decoded = res.events[0].decoded.as_ref().unwrap();
+    assert_eq!(decoded.name, "Transfer");
+    assert_eq!(
+        decoded.data,
+        vec![felt_field("from_", stark_felt!("0x1")), felt_field("to", stark_felt!("0x2"))]
+    );
+    // The class of the contract isn't known.
+    assert_eq!(res.events[1].decoded, None);
+
+    let res = module
+        .call::<_, Option<DecodedFunctionCall>>("papyrus_getDecodedCalldata", [transaction_hash])
+        .await
+        .unwrap();
+    let expected = DecodedFunctionCall {
+        name: "__execute__".to_owned(),
+        inputs: vec![
+            felt_field("to", stark_felt!("0x3")),
+            felt_field("selector", stark_felt!("0x4")),
+        ],
+    };
+    assert_eq!(res, Some(expected));
+
+    let err = module
+        .call::<_, Option<DecodedFunctionCall>>(
+            "papyrus_getDecodedCalldata",
+            [TransactionHash(StarkHash::from(1))],
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::TransactionHashNotFound as i32,
+        JsonRpcError::TransactionHashNotFound.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn get_messages() {
+    let (module, mut storage_writer) =
+        get_test_rpc_server_and_storage_writer::<JsonRpcServerPapyrusImpl>();
+
+    // An L1 handler that consumes a message and sends three messages to L1.
+    let transaction = L1HandlerTransaction {
+        calldata: Calldata(Arc::new(vec![stark_felt!("0x1"), stark_felt!("0x2")])),
+        ..L1HandlerTransaction::get_test_instance(&mut get_rng(None))
+    };
+    let message_hash = L1ToL2MessageHash::of_l1_handler(&transaction).unwrap();
+    let transaction_hash = transaction.transaction_hash;
+    let to_address = EthAddress(H160::from_low_u64_be(1));
+    let messages_sent = [stark_felt!("0x3"), stark_felt!("0x4"), stark_felt!("0x5")]
+        .into_iter()
+        .map(|felt| MessageToL1 {
+            from_address: transaction.contract_address,
+            to_address,
+            payload: L2ToL1Payload(vec![felt]),
+        })
+        .collect::<Vec<_>>();
+    let mut body = get_test_body(None, 1, None, None, None);
+    body.transactions[0] = Transaction::L1Handler(transaction);
+    body.transaction_outputs[0] = TransactionOutput::L1Handler(L1HandlerTransactionOutput {
+        actual_fee: Fee::default(),
+        messages_sent: messages_sent.clone(),
+        events: vec![],
+    });
+    let execution_info = TransactionExecutionInfo {
+        execution_status: TransactionExecutionStatus::Reverted("reason".to_owned()),
+        ..Default::default()
+    };
+
+    let header = BlockHeader::default();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_body_with_execution_info(
+            header.block_number,
+            body,
+            BlockExecutionInfo {
+                starknet_version: None,
+                transaction_execution_infos: Some(vec![execution_info]),
+            },
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let unknown_hash = L1ToL2MessageHash(H256::from_low_u64_be(1));
+    let res = module
+        .call::<_, Vec<MessageStatus>>(
+            "papyrus_getMessagesStatus",
+            [vec![message_hash, unknown_hash]],
+        )
+        .await
+        .unwrap();
+    let consumed_by = ConsumingTransaction {
+        transaction_hash,
+        block_hash: header.block_hash,
+        block_number: header.block_number,
+        execution_status: Some(ExecutionStatus::Reverted),
+        revert_reason: Some("reason".to_owned()),
+    };
+    assert_eq!(
+        res,
+        vec![
+            MessageStatus { message_hash, consumed_by: Some(consumed_by) },
+            MessageStatus { message_hash: unknown_hash, consumed_by: None },
+        ]
+    );
+
+    // The messages in chunks of two.
+    let res = module
+        .call::<_, MessagesToL1Chunk>(
+            "papyrus_getL2ToL1Messages",
+            (to_address, 2, None::<ContinuationToken>),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    for (sent_message, message) in res.messages.iter().zip(&messages_sent) {
+        assert_eq!(sent_message.block_hash, header.block_hash);
+        assert_eq!(sent_message.transaction_hash, transaction_hash);
+        assert_eq!(&sent_message.message, message);
+    }
+    let continuation_token = res.continuation_token.unwrap();
+    let res = module
+        .call::<_, MessagesToL1Chunk>(
+            "papyrus_getL2ToL1Messages",
+            (to_address, 2, Some(continuation_token.clone())),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.continuation_token, None);
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].message, messages_sent[2]);
+
+    // A token of another recipient.
+    let other_address = EthAddress(H160::from_low_u64_be(2));
+    let err = module
+        .call::<_, MessagesToL1Chunk>(
+            "papyrus_getL2ToL1Messages",
+            (other_address, 2, Some(continuation_token)),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::InvalidContinuationToken as i32,
+        JsonRpcError::InvalidContinuationToken.to_string(),
+        None::<()>,
+    ));
+}
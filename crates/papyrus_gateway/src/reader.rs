@@ -0,0 +1,660 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use jsonrpsee::core::Error;
+use once_cell::sync::OnceCell;
+use papyrus_storage::body::events::{EventIndex, EventsReader, ThinTransactionOutput};
+use papyrus_storage::body::{BodyStorageReader, StarknetVersion, TransactionIndex};
+use papyrus_storage::checkpoint::CheckpointStorageReader;
+use papyrus_storage::db::TransactionKind;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::trace::{TraceStorageReader, TransactionTrace};
+use papyrus_storage::{StorageResult, StorageTxn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use starknet_api::block::{BlockHash, BlockHeader, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
+use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
+use starknet_api::hash::{StarkFelt, StarkHash, GENESIS_HASH};
+use starknet_api::state::{ContractClass, StateNumber, StorageKey, ThinStateDiff};
+use starknet_api::transaction::{
+    Event, EventIndexInTransactionOutput, Transaction, TransactionHash, TransactionOffsetInBlock,
+};
+use tracing::debug;
+
+use crate::api::{BlockHashOrNumber, BlockId, ContinuationToken, EventFilter, JsonRpcError, Tag};
+use crate::{internal_server_error, GatewayConfig};
+
+pub(crate) fn get_block_number<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_id: BlockId,
+) -> Result<BlockNumber, Error> {
+    Ok(match block_id {
+        BlockId::HashOrNumber(BlockHashOrNumber::Hash(block_hash)) => txn
+            .get_block_number_by_hash(&block_hash)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?,
+        BlockId::HashOrNumber(BlockHashOrNumber::Number(block_number)) => {
+            // Check that the block exists.
+            let checkpoint = txn.get_checkpoint().map_err(internal_server_error)?;
+            if checkpoint.map_or(false, |checkpoint| block_number < checkpoint) {
+                return Err(Error::from(JsonRpcError::BlockBeforeCheckpoint));
+            }
+            let last_block_number = get_latest_block_number(txn)?
+                .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
+            if block_number > last_block_number {
+                return Err(Error::from(JsonRpcError::BlockNotFound));
+            }
+            block_number
+        }
+        BlockId::Tag(Tag::Latest) => {
+            get_latest_block_number(txn)?.ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?
+        }
+        BlockId::Tag(Tag::Pending) => {
+            todo!("Pending tag is not supported yet.")
+        }
+    })
+}
+
+pub(crate) fn get_latest_block_number<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+) -> Result<Option<BlockNumber>, Error> {
+    let latest_block_number = txn.get_header_marker().map_err(internal_server_error)?.prev();
+    // A storage that starts at a checkpoint has no blocks until the checkpoint block is synced.
+    let checkpoint = txn.get_checkpoint().map_err(internal_server_error)?;
+    Ok(latest_block_number.filter(|block_number| Some(*block_number) >= checkpoint))
+}
+
+pub(crate) fn get_block_header<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<BlockHeader, Error> {
+    txn.get_block_header(block_number)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))
+}
+
+pub(crate) fn get_starknet_version<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<Option<StarknetVersion>, Error> {
+    txn.get_starknet_version(block_number).map_err(internal_server_error)
+}
+
+pub(crate) fn get_block_transactions<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<Vec<Transaction>, Error> {
+    txn.get_block_transactions(block_number)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| body_not_found(txn, block_number, JsonRpcError::BlockNotFound))
+}
+
+fn get_transaction_index<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_hash: &TransactionHash,
+) -> Result<TransactionIndex, Error> {
+    txn.get_transaction_idx_by_hash(transaction_hash)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))
+}
+
+pub(crate) fn get_transaction_by_hash<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_hash: &TransactionHash,
+) -> Result<Transaction, Error> {
+    let transaction_index = get_transaction_index(txn, transaction_hash)?;
+    txn.get_transaction(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))
+}
+
+pub(crate) fn get_transaction_by_index<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    index: TransactionOffsetInBlock,
+) -> Result<Transaction, Error> {
+    txn.get_transaction(TransactionIndex(block_number, index))
+        .map_err(internal_server_error)?
+        .ok_or_else(|| body_not_found(txn, block_number, JsonRpcError::InvalidTransactionIndex))
+}
+
+pub(crate) fn get_transaction_output<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_hash: &TransactionHash,
+) -> Result<ThinTransactionOutput, Error> {
+    let transaction_index = get_transaction_index(txn, transaction_hash)?;
+    txn.get_transaction_output(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))
+}
+
+/// The stored parts of the receipt of a transaction, which each version of the API converts to its
+/// receipt.
+pub(crate) struct StoredReceipt {
+    pub(crate) block_hash: BlockHash,
+    pub(crate) block_number: BlockNumber,
+    pub(crate) transaction: Transaction,
+    pub(crate) output: ThinTransactionOutput,
+    pub(crate) events: Vec<Event>,
+}
+
+pub(crate) fn get_transaction_receipt<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_hash: &TransactionHash,
+) -> Result<StoredReceipt, Error> {
+    let transaction_index = get_transaction_index(txn, transaction_hash)?;
+    let block_number = transaction_index.0;
+    let header = get_block_header(txn, block_number).map_err(internal_server_error)?;
+
+    let transaction = txn
+        .get_transaction(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+
+    let output = txn
+        .get_transaction_output(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+
+    let events = txn
+        .get_transaction_events(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+
+    Ok(StoredReceipt { block_hash: header.block_hash, block_number, transaction, output, events })
+}
+
+pub(crate) fn get_block_receipts<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<Vec<StoredReceipt>, Error> {
+    let header = get_block_header(txn, block_number)?;
+    let transactions = get_block_transactions(txn, block_number)?;
+
+    let outputs = txn
+        .get_block_transaction_outputs(block_number)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| body_not_found(txn, block_number, JsonRpcError::BlockNotFound))?;
+
+    transactions
+        .into_iter()
+        .zip(outputs)
+        .enumerate()
+        .map(|(offset, (transaction, output))| {
+            let events = txn
+                .get_transaction_events(TransactionIndex(
+                    block_number,
+                    TransactionOffsetInBlock(offset),
+                ))
+                .map_err(internal_server_error)?
+                .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
+            Ok(StoredReceipt {
+                block_hash: header.block_hash,
+                block_number,
+                transaction,
+                output,
+                events,
+            })
+        })
+        .collect()
+}
+
+/// The stored parts of the state update of a block, which each version of the API converts to its
+/// state update.
+pub(crate) struct StoredStateUpdate {
+    pub(crate) block_hash: BlockHash,
+    pub(crate) new_root: GlobalRoot,
+    pub(crate) old_root: GlobalRoot,
+    pub(crate) state_diff: ThinStateDiff,
+}
+
+pub(crate) fn get_state_update<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<StoredStateUpdate, Error> {
+    // Get the block header for the block hash and state root.
+    let header = get_block_header(txn, block_number)?;
+
+    // Get the old root.
+    let parent_block_number =
+        get_block_number(txn, BlockId::HashOrNumber(BlockHashOrNumber::Hash(header.parent_hash)));
+    let mut old_root =
+        GlobalRoot(StarkHash::try_from(GENESIS_HASH).map_err(internal_server_error)?);
+    if let Ok(parent_block_number) = parent_block_number {
+        old_root = get_block_header(txn, parent_block_number)?.state_root;
+    }
+
+    // Get the block state diff.
+    let state_diff = txn
+        .get_state_diff(block_number)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
+
+    Ok(StoredStateUpdate {
+        block_hash: header.block_hash,
+        new_root: header.state_root,
+        old_root,
+        state_diff,
+    })
+}
+
+pub(crate) fn get_storage_at<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    contract_address: &ContractAddress,
+    key: &StorageKey,
+) -> Result<StarkFelt, Error> {
+    // Check that the contract exists.
+    get_class_hash_at(txn, block_number, contract_address)?;
+
+    let state = StateNumber::right_after_block(block_number);
+    let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+    state_reader.get_storage_at(state, contract_address, key).map_err(internal_server_error)
+}
+
+pub(crate) fn get_class_hash_at<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    contract_address: &ContractAddress,
+) -> Result<ClassHash, Error> {
+    let state = StateNumber::right_after_block(block_number);
+    let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+    state_reader
+        .get_class_hash_at(state, contract_address)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))
+}
+
+pub(crate) fn get_nonce_at<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    contract_address: &ContractAddress,
+) -> Result<Nonce, Error> {
+    let state = StateNumber::right_after_block(block_number);
+    let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+    state_reader
+        .get_nonce_at(state, contract_address)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))
+}
+
+/// A stored class, which each version of the API converts to its class.
+pub(crate) enum StoredClass {
+    Cairo0(DeprecatedContractClass),
+    Sierra(ContractClass),
+}
+
+// Returns the class as of the block, or `not_found` if it wasn't declared up to the block.
+pub(crate) fn get_class<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    class_hash: &ClassHash,
+    not_found: JsonRpcError,
+) -> Result<StoredClass, Error> {
+    let state_number = StateNumber::right_after_block(block_number);
+    let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+
+    // The class might be a deprecated class. Search it first in the declared classes and if not
+    // found, search in the deprecated classes.
+    if let Some(class) = state_reader
+        .get_class_definition_at(state_number, class_hash)
+        .map_err(internal_server_error)?
+    {
+        return Ok(StoredClass::Sierra(class));
+    }
+    let class = state_reader
+        .get_deprecated_class_definition_at(state_number, class_hash)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| class_not_found(txn, block_number, class_hash, not_found))?;
+    Ok(StoredClass::Cairo0(class))
+}
+
+pub(crate) fn get_transaction_trace<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    transaction_hash: &TransactionHash,
+) -> Result<(Transaction, TransactionTrace), Error> {
+    let transaction_index = get_transaction_index(txn, transaction_hash)?;
+
+    let transaction = txn
+        .get_transaction(transaction_index)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+
+    let trace =
+        txn.get_transaction_trace(transaction_index).map_err(internal_server_error)?.ok_or_else(
+            || trace_not_found(txn, transaction_index.0, JsonRpcError::TransactionHashNotFound),
+        )?;
+
+    Ok((transaction, trace))
+}
+
+pub(crate) fn get_block_traces<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<Vec<(Transaction, TransactionTrace)>, Error> {
+    let transactions = get_block_transactions(txn, block_number)?;
+
+    let traces = txn
+        .get_block_traces(block_number)
+        .map_err(internal_server_error)?
+        .ok_or_else(|| trace_not_found(txn, block_number, JsonRpcError::BlockNotFound))?;
+
+    Ok(transactions.into_iter().zip(traces).collect())
+}
+
+// Returns ClassPendingDownload if the class is declared up to the block but its definition wasn't
+// stored yet, and `not_found` otherwise.
+fn class_not_found<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    class_hash: &ClassHash,
+    not_found: JsonRpcError,
+) -> Error {
+    match is_class_pending(txn, block_number, class_hash) {
+        Ok(true) => Error::from(JsonRpcError::ClassPendingDownload),
+        Ok(false) => Error::from(not_found),
+        Err(err) => internal_server_error(err),
+    }
+}
+
+fn is_class_pending<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    class_hash: &ClassHash,
+) -> StorageResult<bool> {
+    // The compiled class hashes are stored with the state diffs, so they index the classes that
+    // are declared even if their definitions weren't stored. Cairo 0 classes have no such index.
+    let state_number = StateNumber::right_after_block(block_number);
+    let state_reader = txn.get_state_reader()?;
+    Ok(state_reader.get_compiled_class_hash_at(state_number, class_hash)?.is_some()
+        && state_reader.get_class_definition_at(state_number, class_hash)?.is_none())
+}
+
+// Returns BlockBodyPendingDownload if the header of the block is stored but its body wasn't stored
+// yet, and `not_found` otherwise.
+fn body_not_found<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    not_found: JsonRpcError,
+) -> Error {
+    match is_body_pending(txn, block_number) {
+        Ok(true) => Error::from(JsonRpcError::BlockBodyPendingDownload),
+        Ok(false) => Error::from(not_found),
+        Err(err) => internal_server_error(err),
+    }
+}
+
+fn is_body_pending<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> StorageResult<bool> {
+    Ok(txn.get_body_marker()? <= block_number && block_number < txn.get_header_marker()?)
+}
+
+// Returns TracePendingDownload if the body of the block is stored but its traces weren't stored
+// yet, and `not_found` otherwise.
+fn trace_not_found<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    not_found: JsonRpcError,
+) -> Error {
+    match is_trace_pending(txn, block_number) {
+        Ok(true) => Error::from(JsonRpcError::TracePendingDownload),
+        Ok(false) => Error::from(not_found),
+        Err(err) => internal_server_error(err),
+    }
+}
+
+fn is_trace_pending<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> StorageResult<bool> {
+    Ok(txn.get_trace_marker()? <= block_number && block_number < txn.get_body_marker()?)
+}
+
+pub(crate) struct ContinuationTokenAsStruct(pub(crate) EventIndex);
+
+// The version of the format of continuation tokens, which is the first byte of a decoded token.
+const CONTINUATION_TOKEN_VERSION: u8 = 2;
+
+// The length in bytes of the HMAC-SHA256 tag that ends a decoded token.
+const CONTINUATION_TOKEN_TAG_LENGTH: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// The key continuation tokens are authenticated with, so that clients can't make up positions or
+// reuse a token with another filter. It is the secret of the config, or a random secret of the
+// process if the config has none.
+#[derive(Clone)]
+pub(crate) struct ContinuationTokenKey(Arc<[u8]>);
+
+impl ContinuationTokenKey {
+    pub(crate) fn new(config: &GatewayConfig) -> Self {
+        static RANDOM_SECRET: OnceCell<[u8; 32]> = OnceCell::new();
+        match &config.continuation_token_secret {
+            Some(secret) => Self(secret.as_bytes().into()),
+            None => Self(RANDOM_SECRET.get_or_init(rand::random).as_slice().into()),
+        }
+    }
+
+    // The tag of a token with the content, for the filter.
+    fn mac(&self, content: &[u8], filter: &EventFilter) -> Result<HmacSha256, Error> {
+        let mut mac = HmacSha256::new_from_slice(&self.0).map_err(internal_server_error)?;
+        mac.update(content);
+        mac.update(&filter_bytes(filter)?);
+        Ok(mac)
+    }
+}
+
+// Where a continuation token resumes the scan. Other kinds of positions, like offsets in the
+// pending block, can be added as new variants without invalidating the existing tokens.
+#[derive(Debug, Deserialize, Serialize)]
+enum ScanPosition {
+    Event(EventIndex),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ContinuationTokenContent {
+    position: ScanPosition,
+}
+
+impl ContinuationToken {
+    // Tokens are the URL-safe base64 encoding of the version, followed by the JSON of the content
+    // and by the HMAC-SHA256 tag of both with the filter of the request that returned the token.
+    fn parse(
+        &self,
+        filter: &EventFilter,
+        key: &ContinuationTokenKey,
+    ) -> Result<ContinuationTokenAsStruct, Error> {
+        let invalid_token = || Error::from(JsonRpcError::InvalidContinuationToken);
+        let bytes =
+            base64::decode_config(&self.0, base64::URL_SAFE_NO_PAD).map_err(|_| invalid_token())?;
+        let Some(content_length) = bytes.len().checked_sub(CONTINUATION_TOKEN_TAG_LENGTH) else {
+            return Err(invalid_token());
+        };
+        let (content, tag) = bytes.split_at(content_length);
+        if key.mac(content, filter)?.verify_slice(tag).is_err() {
+            return Err(invalid_token());
+        }
+        let Some((&CONTINUATION_TOKEN_VERSION, content)) = content.split_first() else {
+            return Err(invalid_token());
+        };
+        let content: ContinuationTokenContent =
+            serde_json::from_slice(content).map_err(|_| invalid_token())?;
+
+        match content.position {
+            ScanPosition::Event(event_index) => Ok(ContinuationTokenAsStruct(event_index)),
+        }
+    }
+
+    pub(crate) fn new(
+        ct: ContinuationTokenAsStruct,
+        filter: &EventFilter,
+        key: &ContinuationTokenKey,
+    ) -> Result<Self, Error> {
+        let content = ContinuationTokenContent { position: ScanPosition::Event(ct.0) };
+        let mut bytes = vec![CONTINUATION_TOKEN_VERSION];
+        serde_json::to_writer(&mut bytes, &content).map_err(internal_server_error)?;
+        let tag = key.mac(&bytes, filter)?.finalize().into_bytes();
+        bytes.extend_from_slice(&tag);
+        Ok(Self(base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)))
+    }
+}
+
+// The parts of the filter that choose the events: the block range, the address and the keys. The
+// chunk size and the continuation token don't change the matching events.
+fn filter_bytes(filter: &EventFilter) -> Result<Vec<u8>, Error> {
+    let keys = filter
+        .keys
+        .iter()
+        .map(|keys| {
+            let mut keys = keys.iter().collect::<Vec<_>>();
+            keys.sort_unstable_by(|key, other_key| key.0.bytes().cmp(other_key.0.bytes()));
+            keys
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_vec(&(&filter.from_block, &filter.to_block, &filter.address, keys))
+        .map_err(internal_server_error)
+}
+
+// An event that matches the filter of a getEvents request.
+pub(crate) struct FilteredEvent {
+    pub(crate) block_hash: BlockHash,
+    pub(crate) block_number: BlockNumber,
+    pub(crate) transaction_hash: TransactionHash,
+    pub(crate) event: Event,
+}
+
+// How much work a getEvents request may do before returning.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EventsScanBudget {
+    max_events: usize,
+    max_time: Duration,
+}
+
+impl EventsScanBudget {
+    pub(crate) fn new(config: &GatewayConfig) -> Self {
+        EventsScanBudget {
+            max_events: config.max_events_scanned,
+            max_time: Duration::from_millis(config.max_events_scan_millis),
+        }
+    }
+}
+
+// Returns the events that match the filter, up to the chunk size of the filter, and a continuation
+// token if there are more matching events. If the scan budget runs out first, returns the events
+// found so far and a continuation token pointing to the first event that wasn't examined.
+pub(crate) fn filter_events<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    filter: EventFilter,
+    max_events_chunk_size: usize,
+    max_events_keys: usize,
+    scan_budget: EventsScanBudget,
+    continuation_token_key: &ContinuationTokenKey,
+) -> Result<(Vec<FilteredEvent>, Option<ContinuationToken>), Error> {
+    let scan_start = Instant::now();
+    // Check the chunk size.
+    if filter.chunk_size > max_events_chunk_size {
+        return Err(Error::from(JsonRpcError::PageSizeTooBig));
+    }
+    // Check the number of keys.
+    if filter.keys.len() > max_events_keys {
+        return Err(Error::from(JsonRpcError::TooManyKeysInFilter));
+    }
+
+    // Get the requested block numbers.
+    let from_block_number =
+        filter.from_block.map_or(Ok(BlockNumber(0)), |block_id| get_block_number(txn, block_id))?;
+    let maybe_to_block_number =
+        filter.to_block.map_or(get_latest_block_number(txn), |block_id| {
+            get_block_number(txn, block_id).map(Some)
+        })?;
+    if maybe_to_block_number.is_none() {
+        // There are no blocks.
+        return Ok((vec![], None));
+    }
+    let to_block_number = maybe_to_block_number.unwrap();
+    if from_block_number > to_block_number {
+        return Ok((vec![], None));
+    }
+
+    // Get the event index. If there's a continuation token we take the event index from there.
+    // Otherwise, we take the first index in the from_block_number.
+    let event_index = match filter.continuation_token {
+        Some(ref token) => token.parse(&filter, continuation_token_key)?.0,
+        None => EventIndex(
+            TransactionIndex(from_block_number, TransactionOffsetInBlock(0)),
+            EventIndexInTransactionOutput(0),
+        ),
+    };
+
+    // Collect the requested events.
+    // Once we collected enough events, we continue to check if there are any more events
+    // corresponding to the requested filter. If there are, we return a continuation token
+    // pointing to the next relevant event. Otherwise, we return a continuation token None.
+    let mut filtered_events = vec![];
+    let mut scanned_events = 0;
+    for ((from_address, event_index), content) in txn
+        .iter_events(filter.address, event_index, to_block_number)
+        .map_err(internal_server_error)?
+    {
+        let block_number = (event_index.0).0;
+        if block_number > to_block_number {
+            break;
+        }
+        if filter.address.is_some() && from_address != filter.address.unwrap() {
+            break;
+        }
+        // At least one event is examined, so that every request makes progress.
+        if scanned_events > 0
+            && (scanned_events >= scan_budget.max_events
+                || scan_start.elapsed() >= scan_budget.max_time)
+        {
+            debug!("The scan budget of getEvents ran out after {scanned_events} events.");
+            return Ok((
+                filtered_events,
+                Some(ContinuationToken::new(
+                    ContinuationTokenAsStruct(event_index),
+                    &filter,
+                    continuation_token_key,
+                )?),
+            ));
+        }
+        scanned_events += 1;
+        // TODO: Consider changing empty sets in the filer keys to None.
+        if filter.keys.iter().enumerate().all(|(i, keys)| {
+            content.keys.len() > i && (keys.is_empty() || keys.contains(&content.keys[i]))
+        }) {
+            if filtered_events.len() == filter.chunk_size {
+                return Ok((
+                    filtered_events,
+                    Some(ContinuationToken::new(
+                        ContinuationTokenAsStruct(event_index),
+                        &filter,
+                        continuation_token_key,
+                    )?),
+                ));
+            }
+            let header = txn
+                .get_block_header(block_number)
+                .map_err(internal_server_error)?
+                .ok_or_else(|| internal_server_error("Unknown internal error."))?;
+            let transaction = txn
+                .get_transaction(event_index.0)
+                .map_err(internal_server_error)?
+                .ok_or_else(|| internal_server_error("Unknown internal error."))?;
+            let emitted_event = FilteredEvent {
+                block_hash: header.block_hash,
+                block_number,
+                transaction_hash: transaction.transaction_hash(),
+                event: Event { from_address, content },
+            };
+            filtered_events.push(emitted_event);
+        }
+    }
+
+    Ok((filtered_events, None))
+}
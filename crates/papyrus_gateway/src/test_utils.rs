@@ -1,10 +1,15 @@
+use std::collections::HashMap;
+
 use jsonrpsee::http_server::RpcModule;
 use jsonschema::JSONSchema;
+use papyrus_execution::ExecutionConfig;
 use papyrus_storage::test_utils::get_test_storage;
 use papyrus_storage::StorageWriter;
-use starknet_api::core::ChainId;
+use starknet_api::core::{ChainId, ContractAddress, PatriciaKey};
+use starknet_api::hash::StarkHash;
+use starknet_api::patricia_key;
 
-use crate::{GatewayConfig, JsonRpcServer, JsonRpcServerImpl};
+use crate::{ApiVersion, GatewayConfig, JsonRpcServerImpl, LimitsConfig};
 
 pub fn get_test_gateway_config() -> GatewayConfig {
     GatewayConfig {
@@ -12,27 +17,53 @@ pub fn get_test_gateway_config() -> GatewayConfig {
         server_address: String::from("127.0.0.1:0"),
         max_events_chunk_size: 10,
         max_events_keys: 10,
+        max_events_scanned: 10_000,
+        max_events_scan_millis: 10_000,
+        execution_config: get_test_execution_config(),
+        default_version: ApiVersion::V0_4,
+        limits: get_test_limits_config(),
+        serve_feeder_gateway: false,
+        continuation_token_secret: Some("test secret".to_owned()),
+    }
+}
+
+fn get_test_limits_config() -> LimitsConfig {
+    LimitsConfig {
+        max_request_body_size: 10 * 1024 * 1024,
+        max_response_body_size: 10 * 1024 * 1024,
+        max_concurrent_calls: 100,
+        max_concurrent_batch_calls: 10,
+        ip_rate_limit: None,
+        api_key_rate_limits: HashMap::new(),
+        method_allowlist: None,
+        method_denylist: vec![],
     }
 }
 
-pub(crate) fn get_test_rpc_server_and_storage_writer()
--> (RpcModule<JsonRpcServerImpl>, StorageWriter) {
+fn get_test_execution_config() -> ExecutionConfig {
+    ExecutionConfig {
+        fee_contract_address: ContractAddress(patricia_key!("0x1001")),
+        invoke_tx_max_n_steps: 1_000_000,
+        validate_tx_max_n_steps: 1_000_000,
+        max_recursion_depth: 50,
+    }
+}
+
+pub(crate) fn get_test_rpc_server_and_storage_writer<T: JsonRpcServerImpl>()
+-> (RpcModule<T>, StorageWriter) {
     let (storage_reader, storage_writer) = get_test_storage();
-    let config = get_test_gateway_config();
-    (
-        JsonRpcServerImpl {
-            chain_id: config.chain_id,
-            storage_reader,
-            max_events_chunk_size: config.max_events_chunk_size,
-            max_events_keys: config.max_events_keys,
-        }
-        .into_rpc(),
-        storage_writer,
-    )
+    (T::new(&get_test_gateway_config(), storage_reader).into_rpc_module(), storage_writer)
 }
 
-pub async fn get_starknet_spec_api_schema(component_names: &[&str]) -> JSONSchema {
-    let target = "./resources/starknet_api_openrpc.json";
+pub async fn get_starknet_spec_api_schema(
+    component_names: &[&str],
+    version: ApiVersion,
+) -> JSONSchema {
+    let spec_version = match version {
+        ApiVersion::V0_3 => "V0_3_0",
+        ApiVersion::V0_4 => "V0_4_0",
+    };
+    let target = format!("./resources/{spec_version}/starknet_api_openrpc.json");
     let text = std::fs::read_to_string(target).unwrap();
     let spec: serde_json::Value = serde_json::from_str(&text).unwrap();
 
@@ -0,0 +1,343 @@
+// Only the types that changed in version 0.4 are defined here, the others are shared with it.
+mod api;
+mod block;
+mod transaction;
+#[cfg(test)]
+#[path = "v0_3_test.rs"]
+mod v0_3_test;
+
+use jsonrpsee::core::{async_trait, Error};
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::http_server::RpcModule;
+use papyrus_execution::{estimate_fee, execute_call, ExecutionConfig};
+use papyrus_storage::db::TransactionKind;
+use papyrus_storage::{StorageReader, StorageTxn};
+use starknet_api::block::{BlockNumber, BlockStatus};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{TransactionHash, TransactionOffsetInBlock};
+use tracing::instrument;
+
+use crate::api::{BlockHashAndNumber, BlockId, EventFilter, JsonRpcError};
+use crate::reader::{
+    self, filter_events, get_block_number, get_latest_block_number, ContinuationTokenKey,
+    EventsScanBudget, StoredClass, StoredReceipt,
+};
+use crate::v0_3::api::JsonRpcV0_3Server;
+use crate::v0_3::block::{Block, BlockHeader};
+use crate::v0_3::transaction::{TransactionReceipt, TransactionReceiptWithStatus};
+use crate::v0_4::api::{EventsChunk, GatewayContractClass};
+use crate::v0_4::execution::{
+    to_executable_transaction, BroadcastedTransaction, FeeEstimate, FunctionCall,
+};
+use crate::v0_4::state::StateUpdate;
+use crate::v0_4::transaction::{
+    Event, TransactionOutput, TransactionStatus, TransactionWithType, Transactions,
+};
+use crate::{execution_error, internal_server_error, GatewayConfig, JsonRpcServerImpl};
+
+/// Rpc server of version 0.3 of the specification.
+pub(crate) struct JsonRpcServerV0_3Impl {
+    chain_id: ChainId,
+    storage_reader: StorageReader,
+    max_events_chunk_size: usize,
+    max_events_keys: usize,
+    events_scan_budget: EventsScanBudget,
+    continuation_token_key: ContinuationTokenKey,
+    execution_config: ExecutionConfig,
+}
+
+impl JsonRpcServerImpl for JsonRpcServerV0_3Impl {
+    fn new(config: &GatewayConfig, storage_reader: StorageReader) -> Self {
+        Self {
+            chain_id: config.chain_id.clone(),
+            storage_reader,
+            max_events_chunk_size: config.max_events_chunk_size,
+            max_events_keys: config.max_events_keys,
+            events_scan_budget: EventsScanBudget::new(config),
+            continuation_token_key: ContinuationTokenKey::new(config),
+            execution_config: config.execution_config.clone(),
+        }
+    }
+
+    fn into_rpc_module(self) -> RpcModule<Self> {
+        self.into_rpc()
+    }
+}
+
+fn get_block_header_by_number<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<BlockHeader, Error> {
+    Ok(BlockHeader::from(reader::get_block_header(txn, block_number)?))
+}
+
+fn to_receipt(stored: StoredReceipt) -> TransactionReceiptWithStatus {
+    let output = TransactionOutput::from_thin_transaction_output(stored.output, stored.events);
+    TransactionReceiptWithStatus {
+        receipt: TransactionReceipt::from_transaction_output(
+            output,
+            &stored.transaction,
+            stored.block_hash,
+            stored.block_number,
+        ),
+        status: TransactionStatus::default(),
+    }
+}
+
+fn to_gateway_class(class: StoredClass) -> Result<GatewayContractClass, Error> {
+    Ok(match class {
+        StoredClass::Cairo0(class) => {
+            GatewayContractClass::Cairo0(class.try_into().map_err(internal_server_error)?)
+        }
+        StoredClass::Sierra(class) => {
+            GatewayContractClass::Sierra(class.try_into().map_err(internal_server_error)?)
+        }
+    })
+}
+
+#[async_trait]
+impl JsonRpcV0_3Server for JsonRpcServerV0_3Impl {
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn block_number(&self) -> Result<BlockNumber, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        get_latest_block_number(&txn)?.ok_or_else(|| Error::from(JsonRpcError::NoBlocks))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn block_hash_and_number(&self) -> Result<BlockHashAndNumber, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number =
+            get_latest_block_number(&txn)?.ok_or_else(|| Error::from(JsonRpcError::NoBlocks))?;
+        let header = reader::get_block_header(&txn, block_number)?;
+
+        Ok(BlockHashAndNumber { block_hash: header.block_hash, block_number })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_w_transaction_hashes(&self, block_id: BlockId) -> Result<Block, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let header = get_block_header_by_number(&txn, block_number)?;
+        let transactions = reader::get_block_transactions(&txn, block_number)?;
+
+        Ok(Block {
+            status: BlockStatus::AcceptedOnL2,
+            header,
+            transactions: Transactions::Hashes(
+                transactions.iter().map(|transaction| transaction.transaction_hash()).collect(),
+            ),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<Block, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let header = get_block_header_by_number(&txn, block_number)?;
+        let transactions = reader::get_block_transactions(&txn, block_number)?;
+
+        Ok(Block {
+            status: BlockStatus::AcceptedOnL2,
+            header,
+            transactions: Transactions::Full(
+                transactions.into_iter().map(TransactionWithType::from).collect(),
+            ),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        block_id: BlockId,
+    ) -> Result<StarkFelt, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        reader::get_storage_at(&txn, block_number, &contract_address, &key)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_by_hash(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionWithType, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let transaction = reader::get_transaction_by_hash(&txn, &transaction_hash)?;
+        Ok(TransactionWithType::from(transaction))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_by_block_id_and_index(
+        &self,
+        block_id: BlockId,
+        index: TransactionOffsetInBlock,
+    ) -> Result<TransactionWithType, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let transaction = reader::get_transaction_by_index(&txn, block_number, index)?;
+        Ok(TransactionWithType::from(transaction))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_transaction_count(&self, block_id: BlockId) -> Result<usize, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        Ok(reader::get_block_transactions(&txn, block_number)?.len())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_state_update(&self, block_id: BlockId) -> Result<StateUpdate, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let state_update = reader::get_state_update(&txn, block_number)?;
+
+        Ok(StateUpdate {
+            block_hash: state_update.block_hash,
+            new_root: state_update.new_root,
+            old_root: state_update.old_root,
+            state_diff: state_update.state_diff.into(),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionReceiptWithStatus, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        Ok(to_receipt(reader::get_transaction_receipt(&txn, &transaction_hash)?))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_class(
+        &self,
+        block_id: BlockId,
+        class_hash: ClassHash,
+    ) -> Result<GatewayContractClass, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let class =
+            reader::get_class(&txn, block_number, &class_hash, JsonRpcError::ClassHashNotFound)?;
+        to_gateway_class(class)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_class_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<GatewayContractClass, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let class_hash = reader::get_class_hash_at(&txn, block_number, &contract_address)?;
+        let class =
+            reader::get_class(&txn, block_number, &class_hash, JsonRpcError::ContractNotFound)?;
+        to_gateway_class(class)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_class_hash_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<ClassHash, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        reader::get_class_hash_at(&txn, block_number, &contract_address)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_nonce(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<Nonce, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        reader::get_nonce_at(&txn, block_number, &contract_address)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn chain_id(&self) -> Result<String, Error> {
+        Ok(self.chain_id.as_hex())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let (events, continuation_token) = filter_events(
+            &txn,
+            filter,
+            self.max_events_chunk_size,
+            self.max_events_keys,
+            self.events_scan_budget,
+            &self.continuation_token_key,
+        )?;
+        Ok(EventsChunk {
+            events: events
+                .into_iter()
+                .map(|event| Event {
+                    block_hash: event.block_hash,
+                    block_number: event.block_number,
+                    transaction_hash: event.transaction_hash,
+                    event: event.event,
+                })
+                .collect(),
+            continuation_token,
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn call(&self, request: FunctionCall, block_id: BlockId) -> Result<Vec<StarkFelt>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+
+        execute_call(
+            &txn,
+            &self.chain_id,
+            block_number,
+            &request.contract_address,
+            request.entry_point_selector,
+            request.calldata,
+            &self.execution_config,
+        )
+        .map_err(execution_error)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn estimate_fee(
+        &self,
+        request: Vec<BroadcastedTransaction>,
+        block_id: BlockId,
+    ) -> Result<Vec<FeeEstimate>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let transactions = self.to_transactions(request)?;
+
+        let fee_estimations = estimate_fee(
+            &txn,
+            &self.chain_id,
+            block_number,
+            transactions.into_iter().map(to_executable_transaction).collect(),
+            &self.execution_config,
+        )
+        .map_err(execution_error)?;
+        Ok(fee_estimations.into_iter().map(FeeEstimate::from).collect())
+    }
+}
+
+impl JsonRpcServerV0_3Impl {
+    fn to_transactions(
+        &self,
+        transactions: Vec<BroadcastedTransaction>,
+    ) -> Result<Vec<starknet_api::transaction::Transaction>, Error> {
+        transactions
+            .into_iter()
+            .map(|tx| tx.into_transaction(&self.chain_id))
+            .collect::<Result<_, _>>()
+            .map_err(|err| Error::Call(CallError::InvalidParams(err.into())))
+    }
+}
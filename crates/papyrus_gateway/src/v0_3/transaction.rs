@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::ContractAddress;
+use starknet_api::transaction::TransactionHash;
+
+use crate::v0_4::transaction::{TransactionOutput, TransactionStatus, TransactionType};
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct TransactionReceiptWithStatus {
+    pub status: TransactionStatus,
+    #[serde(flatten)]
+    pub receipt: TransactionReceipt,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum TransactionReceipt {
+    Deploy(DeployTransactionReceipt),
+    Common(CommonTransactionReceipt),
+}
+
+impl TransactionReceipt {
+    pub fn from_transaction_output(
+        output: TransactionOutput,
+        transaction: &starknet_api::transaction::Transaction,
+        block_hash: BlockHash,
+        block_number: BlockNumber,
+    ) -> Self {
+        let common = CommonTransactionReceipt {
+            transaction_hash: transaction.transaction_hash(),
+            r#type: output.r#type(),
+            block_hash,
+            block_number,
+            output,
+        };
+
+        match transaction {
+            starknet_api::transaction::Transaction::DeployAccount(tx) => {
+                Self::Deploy(DeployTransactionReceipt {
+                    common,
+                    contract_address: tx.contract_address,
+                })
+            }
+            starknet_api::transaction::Transaction::Deploy(tx) => {
+                Self::Deploy(DeployTransactionReceipt {
+                    common,
+                    contract_address: tx.contract_address,
+                })
+            }
+            _ => Self::Common(common),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct DeployTransactionReceipt {
+    #[serde(flatten)]
+    pub common: CommonTransactionReceipt,
+    pub contract_address: ContractAddress,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct CommonTransactionReceipt {
+    pub transaction_hash: TransactionHash,
+    pub r#type: TransactionType,
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    #[serde(flatten)]
+    pub output: TransactionOutput,
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::ops::Index;
+
+use indexmap::IndexMap;
+use papyrus_storage::body::events::{
+    ExecutionResources as StorageExecutionResources, TransactionExecutionInfo,
+    TransactionExecutionStatus as StorageTransactionExecutionStatus,
+};
+use papyrus_storage::body::{BlockExecutionInfo, BodyStorageWriter, StarknetVersion};
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::get_test_storage;
+use starknet_api::block::{BlockHash, BlockHeader, BlockNumber, GasPrice};
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use starknet_api::state::StateDiff;
+use test_utils::{get_test_block, get_test_body, send_request_to_path};
+
+use crate::api::{BlockHashOrNumber, BlockId};
+use crate::test_utils::{
+    get_starknet_spec_api_schema, get_test_gateway_config, get_test_rpc_server_and_storage_writer,
+};
+use crate::v0_3::block::{Block, BlockHeader as GatewayBlockHeader};
+use crate::v0_3::transaction::{TransactionReceipt, TransactionReceiptWithStatus};
+use crate::v0_3::JsonRpcServerV0_3Impl;
+use crate::v0_4::transaction::{TransactionOutput, TransactionStatus};
+use crate::{run_server, ApiVersion, GatewayMonitor};
+
+#[tokio::test]
+async fn get_block_w_transaction_hashes() {
+    let (module, mut storage_writer) =
+        get_test_rpc_server_and_storage_writer::<JsonRpcServerV0_3Impl>();
+    let mut block = get_test_block(Some(0), 1, None, None, None);
+    block.header.gas_price = GasPrice(100);
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body_with_execution_info(
+            block.header.block_number,
+            block.body.clone(),
+            BlockExecutionInfo {
+                starknet_version: Some(StarknetVersion("0.12.1".to_owned())),
+                transaction_execution_infos: None,
+            },
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // The header of version 0.3 has neither the gas price nor the Starknet version.
+    let res = module
+        .call::<_, serde_json::Value>(
+            "starknet_getBlockWithTxHashes",
+            [BlockId::HashOrNumber(BlockHashOrNumber::Number(block.header.block_number))],
+        )
+        .await
+        .unwrap();
+    assert!(res.get("l1_gas_price").is_none());
+    assert!(res.get("starknet_version").is_none());
+    let res: Block = serde_json::from_value(res).unwrap();
+    assert_eq!(res.header, GatewayBlockHeader::from(block.header));
+}
+
+#[tokio::test]
+async fn get_transaction_receipt() {
+    let (module, mut storage_writer) =
+        get_test_rpc_server_and_storage_writer::<JsonRpcServerV0_3Impl>();
+    let block = get_test_block(Some(0), 1, None, None, None);
+    let execution_info = TransactionExecutionInfo {
+        execution_status: StorageTransactionExecutionStatus::Reverted("reason".to_owned()),
+        execution_resources: StorageExecutionResources {
+            n_steps: 10,
+            builtin_instance_counter: BTreeMap::new(),
+            n_memory_holes: 3,
+        },
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body_with_execution_info(
+            block.header.block_number,
+            block.body.clone(),
+            BlockExecutionInfo {
+                starknet_version: None,
+                transaction_execution_infos: Some(vec![execution_info]),
+            },
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // The receipt of version 0.3 doesn't have the execution fields.
+    let transaction = block.body.transactions.index(0);
+    let output = TransactionOutput::from(block.body.transaction_outputs.index(0).clone());
+    let expected_receipt = TransactionReceiptWithStatus {
+        receipt: TransactionReceipt::from_transaction_output(
+            output,
+            transaction,
+            block.header.block_hash,
+            block.header.block_number,
+        ),
+        status: TransactionStatus::default(),
+    };
+    let res = module
+        .call::<_, TransactionReceiptWithStatus>(
+            "starknet_getTransactionReceipt",
+            [transaction.transaction_hash()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&res).unwrap(),
+        serde_json::to_string(&expected_receipt).unwrap(),
+    );
+}
+
+#[tokio::test]
+async fn trace_methods_not_served() {
+    let (module, _) = get_test_rpc_server_and_storage_writer::<JsonRpcServerV0_3Impl>();
+    assert!(module.method("starknet_blockNumber").is_some());
+    assert!(module.method("starknet_traceTransaction").is_none());
+    assert!(module.method("starknet_traceBlockTransactions").is_none());
+    assert!(module.method("starknet_simulateTransactions").is_none());
+}
+
+#[tokio::test]
+async fn serialize_returns_valid_json() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let parent_block = starknet_api::block::Block::default();
+    let block = starknet_api::block::Block {
+        header: BlockHeader {
+            parent_hash: parent_block.header.block_hash,
+            block_hash: BlockHash(stark_felt!("0x1")),
+            block_number: BlockNumber(1),
+            ..BlockHeader::default()
+        },
+        body: get_test_body(None, 5, Some(5), None, None),
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(parent_block.header.block_number, &parent_block.header)
+        .unwrap()
+        .append_body(parent_block.header.block_number, parent_block.body)
+        .unwrap()
+        .append_state_diff(parent_block.header.block_number, StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body(block.header.block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let gateway_config = get_test_gateway_config();
+    let (server_address, _handle) =
+        run_server(&gateway_config, storage_reader, GatewayMonitor::default()).await.unwrap();
+    let schema = get_starknet_spec_api_schema(
+        &["BLOCK_WITH_TXS", "BLOCK_WITH_TX_HASHES", "TXN", "TXN_RECEIPT"],
+        ApiVersion::V0_3,
+    )
+    .await;
+
+    let path = ApiVersion::V0_3.path();
+    for method in ["starknet_getBlockWithTxs", "starknet_getBlockWithTxHashes"] {
+        let res =
+            send_request_to_path(server_address, path, method, r#"{"block_number": 1}"#).await;
+        assert!(schema.validate(&res["result"]).is_ok(), "Block is not valid.");
+    }
+
+    let transaction_hash = block.body.transactions.index(0).transaction_hash();
+    let params = format!(r#""0x{}""#, hex::encode(transaction_hash.0.bytes()));
+    let res =
+        send_request_to_path(server_address, path, "starknet_getTransactionByHash", &params).await;
+    assert!(schema.validate(&res["result"]).is_ok(), "Transaction is not valid.");
+    let res =
+        send_request_to_path(server_address, path, "starknet_getTransactionReceipt", &params).await;
+    assert!(schema.validate(&res["result"]).is_ok(), "Transaction receipt is not valid.");
+}
@@ -0,0 +1,61 @@
+use papyrus_storage::body::StarknetVersion;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHash, BlockNumber, BlockStatus, BlockTimestamp, GasPrice};
+use starknet_api::core::{ContractAddress, GlobalRoot};
+use starknet_api::hash::StarkFelt;
+
+use crate::u128_as_stark_felt;
+use crate::v0_4::transaction::Transactions;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct BlockHeader {
+    pub block_hash: BlockHash,
+    pub parent_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub sequencer_address: ContractAddress,
+    pub new_root: GlobalRoot,
+    pub timestamp: BlockTimestamp,
+    pub l1_gas_price: ResourcePrice,
+    // Missing for blocks that were synced before the version was stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starknet_version: Option<String>,
+}
+
+impl BlockHeader {
+    // Takes the Starknet version along with the header, which are stored separately, so that every
+    // header that is served includes the version if it is stored.
+    pub(crate) fn new(
+        header: starknet_api::block::BlockHeader,
+        starknet_version: Option<StarknetVersion>,
+    ) -> Self {
+        BlockHeader {
+            block_hash: header.block_hash,
+            parent_hash: header.parent_hash,
+            block_number: header.block_number,
+            sequencer_address: header.sequencer,
+            new_root: header.state_root,
+            timestamp: header.timestamp,
+            l1_gas_price: ResourcePrice::from(header.gas_price),
+            starknet_version: starknet_version.map(|version| version.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ResourcePrice {
+    pub price_in_wei: StarkFelt,
+}
+
+impl From<GasPrice> for ResourcePrice {
+    fn from(gas_price: GasPrice) -> Self {
+        ResourcePrice { price_in_wei: u128_as_stark_felt(gas_price.0) }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct Block {
+    pub status: BlockStatus,
+    #[serde(flatten)]
+    pub header: BlockHeader,
+    pub transactions: Transactions,
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use papyrus_storage::compression_utils::{CompressionError, GzEncoded};
-use papyrus_storage::db::serialization::{StorageSerde, StorageSerdeError};
+use papyrus_storage::db::serialization::{StorageEncoding, StorageSerde, StorageSerdeError};
 use serde::{Deserialize, Serialize};
 use starknet_api::deprecated_contract_class::{
     EntryPoint, EntryPointType, EventAbiEntry, FunctionAbiEntry, FunctionAbiEntryType,
@@ -122,13 +122,20 @@ impl TryFrom<starknet_api::deprecated_contract_class::ContractClass> for Contrac
 pub struct Program(serde_json::Value);
 impl StorageSerde for Program {
     /// Serializes the entire program as one json value.
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        _encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         serde_json::to_writer(res, &self.0)?;
         Ok(())
     }
 
     /// Deserializes the entire program as one json value.
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(
+        bytes: &mut impl std::io::Read,
+        _encoding: StorageEncoding,
+    ) -> Option<Self> {
         let value = serde_json::from_reader(bytes).ok()?;
         Some(Program(value))
     }
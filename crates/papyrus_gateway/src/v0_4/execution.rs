@@ -0,0 +1,223 @@
+use papyrus_execution::{ExecutableTransactionInput, FeeEstimation, TransactionSimulationOutput};
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{
+    calculate_contract_address, ChainId, ClassHash, ContractAddress, EntryPointSelector, Nonce,
+};
+use starknet_api::hash::{pedersen_hash_array, StarkFelt};
+use starknet_api::transaction::{
+    Calldata, ContractAddressSalt, DeployAccountTransaction, Fee, InvokeTransaction,
+    InvokeTransactionV0, InvokeTransactionV1, Transaction, TransactionHash, TransactionSignature,
+    TransactionVersion,
+};
+use starknet_api::StarknetApiError;
+
+use crate::u128_as_stark_felt;
+use crate::v0_4::trace::TransactionTrace;
+
+// The prefixes of the hashes of the different transaction types.
+const INVOKE_PREFIX: &str = "invoke";
+const DEPLOY_ACCOUNT_PREFIX: &str = "deploy_account";
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FunctionCall {
+    pub contract_address: ContractAddress,
+    pub entry_point_selector: EntryPointSelector,
+    pub calldata: Calldata,
+}
+
+/// A transaction that isn't part of a block, to estimate its fee or simulate it. Declare
+/// transactions aren't supported, since their class hashes aren't calculated.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum BroadcastedTransaction {
+    #[serde(rename = "INVOKE")]
+    Invoke(BroadcastedInvokeTransaction),
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount(BroadcastedDeployAccountTransaction),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum BroadcastedInvokeTransaction {
+    Version0(BroadcastedInvokeTransactionV0),
+    Version1(BroadcastedInvokeTransactionV1),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BroadcastedInvokeTransactionV0 {
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub contract_address: ContractAddress,
+    pub entry_point_selector: EntryPointSelector,
+    pub calldata: Calldata,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BroadcastedInvokeTransactionV1 {
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub sender_address: ContractAddress,
+    pub calldata: Calldata,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BroadcastedDeployAccountTransaction {
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub contract_address_salt: ContractAddressSalt,
+    pub constructor_calldata: Calldata,
+    pub class_hash: ClassHash,
+}
+
+impl BroadcastedTransaction {
+    /// Returns the transaction with its hash (and address, for deploy account transactions), as
+    /// it would be on the chain with the given id.
+    pub fn into_transaction(self, chain_id: &ChainId) -> Result<Transaction, StarknetApiError> {
+        let chain_id = ascii_as_stark_felt(&chain_id.0);
+        Ok(match self {
+            BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::Version0(tx)) => {
+                let transaction_hash = TransactionHash(pedersen_hash_array(&[
+                    ascii_as_stark_felt(INVOKE_PREFIX),
+                    tx.version.0,
+                    *tx.contract_address.0.key(),
+                    tx.entry_point_selector.0,
+                    pedersen_hash_array(&tx.calldata.0),
+                    u128_as_stark_felt(tx.max_fee.0),
+                    chain_id,
+                ]));
+                Transaction::Invoke(InvokeTransaction::V0(InvokeTransactionV0 {
+                    transaction_hash,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    sender_address: tx.contract_address,
+                    entry_point_selector: tx.entry_point_selector,
+                    calldata: tx.calldata,
+                }))
+            }
+            BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::Version1(tx)) => {
+                let transaction_hash = TransactionHash(pedersen_hash_array(&[
+                    ascii_as_stark_felt(INVOKE_PREFIX),
+                    tx.version.0,
+                    *tx.sender_address.0.key(),
+                    StarkFelt::default(),
+                    pedersen_hash_array(&tx.calldata.0),
+                    u128_as_stark_felt(tx.max_fee.0),
+                    chain_id,
+                    tx.nonce.0,
+                ]));
+                Transaction::Invoke(InvokeTransaction::V1(InvokeTransactionV1 {
+                    transaction_hash,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    sender_address: tx.sender_address,
+                    calldata: tx.calldata,
+                }))
+            }
+            BroadcastedTransaction::DeployAccount(tx) => {
+                let contract_address = calculate_contract_address(
+                    tx.contract_address_salt,
+                    tx.class_hash,
+                    &tx.constructor_calldata,
+                    ContractAddress::default(),
+                )?;
+                let mut constructor_elements = vec![tx.class_hash.0, tx.contract_address_salt.0];
+                constructor_elements.extend(tx.constructor_calldata.0.iter());
+                let transaction_hash = TransactionHash(pedersen_hash_array(&[
+                    ascii_as_stark_felt(DEPLOY_ACCOUNT_PREFIX),
+                    tx.version.0,
+                    *contract_address.0.key(),
+                    StarkFelt::default(),
+                    pedersen_hash_array(&constructor_elements),
+                    u128_as_stark_felt(tx.max_fee.0),
+                    chain_id,
+                    tx.nonce.0,
+                ]));
+                Transaction::DeployAccount(DeployAccountTransaction {
+                    transaction_hash,
+                    max_fee: tx.max_fee,
+                    version: tx.version,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    class_hash: tx.class_hash,
+                    contract_address,
+                    contract_address_salt: tx.contract_address_salt,
+                    constructor_calldata: tx.constructor_calldata,
+                })
+            }
+        })
+    }
+}
+
+// Only the transactions that `BroadcastedTransaction::into_transaction` returns are executable.
+pub(crate) fn to_executable_transaction(transaction: Transaction) -> ExecutableTransactionInput {
+    match transaction {
+        Transaction::Invoke(tx) => ExecutableTransactionInput::Invoke(tx),
+        Transaction::DeployAccount(tx) => ExecutableTransactionInput::DeployAccount(tx),
+        _ => unreachable!("Only invoke and deploy account transactions are broadcasted."),
+    }
+}
+
+// The chain id and the prefixes of the transaction hashes are short ascii strings that are hashed
+// as felts.
+fn ascii_as_stark_felt(ascii: &str) -> StarkFelt {
+    let mut bytes = [0_u8; 32];
+    bytes[32 - ascii.len()..].copy_from_slice(ascii.as_bytes());
+    StarkFelt::new(bytes).expect("A short ascii string should fit in a StarkFelt.")
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FeeEstimate {
+    pub gas_consumed: StarkFelt,
+    pub gas_price: StarkFelt,
+    pub overall_fee: StarkFelt,
+}
+
+impl From<FeeEstimation> for FeeEstimate {
+    fn from(estimation: FeeEstimation) -> Self {
+        // Blocks that were synced without their gas price have a zero gas price.
+        let gas_consumed =
+            estimation.overall_fee.0.checked_div(estimation.gas_price.0).unwrap_or_default();
+        FeeEstimate {
+            gas_consumed: u128_as_stark_felt(gas_consumed),
+            gas_price: u128_as_stark_felt(estimation.gas_price.0),
+            overall_fee: u128_as_stark_felt(estimation.overall_fee.0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub enum SimulationFlag {
+    #[serde(rename = "SKIP_VALIDATE")]
+    SkipValidate,
+    #[serde(rename = "SKIP_FEE_CHARGE")]
+    SkipFeeCharge,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct SimulatedTransaction {
+    pub transaction_trace: TransactionTrace,
+    pub fee_estimation: FeeEstimate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+}
+
+impl SimulatedTransaction {
+    pub fn from_simulation_output(
+        output: TransactionSimulationOutput,
+        transaction: &Transaction,
+    ) -> Self {
+        Self {
+            transaction_trace: TransactionTrace::from_stored_trace(output.trace, transaction),
+            fee_estimation: output.fee_estimation.into(),
+            revert_reason: output.revert_error,
+        }
+    }
+}
@@ -0,0 +1,438 @@
+// The types of version 0.3 that didn't change are shared with it.
+pub(crate) mod api;
+mod block;
+pub(crate) mod deprecated_contract_class;
+pub(crate) mod execution;
+pub(crate) mod state;
+mod trace;
+pub(crate) mod transaction;
+#[cfg(test)]
+#[path = "v0_4_test.rs"]
+mod v0_4_test;
+
+use jsonrpsee::core::{async_trait, Error};
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::http_server::RpcModule;
+use papyrus_execution::{estimate_fee, execute_call, simulate_transactions, ExecutionConfig};
+use papyrus_storage::db::TransactionKind;
+use papyrus_storage::{StorageReader, StorageTxn};
+use starknet_api::block::{BlockNumber, BlockStatus};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{TransactionHash, TransactionOffsetInBlock};
+use tracing::instrument;
+
+use crate::api::{BlockHashAndNumber, BlockId, EventFilter, JsonRpcError};
+use crate::reader::{
+    self, filter_events, get_block_number, get_latest_block_number, ContinuationTokenKey,
+    EventsScanBudget, StoredClass, StoredReceipt,
+};
+use crate::v0_4::api::{EventsChunk, GatewayContractClass, JsonRpcV0_4Server};
+use crate::v0_4::block::{Block, BlockHeader};
+use crate::v0_4::execution::{
+    to_executable_transaction, BroadcastedTransaction, FeeEstimate, FunctionCall,
+    SimulatedTransaction, SimulationFlag,
+};
+use crate::v0_4::state::StateUpdate;
+use crate::v0_4::trace::{TransactionTrace, TransactionTraceWithHash};
+use crate::v0_4::transaction::{
+    Event, TransactionExecutionStatus, TransactionFinalityAndExecutionStatus, TransactionOutput,
+    TransactionReceipt, TransactionReceiptWithStatus, TransactionStatus, TransactionWithType,
+    Transactions,
+};
+use crate::{execution_error, internal_server_error, GatewayConfig, JsonRpcServerImpl};
+
+/// Rpc server of version 0.4 of the specification.
+pub(crate) struct JsonRpcServerV0_4Impl {
+    chain_id: ChainId,
+    storage_reader: StorageReader,
+    max_events_chunk_size: usize,
+    max_events_keys: usize,
+    events_scan_budget: EventsScanBudget,
+    continuation_token_key: ContinuationTokenKey,
+    execution_config: ExecutionConfig,
+}
+
+impl JsonRpcServerImpl for JsonRpcServerV0_4Impl {
+    fn new(config: &GatewayConfig, storage_reader: StorageReader) -> Self {
+        Self {
+            chain_id: config.chain_id.clone(),
+            storage_reader,
+            max_events_chunk_size: config.max_events_chunk_size,
+            max_events_keys: config.max_events_keys,
+            events_scan_budget: EventsScanBudget::new(config),
+            continuation_token_key: ContinuationTokenKey::new(config),
+            execution_config: config.execution_config.clone(),
+        }
+    }
+
+    fn into_rpc_module(self) -> RpcModule<Self> {
+        self.into_rpc()
+    }
+}
+
+fn get_block_header_by_number<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<BlockHeader, Error> {
+    let header = reader::get_block_header(txn, block_number)?;
+    let starknet_version = reader::get_starknet_version(txn, block_number)?;
+    Ok(BlockHeader::new(header, starknet_version))
+}
+
+fn to_receipt(stored: StoredReceipt) -> TransactionReceiptWithStatus {
+    let execution_info = stored.output.execution_info().cloned();
+    let output = TransactionOutput::from_thin_transaction_output(stored.output, stored.events);
+    TransactionReceiptWithStatus {
+        receipt: TransactionReceipt::from_transaction_output(
+            output,
+            execution_info,
+            &stored.transaction,
+            stored.block_hash,
+            stored.block_number,
+        ),
+        status: TransactionStatus::default(),
+    }
+}
+
+fn to_gateway_class(class: StoredClass) -> Result<GatewayContractClass, Error> {
+    Ok(match class {
+        StoredClass::Cairo0(class) => {
+            GatewayContractClass::Cairo0(class.try_into().map_err(internal_server_error)?)
+        }
+        StoredClass::Sierra(class) => {
+            GatewayContractClass::Sierra(class.try_into().map_err(internal_server_error)?)
+        }
+    })
+}
+
+#[async_trait]
+impl JsonRpcV0_4Server for JsonRpcServerV0_4Impl {
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn block_number(&self) -> Result<BlockNumber, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        get_latest_block_number(&txn)?.ok_or_else(|| Error::from(JsonRpcError::NoBlocks))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn block_hash_and_number(&self) -> Result<BlockHashAndNumber, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number =
+            get_latest_block_number(&txn)?.ok_or_else(|| Error::from(JsonRpcError::NoBlocks))?;
+        let header = reader::get_block_header(&txn, block_number)?;
+
+        Ok(BlockHashAndNumber { block_hash: header.block_hash, block_number })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_w_transaction_hashes(&self, block_id: BlockId) -> Result<Block, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let header = get_block_header_by_number(&txn, block_number)?;
+        let transactions = reader::get_block_transactions(&txn, block_number)?;
+
+        Ok(Block {
+            status: BlockStatus::AcceptedOnL2,
+            header,
+            transactions: Transactions::Hashes(
+                transactions.iter().map(|transaction| transaction.transaction_hash()).collect(),
+            ),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<Block, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let header = get_block_header_by_number(&txn, block_number)?;
+        let transactions = reader::get_block_transactions(&txn, block_number)?;
+
+        Ok(Block {
+            status: BlockStatus::AcceptedOnL2,
+            header,
+            transactions: Transactions::Full(
+                transactions.into_iter().map(TransactionWithType::from).collect(),
+            ),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        block_id: BlockId,
+    ) -> Result<StarkFelt, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        reader::get_storage_at(&txn, block_number, &contract_address, &key)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_by_hash(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionWithType, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let transaction = reader::get_transaction_by_hash(&txn, &transaction_hash)?;
+        Ok(TransactionWithType::from(transaction))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_by_block_id_and_index(
+        &self,
+        block_id: BlockId,
+        index: TransactionOffsetInBlock,
+    ) -> Result<TransactionWithType, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let transaction = reader::get_transaction_by_index(&txn, block_number, index)?;
+        Ok(TransactionWithType::from(transaction))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_transaction_count(&self, block_id: BlockId) -> Result<usize, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        Ok(reader::get_block_transactions(&txn, block_number)?.len())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_state_update(&self, block_id: BlockId) -> Result<StateUpdate, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let state_update = reader::get_state_update(&txn, block_number)?;
+
+        Ok(StateUpdate {
+            block_hash: state_update.block_hash,
+            new_root: state_update.new_root,
+            old_root: state_update.old_root,
+            state_diff: state_update.state_diff.into(),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionReceiptWithStatus, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        Ok(to_receipt(reader::get_transaction_receipt(&txn, &transaction_hash)?))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_receipts(
+        &self,
+        block_id: BlockId,
+    ) -> Result<Vec<TransactionReceiptWithStatus>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        Ok(reader::get_block_receipts(&txn, block_number)?.into_iter().map(to_receipt).collect())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transaction_status(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionFinalityAndExecutionStatus, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let output = reader::get_transaction_output(&txn, &transaction_hash)?;
+
+        // Only accepted blocks are synced and the base layer isn't tracked, so every stored
+        // transaction is accepted on L2.
+        Ok(TransactionFinalityAndExecutionStatus {
+            finality_status: TransactionStatus::AcceptedOnL2,
+            execution_status: output
+                .execution_info()
+                .map(|info| TransactionExecutionStatus::from(&info.execution_status)),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_class(
+        &self,
+        block_id: BlockId,
+        class_hash: ClassHash,
+    ) -> Result<GatewayContractClass, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let class =
+            reader::get_class(&txn, block_number, &class_hash, JsonRpcError::ClassHashNotFound)?;
+        to_gateway_class(class)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_class_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<GatewayContractClass, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let class_hash = reader::get_class_hash_at(&txn, block_number, &contract_address)?;
+        let class =
+            reader::get_class(&txn, block_number, &class_hash, JsonRpcError::ContractNotFound)?;
+        to_gateway_class(class)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_class_hash_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<ClassHash, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        reader::get_class_hash_at(&txn, block_number, &contract_address)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_nonce(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<Nonce, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        reader::get_nonce_at(&txn, block_number, &contract_address)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn chain_id(&self) -> Result<String, Error> {
+        Ok(self.chain_id.as_hex())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let (events, continuation_token) = filter_events(
+            &txn,
+            filter,
+            self.max_events_chunk_size,
+            self.max_events_keys,
+            self.events_scan_budget,
+            &self.continuation_token_key,
+        )?;
+        Ok(EventsChunk {
+            events: events
+                .into_iter()
+                .map(|event| Event {
+                    block_hash: event.block_hash,
+                    block_number: event.block_number,
+                    transaction_hash: event.transaction_hash,
+                    event: event.event,
+                })
+                .collect(),
+            continuation_token,
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn trace_transaction(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionTrace, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let (transaction, trace) = reader::get_transaction_trace(&txn, &transaction_hash)?;
+        Ok(TransactionTrace::from_stored_trace(trace, &transaction))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn trace_block_transactions(
+        &self,
+        block_id: BlockId,
+    ) -> Result<Vec<TransactionTraceWithHash>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+
+        Ok(reader::get_block_traces(&txn, block_number)?
+            .into_iter()
+            .map(|(transaction, trace)| TransactionTraceWithHash {
+                transaction_hash: transaction.transaction_hash(),
+                trace_root: TransactionTrace::from_stored_trace(trace, &transaction),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn call(&self, request: FunctionCall, block_id: BlockId) -> Result<Vec<StarkFelt>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+
+        execute_call(
+            &txn,
+            &self.chain_id,
+            block_number,
+            &request.contract_address,
+            request.entry_point_selector,
+            request.calldata,
+            &self.execution_config,
+        )
+        .map_err(execution_error)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn estimate_fee(
+        &self,
+        request: Vec<BroadcastedTransaction>,
+        block_id: BlockId,
+    ) -> Result<Vec<FeeEstimate>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let transactions = self.to_transactions(request)?;
+
+        let fee_estimations = estimate_fee(
+            &txn,
+            &self.chain_id,
+            block_number,
+            transactions.into_iter().map(to_executable_transaction).collect(),
+            &self.execution_config,
+        )
+        .map_err(execution_error)?;
+        Ok(fee_estimations.into_iter().map(FeeEstimate::from).collect())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn simulate_transactions(
+        &self,
+        block_id: BlockId,
+        transactions: Vec<BroadcastedTransaction>,
+        simulation_flags: Vec<SimulationFlag>,
+    ) -> Result<Vec<SimulatedTransaction>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let transactions = self.to_transactions(transactions)?;
+
+        let outputs = simulate_transactions(
+            &txn,
+            &self.chain_id,
+            block_number,
+            transactions.iter().cloned().map(to_executable_transaction).collect(),
+            &self.execution_config,
+            !simulation_flags.contains(&SimulationFlag::SkipFeeCharge),
+            !simulation_flags.contains(&SimulationFlag::SkipValidate),
+        )
+        .map_err(execution_error)?;
+        Ok(outputs
+            .into_iter()
+            .zip(transactions.iter())
+            .map(|(output, transaction)| {
+                SimulatedTransaction::from_simulation_output(output, transaction)
+            })
+            .collect())
+    }
+}
+
+impl JsonRpcServerV0_4Impl {
+    fn to_transactions(
+        &self,
+        transactions: Vec<BroadcastedTransaction>,
+    ) -> Result<Vec<starknet_api::transaction::Transaction>, Error> {
+        transactions
+            .into_iter()
+            .map(|tx| tx.into_transaction(&self.chain_id))
+            .collect::<Result<_, _>>()
+            .map_err(|err| Error::Call(CallError::InvalidParams(err.into())))
+    }
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector};
+use starknet_api::deprecated_contract_class::EntryPointType;
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{
+    Calldata, EthAddress, EventData, EventKey, L2ToL1Payload, Transaction, TransactionHash,
+};
+
+// The traces of the different transaction types, as in the specification. The traces of deploy
+// transactions, which the specification doesn't cover, are given like the ones of deploy account
+// transactions.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TransactionTrace {
+    Invoke(InvokeTransactionTrace),
+    DeployAccount(DeployAccountTransactionTrace),
+    L1Handler(L1HandlerTransactionTrace),
+    Declare(DeclareTransactionTrace),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct InvokeTransactionTrace {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validate_invocation: Option<FunctionInvocation>,
+    pub execute_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DeployAccountTransactionTrace {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validate_invocation: Option<FunctionInvocation>,
+    pub constructor_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct L1HandlerTransactionTrace {
+    pub function_invocation: Option<FunctionInvocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DeclareTransactionTrace {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validate_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+}
+
+impl TransactionTrace {
+    pub fn from_stored_trace(
+        trace: papyrus_storage::trace::TransactionTrace,
+        transaction: &Transaction,
+    ) -> Self {
+        let validate_invocation = trace.validate_invocation.map(FunctionInvocation::from);
+        let function_invocation = trace.function_invocation.map(FunctionInvocation::from);
+        let fee_transfer_invocation = trace.fee_transfer_invocation.map(FunctionInvocation::from);
+        match transaction {
+            Transaction::Invoke(_) => Self::Invoke(InvokeTransactionTrace {
+                validate_invocation,
+                execute_invocation: function_invocation,
+                fee_transfer_invocation,
+            }),
+            Transaction::DeployAccount(_) | Transaction::Deploy(_) => {
+                Self::DeployAccount(DeployAccountTransactionTrace {
+                    validate_invocation,
+                    constructor_invocation: function_invocation,
+                    fee_transfer_invocation,
+                })
+            }
+            Transaction::L1Handler(_) => {
+                Self::L1Handler(L1HandlerTransactionTrace { function_invocation })
+            }
+            Transaction::Declare(_) => Self::Declare(DeclareTransactionTrace {
+                validate_invocation,
+                fee_transfer_invocation,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct TransactionTraceWithHash {
+    pub transaction_hash: TransactionHash,
+    pub trace_root: TransactionTrace,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FunctionInvocation {
+    pub contract_address: ContractAddress,
+    // Missing from the traces of old blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_point_selector: Option<EntryPointSelector>,
+    pub calldata: Calldata,
+    pub caller_address: ContractAddress,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_hash: Option<ClassHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_point_type: Option<EntryPointType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_type: Option<CallType>,
+    pub result: Vec<StarkFelt>,
+    pub calls: Vec<FunctionInvocation>,
+    pub events: Vec<Event>,
+    pub messages: Vec<MessageToL1>,
+}
+
+impl From<papyrus_storage::trace::FunctionInvocation> for FunctionInvocation {
+    fn from(invocation: papyrus_storage::trace::FunctionInvocation) -> Self {
+        Self {
+            contract_address: invocation.contract_address,
+            entry_point_selector: invocation.selector,
+            calldata: invocation.calldata,
+            caller_address: invocation.caller_address,
+            class_hash: invocation.class_hash,
+            entry_point_type: invocation.entry_point_type,
+            call_type: invocation.call_type.map(CallType::from),
+            result: invocation.result,
+            calls: invocation.internal_calls.into_iter().map(FunctionInvocation::from).collect(),
+            events: invocation
+                .events
+                .into_iter()
+                .map(|event| Event { keys: event.keys, data: event.data })
+                .collect(),
+            messages: invocation
+                .messages
+                .into_iter()
+                .map(|message| MessageToL1 {
+                    to_address: message.to_address,
+                    payload: message.payload,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub enum CallType {
+    #[serde(rename = "CALL")]
+    Call,
+    #[serde(rename = "LIBRARY_CALL")]
+    LibraryCall,
+}
+
+impl From<papyrus_storage::trace::CallType> for CallType {
+    fn from(call_type: papyrus_storage::trace::CallType) -> Self {
+        match call_type {
+            papyrus_storage::trace::CallType::Call => Self::Call,
+            papyrus_storage::trace::CallType::Delegate => Self::LibraryCall,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Event {
+    pub keys: Vec<EventKey>,
+    pub data: EventData,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct MessageToL1 {
+    pub to_address: EthAddress,
+    pub payload: L2ToL1Payload,
+}
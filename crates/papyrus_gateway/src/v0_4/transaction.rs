@@ -1,4 +1,9 @@
-use papyrus_storage::body::events::ThinTransactionOutput;
+use std::collections::BTreeMap;
+
+use papyrus_storage::body::events::{
+    ThinTransactionOutput, TransactionExecutionInfo,
+    TransactionExecutionStatus as StorageTransactionExecutionStatus,
+};
 use serde::{Deserialize, Serialize};
 use starknet_api::block::{BlockHash, BlockNumber, BlockStatus};
 use starknet_api::core::{
@@ -309,15 +314,33 @@ pub enum TransactionReceipt {
 impl TransactionReceipt {
     pub fn from_transaction_output(
         output: TransactionOutput,
+        execution_info: Option<TransactionExecutionInfo>,
         transaction: &starknet_api::transaction::Transaction,
         block_hash: BlockHash,
         block_number: BlockNumber,
     ) -> Self {
+        let (execution_status, revert_reason, execution_resources) = match execution_info {
+            Some(info) => {
+                let (status, reason) = match info.execution_status {
+                    StorageTransactionExecutionStatus::Succeeded => {
+                        (TransactionExecutionStatus::Succeeded, None)
+                    }
+                    StorageTransactionExecutionStatus::Reverted(reason) => {
+                        (TransactionExecutionStatus::Reverted, Some(reason))
+                    }
+                };
+                (Some(status), reason, Some(ExecutionResources::from(info.execution_resources)))
+            }
+            None => (None, None, None),
+        };
         let common = CommonTransactionReceipt {
             transaction_hash: transaction.transaction_hash(),
             r#type: output.r#type(),
             block_hash,
             block_number,
+            execution_status,
+            revert_reason,
+            execution_resources,
             output,
         };
 
@@ -352,10 +375,77 @@ pub struct CommonTransactionReceipt {
     pub r#type: TransactionType,
     pub block_hash: BlockHash,
     pub block_number: BlockNumber,
+    // The execution fields are missing for transactions that were synced before they were stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_status: Option<TransactionExecutionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_resources: Option<ExecutionResources>,
     #[serde(flatten)]
     pub output: TransactionOutput,
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub enum TransactionExecutionStatus {
+    #[serde(rename = "SUCCEEDED")]
+    Succeeded,
+    #[serde(rename = "REVERTED")]
+    Reverted,
+}
+
+impl From<&StorageTransactionExecutionStatus> for TransactionExecutionStatus {
+    fn from(status: &StorageTransactionExecutionStatus) -> Self {
+        match status {
+            StorageTransactionExecutionStatus::Succeeded => TransactionExecutionStatus::Succeeded,
+            StorageTransactionExecutionStatus::Reverted(_) => TransactionExecutionStatus::Reverted,
+        }
+    }
+}
+
+/// The finality status of a transaction together with the result of its execution.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct TransactionFinalityAndExecutionStatus {
+    pub finality_status: TransactionStatus,
+    // Missing for transactions that were synced before the execution status was stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_status: Option<TransactionExecutionStatus>,
+}
+
+/// The resources a transaction consumed, with the numbers given in hex as in the specification.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ExecutionResources {
+    pub steps: StarkFelt,
+    pub memory_holes: StarkFelt,
+    pub range_check_builtin_applications: StarkFelt,
+    pub pedersen_builtin_applications: StarkFelt,
+    pub poseidon_builtin_applications: StarkFelt,
+    pub ec_op_builtin_applications: StarkFelt,
+    pub ecdsa_builtin_applications: StarkFelt,
+    pub bitwise_builtin_applications: StarkFelt,
+    pub keccak_builtin_applications: StarkFelt,
+}
+
+impl From<papyrus_storage::body::events::ExecutionResources> for ExecutionResources {
+    fn from(resources: papyrus_storage::body::events::ExecutionResources) -> Self {
+        let builtin = |counter: &BTreeMap<String, u64>, name: &str| {
+            StarkFelt::from(counter.get(name).copied().unwrap_or_default())
+        };
+        let counter = &resources.builtin_instance_counter;
+        ExecutionResources {
+            steps: StarkFelt::from(resources.n_steps),
+            memory_holes: StarkFelt::from(resources.n_memory_holes),
+            range_check_builtin_applications: builtin(counter, "range_check_builtin"),
+            pedersen_builtin_applications: builtin(counter, "pedersen_builtin"),
+            poseidon_builtin_applications: builtin(counter, "poseidon_builtin"),
+            ec_op_builtin_applications: builtin(counter, "ec_op_builtin"),
+            ecdsa_builtin_applications: builtin(counter, "ecdsa_builtin"),
+            bitwise_builtin_applications: builtin(counter, "bitwise_builtin"),
+            keccak_builtin_applications: builtin(counter, "keccak_builtin"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
 #[serde(untagged)]
 pub enum TransactionOutput {
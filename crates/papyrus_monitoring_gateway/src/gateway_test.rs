@@ -3,19 +3,45 @@ use std::net::{SocketAddr, TcpListener};
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use axum::Router;
+use papyrus_gateway::{GatewayMonitor, LimitsStats};
 use papyrus_storage::{table_names, test_utils};
 use serde_json::{json, Value};
+use starknet_client::{
+    EndpointConfig, EndpointStats, FailoverConfig, FailoverStarknetClient, RetryConfig,
+};
 use tower::ServiceExt;
 
 use crate::{app, MONITORING_PREFIX};
 
 const TEST_CONFIG_REPRESENTATION: &str = "general_config_representation";
 const TEST_VERSION: &str = "1.2.3-dev";
+const TEST_ENDPOINTS: [&str; 2] = ["https://primary/", "https://secondary/"];
 
 // TODO(dan): consider using a proper fixture.
 fn setup_app() -> Router {
     let (storage_reader, _) = test_utils::get_test_storage();
-    app(storage_reader, TEST_VERSION, serde_json::to_value(TEST_CONFIG_REPRESENTATION).unwrap())
+    let starknet_client = FailoverStarknetClient::new(
+        TEST_ENDPOINTS
+            .iter()
+            .map(|url| EndpointConfig { url: url.to_string(), http_headers: None })
+            .collect(),
+        TEST_VERSION,
+        RetryConfig { retry_base_millis: 30, retry_max_delay_millis: 30000, max_retries: 10 },
+        None,
+        FailoverConfig {
+            max_consecutive_errors: 3,
+            failback_delay_millis: 60000,
+            hedge_after_millis: None,
+        },
+    )
+    .unwrap();
+    app(
+        storage_reader,
+        TEST_VERSION,
+        serde_json::to_value(TEST_CONFIG_REPRESENTATION).unwrap(),
+        Some(starknet_client.monitor()),
+        Some(GatewayMonitor::default()),
+    )
 }
 
 #[tokio::test]
@@ -79,6 +105,57 @@ async fn node_config() {
     assert_eq!(body, json!(TEST_CONFIG_REPRESENTATION));
 }
 
+#[tokio::test]
+async fn central_endpoints() {
+    let app = setup_app();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{MONITORING_PREFIX}/centralEndpoints").as_str())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Vec<EndpointStats> = serde_json::from_slice(&body).unwrap();
+    let expected_body: Vec<_> = TEST_ENDPOINTS
+        .iter()
+        .map(|url| EndpointStats {
+            url: url.to_string(),
+            healthy: true,
+            requests: 0,
+            errors: 0,
+            average_latency_millis: 0,
+            throttled_millis: 0,
+        })
+        .collect();
+    assert_eq!(body, expected_body);
+}
+
+#[tokio::test]
+async fn gateway_limits() {
+    let app = setup_app();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{MONITORING_PREFIX}/gatewayLimits").as_str())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: LimitsStats = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body, LimitsStats::default());
+}
+
 #[tokio::test]
 async fn run_server() {
     let listener = TcpListener::bind("0.0.0.0:0".parse::<SocketAddr>().unwrap()).unwrap();
@@ -9,8 +9,10 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{Json, Router};
+use papyrus_gateway::{GatewayMonitor, LimitsStats};
 use papyrus_storage::{DbTablesStats, StorageError, StorageReader};
 use serde::{Deserialize, Serialize};
+use starknet_client::{EndpointStats, FailoverMonitor};
 use tracing::{debug, instrument};
 
 const MONITORING_PREFIX: &str = "monitoring";
@@ -30,6 +32,8 @@ pub struct MonitoringServer {
     config: MonitoringGatewayConfig,
     general_config_representation: serde_json::Value,
     storage_reader: StorageReader,
+    failover_monitor: Option<FailoverMonitor>,
+    gateway_monitor: Option<GatewayMonitor>,
     version: &'static str,
 }
 
@@ -38,9 +42,18 @@ impl MonitoringServer {
         config: MonitoringGatewayConfig,
         general_config_representation: serde_json::Value,
         storage_reader: StorageReader,
+        failover_monitor: Option<FailoverMonitor>,
+        gateway_monitor: Option<GatewayMonitor>,
         version: &'static str,
     ) -> Self {
-        MonitoringServer { config, storage_reader, general_config_representation, version }
+        MonitoringServer {
+            config,
+            storage_reader,
+            general_config_representation,
+            failover_monitor,
+            gateway_monitor,
+            version,
+        }
     }
 
     /// Spawns a monitoring server.
@@ -62,6 +75,8 @@ impl MonitoringServer {
             self.storage_reader.clone(),
             self.version,
             self.general_config_representation.clone(),
+            self.failover_monitor.clone(),
+            self.gateway_monitor.clone(),
         );
         debug!("Starting monitoring gateway.");
         axum::Server::bind(&server_address).serve(app.into_make_service()).await
@@ -72,6 +87,8 @@ fn app(
     storage_reader: StorageReader,
     version: &'static str,
     general_config_representation: serde_json::Value,
+    failover_monitor: Option<FailoverMonitor>,
+    gateway_monitor: Option<GatewayMonitor>,
 ) -> Router {
     Router::new()
         .route(
@@ -86,6 +103,14 @@ fn app(
             format!("/{MONITORING_PREFIX}/nodeVersion").as_str(),
             get(move || node_version(version)),
         )
+        .route(
+            format!("/{MONITORING_PREFIX}/centralEndpoints").as_str(),
+            get(move || central_endpoints(failover_monitor)),
+        )
+        .route(
+            format!("/{MONITORING_PREFIX}/gatewayLimits").as_str(),
+            get(move || gateway_limits(gateway_monitor)),
+        )
 }
 
 /// Returns DB statistics.
@@ -110,6 +135,20 @@ async fn node_version(version: &'static str) -> String {
     version.to_string()
 }
 
+/// Returns the health and the request counters of the central's endpoints, by priority.
+#[instrument(skip(failover_monitor), level = "debug", ret)]
+async fn central_endpoints(
+    failover_monitor: Option<FailoverMonitor>,
+) -> axum::Json<Vec<EndpointStats>> {
+    failover_monitor.map(|monitor| monitor.endpoints_stats()).unwrap_or_default().into()
+}
+
+/// Returns the number of requests the JSON-RPC server rejected, by the limit they exceeded.
+#[instrument(skip(gateway_monitor), level = "debug", ret)]
+async fn gateway_limits(gateway_monitor: Option<GatewayMonitor>) -> axum::Json<LimitsStats> {
+    gateway_monitor.map(|monitor| monitor.limits_stats()).unwrap_or_default().into()
+}
+
 #[derive(thiserror::Error, Debug)]
 enum ServerError {
     #[error(transparent)]
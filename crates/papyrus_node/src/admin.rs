@@ -0,0 +1,195 @@
+#[cfg(test)]
+#[path = "admin_test.rs"]
+mod admin_test;
+
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use jsonrpsee::core::server::rpc_module::Methods;
+use jsonrpsee::core::{async_trait, Error};
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::types::error::ErrorObject;
+use papyrus_gateway::GatewayMonitor;
+use papyrus_sync::SyncControl;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::BlockNumber;
+use subtle::ConstantTimeEq;
+use tokio::task::JoinHandle;
+use tracing::{info, instrument};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Changes the tracing filter of the node while it runs.
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+// The code of the errors of the admin methods, from the range the JSON-RPC specification reserves
+// for implementation-defined server errors.
+const ADMIN_ERROR_CODE: i32 = -32000;
+
+/// The admin server, served on a local address only to the holders of its token.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AdminConfig {
+    pub server_address: String,
+    /// A file with the token the requests should carry as `Authorization: Bearer <token>`.
+    pub token_file: PathBuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AdminServerError {
+    #[error("The admin server should listen on a loopback address, got {address}.")]
+    NonLocalAddress { address: SocketAddr },
+    #[error("The admin token file {path:?} is empty.")]
+    EmptyToken { path: PathBuf },
+    #[error("Unable to read the admin token file {path:?}: {source}")]
+    ReadToken { path: PathBuf, source: std::io::Error },
+}
+
+/// Methods for operating the node at runtime.
+#[rpc(server, client, namespace = "papyrus_admin")]
+pub trait JsonRpcAdmin {
+    /// Pauses the sync once it finishes writing its current block. Pausing a paused sync does
+    /// nothing.
+    #[method(name = "pauseSync")]
+    fn pause_sync(&self) -> Result<(), Error>;
+
+    /// Resumes a paused sync.
+    #[method(name = "resumeSync")]
+    fn resume_sync(&self) -> Result<(), Error>;
+
+    /// Reverts the synced blocks from the last one down to `block_number` (inclusive), which the
+    /// sync then downloads again unless it is paused.
+    #[method(name = "revertToBlock")]
+    async fn revert_to_block(&self, block_number: BlockNumber) -> Result<(), Error>;
+
+    /// Replaces the tracing filter of the node, given in the syntax of `RUST_LOG`.
+    #[method(name = "setLogFilter")]
+    fn set_log_filter(&self, filter: String) -> Result<(), Error>;
+
+    /// Gets the number of requests the JSON-RPC server is serving.
+    #[method(name = "getInFlightRequests")]
+    fn get_in_flight_requests(&self) -> Result<u64, Error>;
+}
+
+/// Rpc server of the admin methods.
+pub struct JsonRpcAdminImpl {
+    /// None if the node runs without the sync.
+    pub sync_control: Option<SyncControl>,
+    pub gateway_monitor: GatewayMonitor,
+    pub log_filter_handle: LogFilterHandle,
+}
+
+impl JsonRpcAdminImpl {
+    fn sync_control(&self) -> Result<&SyncControl, Error> {
+        self.sync_control.as_ref().ok_or_else(|| admin_error("The node runs without the sync."))
+    }
+}
+
+#[async_trait]
+impl JsonRpcAdminServer for JsonRpcAdminImpl {
+    #[instrument(skip(self), level = "info", err)]
+    fn pause_sync(&self) -> Result<(), Error> {
+        self.sync_control()?.pause();
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "info", err)]
+    fn resume_sync(&self) -> Result<(), Error> {
+        self.sync_control()?.resume();
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "info", err)]
+    async fn revert_to_block(&self, block_number: BlockNumber) -> Result<(), Error> {
+        self.sync_control()?.revert(block_number).await.map_err(admin_error)
+    }
+
+    #[instrument(skip(self), level = "info", err)]
+    fn set_log_filter(&self, filter: String) -> Result<(), Error> {
+        let filter = EnvFilter::try_new(filter)
+            .map_err(|err| Error::Call(CallError::InvalidParams(err.into())))?;
+        self.log_filter_handle.reload(filter).map_err(admin_error)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_in_flight_requests(&self) -> Result<u64, Error> {
+        Ok(self.gateway_monitor.in_flight_requests())
+    }
+}
+
+fn admin_error(err: impl ToString) -> Error {
+    Error::Call(CallError::Custom(ErrorObject::owned(
+        ADMIN_ERROR_CODE,
+        err.to_string(),
+        None::<()>,
+    )))
+}
+
+/// Runs the admin server. Fails if its address isn't a loopback one or its token is missing.
+#[instrument(skip(admin), level = "debug", err)]
+pub async fn run_admin_server(
+    config: &AdminConfig,
+    admin: JsonRpcAdminImpl,
+) -> anyhow::Result<(SocketAddr, JoinHandle<Result<(), hyper::Error>>)> {
+    let server_address = SocketAddr::from_str(&config.server_address)?;
+    if !server_address.ip().is_loopback() {
+        return Err(AdminServerError::NonLocalAddress { address: server_address }.into());
+    }
+    let token = read_token(&config.token_file)?;
+
+    let server =
+        axum::Server::try_bind(&server_address)?.serve(app(admin, token).into_make_service());
+    let addr = server.local_addr();
+    let handle = tokio::spawn(server);
+    info!(local_address = %addr, "Admin server is running.");
+    Ok((addr, handle))
+}
+
+pub(crate) fn read_token(path: &Path) -> Result<String, AdminServerError> {
+    let token = fs::read_to_string(path)
+        .map_err(|source| AdminServerError::ReadToken { path: path.to_path_buf(), source })?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(AdminServerError::EmptyToken { path: path.to_path_buf() });
+    }
+    Ok(token.to_owned())
+}
+
+fn app(admin: JsonRpcAdminImpl, token: String) -> Router {
+    let methods: Methods = admin.into_rpc().into();
+    let token = Arc::new(token);
+    Router::new().route(
+        "/",
+        post(move |headers: HeaderMap, body: String| {
+            handle_request(methods.clone(), token.clone(), headers, body)
+        }),
+    )
+}
+
+async fn handle_request(
+    methods: Methods,
+    token: Arc<String>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let authorized = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // Compared in constant time, so the time of the comparison doesn't reveal the token.
+        .map_or(false, |request_token| bool::from(request_token.as_bytes().ct_eq(token.as_bytes())));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    match methods.raw_json_request(&body).await {
+        Ok((response, _)) => ([(CONTENT_TYPE, "application/json")], response).into_response(),
+        Err(_) => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
@@ -0,0 +1,158 @@
+use std::io::Write;
+use std::net::SocketAddr;
+
+use papyrus_gateway::GatewayMonitor;
+use papyrus_storage::test_utils::get_test_storage;
+use papyrus_sync::{CentralSource, StateSync, SyncControl};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+use tokio::task::JoinHandle;
+use tracing_subscriber::layer::Layered;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::admin::{run_admin_server, AdminConfig, AdminServerError, JsonRpcAdminImpl};
+use crate::config::ConfigBuilder;
+use crate::version::VERSION_FULL;
+
+const TOKEN: &str = "admin_token";
+
+// The subscriber keeps the filter that the admin server reloads alive.
+type TestSubscriber = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
+struct TestAdminServer {
+    addr: SocketAddr,
+    _handle: JoinHandle<Result<(), hyper::Error>>,
+    _subscriber: TestSubscriber,
+    _token_file: NamedTempFile,
+}
+
+impl TestAdminServer {
+    async fn call(&self, token: &str, method: &str, params: Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}", self.addr))
+            .bearer_auth(token)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn call_authorized(&self, method: &str, params: Value) -> Value {
+        let response = self.call(TOKEN, method, params).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        response.json().await.unwrap()
+    }
+}
+
+fn token_file(token: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(token.as_bytes()).unwrap();
+    file
+}
+
+async fn run_test_admin_server(sync_control: Option<SyncControl>) -> TestAdminServer {
+    let (filter, log_filter_handle) = reload::Layer::new(EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::registry().with(filter);
+    let file = token_file(&format!("{TOKEN}\n"));
+    let config = AdminConfig {
+        server_address: "127.0.0.1:0".to_owned(),
+        token_file: file.path().to_path_buf(),
+    };
+    let admin = JsonRpcAdminImpl {
+        sync_control,
+        gateway_monitor: GatewayMonitor::default(),
+        log_filter_handle,
+    };
+    let (addr, handle) = run_admin_server(&config, admin).await.unwrap();
+    TestAdminServer { addr, _handle: handle, _subscriber: subscriber, _token_file: file }
+}
+
+#[tokio::test]
+async fn reject_requests_without_the_token() {
+    let server = run_test_admin_server(None).await;
+    let response = server.call("wrong_token", "papyrus_admin_getInFlightRequests", json!([])).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}", server.addr))
+        .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "papyrus_admin_getInFlightRequests"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = server.call_authorized("papyrus_admin_getInFlightRequests", json!([])).await;
+    assert_eq!(response["result"], json!(0));
+}
+
+#[tokio::test]
+async fn pause_and_resume_sync() {
+    let (storage_reader, storage_writer) = get_test_storage();
+    let config = ConfigBuilder::default().config;
+    let central_source =
+        CentralSource::new(config.central, VERSION_FULL, storage_reader.clone()).unwrap();
+    let sync = StateSync::new(config.sync.unwrap(), central_source, storage_reader, storage_writer);
+    let control = sync.control();
+    let server = run_test_admin_server(Some(control.clone())).await;
+
+    let response = server.call_authorized("papyrus_admin_pauseSync", json!([])).await;
+    assert_eq!(response["result"], Value::Null);
+    assert!(control.is_paused());
+    server.call_authorized("papyrus_admin_resumeSync", json!([])).await;
+    assert!(!control.is_paused());
+}
+
+#[tokio::test]
+async fn sync_methods_without_sync() {
+    let server = run_test_admin_server(None).await;
+    let response = server.call_authorized("papyrus_admin_pauseSync", json!([])).await;
+    assert_eq!(response["error"]["message"], json!("The node runs without the sync."));
+    let response = server.call_authorized("papyrus_admin_revertToBlock", json!([1])).await;
+    assert_eq!(response["error"]["message"], json!("The node runs without the sync."));
+}
+
+#[tokio::test]
+async fn set_log_filter() {
+    let server = run_test_admin_server(None).await;
+    let response =
+        server.call_authorized("papyrus_admin_setLogFilter", json!(["debug,hyper=info"])).await;
+    assert_eq!(response["result"], Value::Null);
+    let response =
+        server.call_authorized("papyrus_admin_setLogFilter", json!(["info,papyrus=verbose"])).await;
+    assert!(response.get("error").is_some());
+}
+
+#[tokio::test]
+async fn admin_server_should_be_local_and_have_a_token() {
+    let admin = || {
+        let (_, log_filter_handle) = reload::Layer::new(EnvFilter::new("info"));
+        JsonRpcAdminImpl {
+            sync_control: None,
+            gateway_monitor: GatewayMonitor::default(),
+            log_filter_handle,
+        }
+    };
+    let file = token_file(TOKEN);
+    let config = AdminConfig {
+        server_address: "0.0.0.0:0".to_owned(),
+        token_file: file.path().to_path_buf(),
+    };
+    let err = run_admin_server(&config, admin()).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AdminServerError>(),
+        Some(AdminServerError::NonLocalAddress { .. })
+    ));
+
+    let empty_file = token_file(" \n");
+    let config = AdminConfig {
+        server_address: "127.0.0.1:0".to_owned(),
+        token_file: empty_file.path().to_path_buf(),
+    };
+    let err = run_admin_server(&config, admin()).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AdminServerError>(),
+        Some(AdminServerError::EmptyToken { .. })
+    ));
+}
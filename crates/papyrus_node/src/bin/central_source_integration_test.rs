@@ -32,7 +32,7 @@ async fn main() {
     let mut block_marker = initial_block_number;
     let block_stream = central_source.stream_new_blocks(block_marker, last_block_number).fuse();
     pin_mut!(block_stream);
-    while let Some(Ok((block_number, _block))) = block_stream.next().await {
+    while let Some(Ok((block_number, _block, _execution_info))) = block_stream.next().await {
         assert!(
             block_marker == block_number,
             "Expected block number ({block_marker}) does not match the result ({block_number}).",
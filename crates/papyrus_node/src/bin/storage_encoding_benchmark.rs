@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use futures_util::pin_mut;
+use papyrus_node::config::Config;
+use papyrus_node::version::VERSION_FULL;
+use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::db::serialization::StorageEncoding;
+use papyrus_storage::db::{DbConfig, RW};
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::{open_storage, StorageResult, StorageTxn, StorageWriter};
+use papyrus_sync::{CentralSource, CentralSourceTrait};
+use starknet_api::block::BlockNumber;
+use tokio_stream::StreamExt;
+
+// Number of the latest mainnet blocks in the sample.
+const SAMPLE_SIZE: u64 = 100;
+
+// The file of the storage grows by this step, which bounds the error of the measured sizes.
+const GROWTH_STEP: isize = 1 << 20;
+
+// A storage that the sample is written to in a single encoding.
+struct EncodedStorage {
+    path: PathBuf,
+    writer: StorageWriter,
+}
+
+impl EncodedStorage {
+    fn new(encoding: StorageEncoding, db_config: &DbConfig) -> Self {
+        let path = db_config.path.join(format!("{encoding:?}").to_lowercase());
+        fs::create_dir_all(&path).expect("Should make the storage directory");
+        let db_config = DbConfig {
+            path: path.clone(),
+            min_size: GROWTH_STEP as usize,
+            growth_step: GROWTH_STEP,
+            ..db_config.clone()
+        };
+        let (_, writer) = open_storage(db_config).expect("Open storage");
+        Self { path, writer: writer.with_encoding(encoding) }
+    }
+
+    // Runs `f` on a write transaction of the storage, serializing the values in its encoding.
+    fn write(&mut self, f: impl FnOnce(StorageTxn<'_, RW>) -> StorageResult<StorageTxn<'_, RW>>) {
+        let txn = self.writer.begin_rw_txn().expect("Begin transaction");
+        f(txn).expect("Write to storage").commit().expect("Commit transaction");
+    }
+
+    fn file_size(&self) -> u64 {
+        dir_size(&self.path)
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .expect("Read storage directory")
+        .map(|entry| {
+            entry.expect("Read storage directory").metadata().expect("File metadata").len()
+        })
+        .sum()
+}
+
+// Downloads a sample of the latest mainnet blocks, writes it into a storage in the legacy encoding
+// and into a storage in the compact encoding, and reports the size of the files of the storages.
+#[tokio::main]
+async fn main() {
+    let mut path = env::temp_dir();
+    path.push("data");
+    let _ = fs::remove_dir_all(path.clone());
+    fs::create_dir_all(path.clone()).expect("Should make a temporary `data` directory");
+    let config = Config::load(vec![
+        "Papyrus".to_owned(),
+        "--chain_id=SN_MAIN".to_owned(),
+        "--central_url=https://alpha-mainnet.starknet.io/".to_owned(),
+        format!("--storage={}", path.display()),
+    ])
+    .expect("Load config");
+    let (storage_reader, _) = open_storage(config.storage.db_config.clone()).expect("Open storage");
+    let central_source = CentralSource::new(config.central, VERSION_FULL, storage_reader)
+        .expect("Create new client");
+    let last_block_number =
+        central_source.get_block_marker().await.expect("Central get block marker");
+    let initial_block_number = BlockNumber(last_block_number.0 - SAMPLE_SIZE);
+
+    let mut storages = [
+        EncodedStorage::new(StorageEncoding::Legacy, &config.storage.db_config),
+        EncodedStorage::new(StorageEncoding::Compact, &config.storage.db_config),
+    ];
+    // The storages are written from block 0, so the sample is stored under its offset.
+    let storage_block_number =
+        |block_number: BlockNumber| BlockNumber(block_number.0 - initial_block_number.0);
+
+    let block_stream =
+        central_source.stream_new_blocks(initial_block_number, last_block_number).fuse();
+    pin_mut!(block_stream);
+    while let Some(Ok((block_number, block, _execution_info))) = block_stream.next().await {
+        let block_number = storage_block_number(block_number);
+        for storage in &mut storages {
+            storage.write(|txn| {
+                txn.append_header(block_number, &block.header)?
+                    .append_body(block_number, block.body.clone())
+            });
+        }
+    }
+
+    let state_update_stream =
+        central_source.stream_state_updates(initial_block_number, last_block_number).fuse();
+    pin_mut!(state_update_stream);
+    while let Some(Ok((block_number, _block_hash, state_diff, deployed_classes))) =
+        state_update_stream.next().await
+    {
+        let block_number = storage_block_number(block_number);
+        for storage in &mut storages {
+            storage.write(|txn| {
+                txn.append_state_diff(block_number, state_diff.clone(), deployed_classes.clone())
+            });
+        }
+    }
+
+    println!("Storage files of blocks {initial_block_number} to {last_block_number}:");
+    let [legacy, compact] = storages.map(|storage| storage.file_size());
+    let reduction = 100.0 * (1.0 - compact as f64 / legacy.max(1) as f64);
+    println!("legacy: {legacy} bytes, compact: {compact} bytes, reduction: {reduction:.1}%");
+}
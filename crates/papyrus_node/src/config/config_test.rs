@@ -3,11 +3,17 @@ use std::env::{self, args};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use papyrus_gateway::ApiVersion;
+use papyrus_sync::{CheckpointConfig, StopAt};
+use starknet_api::block::{BlockHash, BlockNumber};
 use starknet_api::core::ChainId;
+use starknet_api::hash::StarkHash;
+use starknet_client::{EndpointConfig, RateLimitConfig};
 use tempfile::NamedTempFile;
 use test_utils::get_absolute_path;
 
-use crate::config::{Config, ConfigBuilder};
+use crate::admin::{AdminConfig, AdminServerError};
+use crate::config::{Config, ConfigBuilder, ConfigError, NodeCommand};
 
 #[test]
 fn load_default_config() {
@@ -20,7 +26,10 @@ fn load_default_config() {
 fn default_builder() {
     let builder = ConfigBuilder::default();
     assert_eq!(builder.config.gateway.chain_id, ChainId("SN_MAIN".to_owned()));
-    assert!(builder.config.sync.is_some())
+    assert!(builder.config.sync.is_some());
+    assert!(builder.config.peer.is_none());
+    assert!(builder.config.admin.is_none());
+    assert!(builder.config.run_gateway);
 }
 
 #[test]
@@ -81,6 +90,19 @@ fn load_yaml_config() {
 chain_id: TEST
 gateway:
     max_events_keys: 1234
+    max_events_scan_millis: 100
+    execution:
+        max_recursion_depth: 10
+    default_version: v0_3
+    limits:
+        max_concurrent_calls: 5
+        api_key_rate_limits:
+            key:
+                requests_per_second: 10
+                burst: 20
+        method_denylist: [starknet_getEvents]
+    serve_feeder_gateway: true
+    continuation_token_secret: secret
 ";
     f.write_all(yaml.as_bytes()).unwrap();
     let args = vec!["Papyrus".to_owned(), format!("--config_file={}", f.path().to_str().unwrap())];
@@ -88,6 +110,20 @@ gateway:
 
     assert_eq!(builder.chain_id, ChainId("TEST".to_owned()));
     assert_eq!(builder.config.gateway.max_events_keys, 1234);
+    assert_eq!(builder.config.gateway.max_events_scan_millis, 100);
+    assert_eq!(builder.config.gateway.execution_config.max_recursion_depth, 10);
+    assert_eq!(builder.config.gateway.default_version, ApiVersion::V0_3);
+    assert!(builder.config.gateway.serve_feeder_gateway);
+    assert_eq!(builder.config.gateway.continuation_token_secret, Some("secret".to_owned()));
+    let limits = &builder.config.gateway.limits;
+    assert_eq!(limits.max_concurrent_calls, 5);
+    assert_eq!(
+        limits.api_key_rate_limits.get("key"),
+        Some(&RateLimitConfig { requests_per_second: 10, burst: 20 })
+    );
+    assert_eq!(limits.method_denylist, vec!["starknet_getEvents".to_owned()]);
+    // Limits that aren't in the file keep their default.
+    assert_eq!(limits.max_request_body_size, 10 * 1024 * 1024);
 }
 
 #[test]
@@ -115,3 +151,227 @@ central:
     ]);
     assert_eq!(builder.config.central.http_headers.unwrap(), target_http_headers);
 }
+
+#[test]
+fn load_peer_config() {
+    let mut f = NamedTempFile::new().unwrap();
+    let yaml = r"
+peer:
+    url: http://127.0.0.1:8080
+    concurrent_requests: 5
+";
+    f.write_all(yaml.as_bytes()).unwrap();
+    let args = vec![
+        "Papyrus".to_owned(),
+        format!("--config_file={}", f.path().to_str().unwrap()),
+        "--peer_url=http://127.0.0.1:9090".to_owned(),
+    ];
+    let builder =
+        ConfigBuilder::default().prepare_command(args).unwrap().yaml().unwrap().args().unwrap();
+
+    let peer = builder.config.peer.expect("Expected a peer config");
+    assert_eq!(peer.url, "http://127.0.0.1:9090".to_owned());
+    assert_eq!(peer.concurrent_requests, 5);
+}
+
+#[test]
+fn load_admin_config() {
+    let mut f = NamedTempFile::new().unwrap();
+    let yaml = r"
+admin:
+    token_file: ./admin_token
+";
+    f.write_all(yaml.as_bytes()).unwrap();
+    let args = vec!["Papyrus".to_owned(), format!("--config_file={}", f.path().to_str().unwrap())];
+    let builder =
+        ConfigBuilder::default().prepare_command(args).unwrap().yaml().unwrap().args().unwrap();
+
+    let admin = builder.config.admin.expect("Expected an admin config");
+    assert_eq!(admin.server_address, "127.0.0.1:8082".to_owned());
+    assert_eq!(admin.token_file, PathBuf::from("./admin_token"));
+}
+
+#[test]
+fn reject_empty_admin_token() {
+    let mut token_file = NamedTempFile::new().unwrap();
+    token_file.write_all(b" \n").unwrap();
+    let mut builder = ConfigBuilder::default();
+    builder.config.admin = Some(AdminConfig {
+        server_address: "127.0.0.1:8082".to_owned(),
+        token_file: token_file.path().to_path_buf(),
+    });
+
+    let err = builder.validate_admin().err().expect("Expected an empty token error");
+    assert!(matches!(err, ConfigError::Admin(AdminServerError::EmptyToken { .. })));
+}
+
+#[test]
+fn reject_empty_rate_limit() {
+    for config in [
+        RateLimitConfig { requests_per_second: 0, burst: 40 },
+        RateLimitConfig { requests_per_second: 20, burst: 0 },
+    ] {
+        let mut builder = ConfigBuilder::default();
+        builder.config.central.rate_limit = Some(config);
+        let err = builder.validate_rate_limit().err().expect("Expected a rate limit error");
+        assert!(
+            matches!(err, ConfigError::BadRateLimit { config: err_config } if err_config == config)
+        );
+
+        let mut builder = ConfigBuilder::default();
+        builder.config.gateway.limits.ip_rate_limit = Some(config);
+        let err = builder.validate_rate_limit().err().expect("Expected a rate limit error");
+        assert!(
+            matches!(err, ConfigError::BadRateLimit { config: err_config } if err_config == config)
+        );
+    }
+}
+
+#[test]
+fn revert_command() {
+    let args = vec![
+        "Papyrus".to_owned(),
+        "--chain_id=CHAIN_ID".to_owned(),
+        "revert".to_owned(),
+        "--block_number=10".to_owned(),
+        "--to_ommer".to_owned(),
+    ];
+    let builder = ConfigBuilder::default().prepare_command(args).unwrap().args().unwrap();
+
+    assert_eq!(
+        builder.config.command,
+        Some(NodeCommand::Revert {
+            block_number: BlockNumber(10),
+            move_to_ommer: true,
+            assume_yes: false
+        })
+    );
+    assert_eq!(builder.chain_id, ChainId("CHAIN_ID".to_owned()));
+}
+
+#[test]
+fn import_command() {
+    let args =
+        vec!["Papyrus".to_owned(), "import".to_owned(), "--path=/tmp/blocks.tar.gz".to_owned()];
+    let builder = ConfigBuilder::default().prepare_command(args).unwrap().args().unwrap();
+
+    assert_eq!(
+        builder.config.command,
+        Some(NodeCommand::Import { path: PathBuf::from("/tmp/blocks.tar.gz") })
+    );
+}
+
+#[test]
+fn load_stop_at_config() {
+    let mut f = NamedTempFile::new().unwrap();
+    let yaml = r"
+sync:
+    stop_at:
+        block_number: 5
+";
+    f.write_all(yaml.as_bytes()).unwrap();
+    let args = vec![
+        "Papyrus".to_owned(),
+        format!("--config_file={}", f.path().to_str().unwrap()),
+        "--one_shot".to_owned(),
+        "--no_gateway".to_owned(),
+    ];
+    let builder =
+        ConfigBuilder::default().prepare_command(args).unwrap().yaml().unwrap().args().unwrap();
+    let sync_config = builder.config.sync.expect("Expected a sync config");
+    assert_eq!(sync_config.stop_at, Some(StopAt::BlockNumber(BlockNumber(5))));
+    assert!(sync_config.one_shot);
+    assert!(!builder.config.run_gateway);
+
+    let args = vec!["Papyrus".to_owned(), "--stop_at_block=0x1a".to_owned()];
+    let builder = ConfigBuilder::default().prepare_command(args).unwrap().args().unwrap();
+    assert_eq!(
+        builder.config.sync.unwrap().stop_at,
+        Some(StopAt::BlockHash(BlockHash(StarkHash::from(0x1a_u64))))
+    );
+
+    let args = vec!["Papyrus".to_owned(), "--stop_at_block=latest".to_owned()];
+    assert!(ConfigBuilder::default().prepare_command(args).unwrap().args().is_err());
+}
+
+#[test]
+fn load_adaptive_concurrency_config() {
+    let mut f = NamedTempFile::new().unwrap();
+    let yaml = r"
+central:
+    max_bytes_in_flight: 1024
+    adaptive_concurrency:
+        target_latency_millis: 100
+";
+    f.write_all(yaml.as_bytes()).unwrap();
+    let args = vec!["Papyrus".to_owned(), format!("--config_file={}", f.path().to_str().unwrap())];
+    let default_min_concurrent_requests =
+        ConfigBuilder::default().config.central.adaptive_concurrency.min_concurrent_requests;
+    let builder =
+        ConfigBuilder::default().prepare_command(args).unwrap().yaml().unwrap().args().unwrap();
+
+    let central = builder.config.central;
+    assert_eq!(central.max_bytes_in_flight, 1024);
+    assert_eq!(central.adaptive_concurrency.target_latency_millis, 100);
+    assert_eq!(
+        central.adaptive_concurrency.min_concurrent_requests,
+        default_min_concurrent_requests
+    );
+}
+
+#[test]
+fn load_fallback_endpoints_config() {
+    let mut f = NamedTempFile::new().unwrap();
+    let yaml = r"
+central:
+    fallback_endpoints:
+        - url: https://fallback/
+          http_headers:
+              NAME: VALUE
+    failover:
+        hedge_after_millis: 100
+";
+    f.write_all(yaml.as_bytes()).unwrap();
+    let args = vec!["Papyrus".to_owned(), format!("--config_file={}", f.path().to_str().unwrap())];
+    let builder =
+        ConfigBuilder::default().prepare_command(args).unwrap().yaml().unwrap().args().unwrap();
+
+    let central = builder.config.central;
+    assert_eq!(
+        central.fallback_endpoints,
+        vec![EndpointConfig {
+            url: "https://fallback/".to_owned(),
+            http_headers: Some(HashMap::from([("NAME".to_owned(), "VALUE".to_owned())])),
+        }]
+    );
+    assert_eq!(central.failover.hedge_after_millis, Some(100));
+    assert_eq!(
+        central.failover.max_consecutive_errors,
+        ConfigBuilder::default().config.central.failover.max_consecutive_errors
+    );
+}
+
+#[test]
+fn load_checkpoint_config() {
+    let mut f = NamedTempFile::new().unwrap();
+    let yaml = r"
+sync:
+    checkpoint:
+        block_number: 1000
+        block_hash: 0x1a
+        state_snapshot: ./state_snapshot.json
+";
+    f.write_all(yaml.as_bytes()).unwrap();
+    let args = vec!["Papyrus".to_owned(), format!("--config_file={}", f.path().to_str().unwrap())];
+    let builder =
+        ConfigBuilder::default().prepare_command(args).unwrap().yaml().unwrap().args().unwrap();
+
+    assert_eq!(
+        builder.config.sync.unwrap().checkpoint,
+        Some(CheckpointConfig {
+            block_number: BlockNumber(1000),
+            block_hash: BlockHash(StarkHash::from(0x1a_u64)),
+            state_snapshot: Some(PathBuf::from("./state_snapshot.json")),
+        })
+    );
+}
@@ -3,16 +3,23 @@ use std::path::PathBuf;
 use std::time::Duration;
 use std::{env, fs};
 
-use papyrus_gateway::GatewayConfig;
+use papyrus_execution::ExecutionConfig;
+use papyrus_gateway::{ApiVersion, GatewayConfig, LimitsConfig};
 use papyrus_monitoring_gateway::MonitoringGatewayConfig;
 use papyrus_storage::db::DbConfig;
 use papyrus_storage::StorageConfig;
-use papyrus_sync::{CentralSourceConfig, SyncConfig};
+use papyrus_sync::{
+    AdaptiveConcurrencyConfig, CentralSourceConfig, CheckpointConfig, PeerSourceConfig, StopAt,
+    SyncConfig,
+};
 use serde::{Deserialize, Serialize};
-use starknet_api::core::ChainId;
-use starknet_client::RetryConfig;
+use starknet_api::core::{ChainId, ContractAddress};
+use starknet_client::{EndpointConfig, FailoverConfig, RateLimitConfig, RetryConfig};
 
-use crate::config::{Config, ConfigBuilder};
+use crate::admin::AdminConfig;
+use crate::config::{
+    Config, ConfigBuilder, DEFAULT_ADMIN_SERVER_ADDRESS, DEFAULT_PEER_CONCURRENT_REQUESTS,
+};
 
 
 // Defines the expected structure of the configuration file. All the fields are optional so the user
@@ -23,8 +30,10 @@ use crate::config::{Config, ConfigBuilder};
 pub struct FileConfigFormat {
     chain_id: Option<ChainId>,
     central: Option<Central>,
+    peer: Option<Peer>,
     gateway: Option<Gateway>,
     monitoring_gateway: Option<MonitoringGateway>,
+    admin: Option<Admin>,
     storage: Option<Storage>,
     sync: Option<Sync>,
 }
@@ -40,6 +49,10 @@ impl FileConfigFormat {
             central.update_central(&mut builder.config.central);
         }
 
+        if let Some(peer) = self.peer {
+            peer.update_peer(&mut builder.config.peer);
+        }
+
         if let Some(gateway) = self.gateway {
             gateway.update_gateway(&mut builder.config.gateway);
         }
@@ -48,6 +61,10 @@ impl FileConfigFormat {
             monitoring_gateway.update_monitoring_gateway(&mut builder.config.monitoring_gateway);
         }
 
+        if let Some(admin) = self.admin {
+            admin.update_admin(&mut builder.config.admin);
+        }
+
         if let Some(storage) = self.storage {
             storage.update_storage(&mut builder.config.storage);
         }
@@ -65,8 +82,10 @@ impl From<Config> for FileConfigFormat {
         FileConfigFormat {
             chain_id: Some(config.gateway.chain_id.clone()),
             central: Some(Central::from(config.central)),
+            peer: config.peer.map(Peer::from),
             gateway: Some(Gateway::from(config.gateway)),
             monitoring_gateway: Some(MonitoringGateway::from(config.monitoring_gateway)),
+            admin: config.admin.map(Admin::from),
             storage: Some(Storage::from(config.storage)),
             sync: config.sync.map(Sync::from),
         }
@@ -79,6 +98,39 @@ impl From<GatewayConfig> for Gateway {
             server_address: Some(config.server_address),
             max_events_chunk_size: Some(config.max_events_chunk_size),
             max_events_keys: Some(config.max_events_keys),
+            max_events_scanned: Some(config.max_events_scanned),
+            max_events_scan_millis: Some(config.max_events_scan_millis),
+            execution: Some(Execution::from(config.execution_config)),
+            default_version: Some(config.default_version),
+            limits: Some(Limits::from(config.limits)),
+            serve_feeder_gateway: Some(config.serve_feeder_gateway),
+            continuation_token_secret: config.continuation_token_secret,
+        }
+    }
+}
+
+impl From<LimitsConfig> for Limits {
+    fn from(config: LimitsConfig) -> Self {
+        Limits {
+            max_request_body_size: Some(config.max_request_body_size),
+            max_response_body_size: Some(config.max_response_body_size),
+            max_concurrent_calls: Some(config.max_concurrent_calls),
+            max_concurrent_batch_calls: Some(config.max_concurrent_batch_calls),
+            ip_rate_limit: config.ip_rate_limit,
+            api_key_rate_limits: Some(config.api_key_rate_limits),
+            method_allowlist: config.method_allowlist,
+            method_denylist: Some(config.method_denylist),
+        }
+    }
+}
+
+impl From<ExecutionConfig> for Execution {
+    fn from(config: ExecutionConfig) -> Self {
+        Execution {
+            fee_contract_address: Some(config.fee_contract_address),
+            invoke_tx_max_n_steps: Some(config.invoke_tx_max_n_steps),
+            validate_tx_max_n_steps: Some(config.validate_tx_max_n_steps),
+            max_recursion_depth: Some(config.max_recursion_depth),
         }
     }
 }
@@ -87,13 +139,30 @@ impl From<CentralSourceConfig> for Central {
     fn from(config: CentralSourceConfig) -> Self {
         Central {
             concurrent_requests: Some(config.concurrent_requests),
+            max_bytes_in_flight: Some(config.max_bytes_in_flight),
+            adaptive_concurrency: Some(AdaptiveConcurrency::from(config.adaptive_concurrency)),
             url: Some(config.url),
             http_headers: config.http_headers,
+            fallback_endpoints: Some(config.fallback_endpoints),
+            failover: Some(Failover::from(config.failover)),
             retry: Some(Retry::from(config.retry_config)),
+            rate_limit: config.rate_limit,
         }
     }
 }
 
+impl From<PeerSourceConfig> for Peer {
+    fn from(config: PeerSourceConfig) -> Self {
+        Peer { url: Some(config.url), concurrent_requests: Some(config.concurrent_requests) }
+    }
+}
+
+impl From<AdminConfig> for Admin {
+    fn from(config: AdminConfig) -> Self {
+        Admin { server_address: Some(config.server_address), token_file: Some(config.token_file) }
+    }
+}
+
 impl From<RetryConfig> for Retry {
     fn from(config: RetryConfig) -> Self {
         Retry {
@@ -104,6 +173,25 @@ impl From<RetryConfig> for Retry {
     }
 }
 
+impl From<AdaptiveConcurrencyConfig> for AdaptiveConcurrency {
+    fn from(config: AdaptiveConcurrencyConfig) -> Self {
+        AdaptiveConcurrency {
+            min_concurrent_requests: Some(config.min_concurrent_requests),
+            target_latency_millis: Some(config.target_latency_millis),
+        }
+    }
+}
+
+impl From<FailoverConfig> for Failover {
+    fn from(config: FailoverConfig) -> Self {
+        Failover {
+            max_consecutive_errors: Some(config.max_consecutive_errors),
+            failback_delay_millis: Some(config.failback_delay_millis),
+            hedge_after_millis: config.hedge_after_millis,
+        }
+    }
+}
+
 impl From<MonitoringGatewayConfig> for MonitoringGateway {
     fn from(config: MonitoringGatewayConfig) -> Self {
         MonitoringGateway { server_address: Some(config.server_address) }
@@ -141,6 +229,13 @@ impl From<SyncConfig> for Sync {
             ),
             blocks_max_stream_size: Some(config.blocks_max_stream_size),
             state_updates_max_stream_size: Some(config.state_updates_max_stream_size),
+            stop_at: config.stop_at,
+            one_shot: Some(config.one_shot),
+            checkpoint: config.checkpoint,
+            separate_class_sync: Some(config.separate_class_sync),
+            header_first_sync: Some(config.header_first_sync),
+            trace_sync: Some(config.trace_sync),
+            compiled_class_sync: Some(config.compiled_class_sync),
         }
     }
 }
@@ -149,9 +244,14 @@ impl From<SyncConfig> for Sync {
 #[serde(deny_unknown_fields)]
 struct Central {
     concurrent_requests: Option<usize>,
+    max_bytes_in_flight: Option<usize>,
+    adaptive_concurrency: Option<AdaptiveConcurrency>,
     url: Option<String>,
     http_headers: Option<HashMap<String, String>>,
+    fallback_endpoints: Option<Vec<EndpointConfig>>,
+    failover: Option<Failover>,
     retry: Option<Retry>,
+    rate_limit: Option<RateLimitConfig>,
 }
 
 impl Central {
@@ -159,6 +259,12 @@ impl Central {
         if let Some(concurrent_requests) = self.concurrent_requests {
             config.concurrent_requests = concurrent_requests;
         }
+        if let Some(max_bytes_in_flight) = self.max_bytes_in_flight {
+            config.max_bytes_in_flight = max_bytes_in_flight;
+        }
+        if let Some(adaptive_concurrency) = self.adaptive_concurrency {
+            adaptive_concurrency.update_adaptive_concurrency(&mut config.adaptive_concurrency);
+        }
         if let Some(url) = self.url {
             config.url = url;
         }
@@ -172,9 +278,66 @@ impl Central {
                 }
             };
         }
+        if let Some(fallback_endpoints) = self.fallback_endpoints {
+            config.fallback_endpoints = fallback_endpoints;
+        }
+        if let Some(failover) = self.failover {
+            failover.update_failover_config(&mut config.failover);
+        }
         if let Some(retry) = self.retry {
             retry.update_retry_config(&mut config.retry_config);
         }
+        if let Some(rate_limit) = self.rate_limit {
+            config.rate_limit = Some(rate_limit);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Peer {
+    url: Option<String>,
+    concurrent_requests: Option<usize>,
+}
+
+impl Peer {
+    // The peer is optional, it is enabled by setting its url.
+    fn update_peer(self, config: &mut Option<PeerSourceConfig>) {
+        if let Some(url) = self.url {
+            config
+                .get_or_insert_with(|| PeerSourceConfig {
+                    url: String::new(),
+                    concurrent_requests: DEFAULT_PEER_CONCURRENT_REQUESTS,
+                })
+                .url = url;
+        }
+        if let (Some(config), Some(concurrent_requests)) = (config, self.concurrent_requests) {
+            config.concurrent_requests = concurrent_requests;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Admin {
+    server_address: Option<String>,
+    token_file: Option<PathBuf>,
+}
+
+impl Admin {
+    // The admin server is optional, it is enabled by setting its token file.
+    fn update_admin(self, config: &mut Option<AdminConfig>) {
+        if let Some(token_file) = self.token_file {
+            config
+                .get_or_insert_with(|| AdminConfig {
+                    server_address: DEFAULT_ADMIN_SERVER_ADDRESS.to_owned(),
+                    token_file: PathBuf::new(),
+                })
+                .token_file = token_file;
+        }
+        if let (Some(config), Some(server_address)) = (config, self.server_address) {
+            config.server_address = server_address;
+        }
     }
 }
 
@@ -200,12 +363,59 @@ impl Retry {
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Failover {
+    max_consecutive_errors: Option<usize>,
+    failback_delay_millis: Option<u64>,
+    hedge_after_millis: Option<u64>,
+}
+
+impl Failover {
+    fn update_failover_config(self, config: &mut FailoverConfig) {
+        if let Some(max_consecutive_errors) = self.max_consecutive_errors {
+            config.max_consecutive_errors = max_consecutive_errors;
+        }
+        if let Some(failback_delay_millis) = self.failback_delay_millis {
+            config.failback_delay_millis = failback_delay_millis;
+        }
+        if let Some(hedge_after_millis) = self.hedge_after_millis {
+            config.hedge_after_millis = Some(hedge_after_millis);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct AdaptiveConcurrency {
+    min_concurrent_requests: Option<usize>,
+    target_latency_millis: Option<u64>,
+}
+
+impl AdaptiveConcurrency {
+    fn update_adaptive_concurrency(self, config: &mut AdaptiveConcurrencyConfig) {
+        if let Some(min_concurrent_requests) = self.min_concurrent_requests {
+            config.min_concurrent_requests = min_concurrent_requests;
+        }
+        if let Some(target_latency_millis) = self.target_latency_millis {
+            config.target_latency_millis = target_latency_millis;
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 #[serde(deny_unknown_fields)]
 struct Gateway {
     server_address: Option<String>,
     max_events_chunk_size: Option<usize>,
     max_events_keys: Option<usize>,
+    max_events_scanned: Option<usize>,
+    max_events_scan_millis: Option<u64>,
+    execution: Option<Execution>,
+    default_version: Option<ApiVersion>,
+    limits: Option<Limits>,
+    serve_feeder_gateway: Option<bool>,
+    continuation_token_secret: Option<String>,
 }
 
 impl Gateway {
@@ -219,6 +429,95 @@ impl Gateway {
         if let Some(max_events_keys) = self.max_events_keys {
             config.max_events_keys = max_events_keys;
         }
+        if let Some(max_events_scanned) = self.max_events_scanned {
+            config.max_events_scanned = max_events_scanned;
+        }
+        if let Some(max_events_scan_millis) = self.max_events_scan_millis {
+            config.max_events_scan_millis = max_events_scan_millis;
+        }
+        if let Some(execution) = self.execution {
+            execution.update_execution(&mut config.execution_config);
+        }
+        if let Some(default_version) = self.default_version {
+            config.default_version = default_version;
+        }
+        if let Some(limits) = self.limits {
+            limits.update_limits(&mut config.limits);
+        }
+        if let Some(serve_feeder_gateway) = self.serve_feeder_gateway {
+            config.serve_feeder_gateway = serve_feeder_gateway;
+        }
+        if let Some(continuation_token_secret) = self.continuation_token_secret {
+            config.continuation_token_secret = Some(continuation_token_secret);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Limits {
+    max_request_body_size: Option<u32>,
+    max_response_body_size: Option<u32>,
+    max_concurrent_calls: Option<u32>,
+    max_concurrent_batch_calls: Option<u32>,
+    ip_rate_limit: Option<RateLimitConfig>,
+    api_key_rate_limits: Option<HashMap<String, RateLimitConfig>>,
+    method_allowlist: Option<Vec<String>>,
+    method_denylist: Option<Vec<String>>,
+}
+
+impl Limits {
+    fn update_limits(self, config: &mut LimitsConfig) {
+        if let Some(max_request_body_size) = self.max_request_body_size {
+            config.max_request_body_size = max_request_body_size;
+        }
+        if let Some(max_response_body_size) = self.max_response_body_size {
+            config.max_response_body_size = max_response_body_size;
+        }
+        if let Some(max_concurrent_calls) = self.max_concurrent_calls {
+            config.max_concurrent_calls = max_concurrent_calls;
+        }
+        if let Some(max_concurrent_batch_calls) = self.max_concurrent_batch_calls {
+            config.max_concurrent_batch_calls = max_concurrent_batch_calls;
+        }
+        if let Some(ip_rate_limit) = self.ip_rate_limit {
+            config.ip_rate_limit = Some(ip_rate_limit);
+        }
+        if let Some(api_key_rate_limits) = self.api_key_rate_limits {
+            config.api_key_rate_limits = api_key_rate_limits;
+        }
+        if let Some(method_allowlist) = self.method_allowlist {
+            config.method_allowlist = Some(method_allowlist);
+        }
+        if let Some(method_denylist) = self.method_denylist {
+            config.method_denylist = method_denylist;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Execution {
+    fee_contract_address: Option<ContractAddress>,
+    invoke_tx_max_n_steps: Option<u32>,
+    validate_tx_max_n_steps: Option<u32>,
+    max_recursion_depth: Option<usize>,
+}
+
+impl Execution {
+    fn update_execution(self, config: &mut ExecutionConfig) {
+        if let Some(fee_contract_address) = self.fee_contract_address {
+            config.fee_contract_address = fee_contract_address;
+        }
+        if let Some(invoke_tx_max_n_steps) = self.invoke_tx_max_n_steps {
+            config.invoke_tx_max_n_steps = invoke_tx_max_n_steps;
+        }
+        if let Some(validate_tx_max_n_steps) = self.validate_tx_max_n_steps {
+            config.validate_tx_max_n_steps = validate_tx_max_n_steps;
+        }
+        if let Some(max_recursion_depth) = self.max_recursion_depth {
+            config.max_recursion_depth = max_recursion_depth;
+        }
     }
 }
 
@@ -283,6 +582,13 @@ struct Sync {
     recoverable_error_sleep_duration_secs: Option<u64>,
     blocks_max_stream_size: Option<u32>,
     state_updates_max_stream_size: Option<u32>,
+    stop_at: Option<StopAt>,
+    one_shot: Option<bool>,
+    checkpoint: Option<CheckpointConfig>,
+    separate_class_sync: Option<bool>,
+    header_first_sync: Option<bool>,
+    trace_sync: Option<bool>,
+    compiled_class_sync: Option<bool>,
 }
 
 impl Sync {
@@ -301,5 +607,26 @@ impl Sync {
         if let Some(state_updates_max_stream_size) = self.state_updates_max_stream_size {
             config.state_updates_max_stream_size = state_updates_max_stream_size;
         }
+        if let Some(stop_at) = self.stop_at {
+            config.stop_at = Some(stop_at);
+        }
+        if let Some(one_shot) = self.one_shot {
+            config.one_shot = one_shot;
+        }
+        if let Some(checkpoint) = self.checkpoint {
+            config.checkpoint = Some(checkpoint);
+        }
+        if let Some(separate_class_sync) = self.separate_class_sync {
+            config.separate_class_sync = separate_class_sync;
+        }
+        if let Some(header_first_sync) = self.header_first_sync {
+            config.header_first_sync = header_first_sync;
+        }
+        if let Some(trace_sync) = self.trace_sync {
+            config.trace_sync = trace_sync;
+        }
+        if let Some(compiled_class_sync) = self.compiled_class_sync {
+            config.compiled_class_sync = compiled_class_sync;
+        }
     }
 }
@@ -13,15 +13,22 @@ use serde_json::value::Value;
 
 use clap::{arg, value_parser, Arg, ArgMatches, Command, ArgAction, builder::ValueParser};
 use file_config::FileConfigFormat;
-use papyrus_gateway::GatewayConfig;
+use papyrus_execution::ExecutionConfig;
+use papyrus_gateway::{ApiVersion, GatewayConfig, LimitsConfig};
 use papyrus_monitoring_gateway::MonitoringGatewayConfig;
 use papyrus_storage::db::DbConfig;
 use papyrus_storage::StorageConfig;
-use papyrus_sync::{CentralSourceConfig, SyncConfig};
+use papyrus_sync::{
+    AdaptiveConcurrencyConfig, CentralSourceConfig, PeerSourceConfig, StopAt, SyncConfig,
+};
 use serde::{Deserialize, Serialize};
-use starknet_api::core::ChainId;
-use starknet_client::RetryConfig;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ChainId, ContractAddress, PatriciaKey};
+use starknet_api::hash::StarkHash;
+use starknet_api::patricia_key;
+use starknet_client::{FailoverConfig, RateLimitConfig, RetryConfig};
 
+use crate::admin::{read_token, AdminConfig, AdminServerError};
 use crate::version::VERSION_FULL;
 
 lazy_static! { static ref CONFIG: Config = ConfigBuilder::build(env::args().collect()).unwrap(); 
@@ -31,6 +38,12 @@ lazy_static! { static ref CONFIG: Config = ConfigBuilder::build(env::args().coll
 // The path of the default configuration file, provided as part of the crate.
 const CONFIG_FILE: &str = "config/default.yaml";
 
+// The default number of concurrent requests to a peer node, used if the peer is given without it.
+pub(crate) const DEFAULT_PEER_CONCURRENT_REQUESTS: usize = 10;
+
+// The default address of the admin server, used if it is given without one.
+pub(crate) const DEFAULT_ADMIN_SERVER_ADDRESS: &str = "127.0.0.1:8082";
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ConfigAttr {
     pub default: Option<Value>,
@@ -146,10 +159,36 @@ impl BuilderConfig {
 pub struct Config {
     pub gateway: GatewayConfig,
     pub central: CentralSourceConfig,
+    /// If set, the sync uses the JSON-RPC server of another node instead of the central.
+    pub peer: Option<PeerSourceConfig>,
     pub monitoring_gateway: MonitoringGatewayConfig,
+    /// If set, the admin JSON-RPC server runs along with the JSON-RPC server.
+    pub admin: Option<AdminConfig>,
     pub storage: StorageConfig,
     /// None if the syncing should be disabled.
     pub sync: Option<SyncConfig>,
+    /// If false, the node runs without the JSON-RPC and monitoring servers, and exits once the sync
+    /// stops.
+    pub run_gateway: bool,
+    /// A command to run instead of the node, None for running the node.
+    #[serde(skip)]
+    pub command: Option<NodeCommand>,
+}
+
+/// Commands that operate on the node's storage instead of running the node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeCommand {
+    /// Reverts the storage so that `block_number` is the first block that doesn't exist.
+    Revert {
+        block_number: BlockNumber,
+        /// Whether to keep the reverted blocks in the ommer tables.
+        move_to_ommer: bool,
+        /// Whether to skip the confirmation prompt.
+        assume_yes: bool,
+    },
+    /// Imports blocks and state updates from a directory or a tar archive of feeder gateway
+    /// responses.
+    Import { path: PathBuf },
 }
 
 #[derive(Debug)]
@@ -226,6 +265,17 @@ pub enum ConfigError {
         "CLA http_header \"{illegal_header}\" is not valid. The Expected format is name:value"
     )]
     CLAHttpHeader { illegal_header: String },
+    #[error(
+        "CLA stop_at_block \"{value}\" is not valid. Expected a block number or a 0x-prefixed \
+         block hash"
+    )]
+    CLAStopAtBlock { value: String },
+    #[error(transparent)]
+    Admin(#[from] AdminServerError),
+    #[error(
+        "rate_limit {config:?} is not valid. Expected positive requests_per_second and burst"
+    )]
+    BadRateLimit { config: RateLimitConfig },
 }
 
 // Builds the configuration for the node based on default values, yaml configuration file and
@@ -234,7 +284,7 @@ pub enum ConfigError {
 pub(crate) struct ConfigBuilder {
     args: Option<ArgMatches>,
     chain_id: ChainId,
-    config: Config,
+    pub(crate) config: Config,
 }
 
 // Default configuration values.
@@ -248,23 +298,61 @@ impl Default for ConfigBuilder {
             config: Config {
                 central: CentralSourceConfig {
                     concurrent_requests: 300,
+                    max_bytes_in_flight: 1 << 29,
+                    adaptive_concurrency: AdaptiveConcurrencyConfig {
+                        min_concurrent_requests: 10,
+                        target_latency_millis: 5000,
+                    },
                     url: String::from("https://alpha-mainnet.starknet.io/"),
                     http_headers: None,
+                    fallback_endpoints: Vec::new(),
+                    failover: FailoverConfig {
+                        max_consecutive_errors: 3,
+                        failback_delay_millis: 60000,
+                        hedge_after_millis: None,
+                    },
                     retry_config: RetryConfig {
                         retry_base_millis: 30,
                         retry_max_delay_millis: 30000,
                         max_retries: 10,
                     },
+                    rate_limit: None,
                 },
+                peer: None,
                 gateway: GatewayConfig {
                     chain_id,
                     server_address: String::from("0.0.0.0:8080"),
                     max_events_chunk_size: 1000,
                     max_events_keys: 100,
+                    max_events_scanned: 1_000_000,
+                    max_events_scan_millis: 5000,
+                    execution_config: ExecutionConfig {
+                        // The ETH token contract, in which the fees are paid.
+                        fee_contract_address: ContractAddress(patricia_key!(
+                            "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7"
+                        )),
+                        invoke_tx_max_n_steps: 1_000_000,
+                        validate_tx_max_n_steps: 1_000_000,
+                        max_recursion_depth: 50,
+                    },
+                    default_version: ApiVersion::V0_4,
+                    limits: LimitsConfig {
+                        max_request_body_size: 10 * 1024 * 1024,
+                        max_response_body_size: 10 * 1024 * 1024,
+                        max_concurrent_calls: 100,
+                        max_concurrent_batch_calls: 10,
+                        ip_rate_limit: None,
+                        api_key_rate_limits: HashMap::new(),
+                        method_allowlist: None,
+                        method_denylist: vec![],
+                    },
+                    serve_feeder_gateway: false,
+                    continuation_token_secret: None,
                 },
                 monitoring_gateway: MonitoringGatewayConfig {
                     server_address: String::from("0.0.0.0:8081"),
                 },
+                admin: None,
                 storage: StorageConfig {
                     db_config: DbConfig {
                         path: PathBuf::from("./data"),
@@ -278,7 +366,16 @@ impl Default for ConfigBuilder {
                     recoverable_error_sleep_duration: Duration::from_secs(10),
                     blocks_max_stream_size: 1000,
                     state_updates_max_stream_size: 1000,
+                    stop_at: None,
+                    one_shot: false,
+                    checkpoint: None,
+                    separate_class_sync: true,
+                    header_first_sync: false,
+                    trace_sync: false,
+                    compiled_class_sync: true,
                 }),
+                run_gateway: true,
+                command: None,
             },
         }
     }
@@ -287,7 +384,43 @@ impl Default for ConfigBuilder {
 impl ConfigBuilder {
     // Creates the configuration struct.
     fn build(args: Vec<String>) -> Result<Config, ConfigError> {
-        Ok(Self::default().prepare_command(args)?.yaml()?.args()?.propagate_chain_id().config)
+        Ok(Self::default()
+            .prepare_command(args)?
+            .yaml()?
+            .args()?
+            .propagate_chain_id()
+            .validate_admin()?
+            .validate_rate_limit()?
+            .config)
+    }
+
+    // Fails on loading the configuration, rather than on starting the admin server, if the admin
+    // token is missing.
+    fn validate_admin(self) -> Result<Self, ConfigError> {
+        if let Some(admin) = &self.config.admin {
+            read_token(&admin.token_file)?;
+        }
+        Ok(self)
+    }
+
+    // A rate limit without requests per second would never let a request through, and one without
+    // a burst would never have a token to give. Applies to the requests to the central and to the
+    // calls to the gateway.
+    fn validate_rate_limit(self) -> Result<Self, ConfigError> {
+        let limits = &self.config.gateway.limits;
+        let rate_limits = self
+            .config
+            .central
+            .rate_limit
+            .iter()
+            .chain(limits.ip_rate_limit.iter())
+            .chain(limits.api_key_rate_limits.values());
+        for config in rate_limits {
+            if config.requests_per_second == 0 || config.burst == 0 {
+                return Err(ConfigError::BadRateLimit { config: *config });
+            }
+        }
+        Ok(self)
     }
 
     // Builds the applications command-line interface.
@@ -304,7 +437,25 @@ impl ConfigBuilder {
                 arg!(-s --storage [path] "Optionally sets storage path to use (automatically extended with chain ID)").value_parser(value_parser!(PathBuf)),
                 arg!(-n --no_sync [bool] "Optionally run without sync").value_parser(value_parser!(bool)).default_missing_value("true"),
                 arg!(--central_url ["URL"] "Central URL. It should match chain_id."),
+                arg!(--peer_url ["URL"] "Optionally syncs from the JSON-RPC server of another node instead of the central"),
+                arg!(--stop_at_block [block] "Optionally stops the sync once the given block number or 0x-prefixed block hash is synced"),
+                arg!(--one_shot [bool] "Optionally stops the sync once it reaches the last block the central had when it started").value_parser(value_parser!(bool)).default_missing_value("true"),
+                arg!(--no_gateway [bool] "Optionally run without the JSON-RPC and monitoring servers, exiting once the sync stops").value_parser(value_parser!(bool)).default_missing_value("true"),
             ])
+            .subcommand(
+                Command::new("revert")
+                .about("Reverts the storage from its last block down to the given block (inclusive).")
+                .args(&[
+                    arg!(--block_number <number> "The first block to revert").value_parser(value_parser!(u64)),
+                    arg!(--to_ommer "Moves the reverted blocks to the ommer tables").action(ArgAction::SetTrue),
+                    arg!(-y --yes "Reverts without asking for a confirmation").action(ArgAction::SetTrue),
+                ]),
+            )
+            .subcommand(
+                Command::new("import")
+                .about("Imports blocks, state updates and classes from a directory or a tar (optionally gzipped) archive.")
+                .arg(arg!(--path <path> "The directory or archive to import").value_parser(value_parser!(PathBuf))),
+            )
             .try_get_matches_from(args).unwrap_or_else(|e| e.exit()),
         );
         Ok(self)
@@ -367,9 +518,50 @@ impl ConfigBuilder {
                         self.config.sync = None;
                     }
                 }
+                if let Some(sync_config) = self.config.sync.as_mut() {
+                    if let Some(stop_at_block) = args.try_get_one::<String>("stop_at_block")? {
+                        sync_config.stop_at = Some(parse_stop_at(stop_at_block)?);
+                    }
+                    if let Some(one_shot) = args.try_get_one::<bool>("one_shot")? {
+                        sync_config.one_shot = *one_shot;
+                    }
+                }
+                if let Some(no_gateway) = args.try_get_one::<bool>("no_gateway")? {
+                    self.config.run_gateway = !*no_gateway;
+                }
                 if let Some(central_url) = args.try_get_one::<String>("central_url")? {
                     self.config.central.url = central_url.to_string()
                 }
+                if let Some(peer_url) = args.try_get_one::<String>("peer_url")? {
+                    self.config
+                        .peer
+                        .get_or_insert_with(|| PeerSourceConfig {
+                            url: String::new(),
+                            concurrent_requests: DEFAULT_PEER_CONCURRENT_REQUESTS,
+                        })
+                        .url = peer_url.to_string();
+                }
+
+                match args.subcommand() {
+                    Some(("revert", revert_args)) => {
+                        let block_number = revert_args
+                            .try_get_one::<u64>("block_number")?
+                            .expect("block_number is a required argument");
+                        self.config.command = Some(NodeCommand::Revert {
+                            block_number: BlockNumber(*block_number),
+                            move_to_ommer: revert_args.try_get_one::<bool>("to_ommer")?
+                                == Some(&true),
+                            assume_yes: revert_args.try_get_one::<bool>("yes")? == Some(&true),
+                        });
+                    }
+                    Some(("import", import_args)) => {
+                        let path = import_args
+                            .try_get_one::<PathBuf>("path")?
+                            .expect("path is a required argument");
+                        self.config.command = Some(NodeCommand::Import { path: path.clone() });
+                    }
+                    _ => {}
+                }
 
                 Ok(self)
             }
@@ -384,3 +576,14 @@ impl ConfigBuilder {
         self
     }
 }
+
+// Parses a block to stop the sync at, given either as a number or as a 0x-prefixed hash.
+fn parse_stop_at(value: &str) -> Result<StopAt, ConfigError> {
+    let err = || ConfigError::CLAStopAtBlock { value: value.to_owned() };
+    if value.starts_with("0x") {
+        let block_hash = StarkHash::try_from(value).map_err(|_| err())?;
+        return Ok(StopAt::BlockHash(BlockHash(block_hash)));
+    }
+    let block_number = value.parse::<u64>().map_err(|_| err())?;
+    Ok(StopAt::BlockNumber(BlockNumber(block_number)))
+}
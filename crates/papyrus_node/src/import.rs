@@ -0,0 +1,39 @@
+#[cfg(test)]
+#[path = "import_test.rs"]
+mod import_test;
+
+use std::io::Write;
+use std::path::Path;
+
+use papyrus_storage::{StorageReader, StorageWriter};
+use papyrus_sync::{CentralSourceTrait, FileSource, GenericStateSync, SyncConfig};
+
+/// Imports the blocks, state updates and classes in `path`, a directory or a tar archive of feeder
+/// gateway responses, through the regular sync. Returns once all of the blocks in `path` are
+/// stored, or when the sync fails. The progress is written to `output`.
+pub async fn import_files(
+    path: &Path,
+    mut sync_config: SyncConfig,
+    storage_reader: StorageReader,
+    storage_writer: StorageWriter,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let file_source = FileSource::new(path, storage_reader.clone())?;
+    let target_block_marker = file_source.get_block_marker().await?;
+    writeln!(output, "Importing blocks up to {target_block_marker} from {}.", path.display())?;
+
+    // The block marker of the files is fixed, so the sync stops once all the files are imported.
+    sync_config.stop_at = None;
+    sync_config.one_shot = true;
+    // The files hold the classes with the state updates.
+    sync_config.separate_class_sync = false;
+    // The files hold the headers with the bodies.
+    sync_config.header_first_sync = false;
+    // The files don't hold the traces and the compiled classes.
+    sync_config.trace_sync = false;
+    sync_config.compiled_class_sync = false;
+    GenericStateSync::new(sync_config, file_source, storage_reader, storage_writer).run().await?;
+
+    writeln!(output, "Imported blocks up to {target_block_marker}.")?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::fs::{self, File};
+use std::time::Duration;
+
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::test_utils::get_test_storage;
+use papyrus_sync::SyncConfig;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::hash::StarkHash;
+use starknet_client::{Block, StateUpdate};
+use tempfile::tempdir;
+
+use crate::import::import_files;
+
+const N_BLOCKS: u64 = 3;
+
+#[tokio::test]
+async fn import_directory() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("blocks")).unwrap();
+    fs::create_dir(dir.path().join("state_updates")).unwrap();
+    for i in 0..N_BLOCKS {
+        let block_hash = BlockHash(StarkHash::from(i + 1));
+        let block = Block {
+            block_hash,
+            block_number: BlockNumber(i),
+            parent_block_hash: BlockHash(StarkHash::from(i)),
+            ..Block::default()
+        };
+        let state_update = StateUpdate { block_hash, ..StateUpdate::default() };
+        let blocks_file = File::create(dir.path().join(format!("blocks/{i}.json"))).unwrap();
+        serde_json::to_writer(blocks_file, &block).unwrap();
+        let state_updates_file =
+            File::create(dir.path().join(format!("state_updates/{i}.json"))).unwrap();
+        serde_json::to_writer(state_updates_file, &state_update).unwrap();
+    }
+
+    let (reader, writer) = get_test_storage();
+    let sync_config = SyncConfig {
+        block_propagation_sleep_duration: Duration::from_millis(10),
+        recoverable_error_sleep_duration: Duration::from_millis(10),
+        blocks_max_stream_size: 10,
+        state_updates_max_stream_size: 10,
+        stop_at: None,
+        one_shot: false,
+        checkpoint: None,
+        separate_class_sync: false,
+        header_first_sync: false,
+        trace_sync: false,
+        compiled_class_sync: false,
+    };
+    let mut output = Vec::new();
+    import_files(dir.path(), sync_config, reader.clone(), writer, &mut output).await.unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), BlockNumber(N_BLOCKS));
+    assert_eq!(txn.get_state_marker().unwrap(), BlockNumber(N_BLOCKS));
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(&format!("Imported blocks up to {N_BLOCKS}.")));
+}
@@ -1,5 +1,8 @@
+pub mod admin;
 #[allow(unused_imports)]
 pub mod config;
+pub mod import;
+pub mod revert;
 pub mod version;
 #[macro_use]
 extern crate lazy_static;
@@ -1,79 +1,177 @@
 use std::env::args;
+use std::io;
+use std::sync::Arc;
 
-use papyrus_gateway::run_server;
+use anyhow::anyhow;
+use futures_util::future::{self, BoxFuture};
+use futures_util::FutureExt;
+use papyrus_gateway::{run_server, GatewayMonitor};
 use papyrus_monitoring_gateway::MonitoringServer;
-use papyrus_node::config::Config;
+use papyrus_node::admin::{run_admin_server, JsonRpcAdminImpl, LogFilterHandle};
+use papyrus_node::config::{Config, NodeCommand};
+use papyrus_node::import::import_files;
+use papyrus_node::revert::revert_storage;
 use papyrus_node::version::VERSION_FULL;
 use papyrus_storage::{open_storage, StorageReader, StorageWriter};
-use papyrus_sync::{CentralError, CentralSource, StateSync, StateSyncError};
+use papyrus_sync::{
+    CentralError, CentralSource, GenericStateSync, PeerSource, StateSync, StateSyncError,
+    SyncControl,
+};
 use tracing::info;
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::{fmt, reload, EnvFilter};
 
 // TODO(yair): Add to config.
 const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
 
-async fn run_threads(config: Config) -> anyhow::Result<()> {
+async fn run_threads(config: Config, log_filter_handle: LogFilterHandle) -> anyhow::Result<()> {
     let (storage_reader, storage_writer) = open_storage(config.storage.db_config.clone())?;
 
+    // Created before the monitoring server, which reports the health of the central's endpoints.
+    let central_source = match (&config.sync, &config.peer) {
+        (Some(_), None) => Some(
+            CentralSource::new(config.central.clone(), VERSION_FULL, storage_reader.clone())
+                .map_err(CentralError::ClientCreation)?,
+        ),
+        _ => None,
+    };
+
+    if !config.run_gateway {
+        let (_, sync_future) =
+            create_sync(config, central_source, storage_reader.clone(), storage_writer)?;
+        sync_future.await?;
+        return Ok(());
+    }
+
+    // Counts the requests the JSON-RPC server rejects, reported by the monitoring server.
+    let gateway_monitor = GatewayMonitor::default();
+
     // Monitoring server.
     let monitoring_server = MonitoringServer::new(
         config.monitoring_gateway.clone(),
         config.get_config_representation()?,
         storage_reader.clone(),
+        central_source.as_ref().map(|central_source| central_source.failover_monitor()),
+        Some(gateway_monitor.clone()),
         VERSION_FULL,
     );
     let monitoring_server_handle = monitoring_server.spawn_server().await;
 
     // JSON-RPC server.
-    let (_, server_future) = run_server(&config.gateway, storage_reader.clone()).await?;
-    let server_handle = tokio::spawn(server_future);
+    let (_, server_handle) =
+        run_server(&config.gateway, storage_reader.clone(), gateway_monitor.clone()).await?;
+
+    // Created before running, for the admin server to control it.
+    let admin_config = config.admin.clone();
+    let (sync_control, sync_future) =
+        create_sync(config, central_source, storage_reader.clone(), storage_writer)?;
+
+    // Admin server.
+    let admin_server_handle = match admin_config {
+        Some(admin_config) => {
+            let admin = JsonRpcAdminImpl { sync_control, gateway_monitor, log_filter_handle };
+            run_admin_server(&admin_config, admin).await?.1
+        }
+        // Never finishes, like the other servers.
+        None => tokio::spawn(future::pending()),
+    };
 
     // Sync task.
-    let sync_future = run_sync(config, storage_reader.clone(), storage_writer);
     let sync_handle = tokio::spawn(sync_future);
 
-    let (_, _, sync_result) =
-        tokio::try_join!(server_handle, monitoring_server_handle, sync_handle)?;
+    let (_, _, _, sync_result) = tokio::try_join!(
+        server_handle,
+        monitoring_server_handle,
+        admin_server_handle,
+        sync_handle
+    )?;
     sync_result?;
     return Ok(());
 
-    async fn run_sync(
+    // Returns the control of the sync, None if it is disabled, and the future that runs it.
+    fn create_sync(
         config: Config,
+        central_source: Option<CentralSource>,
         storage_reader: StorageReader,
         storage_writer: StorageWriter,
-    ) -> Result<(), StateSyncError> {
-        if let Some(sync_config) = config.sync {
+    ) -> Result<(Option<SyncControl>, BoxFuture<'static, Result<(), StateSyncError>>), StateSyncError>
+    {
+        if let Some(mut sync_config) = config.sync {
+            if let Some(peer_config) = config.peer {
+                // The peer serves the classes with the state updates.
+                sync_config.separate_class_sync = false;
+                // The peer doesn't serve the traces and the compiled classes.
+                sync_config.trace_sync = false;
+                sync_config.compiled_class_sync = false;
+                let peer_source = PeerSource::new(peer_config, storage_reader.clone())
+                    .map_err(|err| CentralError::PeerError(Arc::new(err)))?;
+                let mut sync = GenericStateSync::new(
+                    sync_config,
+                    peer_source,
+                    storage_reader.clone(),
+                    storage_writer,
+                );
+                return Ok((Some(sync.control()), async move { sync.run().await }.boxed()));
+            }
             let central_source =
-                CentralSource::new(config.central.clone(), VERSION_FULL, storage_reader.clone())
-                    .map_err(CentralError::ClientCreation)?;
+                central_source.expect("The central source should be created when syncing from it.");
             let mut sync =
                 StateSync::new(sync_config, central_source, storage_reader.clone(), storage_writer);
-            return sync.run().await;
+            return Ok((Some(sync.control()), async move { sync.run().await }.boxed()));
         }
 
-        Ok(())
+        Ok((None, future::ready(Ok(())).boxed()))
     }
 }
 
-// TODO(yair): add dynamic level filtering.
 // TODO(dan): filter out logs from dependencies (happens when RUST_LOG=DEBUG)
 // TODO(yair): define and implement configurable filtering.
-fn configure_tracing() {
+// Returns a handle for replacing the filter while the node runs.
+fn configure_tracing() -> LogFilterHandle {
     let fmt_layer = fmt::layer().compact().with_target(false);
     let level_filter_layer =
         EnvFilter::builder().with_default_directive(DEFAULT_LEVEL.into()).from_env_lossy();
+    let (level_filter_layer, log_filter_handle) = reload::Layer::new(level_filter_layer);
 
     // This sets a single subscriber to all of the threads. We may want to implement different
     // subscriber for some threads and use set_global_default instead of init.
-    tracing_subscriber::registry().with(fmt_layer).with(level_filter_layer).init();
+    tracing_subscriber::registry().with(level_filter_layer).with(fmt_layer).init();
+    log_filter_handle
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let config = Config::load(args().collect())?;
-    configure_tracing();
+    let log_filter_handle = configure_tracing();
+    match config.command {
+        Some(NodeCommand::Revert { block_number, move_to_ommer, assume_yes }) => {
+            let (storage_reader, mut storage_writer) = open_storage(config.storage.db_config)?;
+            return revert_storage(
+                &storage_reader,
+                &mut storage_writer,
+                block_number,
+                move_to_ommer,
+                assume_yes,
+                &mut io::stdin().lock(),
+                &mut io::stdout(),
+            );
+        }
+        Some(NodeCommand::Import { path }) => {
+            let sync_config =
+                config.sync.ok_or_else(|| anyhow!("Importing requires the sync to be enabled."))?;
+            let (storage_reader, storage_writer) = open_storage(config.storage.db_config)?;
+            return import_files(
+                &path,
+                sync_config,
+                storage_reader,
+                storage_writer,
+                &mut io::stdout(),
+            )
+            .await;
+        }
+        None => {}
+    }
     info!("Booting up.");
-    run_threads(config).await
+    run_threads(config, log_filter_handle).await
 }
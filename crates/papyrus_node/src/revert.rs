@@ -0,0 +1,66 @@
+#[cfg(test)]
+#[path = "revert_test.rs"]
+mod revert_test;
+
+use std::io::{BufRead, Write};
+
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::revert::RevertStorageWriter;
+use papyrus_storage::{StorageReader, StorageWriter};
+use starknet_api::block::BlockNumber;
+
+/// Reverts the blocks of the storage from the last one down to `target_block_number` (inclusive),
+/// one block per transaction. Unless `assume_yes` is set, asks for a confirmation on `input`
+/// first. The progress is written to `output`.
+pub fn revert_storage(
+    reader: &StorageReader,
+    writer: &mut StorageWriter,
+    target_block_number: BlockNumber,
+    move_to_ommer: bool,
+    assume_yes: bool,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let header_marker = reader.begin_ro_txn()?.get_header_marker()?;
+    if target_block_number >= header_marker {
+        writeln!(
+            output,
+            "Nothing to revert, the storage has no blocks from {target_block_number}."
+        )?;
+        return Ok(());
+    }
+
+    let n_blocks = header_marker.0 - target_block_number.0;
+    if !assume_yes {
+        let destination = if move_to_ommer { "moved to the ommer tables" } else { "deleted" };
+        write!(
+            output,
+            "Blocks {target_block_number} to {} ({n_blocks} blocks) will be {destination}. Continue? \
+             [y/N] ",
+            header_marker.0 - 1
+        )?;
+        output.flush()?;
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            writeln!(output, "Aborted.")?;
+            return Ok(());
+        }
+    }
+
+    for (i, block_number) in (target_block_number.0..header_marker.0).rev().enumerate() {
+        let block_number = BlockNumber(block_number);
+        let (txn, reverted_block_hash) =
+            writer.begin_rw_txn()?.revert_block(block_number, move_to_ommer)?;
+        txn.commit()?;
+        if let Some(block_hash) = reverted_block_hash {
+            writeln!(
+                output,
+                "[{}/{n_blocks}] Reverted block {block_number} ({block_hash}).",
+                i + 1
+            )?;
+        }
+    }
+    writeln!(output, "Done, the next block to sync is {target_block_number}.")?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use papyrus_storage::header::{HeaderStorageReader, HeaderStorageWriter};
+use papyrus_storage::test_utils::get_test_storage;
+use papyrus_storage::StorageWriter;
+use starknet_api::block::{BlockHash, BlockHeader, BlockNumber};
+use starknet_api::hash::StarkHash;
+
+use crate::revert::revert_storage;
+
+fn append_headers(writer: &mut StorageWriter, n_blocks: u64) {
+    for i in 0..n_blocks {
+        let header = BlockHeader {
+            block_hash: BlockHash(StarkHash::from(i + 1)),
+            block_number: BlockNumber(i),
+            ..BlockHeader::default()
+        };
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_header(header.block_number, &header)
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+}
+
+#[test]
+fn revert_after_confirmation() {
+    let (reader, mut writer) = get_test_storage();
+    append_headers(&mut writer, 5);
+
+    let mut output = Vec::new();
+    revert_storage(
+        &reader,
+        &mut writer,
+        BlockNumber(2),
+        false,
+        false,
+        &mut "y\n".as_bytes(),
+        &mut output,
+    )
+    .unwrap();
+
+    assert_eq!(reader.begin_ro_txn().unwrap().get_header_marker().unwrap(), BlockNumber(2));
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("[3/3] Reverted block 2"));
+}
+
+#[test]
+fn revert_aborted() {
+    let (reader, mut writer) = get_test_storage();
+    append_headers(&mut writer, 5);
+
+    let mut output = Vec::new();
+    revert_storage(
+        &reader,
+        &mut writer,
+        BlockNumber(2),
+        true,
+        false,
+        &mut "n\n".as_bytes(),
+        &mut output,
+    )
+    .unwrap();
+
+    assert_eq!(reader.begin_ro_txn().unwrap().get_header_marker().unwrap(), BlockNumber(5));
+    assert!(String::from_utf8(output).unwrap().ends_with("Aborted.\n"));
+}
@@ -3,8 +3,12 @@ use starknet_api::block::{BlockBody, BlockNumber};
 use starknet_api::transaction::TransactionOffsetInBlock;
 use test_utils::{get_test_block, get_test_body};
 
-use crate::body::events::ThinTransactionOutput;
-use crate::body::{BodyStorageReader, BodyStorageWriter, TransactionIndex};
+use crate::body::events::{
+    ExecutionResources, ThinTransactionOutput, TransactionExecutionInfo, TransactionExecutionStatus,
+};
+use crate::body::{
+    BlockExecutionInfo, BodyStorageReader, BodyStorageWriter, StarknetVersion, TransactionIndex,
+};
 use crate::test_utils::get_test_storage;
 use crate::{StorageError, StorageWriter};
 
@@ -270,6 +274,91 @@ async fn revert_transactions() {
     );
 }
 
+#[tokio::test]
+async fn append_body_with_execution_info() {
+    let (reader, mut writer) = get_test_storage();
+    let body = get_test_body(Some(0), 2, None, None, None);
+    let execution_infos = vec![
+        TransactionExecutionInfo {
+            execution_status: TransactionExecutionStatus::Succeeded,
+            execution_resources: ExecutionResources {
+                n_steps: 10,
+                builtin_instance_counter: [("range_check_builtin".to_string(), 2)].into(),
+                n_memory_holes: 1,
+            },
+        },
+        TransactionExecutionInfo {
+            execution_status: TransactionExecutionStatus::Reverted("Out of gas.".to_string()),
+            execution_resources: ExecutionResources::default(),
+        },
+    ];
+
+    // The execution infos must match the transactions of the block.
+    let err = writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_body_with_execution_info(
+            BlockNumber(0),
+            body.clone(),
+            BlockExecutionInfo {
+                starknet_version: None,
+                transaction_execution_infos: Some(vec![execution_infos[0].clone()]),
+            },
+        )
+        .unwrap_err();
+    assert_matches!(
+        err,
+        StorageError::ExecutionInfosMismatchTransactions {
+            n_execution_infos: 1,
+            n_transactions: 2,
+            ..
+        }
+    );
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_body_with_execution_info(
+            BlockNumber(0),
+            body,
+            BlockExecutionInfo {
+                starknet_version: Some(StarknetVersion("0.12.1".to_string())),
+                transaction_execution_infos: Some(execution_infos.clone()),
+            },
+        )
+        .unwrap()
+        .append_body(BlockNumber(1), BlockBody::default())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    let tx_outputs = txn.get_block_transaction_outputs(BlockNumber(0)).unwrap().unwrap();
+    assert_eq!(
+        tx_outputs.iter().map(|tx_output| tx_output.execution_info().cloned()).collect::<Vec<_>>(),
+        execution_infos.into_iter().map(Some).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        txn.get_starknet_version(BlockNumber(0)).unwrap(),
+        Some(StarknetVersion("0.12.1".to_string()))
+    );
+    assert_eq!(txn.get_starknet_version(BlockNumber(1)).unwrap(), None);
+    drop(txn);
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .revert_body(BlockNumber(1))
+        .unwrap()
+        .0
+        .revert_body(BlockNumber(0))
+        .unwrap()
+        .0
+        .commit()
+        .unwrap();
+    assert_eq!(reader.begin_ro_txn().unwrap().get_starknet_version(BlockNumber(0)).unwrap(), None);
+}
+
 fn append_2_bodies(writer: &mut StorageWriter) {
     writer
         .begin_rw_txn()
@@ -2,6 +2,8 @@
 #[path = "events_test.rs"]
 mod events_test;
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use starknet_api::block::BlockNumber;
 use starknet_api::core::ContractAddress;
@@ -214,6 +216,68 @@ impl ThinTransactionOutput {
             ThinTransactionOutput::L1Handler(tx_output) => &tx_output.events_contract_addresses,
         }
     }
+
+    /// The messages the transaction sent to L1.
+    pub fn messages_sent(&self) -> &Vec<MessageToL1> {
+        match self {
+            ThinTransactionOutput::Declare(tx_output) => &tx_output.messages_sent,
+            ThinTransactionOutput::Deploy(tx_output) => &tx_output.messages_sent,
+            ThinTransactionOutput::DeployAccount(tx_output) => &tx_output.messages_sent,
+            ThinTransactionOutput::Invoke(tx_output) => &tx_output.messages_sent,
+            ThinTransactionOutput::L1Handler(tx_output) => &tx_output.messages_sent,
+        }
+    }
+
+    /// The execution info of the transaction. None for transactions that were stored before the
+    /// execution info was, and for transactions from sources that don't report it.
+    pub fn execution_info(&self) -> Option<&TransactionExecutionInfo> {
+        match self {
+            ThinTransactionOutput::Declare(tx_output) => tx_output.execution_info.as_ref(),
+            ThinTransactionOutput::Deploy(tx_output) => tx_output.execution_info.as_ref(),
+            ThinTransactionOutput::DeployAccount(tx_output) => tx_output.execution_info.as_ref(),
+            ThinTransactionOutput::Invoke(tx_output) => tx_output.execution_info.as_ref(),
+            ThinTransactionOutput::L1Handler(tx_output) => tx_output.execution_info.as_ref(),
+        }
+    }
+
+    pub(crate) fn set_execution_info(&mut self, execution_info: Option<TransactionExecutionInfo>) {
+        match self {
+            ThinTransactionOutput::Declare(tx_output) => tx_output.execution_info = execution_info,
+            ThinTransactionOutput::Deploy(tx_output) => tx_output.execution_info = execution_info,
+            ThinTransactionOutput::DeployAccount(tx_output) => {
+                tx_output.execution_info = execution_info
+            }
+            ThinTransactionOutput::Invoke(tx_output) => tx_output.execution_info = execution_info,
+            ThinTransactionOutput::L1Handler(tx_output) => {
+                tx_output.execution_info = execution_info
+            }
+        }
+    }
+}
+
+/// How a transaction was executed, as reported by the starknet gateway.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct TransactionExecutionInfo {
+    pub execution_status: TransactionExecutionStatus,
+    pub execution_resources: ExecutionResources,
+}
+
+/// Whether a transaction succeeded. A reverted transaction is included in its block and pays its
+/// fee, but its state changes are discarded.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub enum TransactionExecutionStatus {
+    #[default]
+    Succeeded,
+    /// The error that caused the revert.
+    Reverted(String),
+}
+
+/// The resources used by the execution of a transaction.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ExecutionResources {
+    pub n_steps: u64,
+    pub builtin_instance_counter: BTreeMap<String, u64>,
+    pub n_memory_holes: u64,
 }
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
@@ -221,6 +285,7 @@ pub struct ThinInvokeTransactionOutput {
     pub actual_fee: Fee,
     pub messages_sent: Vec<MessageToL1>,
     pub events_contract_addresses: Vec<ContractAddress>,
+    pub execution_info: Option<TransactionExecutionInfo>,
 }
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
@@ -228,6 +293,7 @@ pub struct ThinL1HandlerTransactionOutput {
     pub actual_fee: Fee,
     pub messages_sent: Vec<MessageToL1>,
     pub events_contract_addresses: Vec<ContractAddress>,
+    pub execution_info: Option<TransactionExecutionInfo>,
 }
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
@@ -235,6 +301,7 @@ pub struct ThinDeclareTransactionOutput {
     pub actual_fee: Fee,
     pub messages_sent: Vec<MessageToL1>,
     pub events_contract_addresses: Vec<ContractAddress>,
+    pub execution_info: Option<TransactionExecutionInfo>,
 }
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
@@ -242,6 +309,7 @@ pub struct ThinDeployTransactionOutput {
     pub actual_fee: Fee,
     pub messages_sent: Vec<MessageToL1>,
     pub events_contract_addresses: Vec<ContractAddress>,
+    pub execution_info: Option<TransactionExecutionInfo>,
 }
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
@@ -249,6 +317,7 @@ pub struct ThinDeployAccountTransactionOutput {
     pub actual_fee: Fee,
     pub messages_sent: Vec<MessageToL1>,
     pub events_contract_addresses: Vec<ContractAddress>,
+    pub execution_info: Option<TransactionExecutionInfo>,
 }
 
 impl From<TransactionOutput> for ThinTransactionOutput {
@@ -261,6 +330,7 @@ impl From<TransactionOutput> for ThinTransactionOutput {
                     actual_fee: tx_output.actual_fee,
                     messages_sent: tx_output.messages_sent,
                     events_contract_addresses,
+                    execution_info: None,
                 })
             }
             TransactionOutput::Deploy(tx_output) => {
@@ -268,6 +338,7 @@ impl From<TransactionOutput> for ThinTransactionOutput {
                     actual_fee: tx_output.actual_fee,
                     messages_sent: tx_output.messages_sent,
                     events_contract_addresses,
+                    execution_info: None,
                 })
             }
             TransactionOutput::DeployAccount(tx_output) => {
@@ -275,6 +346,7 @@ impl From<TransactionOutput> for ThinTransactionOutput {
                     actual_fee: tx_output.actual_fee,
                     messages_sent: tx_output.messages_sent,
                     events_contract_addresses,
+                    execution_info: None,
                 })
             }
             TransactionOutput::Invoke(tx_output) => {
@@ -282,6 +354,7 @@ impl From<TransactionOutput> for ThinTransactionOutput {
                     actual_fee: tx_output.actual_fee,
                     messages_sent: tx_output.messages_sent,
                     events_contract_addresses,
+                    execution_info: None,
                 })
             }
             TransactionOutput::L1Handler(tx_output) => {
@@ -289,6 +362,7 @@ impl From<TransactionOutput> for ThinTransactionOutput {
                     actual_fee: tx_output.actual_fee,
                     messages_sent: tx_output.messages_sent,
                     events_contract_addresses,
+                    execution_info: None,
                 })
             }
         }
@@ -0,0 +1,142 @@
+#[cfg(test)]
+#[path = "messages_test.rs"]
+mod messages_test;
+
+use serde::{Deserialize, Serialize};
+use starknet_api::transaction::{EthAddress, L1HandlerTransaction, MessageToL1};
+use web3::signing::keccak256;
+use web3::types::H256;
+
+use crate::body::TransactionIndex;
+use crate::db::{DbTransaction, TableHandle, TransactionKind, RW};
+use crate::{StorageResult, StorageTxn};
+
+pub(crate) type L1ToL2MessagesTable<'env> = TableHandle<'env, L1ToL2MessageHash, TransactionIndex>;
+pub(crate) type L2ToL1MessagesTable<'env> =
+    TableHandle<'env, (EthAddress, MessageToL1Index), MessageToL1>;
+
+/// The hash of a message from L1 to L2, as computed by the Starknet core contract on L1 when the
+/// message is sent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct L1ToL2MessageHash(pub H256);
+
+impl L1ToL2MessageHash {
+    /// Returns the hash of the message an L1 handler transaction consumes, None if the transaction
+    /// has no calldata. The first element of the calldata is the L1 sender, and the rest is the
+    /// payload of the message.
+    pub fn of_l1_handler(tx: &L1HandlerTransaction) -> Option<Self> {
+        let (from_address, payload) = tx.calldata.0.split_first()?;
+        let mut encoded = Vec::with_capacity(32 * (5 + payload.len()));
+        encoded.extend_from_slice(from_address.bytes());
+        encoded.extend_from_slice(tx.contract_address.0.key().bytes());
+        encoded.extend_from_slice(tx.nonce.0.bytes());
+        encoded.extend_from_slice(tx.entry_point_selector.0.bytes());
+        let mut payload_length = [0_u8; 32];
+        payload_length[24..].copy_from_slice(&(payload.len() as u64).to_be_bytes());
+        encoded.extend_from_slice(&payload_length);
+        for felt in payload {
+            encoded.extend_from_slice(felt.bytes());
+        }
+        Some(Self(H256(keccak256(&encoded))))
+    }
+}
+
+/// The index of a message from L2 to L1 among the messages sent by the transactions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct MessageToL1Index(pub TransactionIndex, pub usize);
+
+pub trait MessagesReader {
+    /// Returns the L1 handler transaction that consumed the message.
+    fn get_l1_handler_by_message_hash(
+        &self,
+        message_hash: &L1ToL2MessageHash,
+    ) -> StorageResult<Option<TransactionIndex>>;
+
+    /// Returns up to `limit` messages sent to `to_address`, from `from_index` on, by their order.
+    fn get_messages_to_l1(
+        &self,
+        to_address: EthAddress,
+        from_index: MessageToL1Index,
+        limit: usize,
+    ) -> StorageResult<Vec<(MessageToL1Index, MessageToL1)>>;
+}
+
+impl<'env, Mode: TransactionKind> MessagesReader for StorageTxn<'env, Mode> {
+    fn get_l1_handler_by_message_hash(
+        &self,
+        message_hash: &L1ToL2MessageHash,
+    ) -> StorageResult<Option<TransactionIndex>> {
+        let l1_to_l2_messages_table = self.txn.open_table(&self.tables.l1_to_l2_messages)?;
+        Ok(l1_to_l2_messages_table.get(&self.txn, message_hash)?)
+    }
+
+    fn get_messages_to_l1(
+        &self,
+        to_address: EthAddress,
+        from_index: MessageToL1Index,
+        limit: usize,
+    ) -> StorageResult<Vec<(MessageToL1Index, MessageToL1)>> {
+        let l2_to_l1_messages_table = self.txn.open_table(&self.tables.l2_to_l1_messages)?;
+        let mut cursor = l2_to_l1_messages_table.cursor(&self.txn)?;
+        let mut current = cursor.lower_bound(&(to_address, from_index))?;
+        let mut res = Vec::new();
+        while let Some(((current_address, index), message)) = current {
+            if current_address != to_address || res.len() == limit {
+                break;
+            }
+            res.push((index, message));
+            current = cursor.next()?;
+        }
+        Ok(res)
+    }
+}
+
+// Indexes the message consumed by an L1 handler transaction.
+pub(crate) fn write_l1_to_l2_message<'env>(
+    tx: &L1HandlerTransaction,
+    txn: &DbTransaction<'env, RW>,
+    l1_to_l2_messages_table: &'env L1ToL2MessagesTable<'env>,
+    transaction_index: TransactionIndex,
+) -> StorageResult<()> {
+    if let Some(message_hash) = L1ToL2MessageHash::of_l1_handler(tx) {
+        // The nonce makes the hash unique, yet a repeated hash shouldn't fail the sync.
+        l1_to_l2_messages_table.upsert(txn, &message_hash, &transaction_index)?;
+    }
+    Ok(())
+}
+
+// Indexes the messages sent by a transaction by their recipients.
+pub(crate) fn write_l2_to_l1_messages<'env>(
+    messages: &[MessageToL1],
+    txn: &DbTransaction<'env, RW>,
+    l2_to_l1_messages_table: &'env L2ToL1MessagesTable<'env>,
+    transaction_index: TransactionIndex,
+) -> StorageResult<()> {
+    for (index, message) in messages.iter().enumerate() {
+        let key = (message.to_address, MessageToL1Index(transaction_index, index));
+        l2_to_l1_messages_table.insert(txn, &key, message)?;
+    }
+    Ok(())
+}
+
+// Removes the indexes of the messages of a reverted transaction.
+pub(crate) fn delete_messages<'env>(
+    tx: Option<&L1HandlerTransaction>,
+    messages: &[MessageToL1],
+    txn: &DbTransaction<'env, RW>,
+    l1_to_l2_messages_table: &'env L1ToL2MessagesTable<'env>,
+    l2_to_l1_messages_table: &'env L2ToL1MessagesTable<'env>,
+    transaction_index: TransactionIndex,
+) -> StorageResult<()> {
+    if let Some(message_hash) = tx.and_then(L1ToL2MessageHash::of_l1_handler) {
+        // Kept if the hash was repeated by a later transaction.
+        if l1_to_l2_messages_table.get(txn, &message_hash)? == Some(transaction_index) {
+            l1_to_l2_messages_table.delete(txn, &message_hash)?;
+        }
+    }
+    for (index, message) in messages.iter().enumerate() {
+        let key = (message.to_address, MessageToL1Index(transaction_index, index));
+        l2_to_l1_messages_table.delete(txn, &key)?;
+    }
+    Ok(())
+}
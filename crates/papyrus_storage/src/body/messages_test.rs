@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use starknet_api::block::{BlockBody, BlockNumber};
+use starknet_api::core::{ContractAddress, EntryPointSelector, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::transaction::{
+    Calldata, EthAddress, L1HandlerTransaction, L1HandlerTransactionOutput, L2ToL1Payload,
+    MessageToL1, Transaction, TransactionHash, TransactionOffsetInBlock, TransactionOutput,
+    TransactionVersion,
+};
+use starknet_api::{patricia_key, stark_felt};
+use web3::types::H160;
+
+use crate::body::messages::{L1ToL2MessageHash, MessageToL1Index, MessagesReader};
+use crate::body::{BodyStorageWriter, TransactionIndex};
+use crate::test_utils::get_test_storage;
+
+fn felt(value: u64) -> StarkFelt {
+    StarkFelt::try_from(format!("{value:#x}").as_str()).unwrap()
+}
+
+fn l1_handler(nonce: u64, calldata: Vec<StarkFelt>) -> L1HandlerTransaction {
+    L1HandlerTransaction {
+        transaction_hash: TransactionHash(felt(nonce)),
+        version: TransactionVersion(felt(0)),
+        nonce: Nonce(felt(nonce)),
+        contract_address: ContractAddress(patricia_key!("0x11")),
+        entry_point_selector: EntryPointSelector(stark_felt!("0x12")),
+        calldata: Calldata(Arc::new(calldata)),
+    }
+}
+
+// An L1 handler that consumes a message with the given nonce.
+fn consuming_l1_handler(nonce: u64) -> L1HandlerTransaction {
+    l1_handler(nonce, vec![stark_felt!("0x1"), stark_felt!("0x2")])
+}
+
+fn message_to_l1(to_address: u64, payload: u64) -> MessageToL1 {
+    MessageToL1 {
+        from_address: ContractAddress(patricia_key!("0x11")),
+        to_address: EthAddress(H160::from_low_u64_be(to_address)),
+        payload: L2ToL1Payload(vec![felt(payload)]),
+    }
+}
+
+// A block with an L1 handler that sends a message to each of `to_addresses`.
+fn block_body(nonce: u64, to_addresses: &[u64]) -> BlockBody {
+    let tx = consuming_l1_handler(nonce);
+    let messages_sent =
+        to_addresses.iter().map(|to_address| message_to_l1(*to_address, nonce)).collect();
+    BlockBody {
+        transactions: vec![Transaction::L1Handler(tx)],
+        transaction_outputs: vec![TransactionOutput::L1Handler(L1HandlerTransactionOutput {
+            messages_sent,
+            ..Default::default()
+        })],
+    }
+}
+
+#[test]
+fn message_hash_of_l1_handler() {
+    let hash = L1ToL2MessageHash::of_l1_handler(&consuming_l1_handler(1)).unwrap();
+    // The nonce distinguishes between messages with the same content.
+    assert_ne!(L1ToL2MessageHash::of_l1_handler(&consuming_l1_handler(2)), Some(hash));
+    assert_ne!(
+        L1ToL2MessageHash::of_l1_handler(&l1_handler(1, vec![stark_felt!("0x1")])),
+        Some(hash)
+    );
+    // The calldata should start with the L1 sender.
+    assert_eq!(L1ToL2MessageHash::of_l1_handler(&l1_handler(1, vec![])), None);
+}
+
+#[test]
+fn index_messages() {
+    let (reader, mut writer) = get_test_storage();
+    let body0 = block_body(0, &[1, 2, 1]);
+    let body1 = block_body(1, &[1]);
+    let hash0 = L1ToL2MessageHash::of_l1_handler(&consuming_l1_handler(0)).unwrap();
+    let hash1 = L1ToL2MessageHash::of_l1_handler(&consuming_l1_handler(1)).unwrap();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_body(BlockNumber(0), body0)
+        .unwrap()
+        .append_body(BlockNumber(1), body1)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let tx_index =
+        |block_number| TransactionIndex(BlockNumber(block_number), TransactionOffsetInBlock(0));
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_l1_handler_by_message_hash(&hash0).unwrap(), Some(tx_index(0)));
+    assert_eq!(txn.get_l1_handler_by_message_hash(&hash1).unwrap(), Some(tx_index(1)));
+
+    let to_address = EthAddress(H160::from_low_u64_be(1));
+    let first_index = MessageToL1Index(tx_index(0), 0);
+    let expected_messages = vec![
+        (MessageToL1Index(tx_index(0), 0), message_to_l1(1, 0)),
+        (MessageToL1Index(tx_index(0), 2), message_to_l1(1, 0)),
+        (MessageToL1Index(tx_index(1), 0), message_to_l1(1, 1)),
+    ];
+    assert_eq!(txn.get_messages_to_l1(to_address, first_index, 10).unwrap(), expected_messages);
+    assert_eq!(
+        txn.get_messages_to_l1(to_address, first_index, 2).unwrap(),
+        expected_messages[..2].to_vec()
+    );
+    assert_eq!(
+        txn.get_messages_to_l1(to_address, MessageToL1Index(tx_index(0), 1), 10).unwrap(),
+        expected_messages[1..].to_vec()
+    );
+    let other_address = EthAddress(H160::from_low_u64_be(3));
+    assert!(txn.get_messages_to_l1(other_address, first_index, 10).unwrap().is_empty());
+    drop(txn);
+
+    let (txn, _) = writer.begin_rw_txn().unwrap().revert_body(BlockNumber(1)).unwrap();
+    txn.commit().unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_l1_handler_by_message_hash(&hash0).unwrap(), Some(tx_index(0)));
+    assert_eq!(txn.get_l1_handler_by_message_hash(&hash1).unwrap(), None);
+    assert_eq!(
+        txn.get_messages_to_l1(to_address, first_index, 10).unwrap(),
+        expected_messages[..2].to_vec()
+    );
+}
@@ -2,6 +2,7 @@
 #[path = "body_test.rs"]
 mod body_test;
 pub mod events;
+pub mod messages;
 
 use serde::{Deserialize, Serialize};
 use starknet_api::block::{BlockBody, BlockNumber};
@@ -12,7 +13,11 @@ use starknet_api::transaction::{
 };
 use tracing::debug;
 
-use crate::body::events::{EventIndex, ThinTransactionOutput};
+use crate::body::events::{EventIndex, ThinTransactionOutput, TransactionExecutionInfo};
+use crate::body::messages::{
+    delete_messages, write_l1_to_l2_message, write_l2_to_l1_messages, L1ToL2MessagesTable,
+    L2ToL1MessagesTable,
+};
 use crate::db::{DbError, DbTransaction, TableHandle, TransactionKind, RW};
 use crate::{MarkerKind, MarkersTable, StorageError, StorageResult, StorageTxn};
 
@@ -21,10 +26,24 @@ type TransactionOutputsTable<'env> = TableHandle<'env, TransactionIndex, ThinTra
 type TransactionHashToIdxTable<'env> = TableHandle<'env, TransactionHash, TransactionIndex>;
 type EventsTableKey = (ContractAddress, EventIndex);
 type EventsTable<'env> = TableHandle<'env, EventsTableKey, EventContent>;
+type StarknetVersionsTable<'env> = TableHandle<'env, BlockNumber, StarknetVersion>;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct TransactionIndex(pub BlockNumber, pub TransactionOffsetInBlock);
 
+/// The version of starknet a block was created in, e.g., "0.12.1".
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct StarknetVersion(pub String);
+
+/// Block data that the starknet gateway reports alongside the body. The fields are optional, since
+/// not every source of blocks has them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockExecutionInfo {
+    pub starknet_version: Option<StarknetVersion>,
+    /// The execution info of the transactions of the block, in their order.
+    pub transaction_execution_infos: Option<Vec<TransactionExecutionInfo>>,
+}
+
 pub trait BodyStorageReader {
     // The block number marker is the first block number that doesn't exist yet.
     fn get_body_marker(&self) -> StorageResult<BlockNumber>;
@@ -59,6 +78,11 @@ pub trait BodyStorageReader {
         &self,
         block_number: BlockNumber,
     ) -> StorageResult<Option<Vec<ThinTransactionOutput>>>;
+
+    fn get_starknet_version(
+        &self,
+        block_number: BlockNumber,
+    ) -> StorageResult<Option<StarknetVersion>>;
 }
 
 type RevertedBlockBody = (Vec<Transaction>, Vec<ThinTransactionOutput>, Vec<Vec<EventContent>>);
@@ -68,8 +92,19 @@ where
     Self: Sized,
 {
     // To enforce that no commit happen after a failure, we consume and return Self on success.
+    // The body marker advances independently of the header marker, so the headers can be stored
+    // ahead of the bodies.
     fn append_body(self, block_number: BlockNumber, block_body: BlockBody) -> StorageResult<Self>;
 
+    // Like append_body, and also stores the data the source reported about the execution of the
+    // block.
+    fn append_body_with_execution_info(
+        self,
+        block_number: BlockNumber,
+        block_body: BlockBody,
+        execution_info: BlockExecutionInfo,
+    ) -> StorageResult<Self>;
+
     fn revert_body(
         self,
         block_number: BlockNumber,
@@ -178,16 +213,51 @@ impl<'env, Mode: TransactionKind> BodyStorageReader for StorageTxn<'env, Mode> {
         }
         Ok(Some(res))
     }
+
+    fn get_starknet_version(
+        &self,
+        block_number: BlockNumber,
+    ) -> StorageResult<Option<StarknetVersion>> {
+        let starknet_versions_table = self.txn.open_table(&self.tables.starknet_versions)?;
+        let starknet_version = starknet_versions_table.get(&self.txn, &block_number)?;
+        Ok(starknet_version)
+    }
 }
 
 impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
     fn append_body(self, block_number: BlockNumber, block_body: BlockBody) -> StorageResult<Self> {
+        self.append_body_with_execution_info(
+            block_number,
+            block_body,
+            BlockExecutionInfo::default(),
+        )
+    }
+
+    fn append_body_with_execution_info(
+        self,
+        block_number: BlockNumber,
+        block_body: BlockBody,
+        execution_info: BlockExecutionInfo,
+    ) -> StorageResult<Self> {
+        if let Some(transaction_execution_infos) = &execution_info.transaction_execution_infos {
+            if transaction_execution_infos.len() != block_body.transactions.len() {
+                return Err(StorageError::ExecutionInfosMismatchTransactions {
+                    block_number,
+                    n_execution_infos: transaction_execution_infos.len(),
+                    n_transactions: block_body.transactions.len(),
+                });
+            }
+        }
+
         let markers_table = self.txn.open_table(&self.tables.markers)?;
         let transactions_table = self.txn.open_table(&self.tables.transactions)?;
         let transaction_outputs_table = self.txn.open_table(&self.tables.transaction_outputs)?;
         let events_table = self.txn.open_table(&self.tables.events)?;
         let transaction_hash_to_idx_table =
             self.txn.open_table(&self.tables.transaction_hash_to_idx)?;
+        let starknet_versions_table = self.txn.open_table(&self.tables.starknet_versions)?;
+        let l1_to_l2_messages_table = self.txn.open_table(&self.tables.l1_to_l2_messages)?;
+        let l2_to_l1_messages_table = self.txn.open_table(&self.tables.l2_to_l1_messages)?;
 
         update_marker(&self.txn, &markers_table, block_number)?;
         write_transactions(
@@ -195,15 +265,26 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
             &self.txn,
             &transactions_table,
             &transaction_hash_to_idx_table,
+            &l1_to_l2_messages_table,
             block_number,
         )?;
         write_transaction_outputs(
             block_body,
+            execution_info.transaction_execution_infos,
             &self.txn,
             &transaction_outputs_table,
             &events_table,
+            &l2_to_l1_messages_table,
             block_number,
         )?;
+        if let Some(starknet_version) = execution_info.starknet_version {
+            write_starknet_version(
+                &starknet_version,
+                &self.txn,
+                &starknet_versions_table,
+                block_number,
+            )?;
+        }
 
         Ok(self)
     }
@@ -218,6 +299,10 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
         let transaction_hash_to_idx_table =
             self.txn.open_table(&self.tables.transaction_hash_to_idx)?;
         let events_table = self.txn.open_table(&self.tables.events)?;
+        let transaction_traces_table = self.txn.open_table(&self.tables.transaction_traces)?;
+        let starknet_versions_table = self.txn.open_table(&self.tables.starknet_versions)?;
+        let l1_to_l2_messages_table = self.txn.open_table(&self.tables.l1_to_l2_messages)?;
+        let l2_to_l1_messages_table = self.txn.open_table(&self.tables.l2_to_l1_messages)?;
 
         // Assert that body marker equals the reverted block number + 1
         let current_header_marker = self.get_body_marker()?;
@@ -238,10 +323,8 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
 
         // Delete the transactions data.
         let mut events = vec![];
-        for (offset, (tx_output, tx_hash)) in transaction_outputs
-            .iter()
-            .zip(transactions.iter().map(|t| t.transaction_hash()))
-            .enumerate()
+        for (offset, (tx_output, tx)) in
+            transaction_outputs.iter().zip(transactions.iter()).enumerate()
         {
             let tx_index = TransactionIndex(block_number, TransactionOffsetInBlock(offset));
             let mut tx_events = vec![];
@@ -258,12 +341,33 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
                 events_table.delete(&self.txn, &key)?;
             }
             events.push(tx_events);
+            let l1_handler = match tx {
+                Transaction::L1Handler(tx) => Some(tx),
+                _ => None,
+            };
+            delete_messages(
+                l1_handler,
+                tx_output.messages_sent(),
+                &self.txn,
+                &l1_to_l2_messages_table,
+                &l2_to_l1_messages_table,
+                tx_index,
+            )?;
             transactions_table.delete(&self.txn, &tx_index)?;
             transaction_outputs_table.delete(&self.txn, &tx_index)?;
-            transaction_hash_to_idx_table.delete(&self.txn, &tx_hash)?;
+            transaction_hash_to_idx_table.delete(&self.txn, &tx.transaction_hash())?;
+            transaction_traces_table.delete(&self.txn, &tx_index)?;
         }
 
+        starknet_versions_table.delete(&self.txn, &block_number)?;
+
         markers_table.upsert(&self.txn, &MarkerKind::Body, &block_number)?;
+        // The traces of the block, if synced, were deleted with its transactions.
+        if let Some(trace_marker) = markers_table.get(&self.txn, &MarkerKind::Trace)? {
+            if trace_marker > block_number {
+                markers_table.upsert(&self.txn, &MarkerKind::Trace, &block_number)?;
+            }
+        }
         Ok((self, Some((transactions, transaction_outputs, events))))
     }
 }
@@ -273,6 +377,7 @@ fn write_transactions<'env>(
     txn: &DbTransaction<'env, RW>,
     transactions_table: &'env TransactionsTable<'env>,
     transaction_hash_to_idx_table: &'env TransactionHashToIdxTable<'env>,
+    l1_to_l2_messages_table: &'env L1ToL2MessagesTable<'env>,
     block_number: BlockNumber,
 ) -> StorageResult<()> {
     for (index, tx) in block_body.transactions.iter().enumerate() {
@@ -280,30 +385,53 @@ fn write_transactions<'env>(
         let transaction_index = TransactionIndex(block_number, tx_offset_in_block);
         transactions_table.insert(txn, &transaction_index, tx)?;
         update_tx_hash_mapping(txn, transaction_hash_to_idx_table, tx, transaction_index)?;
+        if let Transaction::L1Handler(tx) = tx {
+            write_l1_to_l2_message(tx, txn, l1_to_l2_messages_table, transaction_index)?;
+        }
     }
     Ok(())
 }
 
 fn write_transaction_outputs<'env>(
     block_body: BlockBody,
+    transaction_execution_infos: Option<Vec<TransactionExecutionInfo>>,
     txn: &DbTransaction<'env, RW>,
     transaction_outputs_table: &'env TransactionOutputsTable<'env>,
     events_table: &'env EventsTable<'env>,
+    l2_to_l1_messages_table: &'env L2ToL1MessagesTable<'env>,
     block_number: BlockNumber,
 ) -> StorageResult<()> {
+    let mut transaction_execution_infos =
+        transaction_execution_infos.map(|infos| infos.into_iter());
     for (index, tx_output) in block_body.transaction_outputs.into_iter().enumerate() {
         let transaction_index = TransactionIndex(block_number, TransactionOffsetInBlock(index));
 
         write_events(&tx_output, txn, events_table, transaction_index)?;
-        transaction_outputs_table.insert(
+        let mut thin_tx_output = ThinTransactionOutput::from(tx_output);
+        thin_tx_output.set_execution_info(
+            transaction_execution_infos.as_mut().and_then(|infos| infos.next()),
+        );
+        write_l2_to_l1_messages(
+            thin_tx_output.messages_sent(),
             txn,
-            &transaction_index,
-            &ThinTransactionOutput::from(tx_output),
+            l2_to_l1_messages_table,
+            transaction_index,
         )?;
+        transaction_outputs_table.insert(txn, &transaction_index, &thin_tx_output)?;
     }
     Ok(())
 }
 
+fn write_starknet_version<'env>(
+    starknet_version: &StarknetVersion,
+    txn: &DbTransaction<'env, RW>,
+    starknet_versions_table: &'env StarknetVersionsTable<'env>,
+    block_number: BlockNumber,
+) -> StorageResult<()> {
+    starknet_versions_table.insert(txn, &block_number, starknet_version)?;
+    Ok(())
+}
+
 fn write_events<'env>(
     tx_output: &TransactionOutput,
     txn: &DbTransaction<'env, RW>,
@@ -0,0 +1,73 @@
+#[cfg(test)]
+#[path = "checkpoint_test.rs"]
+mod checkpoint_test;
+
+use indexmap::IndexMap;
+use starknet_api::block::BlockNumber;
+use starknet_api::state::StateDiff;
+
+use crate::body::BodyStorageReader;
+use crate::db::{TransactionKind, RW};
+use crate::header::HeaderStorageReader;
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::{MarkerKind, StorageError, StorageResult, StorageTxn};
+
+// A storage that starts at a checkpoint doesn't have the blocks before it. The header and body
+// markers start at the checkpoint. The state marker starts at the checkpoint as well, unless a
+// snapshot of the state is given, in which case it is stored as the state diff of the block before
+// the checkpoint.
+
+pub trait CheckpointStorageReader {
+    /// Returns the first block the storage can have, or None if the storage starts at the genesis.
+    fn get_checkpoint(&self) -> StorageResult<Option<BlockNumber>>;
+}
+
+pub trait CheckpointStorageWriter
+where
+    Self: Sized,
+{
+    /// Starts an empty storage at `block_number`. `state_snapshot` is the state right before the
+    /// block, as a state diff from the genesis.
+    // To enforce that no commit happen after a failure, we consume and return Self on success.
+    fn set_checkpoint(
+        self,
+        block_number: BlockNumber,
+        state_snapshot: Option<StateDiff>,
+    ) -> StorageResult<Self>;
+}
+
+impl<'env, Mode: TransactionKind> CheckpointStorageReader for StorageTxn<'env, Mode> {
+    fn get_checkpoint(&self) -> StorageResult<Option<BlockNumber>> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        Ok(markers_table.get(&self.txn, &MarkerKind::Checkpoint)?)
+    }
+}
+
+impl<'env> CheckpointStorageWriter for StorageTxn<'env, RW> {
+    fn set_checkpoint(
+        self,
+        block_number: BlockNumber,
+        state_snapshot: Option<StateDiff>,
+    ) -> StorageResult<Self> {
+        let is_empty = self.get_checkpoint()?.is_none()
+            && self.get_header_marker()? == BlockNumber(0)
+            && self.get_body_marker()? == BlockNumber(0)
+            && self.get_state_marker()? == BlockNumber(0);
+        if !is_empty {
+            return Err(StorageError::CheckpointInNonEmptyStorage { block_number });
+        }
+
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        markers_table.upsert(&self.txn, &MarkerKind::Checkpoint, &block_number)?;
+        markers_table.upsert(&self.txn, &MarkerKind::Header, &block_number)?;
+        markers_table.upsert(&self.txn, &MarkerKind::Body, &block_number)?;
+        let Some(state_snapshot) = state_snapshot else {
+            markers_table.upsert(&self.txn, &MarkerKind::State, &block_number)?;
+            return Ok(self);
+        };
+        let snapshot_block_number =
+            block_number.prev().ok_or(StorageError::StateSnapshotAtGenesis)?;
+        markers_table.upsert(&self.txn, &MarkerKind::State, &snapshot_block_number)?;
+        self.append_state_diff(snapshot_block_number, state_snapshot, IndexMap::new())
+    }
+}
@@ -0,0 +1,101 @@
+use assert_matches::assert_matches;
+use indexmap::{indexmap, IndexMap};
+use starknet_api::block::{BlockBody, BlockHash, BlockHeader, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{StateDiff, StateNumber};
+use starknet_api::{patricia_key, stark_felt};
+
+use crate::body::{BodyStorageReader, BodyStorageWriter};
+use crate::checkpoint::{CheckpointStorageReader, CheckpointStorageWriter};
+use crate::header::{HeaderStorageReader, HeaderStorageWriter};
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::test_utils::get_test_storage;
+use crate::StorageError;
+
+const CHECKPOINT: BlockNumber = BlockNumber(10);
+
+#[test]
+fn set_checkpoint() {
+    let (reader, mut writer) = get_test_storage();
+    assert_eq!(reader.begin_ro_txn().unwrap().get_checkpoint().unwrap(), None);
+
+    writer.begin_rw_txn().unwrap().set_checkpoint(CHECKPOINT, None).unwrap().commit().unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_checkpoint().unwrap(), Some(CHECKPOINT));
+    assert_eq!(txn.get_header_marker().unwrap(), CHECKPOINT);
+    assert_eq!(txn.get_body_marker().unwrap(), CHECKPOINT);
+    assert_eq!(txn.get_state_marker().unwrap(), CHECKPOINT);
+    drop(txn);
+
+    // The storage continues from the checkpoint.
+    let header = BlockHeader {
+        block_hash: BlockHash(stark_felt!("0x1")),
+        block_number: CHECKPOINT,
+        ..BlockHeader::default()
+    };
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(CHECKPOINT, &header)
+        .unwrap()
+        .append_body(CHECKPOINT, BlockBody::default())
+        .unwrap()
+        .append_state_diff(CHECKPOINT, StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), CHECKPOINT.next());
+    assert_eq!(txn.get_state_marker().unwrap(), CHECKPOINT.next());
+}
+
+#[test]
+fn set_checkpoint_with_state_snapshot() {
+    let (reader, mut writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x12"));
+    let nonce = Nonce(stark_felt!("0x13"));
+    let state_snapshot = StateDiff {
+        deployed_contracts: indexmap! { address => class_hash },
+        nonces: indexmap! { address => nonce },
+        ..StateDiff::default()
+    };
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .set_checkpoint(CHECKPOINT, Some(state_snapshot))
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), CHECKPOINT);
+    assert_eq!(txn.get_state_marker().unwrap(), CHECKPOINT);
+    let state_reader = txn.get_state_reader().unwrap();
+    let state_number = StateNumber::right_before_block(CHECKPOINT);
+    assert_eq!(state_reader.get_class_hash_at(state_number, &address).unwrap(), Some(class_hash));
+    assert_eq!(state_reader.get_nonce_at(state_number, &address).unwrap(), Some(nonce));
+}
+
+#[test]
+fn set_checkpoint_in_non_empty_storage() {
+    let (_, mut writer) = get_test_storage();
+    writer.begin_rw_txn().unwrap().set_checkpoint(CHECKPOINT, None).unwrap().commit().unwrap();
+
+    let res = writer.begin_rw_txn().unwrap().set_checkpoint(BlockNumber(5), None);
+    assert_matches!(
+        res,
+        Err(StorageError::CheckpointInNonEmptyStorage { block_number: BlockNumber(5) })
+    );
+}
+
+#[test]
+fn set_state_snapshot_at_genesis() {
+    let (_, mut writer) = get_test_storage();
+    let res =
+        writer.begin_rw_txn().unwrap().set_checkpoint(BlockNumber(0), Some(StateDiff::default()));
+    assert_matches!(res, Err(StorageError::StateSnapshotAtGenesis));
+}
@@ -0,0 +1,89 @@
+#[cfg(test)]
+#[path = "compiled_class_test.rs"]
+mod compiled_class_test;
+
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use indexmap::IndexMap;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+
+use crate::checkpoint::CheckpointStorageReader;
+use crate::db::{TransactionKind, RW};
+use crate::state::StateStorageReader;
+use crate::{MarkerKind, StorageError, StorageResult, StorageTxn};
+
+// The compiled classes (CASM) of the Cairo 1 classes are downloaded from the central, which
+// compiled them when they were declared, so they are synced separately from the state diffs. The
+// compiled class marker advances independently of the state marker, but never beyond it.
+
+pub trait CasmStorageReader {
+    // The compiled class marker is the first block number whose compiled classes weren't stored
+    // yet.
+    fn get_compiled_class_marker(&self) -> StorageResult<BlockNumber>;
+
+    fn get_casm(&self, class_hash: &ClassHash) -> StorageResult<Option<CasmContractClass>>;
+}
+
+pub trait CasmStorageWriter
+where
+    Self: Sized,
+{
+    // To enforce that no commit happen after a failure, we consume and return Self on success.
+    // Stores the compiled classes of the classes that were declared in the block.
+    fn append_casms(
+        self,
+        block_number: BlockNumber,
+        casms: IndexMap<ClassHash, CasmContractClass>,
+    ) -> StorageResult<Self>;
+}
+
+impl<'env, Mode: TransactionKind> CasmStorageReader for StorageTxn<'env, Mode> {
+    fn get_compiled_class_marker(&self) -> StorageResult<BlockNumber> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        match markers_table.get(&self.txn, &MarkerKind::CompiledClass)? {
+            Some(compiled_class_marker) => Ok(compiled_class_marker),
+            None => Ok(self.get_checkpoint()?.unwrap_or_default()),
+        }
+    }
+
+    fn get_casm(&self, class_hash: &ClassHash) -> StorageResult<Option<CasmContractClass>> {
+        let casms_table = self.txn.open_table(&self.tables.casms)?;
+        Ok(casms_table.get(&self.txn, class_hash)?)
+    }
+}
+
+impl<'env> CasmStorageWriter for StorageTxn<'env, RW> {
+    fn append_casms(
+        self,
+        block_number: BlockNumber,
+        casms: IndexMap<ClassHash, CasmContractClass>,
+    ) -> StorageResult<Self> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        let casms_table = self.txn.open_table(&self.tables.casms)?;
+
+        // Make sure the marker is consistent and the casms are of the classes that were declared in
+        // the stored state diff of the block.
+        let compiled_class_marker = self.get_compiled_class_marker()?;
+        if compiled_class_marker != block_number {
+            return Err(StorageError::MarkerMismatch {
+                expected: compiled_class_marker,
+                found: block_number,
+            });
+        }
+        let declared_classes = self
+            .get_state_diff(block_number)?
+            .ok_or(StorageError::CasmsWithoutStateDiff { block_number })?
+            .declared_classes;
+        if declared_classes.len() != casms.len()
+            || !casms.keys().all(|class_hash| declared_classes.contains_key(class_hash))
+        {
+            return Err(StorageError::CasmsMismatchDeclaredClasses { block_number });
+        }
+
+        for (class_hash, casm) in &casms {
+            casms_table.insert(&self.txn, class_hash, casm)?;
+        }
+        markers_table.upsert(&self.txn, &MarkerKind::CompiledClass, &block_number.next())?;
+        Ok(self)
+    }
+}
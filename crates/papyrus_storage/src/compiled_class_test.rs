@@ -0,0 +1,75 @@
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use test_utils::get_test_state_diff;
+
+use crate::compiled_class::{CasmStorageReader, CasmStorageWriter};
+use crate::state::StateStorageWriter;
+use crate::test_utils::{get_test_casm, get_test_storage};
+use crate::{StorageError, StorageWriter};
+
+fn append_test_state_diff(writer: &mut StorageWriter) -> Vec<ClassHash> {
+    let state_diff = get_test_state_diff();
+    let class_hashes = state_diff.declared_classes.keys().copied().collect();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    class_hashes
+}
+
+#[test]
+fn append_casms() {
+    let (reader, mut writer) = get_test_storage();
+    let class_hashes = append_test_state_diff(&mut writer);
+    let casms: IndexMap<_, _> =
+        class_hashes.iter().map(|class_hash| (*class_hash, get_test_casm("1.0.0"))).collect();
+    writer.begin_rw_txn().unwrap().append_casms(BlockNumber(0), casms).unwrap().commit().unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_compiled_class_marker().unwrap(), BlockNumber(1));
+    assert_eq!(txn.get_casm(&class_hashes[0]).unwrap(), Some(get_test_casm("1.0.0")));
+    assert_eq!(txn.get_casm(&ClassHash(stark_felt!("0x1234"))).unwrap(), None);
+}
+
+#[test]
+fn append_casms_errors() {
+    let (_, mut writer) = get_test_storage();
+
+    let res = writer.begin_rw_txn().unwrap().append_casms(BlockNumber(0), IndexMap::new());
+    assert_matches!(res, Err(StorageError::CasmsWithoutStateDiff { block_number: BlockNumber(0) }));
+
+    append_test_state_diff(&mut writer);
+    let res = writer.begin_rw_txn().unwrap().append_casms(BlockNumber(0), IndexMap::new());
+    assert_matches!(
+        res,
+        Err(StorageError::CasmsMismatchDeclaredClasses { block_number: BlockNumber(0) })
+    );
+    let res = writer.begin_rw_txn().unwrap().append_casms(BlockNumber(1), IndexMap::new());
+    assert_matches!(
+        res,
+        Err(StorageError::MarkerMismatch { expected: BlockNumber(0), found: BlockNumber(1) })
+    );
+}
+
+#[test]
+fn revert_state_diff_reverts_casms() {
+    let (reader, mut writer) = get_test_storage();
+    let class_hashes = append_test_state_diff(&mut writer);
+    let casms =
+        class_hashes.iter().map(|class_hash| (*class_hash, get_test_casm("1.0.0"))).collect();
+    writer.begin_rw_txn().unwrap().append_casms(BlockNumber(0), casms).unwrap().commit().unwrap();
+
+    let (txn, _) = writer.begin_rw_txn().unwrap().revert_state_diff(BlockNumber(0)).unwrap();
+    txn.commit().unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_compiled_class_marker().unwrap(), BlockNumber(0));
+    assert_eq!(txn.get_casm(&class_hashes[0]).unwrap(), None);
+}
@@ -2,15 +2,16 @@
 #[path = "compression_utils_test.rs"]
 mod compression_utils_test;
 
-use std::io::Read;
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
 use flate2::Compression;
+use integer_encoding::{VarIntReader, VarIntWriter};
 use serde::{Deserialize, Serialize};
 
-use crate::db::serialization::{StorageSerde, StorageSerdeError};
+use crate::db::serialization::{StorageEncoding, StorageSerde, StorageSerdeError};
 
 /// Errors that may be returned when encoding or decoding with one of the functions in this file.
 #[derive(thiserror::Error, Debug)]
@@ -26,6 +27,51 @@ pub enum CompressionError {
     StorageSerde(#[from] StorageSerdeError),
 }
 
+/// Serialized storage values of at least this many bytes are compressed before being written.
+pub(crate) const COMPRESSION_THRESHOLD: usize = 256;
+
+// The first byte of a value written by [`compress_if_large`].
+const UNCOMPRESSED: u8 = 0;
+const COMPRESSED: u8 = 1;
+
+/// Writes a serialized value, compressed if it is at least [`COMPRESSION_THRESHOLD`] bytes long
+/// and the compression actually makes it smaller.
+pub(crate) fn compress_if_large(raw: &[u8], res: &mut impl Write) -> Result<(), StorageSerdeError> {
+    if raw.len() >= COMPRESSION_THRESHOLD {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(raw)?;
+        let compressed = encoder.finish()?;
+        if compressed.len() < raw.len() {
+            return write_with_flag(COMPRESSED, &compressed, res);
+        }
+    }
+    write_with_flag(UNCOMPRESSED, raw, res)
+}
+
+/// Reads a value written by [`compress_if_large`] and returns its (decompressed) serialization.
+pub(crate) fn decompress_if_needed(bytes: &mut impl Read) -> Option<Vec<u8>> {
+    let mut flag = [0u8; 1];
+    bytes.read_exact(&mut flag).ok()?;
+    let len: usize = bytes.read_varint().ok()?;
+    let mut buf = vec![0u8; len];
+    bytes.read_exact(&mut buf).ok()?;
+    match flag[0] {
+        UNCOMPRESSED => Some(buf),
+        COMPRESSED => {
+            let mut raw = Vec::new();
+            DeflateDecoder::new(buf.as_slice()).read_to_end(&mut raw).ok()?;
+            Some(raw)
+        }
+        _ => None,
+    }
+}
+
+fn write_with_flag(flag: u8, bytes: &[u8], res: &mut impl Write) -> Result<(), StorageSerdeError> {
+    res.write_all(&[flag])?;
+    res.write_varint(bytes.len())?;
+    Ok(res.write_all(bytes)?)
+}
+
 /// An object that was encoded with [`GzEncoder`].
 /// The phantom data represents the type of the object that was encoded.
 #[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
@@ -38,7 +84,7 @@ where
     /// Returns a gzip compression of a given item.
     pub fn encode(item: I) -> Result<Self, CompressionError> {
         let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
-        item.serialize_into(&mut encoder)?;
+        item.serialize_into(&mut encoder, StorageEncoding::Compact)?;
         let bytes = encoder.finish()?;
         Ok(Self(bytes, PhantomData))
     }
@@ -47,7 +93,8 @@ where
     pub fn decode(&self, buff: &mut Vec<u8>) -> Result<I, CompressionError> {
         let mut decoder = GzDecoder::new(self.0.as_slice());
         decoder.read_to_end(buff)?;
-        I::deserialize_from(&mut buff.as_slice()).ok_or(CompressionError::InnerDeserialization)
+        I::deserialize_from(&mut buff.as_slice(), StorageEncoding::Compact)
+            .ok_or(CompressionError::InnerDeserialization)
     }
 }
 
@@ -11,7 +11,7 @@ use std::sync::Arc;
 use libmdbx::{Cursor, DatabaseFlags, Geometry, WriteFlags, WriteMap};
 use serde::{Deserialize, Serialize};
 
-use crate::db::serialization::{StorageSerde, StorageSerdeEx};
+use crate::db::serialization::{StorageEncoding, StorageSerde, StorageSerdeEx};
 
 // Low database layer for interaction with libmdbx. The API is supposedly generic enough to easily
 // replace the database library with other Berkley-like database implementations.
@@ -20,7 +20,7 @@ use crate::db::serialization::{StorageSerde, StorageSerdeEx};
 // The serialization is consistent across code versions (though, not necessarily across machines).
 
 // Maximum number of Sub-Databases.
-const MAX_DBS: usize = 24;
+const MAX_DBS: usize = 33;
 
 // Note that NO_TLS mode is used by default.
 type EnvironmentKind = WriteMap;
@@ -122,7 +122,12 @@ impl DbWriter {
         let txn = self.env.begin_rw_txn()?;
         txn.create_db(Some(name), DatabaseFlags::empty())?;
         txn.commit()?;
-        Ok(TableIdentifier { name, _key_type: PhantomData {}, _value_type: PhantomData {} })
+        Ok(TableIdentifier {
+            name,
+            encoding: StorageEncoding::Compact,
+            _key_type: PhantomData {},
+            _value_type: PhantomData {},
+        })
     }
 }
 
@@ -150,18 +155,53 @@ impl<'a, Mode: TransactionKind> DbTransaction<'a, Mode> {
         table_id: &TableIdentifier<K, V>,
     ) -> Result<TableHandle<'env, K, V>> {
         let database = self.txn.open_db(Some(table_id.name))?;
-        Ok(TableHandle { database, _key_type: PhantomData {}, _value_type: PhantomData {} })
+        Ok(TableHandle {
+            database,
+            encoding: table_id.encoding,
+            _key_type: PhantomData {},
+            _value_type: PhantomData {},
+        })
     }
 }
 
 pub struct TableIdentifier<K: StorageSerde, V: StorageSerde> {
     name: &'static str,
+    encoding: StorageEncoding,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
 
+impl<K: StorageSerde, V: StorageSerde> TableIdentifier<K, V> {
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // The same table, with its values read and written as another type. Used by migrations to
+    // read values that were written in an older layout.
+    pub(crate) fn with_value_type<V2: StorageSerde>(&self) -> TableIdentifier<K, V2> {
+        TableIdentifier {
+            name: self.name,
+            encoding: self.encoding,
+            _key_type: PhantomData {},
+            _value_type: PhantomData {},
+        }
+    }
+
+    // The same table, with its keys and values read and written in another encoding. Used by
+    // migrations and tests to read and write the tables of storages of older versions.
+    pub(crate) fn with_encoding(&self, encoding: StorageEncoding) -> Self {
+        TableIdentifier {
+            name: self.name,
+            encoding,
+            _key_type: PhantomData {},
+            _value_type: PhantomData {},
+        }
+    }
+}
+
 pub struct TableHandle<'env, K: StorageSerde, V: StorageSerde> {
     database: libmdbx::Database<'env>,
+    encoding: StorageEncoding,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -172,7 +212,12 @@ impl<'env, 'txn, K: StorageSerde, V: StorageSerde> TableHandle<'env, K, V> {
         txn: &'txn DbTransaction<'env, Mode>,
     ) -> Result<DbCursor<'txn, Mode, K, V>> {
         let cursor = txn.txn.cursor(&self.database)?;
-        Ok(DbCursor { cursor, _key_type: PhantomData {}, _value_type: PhantomData {} })
+        Ok(DbCursor {
+            cursor,
+            encoding: self.encoding,
+            _key_type: PhantomData {},
+            _value_type: PhantomData {},
+        })
     }
 
     pub(crate) fn get<Mode: TransactionKind>(
@@ -181,9 +226,10 @@ impl<'env, 'txn, K: StorageSerde, V: StorageSerde> TableHandle<'env, K, V> {
         key: &K,
     ) -> Result<Option<V>> {
         // TODO: Support zero-copy. This might require a return type of Cow<'env, ValueType>.
-        let bin_key = key.serialize()?;
+        let bin_key = key.serialize(self.encoding)?;
         if let Some(bytes) = txn.txn.get::<Cow<'env, [u8]>>(&self.database, &bin_key)? {
-            let value = V::deserialize(&mut bytes.as_ref()).ok_or(DbError::InnerDeserialization)?;
+            let value = V::deserialize(&mut bytes.as_ref(), self.encoding)
+                .ok_or(DbError::InnerDeserialization)?;
             Ok(Some(value))
         } else {
             Ok(None)
@@ -196,8 +242,8 @@ impl<'env, 'txn, K: StorageSerde, V: StorageSerde> TableHandle<'env, K, V> {
         key: &K,
         value: &V,
     ) -> Result<()> {
-        let data = value.serialize()?;
-        let bin_key = key.serialize()?;
+        let data = value.serialize(self.encoding)?;
+        let bin_key = key.serialize(self.encoding)?;
         txn.txn.put(&self.database, bin_key, data, WriteFlags::UPSERT)?;
         Ok(())
     }
@@ -208,15 +254,15 @@ impl<'env, 'txn, K: StorageSerde, V: StorageSerde> TableHandle<'env, K, V> {
         key: &K,
         value: &V,
     ) -> Result<()> {
-        let data = value.serialize()?;
-        let bin_key = key.serialize()?;
+        let data = value.serialize(self.encoding)?;
+        let bin_key = key.serialize(self.encoding)?;
         txn.txn.put(&self.database, bin_key, data, WriteFlags::NO_OVERWRITE)?;
         Ok(())
     }
 
     #[allow(dead_code)]
     pub(crate) fn delete(&'env self, txn: &DbTransaction<'env, RW>, key: &K) -> Result<()> {
-        let bin_key = key.serialize()?;
+        let bin_key = key.serialize(self.encoding)?;
         txn.txn.del(&self.database, bin_key, None)?;
         Ok(())
     }
@@ -224,6 +270,7 @@ impl<'env, 'txn, K: StorageSerde, V: StorageSerde> TableHandle<'env, K, V> {
 
 pub(crate) struct DbCursor<'txn, Mode: TransactionKind, K: StorageSerde, V: StorageSerde> {
     cursor: Cursor<'txn, Mode::Internal>,
+    encoding: StorageEncoding,
     _key_type: PhantomData<K>,
     _value_type: PhantomData<V>,
 }
@@ -234,9 +281,9 @@ impl<'txn, Mode: TransactionKind, K: StorageSerde, V: StorageSerde> DbCursor<'tx
         match prev_cursor_res {
             None => Ok(None),
             Some((key_bytes, value_bytes)) => {
-                let key =
-                    K::deserialize(&mut key_bytes.as_ref()).ok_or(DbError::InnerDeserialization)?;
-                let value = V::deserialize(&mut value_bytes.as_ref())
+                let key = K::deserialize(&mut key_bytes.as_ref(), self.encoding)
+                    .ok_or(DbError::InnerDeserialization)?;
+                let value = V::deserialize(&mut value_bytes.as_ref(), self.encoding)
                     .ok_or(DbError::InnerDeserialization)?;
                 Ok(Some((key, value)))
             }
@@ -249,9 +296,9 @@ impl<'txn, Mode: TransactionKind, K: StorageSerde, V: StorageSerde> DbCursor<'tx
         match prev_cursor_res {
             None => Ok(None),
             Some((key_bytes, value_bytes)) => {
-                let key =
-                    K::deserialize(&mut key_bytes.as_ref()).ok_or(DbError::InnerDeserialization)?;
-                let value = V::deserialize(&mut value_bytes.as_ref())
+                let key = K::deserialize(&mut key_bytes.as_ref(), self.encoding)
+                    .ok_or(DbError::InnerDeserialization)?;
+                let value = V::deserialize(&mut value_bytes.as_ref(), self.encoding)
                     .ok_or(DbError::InnerDeserialization)?;
                 Ok(Some((key, value)))
             }
@@ -260,15 +307,15 @@ impl<'txn, Mode: TransactionKind, K: StorageSerde, V: StorageSerde> DbCursor<'tx
 
     /// Position at first key greater than or equal to specified key.
     pub(crate) fn lower_bound(&mut self, key: &K) -> Result<Option<(K, V)>> {
-        let key_bytes = key.serialize()?;
+        let key_bytes = key.serialize(self.encoding)?;
         let prev_cursor_res =
             self.cursor.set_range::<DbKeyType<'_>, DbValueType<'_>>(&key_bytes)?;
         match prev_cursor_res {
             None => Ok(None),
             Some((key_bytes, value_bytes)) => {
-                let key =
-                    K::deserialize(&mut key_bytes.as_ref()).ok_or(DbError::InnerDeserialization)?;
-                let value = V::deserialize(&mut value_bytes.as_ref())
+                let key = K::deserialize(&mut key_bytes.as_ref(), self.encoding)
+                    .ok_or(DbError::InnerDeserialization)?;
+                let value = V::deserialize(&mut value_bytes.as_ref(), self.encoding)
                     .ok_or(DbError::InnerDeserialization)?;
                 Ok(Some((key, value)))
             }
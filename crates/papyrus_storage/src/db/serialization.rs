@@ -1,20 +1,20 @@
 use crate::db::DbError;
 
 pub(crate) trait StorageSerdeEx: StorageSerde {
-    fn serialize(&self) -> Result<Vec<u8>, DbError>;
+    fn serialize(&self, encoding: StorageEncoding) -> Result<Vec<u8>, DbError>;
 
-    fn deserialize(bytes: &mut impl std::io::Read) -> Option<Self>;
+    fn deserialize(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self>;
 }
 
 impl<T: StorageSerde> StorageSerdeEx for T {
-    fn serialize(&self) -> Result<Vec<u8>, DbError> {
+    fn serialize(&self, encoding: StorageEncoding) -> Result<Vec<u8>, DbError> {
         let mut res: Vec<u8> = Vec::new();
-        self.serialize_into(&mut res).map_err(|_| DbError::Serialization)?;
+        self.serialize_into(&mut res, encoding).map_err(|_| DbError::Serialization)?;
         Ok(res)
     }
 
-    fn deserialize(bytes: &mut impl std::io::Read) -> Option<Self> {
-        let res = Self::deserialize_from(bytes)?;
+    fn deserialize(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        let res = Self::deserialize_from(bytes, encoding)?;
         let mut buf = [0u8, 1];
         // Make sure we are at EOF.
         if bytes.read(&mut buf[..]).ok()? != 0 {
@@ -25,9 +25,13 @@ impl<T: StorageSerde> StorageSerdeEx for T {
 }
 
 pub trait StorageSerde: Sized {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError>;
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError>;
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self>;
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self>;
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -37,3 +41,13 @@ pub enum StorageSerdeError {
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
 }
+
+/// The on-disk encodings of the storage values. Every table is read and written in the encoding of
+/// its identifier, which is [`StorageEncoding::Compact`] outside of storage migrations.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StorageEncoding {
+    /// Felts are written as fixed 32 bytes and values are never compressed (storage version 0).
+    Legacy,
+    /// Felts are written without their leading zeros and large values are compressed.
+    Compact,
+}
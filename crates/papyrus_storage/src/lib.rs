@@ -1,10 +1,15 @@
 pub mod body;
+pub mod checkpoint;
+pub mod compiled_class;
 pub mod compression_utils;
 pub mod db;
 pub mod header;
+mod migration;
 pub mod ommer;
+pub mod revert;
 mod serializers;
 pub mod state;
+pub mod trace;
 mod version;
 
 #[cfg(any(feature = "testing", test))]
@@ -15,6 +20,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use body::events::EventIndex;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use db::DbTableStats;
 use ommer::{OmmerEventKey, OmmerTransactionKey};
 use serde::{Deserialize, Serialize};
@@ -22,34 +28,46 @@ use starknet_api::block::{BlockHash, BlockHeader, BlockNumber};
 use starknet_api::core::{ClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::{ContractClass, StorageKey, ThinStateDiff};
-use starknet_api::transaction::{EventContent, Transaction, TransactionHash};
+use starknet_api::transaction::{
+    EthAddress, EventContent, MessageToL1, Transaction, TransactionHash,
+};
 use tracing::debug;
 use version::{StorageVersionError, Version};
 
 use crate::body::events::ThinTransactionOutput;
-use crate::body::TransactionIndex;
+use crate::body::messages::{L1ToL2MessageHash, MessageToL1Index};
+use crate::body::{StarknetVersion, TransactionIndex};
+use crate::db::serialization::StorageEncoding;
 use crate::db::{
     open_env, DbConfig, DbError, DbReader, DbTransaction, DbWriter, TableHandle, TableIdentifier,
     TransactionKind, RO, RW,
 };
+use crate::migration::migrate_if_needed;
 use crate::state::data::{
-    IndexedContractClass, IndexedDeployedContract, IndexedDeprecatedContractClass,
+    IndexedCompiledClassHash, IndexedContractClass, IndexedDeployedContract,
+    IndexedDeprecatedContractClass,
 };
+use crate::trace::TransactionTrace;
 use crate::version::{VersionStorageReader, VersionStorageWriter};
 
-pub const STORAGE_VERSION: Version = Version(0);
+pub const STORAGE_VERSION: Version = Version(4);
 
 pub fn open_storage(db_config: DbConfig) -> StorageResult<(StorageReader, StorageWriter)> {
     let (db_reader, mut db_writer) = open_env(db_config)?;
     let tables = Arc::new(Tables {
         block_hash_to_number: db_writer.create_table("block_hash_to_number")?,
+        casms: db_writer.create_table("casms")?,
+        compiled_class_hashes: db_writer.create_table("compiled_class_hashes")?,
         contract_storage: db_writer.create_table("contract_storage")?,
         declared_classes: db_writer.create_table("declared_classes")?,
         deprecated_declared_classes: db_writer.create_table("deprecated_declared_classes")?,
         deployed_contracts: db_writer.create_table("deployed_contracts")?,
         events: db_writer.create_table("events")?,
         headers: db_writer.create_table("headers")?,
+        l1_to_l2_messages: db_writer.create_table("l1_to_l2_messages")?,
+        l2_to_l1_messages: db_writer.create_table("l2_to_l1_messages")?,
         markers: db_writer.create_table("markers")?,
+        migration_progress: db_writer.create_table("migration_progress")?,
         nonces: db_writer.create_table("nonces")?,
         ommer_contract_storage: db_writer.create_table("ommer_contract_storage")?,
         ommer_declared_classes: db_writer.create_table("ommer_declared_classes")?,
@@ -61,9 +79,11 @@ pub fn open_storage(db_config: DbConfig) -> StorageResult<(StorageReader, Storag
         ommer_transaction_outputs: db_writer.create_table("ommer_transaction_outputs")?,
         ommer_transactions: db_writer.create_table("ommer_transactions")?,
         replaced_classes: db_writer.create_table("replaced_classes")?,
+        starknet_versions: db_writer.create_table("starknet_versions")?,
         state_diffs: db_writer.create_table("state_diffs")?,
         transaction_hash_to_idx: db_writer.create_table("transaction_hash_to_idx")?,
         transaction_outputs: db_writer.create_table("transaction_outputs")?,
+        transaction_traces: db_writer.create_table("transaction_traces")?,
         transactions: db_writer.create_table("transactions")?,
         storage_version: db_writer.create_table("storage_version")?,
     });
@@ -71,6 +91,7 @@ pub fn open_storage(db_config: DbConfig) -> StorageResult<(StorageReader, Storag
     let writer = StorageWriter { db_writer, tables };
 
     let writer = set_initial_version_if_needed(writer)?;
+    let writer = migrate_if_needed(writer)?;
     verify_storage_version(reader.clone())?;
     Ok((reader, writer))
 }
@@ -132,6 +153,13 @@ impl StorageWriter {
     pub fn begin_rw_txn(&mut self) -> StorageResult<StorageTxn<'_, RW>> {
         Ok(StorageTxn { txn: self.db_writer.begin_rw_txn()?, tables: self.tables.clone() })
     }
+
+    /// Returns a writer whose transactions read and write the storage in the given encoding. Only
+    /// storages of older versions are written in the [`StorageEncoding::Legacy`] encoding, e.g. to
+    /// benchmark the encodings or to test the migrations.
+    pub fn with_encoding(self, encoding: StorageEncoding) -> Self {
+        Self { db_writer: self.db_writer, tables: Arc::new(self.tables.with_encoding(encoding)) }
+    }
 }
 
 pub struct StorageTxn<'env, Mode: TransactionKind> {
@@ -152,13 +180,18 @@ pub fn table_names() -> &'static [&'static str] {
 struct_field_names! {
     struct Tables {
         block_hash_to_number: TableIdentifier<BlockHash, BlockNumber>,
+        casms: TableIdentifier<ClassHash, CasmContractClass>,
+        compiled_class_hashes: TableIdentifier<ClassHash, IndexedCompiledClassHash>,
         contract_storage: TableIdentifier<(ContractAddress, StorageKey, BlockNumber), StarkFelt>,
         declared_classes: TableIdentifier<ClassHash, IndexedContractClass>,
         deprecated_declared_classes: TableIdentifier<ClassHash, IndexedDeprecatedContractClass>,
         deployed_contracts: TableIdentifier<ContractAddress, IndexedDeployedContract>,
         events: TableIdentifier<(ContractAddress, EventIndex), EventContent>,
         headers: TableIdentifier<BlockNumber, BlockHeader>,
+        l1_to_l2_messages: TableIdentifier<L1ToL2MessageHash, TransactionIndex>,
+        l2_to_l1_messages: TableIdentifier<(EthAddress, MessageToL1Index), MessageToL1>,
         markers: TableIdentifier<MarkerKind, BlockNumber>,
+        migration_progress: TableIdentifier<String, Vec<u8>>,
         nonces: TableIdentifier<(ContractAddress, BlockNumber), Nonce>,
         ommer_contract_storage: TableIdentifier<(ContractAddress, StorageKey, BlockHash), StarkFelt>,
         //TODO(yair): Consider whether an ommer_deprecated_declared_classes is needed.
@@ -171,9 +204,11 @@ struct_field_names! {
         ommer_transaction_outputs: TableIdentifier<OmmerTransactionKey, ThinTransactionOutput>,
         ommer_transactions: TableIdentifier<OmmerTransactionKey, Transaction>,
         replaced_classes: TableIdentifier<(ContractAddress, BlockNumber),ClassHash>,
+        starknet_versions: TableIdentifier<BlockNumber, StarknetVersion>,
         state_diffs: TableIdentifier<BlockNumber, ThinStateDiff>,
         transaction_hash_to_idx: TableIdentifier<TransactionHash, TransactionIndex>,
         transaction_outputs: TableIdentifier<TransactionIndex, ThinTransactionOutput>,
+        transaction_traces: TableIdentifier<TransactionIndex, TransactionTrace>,
         transactions: TableIdentifier<TransactionIndex, Transaction>,
         storage_version: TableIdentifier<String, Version>
     }
@@ -190,6 +225,10 @@ macro_rules! struct_field_names {
                 static NAMES: &'static [&'static str] = &[$(stringify!($fname)),*];
                 NAMES
             }
+
+            fn with_encoding(&self, encoding: StorageEncoding) -> Self {
+                Self { $($fname: self.$fname.with_encoding(encoding)),* }
+            }
         }
     }
 }
@@ -256,6 +295,31 @@ pub enum StorageError {
     OmmerNonceAlreadyExists { block_hash: BlockHash, contract_address: ContractAddress },
     #[error(transparent)]
     StorageVersionInconcistency(#[from] StorageVersionError),
+    #[error("Can't set a checkpoint at block {block_number} in a non empty storage.")]
+    CheckpointInNonEmptyStorage { block_number: BlockNumber },
+    #[error("A state snapshot can't be set for a checkpoint at the genesis block.")]
+    StateSnapshotAtGenesis,
+    #[error("Can't store the classes of block {block_number} before its state diff.")]
+    ClassesWithoutStateDiff { block_number: BlockNumber },
+    #[error("Can't store the traces of block {block_number} before its body.")]
+    TracesWithoutBody { block_number: BlockNumber },
+    #[error(
+        "Block {block_number} has {n_transactions} transactions, but {n_traces} traces were given."
+    )]
+    TracesMismatchTransactions { block_number: BlockNumber, n_traces: usize, n_transactions: usize },
+    #[error("Can't store the compiled classes of block {block_number} before its state diff.")]
+    CasmsWithoutStateDiff { block_number: BlockNumber },
+    #[error("The compiled classes of block {block_number} don't match its declared classes.")]
+    CasmsMismatchDeclaredClasses { block_number: BlockNumber },
+    #[error(
+        "Block {block_number} has {n_transactions} transactions, but {n_execution_infos} \
+         execution infos were given."
+    )]
+    ExecutionInfosMismatchTransactions {
+        block_number: BlockNumber,
+        n_execution_infos: usize,
+        n_transactions: usize,
+    },
 }
 
 pub type StorageResult<V> = std::result::Result<V, StorageError>;
@@ -276,6 +340,10 @@ pub enum MarkerKind {
     Header,
     Body,
     State,
+    Checkpoint,
+    Class,
+    Trace,
+    CompiledClass,
 }
 
 pub type MarkersTable<'env> = TableHandle<'env, MarkerKind, BlockNumber>;
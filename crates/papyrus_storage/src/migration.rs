@@ -0,0 +1,309 @@
+#[cfg(test)]
+#[path = "migration_test.rs"]
+mod migration_test;
+
+use starknet_api::core::ContractAddress;
+use starknet_api::transaction::{Fee, MessageToL1, Transaction};
+use tracing::info;
+
+use crate::body::events::{
+    ThinDeclareTransactionOutput, ThinDeployAccountTransactionOutput, ThinDeployTransactionOutput,
+    ThinInvokeTransactionOutput, ThinL1HandlerTransactionOutput, ThinTransactionOutput,
+};
+use crate::body::messages::{L1ToL2MessageHash, MessageToL1Index};
+use crate::db::serialization::{StorageEncoding, StorageSerde, StorageSerdeError, StorageSerdeEx};
+use crate::db::{TableIdentifier, RW};
+use crate::state::write_compiled_class_hashes;
+use crate::version::{Version, VersionStorageReader, VersionStorageWriter};
+use crate::{StorageError, StorageResult, StorageTxn, StorageWriter};
+
+// The number of entries a migration pass migrates in each transaction.
+const MIGRATION_BATCH_SIZE: usize = 10000;
+
+// Brings a storage that was written by an older version of the crate to the current version. The
+// migrations run in passes over the tables they change, and every pass migrates its table in
+// batches, each in its own transaction, so a migration doesn't hold the whole storage in a single
+// transaction. The progress of the passes is recorded with every batch, so an interrupted
+// migration resumes where it stopped, and the version is advanced only once all of its passes are
+// done.
+pub(crate) fn migrate_if_needed(mut writer: StorageWriter) -> StorageResult<StorageWriter> {
+    let mut current_storage_version =
+        writer.begin_rw_txn()?.get_version()?.expect("Storage should have a version");
+
+    if current_storage_version == Version(0) {
+        info!("Migrating storage from version 0 to version 1.");
+        migrate_to_compact_encoding(&mut writer)?;
+        finish_migration(&mut writer, Version(1))?;
+        info!("Storage migration to version 1 is done.");
+        current_storage_version = Version(1);
+    }
+
+    if current_storage_version == Version(1) {
+        info!("Migrating storage from version 1 to version 2.");
+        migrate_to_execution_info(&mut writer)?;
+        finish_migration(&mut writer, Version(2))?;
+        info!("Storage migration to version 2 is done.");
+        current_storage_version = Version(2);
+    }
+
+    if current_storage_version == Version(2) {
+        info!("Migrating storage from version 2 to version 3.");
+        migrate_to_message_indexes(&mut writer)?;
+        finish_migration(&mut writer, Version(3))?;
+        info!("Storage migration to version 3 is done.");
+        current_storage_version = Version(3);
+    }
+
+    if current_storage_version == Version(3) {
+        info!("Migrating storage from version 3 to version 4.");
+        migrate_to_compiled_class_hashes(&mut writer)?;
+        finish_migration(&mut writer, Version(4))?;
+        info!("Storage migration to version 4 is done.");
+    }
+    Ok(writer)
+}
+
+// Runs a migration pass over the entries of a table, in batches. The entries are read in the
+// encoding of the table identifier. Every batch records the key of the next entry to migrate in the
+// progress of the pass, which is named after the table, and an empty key once the pass is done.
+fn migrate_table_in_batches<K: StorageSerde, V: StorageSerde>(
+    writer: &mut StorageWriter,
+    table_id: &TableIdentifier<K, V>,
+    mut migrate_entry: impl FnMut(&StorageTxn<'_, RW>, K, V) -> StorageResult<()>,
+) -> StorageResult<()> {
+    let pass = table_id.name().to_owned();
+    loop {
+        let txn = writer.begin_rw_txn()?;
+        let progress_table = txn.txn.open_table(&txn.tables.migration_progress)?;
+        let next_key = progress_table.get(&txn.txn, &pass)?;
+        if matches!(&next_key, Some(next_key) if next_key.is_empty()) {
+            return Ok(());
+        }
+        let next_key = migrate_batch(&txn, table_id, next_key, &mut migrate_entry)?;
+        progress_table.upsert(&txn.txn, &pass, &next_key.unwrap_or_default())?;
+        txn.commit()?;
+    }
+}
+
+// Migrates up to MIGRATION_BATCH_SIZE entries of the table, from the entry with the serialized
+// `next_key` on, and returns the serialized key of the entry after them, if there is one. Keys are
+// written the same in every encoding.
+fn migrate_batch<K: StorageSerde, V: StorageSerde>(
+    txn: &StorageTxn<'_, RW>,
+    table_id: &TableIdentifier<K, V>,
+    next_key: Option<Vec<u8>>,
+    migrate_entry: &mut impl FnMut(&StorageTxn<'_, RW>, K, V) -> StorageResult<()>,
+) -> StorageResult<Option<Vec<u8>>> {
+    let table = txn.txn.open_table(table_id)?;
+    let mut cursor = table.cursor(&txn.txn)?;
+    let mut current = match next_key {
+        Some(next_key) => {
+            let next_key = K::deserialize(&mut next_key.as_slice(), StorageEncoding::Compact)
+                .ok_or_else(|| StorageError::DBInconsistency {
+                    msg: format!("Bad migration progress of table {}.", table_id.name()),
+                })?;
+            cursor.lower_bound(&next_key)?
+        }
+        None => cursor.next()?,
+    };
+    for _ in 0..MIGRATION_BATCH_SIZE {
+        let Some((key, value)) = current else {
+            return Ok(None);
+        };
+        migrate_entry(txn, key, value)?;
+        current = cursor.next()?;
+    }
+    Ok(current.map(|(key, _)| key.serialize(StorageEncoding::Compact)).transpose()?)
+}
+
+// Advances the storage version, once all the passes of the migration to it are done, and clears
+// their progress.
+fn finish_migration(writer: &mut StorageWriter, version: Version) -> StorageResult<()> {
+    let txn = writer.begin_rw_txn()?;
+    let progress_table = txn.txn.open_table(&txn.tables.migration_progress)?;
+    let mut cursor = progress_table.cursor(&txn.txn)?;
+    let mut passes = Vec::new();
+    while let Some((pass, _)) = cursor.next()? {
+        passes.push(pass);
+    }
+    for pass in passes {
+        progress_table.delete(&txn.txn, &pass)?;
+    }
+    txn.set_version(&version)?.commit()
+}
+
+// Version 1 writes felts without their leading zeros and compresses large values. Keys are written
+// the same in both versions, so only the values of the affected tables are rewritten.
+fn migrate_to_compact_encoding(writer: &mut StorageWriter) -> StorageResult<()> {
+    let tables = writer.tables.clone();
+    reencode_table(writer, &tables.contract_storage)?;
+    reencode_table(writer, &tables.declared_classes)?;
+    reencode_table(writer, &tables.deprecated_declared_classes)?;
+    reencode_table(writer, &tables.events)?;
+    reencode_table(writer, &tables.nonces)?;
+    reencode_table(writer, &tables.ommer_contract_storage)?;
+    reencode_table(writer, &tables.ommer_declared_classes)?;
+    reencode_table(writer, &tables.ommer_events)?;
+    reencode_table(writer, &tables.ommer_nonces)?;
+    reencode_table(writer, &tables.ommer_state_diffs)?;
+    reencode_table(
+        writer,
+        &tables.ommer_transaction_outputs.with_value_type::<ThinTransactionOutputV1>(),
+    )?;
+    reencode_table(writer, &tables.ommer_transactions)?;
+    reencode_table(writer, &tables.state_diffs)?;
+    reencode_table(
+        writer,
+        &tables.transaction_outputs.with_value_type::<ThinTransactionOutputV1>(),
+    )?;
+    reencode_table(writer, &tables.transactions)?;
+    Ok(())
+}
+
+// Reads every value of the table in the legacy encoding and writes it back in the current one.
+fn reencode_table<K: StorageSerde, V: StorageSerde>(
+    writer: &mut StorageWriter,
+    table_id: &TableIdentifier<K, V>,
+) -> StorageResult<()> {
+    let legacy_table_id = table_id.with_encoding(StorageEncoding::Legacy);
+    migrate_table_in_batches(writer, &legacy_table_id, |txn, key, value| {
+        let table = txn.txn.open_table(table_id)?;
+        Ok(table.upsert(&txn.txn, &key, &value)?)
+    })
+}
+
+// Version 2 stores the execution info of the transactions in their outputs. The outputs that were
+// stored before have no execution info.
+fn migrate_to_execution_info(writer: &mut StorageWriter) -> StorageResult<()> {
+    let tables = writer.tables.clone();
+    add_empty_execution_info(writer, &tables.ommer_transaction_outputs)?;
+    add_empty_execution_info(writer, &tables.transaction_outputs)?;
+    Ok(())
+}
+
+fn add_empty_execution_info<K: StorageSerde>(
+    writer: &mut StorageWriter,
+    table_id: &TableIdentifier<K, ThinTransactionOutput>,
+) -> StorageResult<()> {
+    let old_table_id = table_id.with_value_type::<ThinTransactionOutputV1>();
+    migrate_table_in_batches(writer, &old_table_id, |txn, key, ThinTransactionOutputV1(value)| {
+        let table = txn.txn.open_table(table_id)?;
+        Ok(table.upsert(&txn.txn, &key, &value)?)
+    })
+}
+
+// Version 3 indexes the messages between L1 and L2, which are built here from the stored
+// transactions and their outputs.
+fn migrate_to_message_indexes(writer: &mut StorageWriter) -> StorageResult<()> {
+    let tables = writer.tables.clone();
+    migrate_table_in_batches(writer, &tables.transactions, |txn, tx_index, tx| {
+        let Transaction::L1Handler(tx) = tx else {
+            return Ok(());
+        };
+        if let Some(message_hash) = L1ToL2MessageHash::of_l1_handler(&tx) {
+            let l1_to_l2_messages_table = txn.txn.open_table(&txn.tables.l1_to_l2_messages)?;
+            l1_to_l2_messages_table.upsert(&txn.txn, &message_hash, &tx_index)?;
+        }
+        Ok(())
+    })?;
+
+    migrate_table_in_batches(writer, &tables.transaction_outputs, |txn, tx_index, tx_output| {
+        let l2_to_l1_messages_table = txn.txn.open_table(&txn.tables.l2_to_l1_messages)?;
+        for (index, message) in tx_output.messages_sent().iter().enumerate() {
+            let key = (message.to_address, MessageToL1Index(tx_index, index));
+            l2_to_l1_messages_table.upsert(&txn.txn, &key, message)?;
+        }
+        Ok(())
+    })
+}
+
+// Version 4 indexes the compiled class hashes of the declared classes, which are taken from the
+// stored state diffs.
+fn migrate_to_compiled_class_hashes(writer: &mut StorageWriter) -> StorageResult<()> {
+    let tables = writer.tables.clone();
+    migrate_table_in_batches(writer, &tables.state_diffs, |txn, block_number, state_diff| {
+        let compiled_class_hashes_table = txn.txn.open_table(&txn.tables.compiled_class_hashes)?;
+        write_compiled_class_hashes(
+            &state_diff.declared_classes,
+            &txn.txn,
+            block_number,
+            &compiled_class_hashes_table,
+        )
+    })
+}
+
+// A thin transaction output in the layout of versions 0 and 1, which didn't have the execution
+// info.
+struct ThinTransactionOutputV1(ThinTransactionOutput);
+
+impl StorageSerde for ThinTransactionOutputV1 {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        let (kind, actual_fee, messages_sent, events_contract_addresses) = match &self.0 {
+            ThinTransactionOutput::Declare(output) => {
+                (0u8, &output.actual_fee, &output.messages_sent, &output.events_contract_addresses)
+            }
+            ThinTransactionOutput::Deploy(output) => {
+                (1u8, &output.actual_fee, &output.messages_sent, &output.events_contract_addresses)
+            }
+            ThinTransactionOutput::DeployAccount(output) => {
+                (2u8, &output.actual_fee, &output.messages_sent, &output.events_contract_addresses)
+            }
+            ThinTransactionOutput::Invoke(output) => {
+                (3u8, &output.actual_fee, &output.messages_sent, &output.events_contract_addresses)
+            }
+            ThinTransactionOutput::L1Handler(output) => {
+                (4u8, &output.actual_fee, &output.messages_sent, &output.events_contract_addresses)
+            }
+        };
+        kind.serialize_into(res, encoding)?;
+        actual_fee.serialize_into(res, encoding)?;
+        messages_sent.serialize_into(res, encoding)?;
+        events_contract_addresses.serialize_into(res, encoding)
+    }
+
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        let kind = u8::deserialize_from(bytes, encoding)?;
+        let actual_fee = Fee::deserialize_from(bytes, encoding)?;
+        let messages_sent = Vec::<MessageToL1>::deserialize_from(bytes, encoding)?;
+        let events_contract_addresses = Vec::<ContractAddress>::deserialize_from(bytes, encoding)?;
+        let execution_info = None;
+        let output = match kind {
+            0 => ThinTransactionOutput::Declare(ThinDeclareTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events_contract_addresses,
+                execution_info,
+            }),
+            1 => ThinTransactionOutput::Deploy(ThinDeployTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events_contract_addresses,
+                execution_info,
+            }),
+            2 => ThinTransactionOutput::DeployAccount(ThinDeployAccountTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events_contract_addresses,
+                execution_info,
+            }),
+            3 => ThinTransactionOutput::Invoke(ThinInvokeTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events_contract_addresses,
+                execution_info,
+            }),
+            4 => ThinTransactionOutput::L1Handler(ThinL1HandlerTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events_contract_addresses,
+                execution_info,
+            }),
+            _ => return None,
+        };
+        Some(Self(output))
+    }
+}
@@ -0,0 +1,228 @@
+use indexmap::IndexMap;
+use starknet_api::block::BlockNumber;
+use starknet_api::state::{StateNumber, ThinStateDiff};
+use starknet_api::transaction::TransactionOffsetInBlock;
+use test_utils::{get_test_block, get_test_state_diff};
+
+use crate::body::events::ThinTransactionOutput;
+use crate::body::messages::{MessageToL1Index, MessagesReader};
+use crate::body::{BodyStorageReader, BodyStorageWriter, TransactionIndex};
+use crate::db::serialization::{StorageEncoding, StorageSerde};
+use crate::db::{TableIdentifier, RW};
+use crate::header::{HeaderStorageReader, HeaderStorageWriter};
+use crate::migration::ThinTransactionOutputV1;
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::test_utils::get_test_config;
+use crate::version::{Version, VersionStorageReader, VERSION_KEY};
+use crate::{open_storage, StorageTxn, STORAGE_VERSION};
+
+#[test]
+fn migrate_legacy_encoding() {
+    let config = get_test_config();
+    let block = get_test_block(Some(0), 5, Some(3), None, None);
+    let state_diff = get_test_state_diff();
+    let (thin_state_diff, _, _) = ThinStateDiff::from_state_diff(state_diff.clone());
+
+    {
+        let (_, writer) = open_storage(config.clone()).unwrap();
+        let mut writer = writer.with_encoding(StorageEncoding::Legacy);
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_header(BlockNumber(0), &block.header)
+            .unwrap()
+            .append_body(BlockNumber(0), block.body.clone())
+            .unwrap()
+            .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // Mark the storage as written by version 0 of the crate.
+        let txn = writer.begin_rw_txn().unwrap();
+        write_transaction_outputs_as_v1(&txn, BlockNumber(0));
+        set_version(&txn, Version(0));
+        txn.commit().unwrap();
+    }
+
+    let (reader, _) = open_storage(config).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_version().unwrap(), Some(STORAGE_VERSION));
+    assert_eq!(txn.get_block_header(BlockNumber(0)).unwrap(), Some(block.header));
+    assert_eq!(txn.get_block_transactions(BlockNumber(0)).unwrap(), Some(block.body.transactions));
+    assert_eq!(txn.get_state_diff(BlockNumber(0)).unwrap(), Some(thin_state_diff));
+}
+
+#[test]
+fn migrate_transaction_outputs_to_execution_info() {
+    let config = get_test_config();
+    let block = get_test_block(Some(0), 5, Some(3), None, None);
+
+    {
+        let (_, mut writer) = open_storage(config.clone()).unwrap();
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_body(BlockNumber(0), block.body.clone())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // Mark the storage as written by version 1 of the crate.
+        let txn = writer.begin_rw_txn().unwrap();
+        write_transaction_outputs_as_v1(&txn, BlockNumber(0));
+        set_version(&txn, Version(1));
+        txn.commit().unwrap();
+    }
+
+    let (reader, _) = open_storage(config).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_version().unwrap(), Some(STORAGE_VERSION));
+    let expected_tx_outputs =
+        block.body.transaction_outputs.into_iter().map(ThinTransactionOutput::from).collect();
+    assert_eq!(
+        txn.get_block_transaction_outputs(BlockNumber(0)).unwrap(),
+        Some(expected_tx_outputs)
+    );
+}
+
+#[test]
+fn migrate_to_message_indexes() {
+    let config = get_test_config();
+    let block = get_test_block(Some(0), 5, Some(3), None, None);
+
+    {
+        let (_, mut writer) = open_storage(config.clone()).unwrap();
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_body(BlockNumber(0), block.body.clone())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // Mark the storage as written by version 2 of the crate, which had no message indexes.
+        let txn = writer.begin_rw_txn().unwrap();
+        clear_table(&txn, &txn.tables.l1_to_l2_messages);
+        clear_table(&txn, &txn.tables.l2_to_l1_messages);
+        set_version(&txn, Version(2));
+        txn.commit().unwrap();
+    }
+
+    let (reader, _) = open_storage(config).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_version().unwrap(), Some(STORAGE_VERSION));
+    for (offset, tx_output) in block.body.transaction_outputs.into_iter().enumerate() {
+        let tx_index = TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(offset));
+        let tx_output = ThinTransactionOutput::from(tx_output);
+        for (index, message) in tx_output.messages_sent().iter().enumerate() {
+            let message_index = MessageToL1Index(tx_index, index);
+            let messages = txn.get_messages_to_l1(message.to_address, message_index, 1).unwrap();
+            assert_eq!(messages, vec![(message_index, message.clone())]);
+        }
+    }
+}
+
+#[test]
+fn migrate_to_compiled_class_hashes() {
+    let config = get_test_config();
+    let state_diff = get_test_state_diff();
+    let (class_hash, (compiled_class_hash, _)) = state_diff.declared_classes.first().unwrap();
+    let (class_hash, compiled_class_hash) = (*class_hash, *compiled_class_hash);
+
+    {
+        let (_, mut writer) = open_storage(config.clone()).unwrap();
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // Mark the storage as written by version 3 of the crate, which had no compiled class
+        // hashes index.
+        let txn = writer.begin_rw_txn().unwrap();
+        clear_table(&txn, &txn.tables.compiled_class_hashes);
+        set_version(&txn, Version(3));
+        txn.commit().unwrap();
+    }
+
+    let (reader, _) = open_storage(config).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_version().unwrap(), Some(STORAGE_VERSION));
+    let state_number = StateNumber::right_after_block(BlockNumber(0));
+    let state_reader = txn.get_state_reader().unwrap();
+    assert_eq!(
+        state_reader.get_compiled_class_hash_at(state_number, &class_hash).unwrap(),
+        Some(compiled_class_hash)
+    );
+}
+
+#[test]
+fn migration_resumes_from_progress() {
+    let config = get_test_config();
+    let state_diff = get_test_state_diff();
+    let class_hash = *state_diff.declared_classes.keys().next().unwrap();
+
+    {
+        let (_, mut writer) = open_storage(config.clone()).unwrap();
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // Mark the migration to version 4 as interrupted after it went over all the state diffs.
+        let txn = writer.begin_rw_txn().unwrap();
+        clear_table(&txn, &txn.tables.compiled_class_hashes);
+        let progress_table = txn.txn.open_table(&txn.tables.migration_progress).unwrap();
+        progress_table.upsert(&txn.txn, &"state_diffs".to_string(), &vec![]).unwrap();
+        set_version(&txn, Version(3));
+        txn.commit().unwrap();
+    }
+
+    let (reader, _) = open_storage(config).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_version().unwrap(), Some(STORAGE_VERSION));
+    // The state diffs weren't migrated again and the progress was cleared.
+    let state_number = StateNumber::right_after_block(BlockNumber(0));
+    let state_reader = txn.get_state_reader().unwrap();
+    assert_eq!(state_reader.get_compiled_class_hash_at(state_number, &class_hash).unwrap(), None);
+    let progress_table = txn.txn.open_table(&txn.tables.migration_progress).unwrap();
+    assert_eq!(progress_table.get(&txn.txn, &"state_diffs".to_string()).unwrap(), None);
+}
+
+// Rewrites the transaction outputs of the block in the layout of version 1, which had no execution
+// info.
+fn write_transaction_outputs_as_v1(txn: &StorageTxn<'_, RW>, block_number: BlockNumber) {
+    let tx_outputs = txn.get_block_transaction_outputs(block_number).unwrap().unwrap();
+    let table_id = txn.tables.transaction_outputs.with_value_type::<ThinTransactionOutputV1>();
+    let table = txn.txn.open_table(&table_id).unwrap();
+    for (offset, tx_output) in tx_outputs.into_iter().enumerate() {
+        let tx_index = TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+        table.upsert(&txn.txn, &tx_index, &ThinTransactionOutputV1(tx_output)).unwrap();
+    }
+}
+
+fn clear_table<K: StorageSerde, V: StorageSerde>(
+    txn: &StorageTxn<'_, RW>,
+    table_id: &TableIdentifier<K, V>,
+) {
+    let table = txn.txn.open_table(table_id).unwrap();
+    let mut cursor = table.cursor(&txn.txn).unwrap();
+    let mut keys = vec![];
+    while let Some((key, _)) = cursor.next().unwrap() {
+        keys.push(key);
+    }
+    for key in keys {
+        table.delete(&txn.txn, &key).unwrap();
+    }
+}
+
+fn set_version(txn: &StorageTxn<'_, RW>, version: Version) {
+    let version_table = txn.txn.open_table(&txn.tables.storage_version).unwrap();
+    version_table.upsert(&txn.txn, &VERSION_KEY.to_string(), &version).unwrap();
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+#[path = "revert_test.rs"]
+mod revert_test;
+
+use starknet_api::block::{BlockHash, BlockNumber};
+
+use crate::body::BodyStorageWriter;
+use crate::db::RW;
+use crate::header::HeaderStorageWriter;
+use crate::ommer::OmmerStorageWriter;
+use crate::state::StateStorageWriter;
+use crate::{StorageResult, StorageTxn};
+
+/// Writer for reverting whole blocks.
+/// To enforce that no commit happen after a failure, we consume and return Self on success.
+pub trait RevertStorageWriter
+where
+    Self: Sized,
+{
+    /// Reverts the header, body and state diff of the given block, which has to be the last block
+    /// in the storage. If `move_to_ommer` is set, the reverted data is kept in the ommer tables.
+    /// Returns the hash of the reverted block, or None if the block doesn't exist.
+    fn revert_block(
+        self,
+        block_number: BlockNumber,
+        move_to_ommer: bool,
+    ) -> StorageResult<(Self, Option<BlockHash>)>;
+}
+
+impl<'env> RevertStorageWriter for StorageTxn<'env, RW> {
+    fn revert_block(
+        self,
+        block_number: BlockNumber,
+        move_to_ommer: bool,
+    ) -> StorageResult<(Self, Option<BlockHash>)> {
+        let (mut txn, reverted_header) = self.revert_header(block_number)?;
+        let header = match reverted_header {
+            Some(header) => header,
+            None => return Ok((txn, None)),
+        };
+        if move_to_ommer {
+            txn = txn.insert_ommer_header(header.block_hash, &header)?;
+        }
+
+        let res = txn.revert_body(block_number)?;
+        txn = res.0;
+        if let (true, Some((transactions, transaction_outputs, events))) = (move_to_ommer, res.1) {
+            txn = txn.insert_ommer_body(
+                header.block_hash,
+                &transactions,
+                &transaction_outputs,
+                events.as_slice(),
+            )?;
+        }
+
+        let res = txn.revert_state_diff(block_number)?;
+        txn = res.0;
+        // TODO(yair): consider inserting to ommer the deprecated_declared_classes.
+        if let (true, Some((thin_state_diff, declared_classes, _deprecated_declared_classes))) =
+            (move_to_ommer, res.1)
+        {
+            txn = txn.insert_ommer_state_diff(
+                header.block_hash,
+                &thin_state_diff,
+                &declared_classes,
+            )?;
+        }
+
+        Ok((txn, Some(header.block_hash)))
+    }
+}
@@ -0,0 +1,115 @@
+use indexmap::IndexMap;
+use starknet_api::block::{BlockBody, BlockHash, BlockHeader, BlockNumber};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StateDiff;
+use test_utils::get_test_block;
+
+use crate::body::{BodyStorageReader, BodyStorageWriter};
+use crate::header::{HeaderStorageReader, HeaderStorageWriter};
+use crate::ommer::OmmerStorageReader;
+use crate::revert::RevertStorageWriter;
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::test_utils::get_test_storage;
+use crate::StorageWriter;
+
+// Appends blocks with empty state diffs. Only the last block has transactions, since the test
+// bodies of different blocks share transaction hashes.
+fn append_blocks(writer: &mut StorageWriter, n_blocks: u64) -> Vec<BlockHash> {
+    let mut block_hashes = vec![];
+    for i in 0..n_blocks {
+        let block_number = BlockNumber(i);
+        let block_hash = BlockHash(StarkFelt::from(i + 1));
+        let header = BlockHeader { block_hash, block_number, ..BlockHeader::default() };
+        let body = if i + 1 == n_blocks {
+            get_test_block(Some(i), 2, Some(1), None, None).body
+        } else {
+            BlockBody::default()
+        };
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_header(block_number, &header)
+            .unwrap()
+            .append_body(block_number, body)
+            .unwrap()
+            .append_state_diff(block_number, StateDiff::default(), IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+        block_hashes.push(block_hash);
+    }
+    block_hashes
+}
+
+#[test]
+fn revert_last_block() {
+    let (reader, mut writer) = get_test_storage();
+    let block_hashes = append_blocks(&mut writer, 3);
+
+    // Only the last block can be reverted.
+    let (txn, reverted_block_hash) =
+        writer.begin_rw_txn().unwrap().revert_block(BlockNumber(1), true).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(reverted_block_hash, None);
+
+    let (txn, reverted_block_hash) =
+        writer.begin_rw_txn().unwrap().revert_block(BlockNumber(2), true).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(reverted_block_hash, Some(block_hashes[2]));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), BlockNumber(2));
+    assert_eq!(txn.get_body_marker().unwrap(), BlockNumber(2));
+    assert_eq!(txn.get_state_marker().unwrap(), BlockNumber(2));
+    assert!(txn.get_ommer_header(block_hashes[2]).unwrap().is_some());
+}
+
+#[test]
+fn revert_block_without_body() {
+    let (reader, mut writer) = get_test_storage();
+    append_blocks(&mut writer, 1);
+
+    // A header stored ahead of its body.
+    let block_hash = BlockHash(StarkFelt::from(2_u64));
+    let header = BlockHeader { block_hash, block_number: BlockNumber(1), ..BlockHeader::default() };
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(1), &header)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let (txn, reverted_block_hash) =
+        writer.begin_rw_txn().unwrap().revert_block(BlockNumber(1), true).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(reverted_block_hash, Some(block_hash));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), BlockNumber(1));
+    assert_eq!(txn.get_body_marker().unwrap(), BlockNumber(1));
+    assert!(txn.get_ommer_header(block_hash).unwrap().is_some());
+    assert!(txn.get_block_header(BlockNumber(0)).unwrap().is_some());
+}
+
+#[test]
+fn revert_without_ommer() {
+    let (reader, mut writer) = get_test_storage();
+    let block_hashes = append_blocks(&mut writer, 2);
+
+    let (txn, reverted_block_hash) =
+        writer.begin_rw_txn().unwrap().revert_block(BlockNumber(1), false).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(reverted_block_hash, Some(block_hashes[1]));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), BlockNumber(1));
+    assert!(txn.get_block_header(BlockNumber(1)).unwrap().is_none());
+    assert!(txn.get_ommer_header(block_hashes[1]).unwrap().is_none());
+    assert!(txn.get_ommer_header(block_hashes[0]).unwrap().is_none());
+    assert_eq!(
+        txn.get_block_header(BlockNumber(0)).unwrap().map(|header| header.block_hash),
+        Some(block_hashes[0])
+    );
+    assert_eq!(txn.get_block_number_by_hash(&block_hashes[1]).unwrap(), None);
+}
@@ -2,13 +2,14 @@
 #[path = "serializers_test.rs"]
 mod serializers_test;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::hash::Hash;
 use std::ops::Deref;
 use std::sync::Arc;
 
 use byteorder::BigEndian;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use indexmap::IndexMap;
 use integer_encoding::*;
 use starknet_api::block::{
@@ -37,26 +38,33 @@ use starknet_api::transaction::{
     MessageToL1, MessageToL2, Transaction, TransactionHash, TransactionOffsetInBlock,
     TransactionSignature, TransactionVersion,
 };
-use web3::types::H160;
+use web3::types::{H160, H256};
 
 use crate::body::events::{
-    EventIndex, ThinDeclareTransactionOutput, ThinDeployAccountTransactionOutput,
-    ThinDeployTransactionOutput, ThinInvokeTransactionOutput, ThinL1HandlerTransactionOutput,
-    ThinTransactionOutput,
+    EventIndex, ExecutionResources, ThinDeclareTransactionOutput,
+    ThinDeployAccountTransactionOutput, ThinDeployTransactionOutput, ThinInvokeTransactionOutput,
+    ThinL1HandlerTransactionOutput, ThinTransactionOutput, TransactionExecutionInfo,
+    TransactionExecutionStatus,
 };
-use crate::body::TransactionIndex;
-use crate::db::serialization::{StorageSerde, StorageSerdeError};
+use crate::body::messages::{L1ToL2MessageHash, MessageToL1Index};
+use crate::body::{StarknetVersion, TransactionIndex};
+use crate::compression_utils::{compress_if_large, decompress_if_needed};
+use crate::db::serialization::{StorageEncoding, StorageSerde, StorageSerdeError};
 use crate::ommer::{OmmerEventKey, OmmerTransactionKey};
 #[cfg(test)]
 use crate::serializers::serializers_test::{create_storage_serde_test, StorageSerdeTest};
 use crate::state::data::{
-    IndexedContractClass, IndexedDeployedContract, IndexedDeprecatedContractClass,
+    IndexedCompiledClassHash, IndexedContractClass, IndexedDeployedContract,
+    IndexedDeprecatedContractClass,
+};
+use crate::trace::{
+    CallType, FunctionInvocation, OrderedEvent, OrderedL2ToL1Message, TransactionTrace,
 };
 use crate::version::Version;
 use crate::MarkerKind;
 
 auto_storage_serde! {
-    pub struct BlockHash(pub StarkHash);
+    hash pub struct BlockHash(pub StarkHash);
     pub struct BlockHeader {
         pub block_hash: BlockHash,
         pub parent_hash: BlockHash,
@@ -74,12 +82,15 @@ auto_storage_serde! {
         Rejected = 3,
     }
     pub struct BlockTimestamp(pub u64);
-    pub struct Calldata(pub Arc<Vec<StarkFelt>>);
-    pub struct CompiledClassHash(pub StarkHash);
-    pub struct ClassHash(pub StarkHash);
-    pub struct ContractAddressSalt(pub StarkHash);
-    // TODO(anatg): Consider using the compression utils.
-    pub struct ContractClass {
+    pub enum CallType {
+        Call = 0,
+        Delegate = 1,
+    }
+    compressed pub struct Calldata(pub Arc<Vec<StarkFelt>>);
+    hash pub struct CompiledClassHash(pub StarkHash);
+    hash pub struct ClassHash(pub StarkHash);
+    hash pub struct ContractAddressSalt(pub StarkHash);
+    compressed pub struct ContractClass {
         pub sierra_program: Vec<StarkFelt>,
         pub entry_point_by_type: HashMap<EntryPointType, Vec<EntryPoint>>,
         pub abi: String,
@@ -145,7 +156,7 @@ auto_storage_serde! {
     }
     pub struct FunctionIndex(pub usize);
     pub struct EntryPointOffset(pub usize);
-    pub struct EntryPointSelector(pub StarkHash);
+    hash pub struct EntryPointSelector(pub StarkHash);
     pub enum DeprecatedEntryPointType {
         Constructor = 0,
         External = 1,
@@ -158,6 +169,11 @@ auto_storage_serde! {
     }
     // TODO(dan): consider implementing directly with no H160 dependency.
     pub struct EthAddress(pub H160);
+    pub struct ExecutionResources {
+        pub n_steps: u64,
+        pub builtin_instance_counter: BTreeMap<String, u64>,
+        pub n_memory_holes: u64,
+    }
     pub struct EventAbiEntry {
         pub name: String,
         pub keys: Vec<TypedParameter>,
@@ -186,9 +202,27 @@ auto_storage_serde! {
         pub r#type: FunctionAbiEntryType,
         pub entry: FunctionAbiEntry,
     }
+    pub struct FunctionInvocation {
+        pub caller_address: ContractAddress,
+        pub contract_address: ContractAddress,
+        pub calldata: Calldata,
+        pub call_type: Option<CallType>,
+        pub class_hash: Option<ClassHash>,
+        pub selector: Option<EntryPointSelector>,
+        pub entry_point_type: Option<DeprecatedEntryPointType>,
+        pub result: Vec<StarkFelt>,
+        pub internal_calls: Vec<FunctionInvocation>,
+        pub events: Vec<OrderedEvent>,
+        pub messages: Vec<OrderedL2ToL1Message>,
+    }
     pub struct GasPrice(pub u128);
-    pub struct GlobalRoot(pub StarkHash);
+    hash pub struct GlobalRoot(pub StarkHash);
     pub struct H160(pub [u8; 20]);
+    pub struct H256(pub [u8; 32]);
+    pub struct IndexedCompiledClassHash {
+        pub block_number: BlockNumber,
+        pub compiled_class_hash: CompiledClassHash,
+    }
     pub struct IndexedDeprecatedContractClass {
         pub block_number: BlockNumber,
         pub contract_class: DeprecatedContractClass,
@@ -222,18 +256,24 @@ auto_storage_serde! {
         pub sender_address: ContractAddress,
         pub calldata: Calldata,
     }
+    pub struct L1ToL2MessageHash(pub H256);
     pub struct L1ToL2Payload(pub Vec<StarkFelt>);
     pub struct L2ToL1Payload(pub Vec<StarkFelt>);
     enum MarkerKind {
         Header = 0,
         Body = 1,
         State = 2,
+        Checkpoint = 3,
+        Class = 4,
+        Trace = 5,
+        CompiledClass = 6,
     }
     pub struct MessageToL1 {
         pub to_address: EthAddress,
         pub payload: L2ToL1Payload,
         pub from_address: ContractAddress,
     }
+    struct MessageToL1Index(pub TransactionIndex, pub usize);
     pub struct MessageToL2 {
         pub from_address: EthAddress,
         pub payload: L1ToL2Payload,
@@ -241,7 +281,17 @@ auto_storage_serde! {
     pub struct Nonce(pub StarkFelt);
     struct OmmerTransactionKey(pub BlockHash, pub TransactionOffsetInBlock);
     struct OmmerEventKey(pub OmmerTransactionKey, pub EventIndexInTransactionOutput);
-    pub struct Program {
+    pub struct OrderedEvent {
+        pub order: usize,
+        pub keys: Vec<EventKey>,
+        pub data: EventData,
+    }
+    pub struct OrderedL2ToL1Message {
+        pub order: usize,
+        pub to_address: EthAddress,
+        pub payload: L2ToL1Payload,
+    }
+    compressed pub struct Program {
         pub attributes: serde_json::Value,
         pub builtins: serde_json::Value,
         pub compiler_version: serde_json::Value,
@@ -253,6 +303,7 @@ auto_storage_serde! {
         pub prime: serde_json::Value,
         pub reference_manager: serde_json::Value,
     }
+    pub struct StarknetVersion(pub String);
     pub struct StructAbiEntry {
         pub name: String,
         pub size: usize,
@@ -266,16 +317,19 @@ auto_storage_serde! {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinDeployTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinDeployAccountTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct TypedParameter {
         pub name: String,
@@ -285,6 +339,7 @@ auto_storage_serde! {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct L1HandlerTransaction {
         pub transaction_hash: TransactionHash,
@@ -298,6 +353,7 @@ auto_storage_serde! {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinStateDiff {
         pub deployed_contracts: IndexMap<ContractAddress, ClassHash>,
@@ -321,10 +377,24 @@ auto_storage_serde! {
         Invoke(InvokeTransaction) = 3,
         L1Handler(L1HandlerTransaction) = 4,
     }
-    pub struct TransactionHash(pub StarkHash);
+    hash pub struct TransactionHash(pub StarkHash);
     struct TransactionIndex(pub BlockNumber, pub TransactionOffsetInBlock);
+    pub struct TransactionExecutionInfo {
+        pub execution_status: TransactionExecutionStatus,
+        pub execution_resources: ExecutionResources,
+    }
+    pub enum TransactionExecutionStatus {
+        Succeeded = 0,
+        Reverted(String) = 1,
+    }
     pub struct TransactionOffsetInBlock(pub usize);
     pub struct TransactionSignature(pub Vec<StarkFelt>);
+    compressed pub struct TransactionTrace {
+        pub validate_invocation: Option<FunctionInvocation>,
+        pub function_invocation: Option<FunctionInvocation>,
+        pub fee_transfer_invocation: Option<FunctionInvocation>,
+        pub signature: TransactionSignature,
+    }
     pub struct TransactionVersion(pub StarkFelt);
     pub struct Version(pub u32);
 
@@ -342,6 +412,7 @@ auto_storage_serde! {
     (ContractAddress, OmmerEventKey);
     (ContractAddress, StorageKey, BlockHash);
     (ContractAddress, StorageKey, BlockNumber);
+    (EthAddress, MessageToL1Index);
 }
 
 ////////////////////////////////////////////////////////////////////////
@@ -352,11 +423,11 @@ macro_rules! auto_storage_serde {
     // Tuple structs (no names associated with fields) - one field.
     ($(pub)? struct $name:ident($(pub)? $ty:ty); $($rest:tt)*) => {
         impl StorageSerde for $name {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-                self.0.serialize_into(res)
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                self.0.serialize_into(res, encoding)
             }
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-                Some(Self (<$ty>::deserialize_from(bytes)?))
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+                Some(Self (<$ty>::deserialize_from(bytes, encoding)?))
             }
         }
         #[cfg(test)]
@@ -366,12 +437,12 @@ macro_rules! auto_storage_serde {
     // Tuple structs (no names associated with fields) - two fields.
     ($(pub)? struct $name:ident($(pub)? $ty0:ty, $(pub)? $ty1:ty) ; $($rest:tt)*) => {
         impl StorageSerde for $name {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-                self.0.serialize_into(res)?;
-                self.1.serialize_into(res)
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                self.0.serialize_into(res, encoding)?;
+                self.1.serialize_into(res, encoding)
             }
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-                Some($name(<$ty0>::deserialize_from(bytes)?, <$ty1>::deserialize_from(bytes)?))
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+                Some($name(<$ty0>::deserialize_from(bytes, encoding)?, <$ty1>::deserialize_from(bytes, encoding)?))
             }
         }
         #[cfg(test)]
@@ -381,16 +452,16 @@ macro_rules! auto_storage_serde {
     // Structs with public fields.
     ($(pub)? struct $name:ident { $(pub $field:ident : $ty:ty ,)* } $($rest:tt)*) => {
         impl StorageSerde for $name {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
                 $(
-                    self.$field.serialize_into(res)?;
+                    self.$field.serialize_into(res, encoding)?;
                 )*
                 Ok(())
             }
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
                 Some(Self {
                     $(
-                        $field: <$ty>::deserialize_from(bytes)?,
+                        $field: <$ty>::deserialize_from(bytes, encoding)?,
                     )*
                 })
             }
@@ -399,17 +470,93 @@ macro_rules! auto_storage_serde {
         create_storage_serde_test!($name);
         auto_storage_serde!($($rest)*);
     };
+    // Hashes - written in full, since their bytes are uniformly distributed and some of them are
+    // used as table keys.
+    (hash $(pub)? struct $name:ident($(pub)? StarkHash); $($rest:tt)*) => {
+        impl StorageSerde for $name {
+            fn serialize_into(&self, res: &mut impl std::io::Write, _encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                Ok(self.0.serialize(res)?)
+            }
+            fn deserialize_from(bytes: &mut impl std::io::Read, _encoding: StorageEncoding) -> Option<Self> {
+                Some(Self(StarkHash::deserialize(bytes)?))
+            }
+        }
+        #[cfg(test)]
+        create_storage_serde_test!($name);
+        auto_storage_serde!($($rest)*);
+    };
+    // Tuple structs with a potentially large field - compressed when it is large enough.
+    (compressed $(pub)? struct $name:ident($(pub)? $ty:ty); $($rest:tt)*) => {
+        impl StorageSerde for $name {
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                let mut raw = Vec::new();
+                self.0.serialize_into(&mut raw, encoding)?;
+                write_maybe_compressed(&raw, res, encoding)
+            }
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+                fn deserialize_fields(
+                    bytes: &mut impl std::io::Read,
+                    encoding: StorageEncoding,
+                ) -> Option<$name> {
+                    Some($name(<$ty>::deserialize_from(bytes, encoding)?))
+                }
+                match encoding {
+                    StorageEncoding::Legacy => deserialize_fields(bytes, encoding),
+                    StorageEncoding::Compact => {
+                        deserialize_fields(&mut decompress_if_needed(bytes)?.as_slice(), encoding)
+                    }
+                }
+            }
+        }
+        #[cfg(test)]
+        create_storage_serde_test!($name);
+        auto_storage_serde!($($rest)*);
+    };
+    // Structs with public fields and a potentially large serialization - compressed when it is
+    // large enough.
+    (compressed $(pub)? struct $name:ident { $(pub $field:ident : $ty:ty ,)* } $($rest:tt)*) => {
+        impl StorageSerde for $name {
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                let mut raw = Vec::new();
+                $(
+                    self.$field.serialize_into(&mut raw, encoding)?;
+                )*
+                write_maybe_compressed(&raw, res, encoding)
+            }
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+                fn deserialize_fields(
+                    bytes: &mut impl std::io::Read,
+                    encoding: StorageEncoding,
+                ) -> Option<$name> {
+                    Some($name {
+                        $(
+                            $field: <$ty>::deserialize_from(bytes, encoding)?,
+                        )*
+                    })
+                }
+                match encoding {
+                    StorageEncoding::Legacy => deserialize_fields(bytes, encoding),
+                    StorageEncoding::Compact => {
+                        deserialize_fields(&mut decompress_if_needed(bytes)?.as_slice(), encoding)
+                    }
+                }
+            }
+        }
+        #[cfg(test)]
+        create_storage_serde_test!($name);
+        auto_storage_serde!($($rest)*);
+    };
     // Tuples - two elements.
     (($ty0:ty, $ty1:ty) ; $($rest:tt)*) => {
         impl StorageSerde for ($ty0, $ty1) {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-                self.0.serialize_into(res)?;
-                self.1.serialize_into(res)
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                self.0.serialize_into(res, encoding)?;
+                self.1.serialize_into(res, encoding)
             }
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
                 Some((
-                    <$ty0>::deserialize_from(bytes)?,
-                    <$ty1>::deserialize_from(bytes)?,
+                    <$ty0>::deserialize_from(bytes, encoding)?,
+                    <$ty1>::deserialize_from(bytes, encoding)?,
                 ))
             }
         }
@@ -418,16 +565,16 @@ macro_rules! auto_storage_serde {
     // Tuples - three elements.
     (($ty0:ty, $ty1:ty, $ty2:ty) ; $($rest:tt)*) => {
         impl StorageSerde for ($ty0, $ty1, $ty2) {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-                self.0.serialize_into(res)?;
-                self.1.serialize_into(res)?;
-                self.2.serialize_into(res)
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
+                self.0.serialize_into(res, encoding)?;
+                self.1.serialize_into(res, encoding)?;
+                self.2.serialize_into(res, encoding)
             }
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
                 Some((
-                    <$ty0>::deserialize_from(bytes)?,
-                    <$ty1>::deserialize_from(bytes)?,
-                    <$ty2>::deserialize_from(bytes)?,
+                    <$ty0>::deserialize_from(bytes, encoding)?,
+                    <$ty1>::deserialize_from(bytes, encoding)?,
+                    <$ty2>::deserialize_from(bytes, encoding)?,
                 ))
             }
         }
@@ -436,26 +583,26 @@ macro_rules! auto_storage_serde {
     // enums.
     ($(pub)? enum $name:ident { $($variant:ident $( ($ty:ty) )? = $num:expr ,)* } $($rest:tt)*) => {
         impl StorageSerde for $name {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+            fn serialize_into(&self, res: &mut impl std::io::Write, encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
                 match self {
                     $(
                         variant!( value, $variant $( ($ty) )?) => {
                             res.write_all(&[$num as u8])?;
                             $(
-                                (value as &$ty).serialize_into(res)?;
+                                (value as &$ty).serialize_into(res, encoding)?;
                             )?
                             Ok(())
                         }
                     )*
                 }
             }
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+            fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
                 let mut kind = [0u8; 1];
                 bytes.read_exact(&mut kind).ok()?;
                 match kind[0] {
                     $(
                         $num => {
-                            Some(Self::$variant $( (<$ty>::deserialize_from(bytes)?) )? )
+                            Some(Self::$variant $( (<$ty>::deserialize_from(bytes, encoding)?) )? )
                         },
                     )*
                     _ => None,}
@@ -468,11 +615,11 @@ macro_rules! auto_storage_serde {
     // Binary.
     (binary($name:ident, $read:ident, $write:ident); $($rest:tt)*) => {
         impl StorageSerde for $name {
-            fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+            fn serialize_into(&self, res: &mut impl std::io::Write, _encoding: StorageEncoding) -> Result<(), StorageSerdeError> {
                 Ok(byteorder::WriteBytesExt::$write::<BigEndian>(res, *self)?)
             }
 
-            fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+            fn deserialize_from(bytes: &mut impl std::io::Read, _encoding: StorageEncoding) -> Option<Self> {
                 byteorder::ReadBytesExt::$read::<BigEndian>(bytes).ok()
             }
         }
@@ -494,46 +641,122 @@ macro_rules! variant {
 }
 pub(crate) use variant;
 
+// Writes the serialization of a compressed struct of the macro. The legacy encoding has no
+// compression.
+fn write_maybe_compressed(
+    raw: &[u8],
+    res: &mut impl std::io::Write,
+    encoding: StorageEncoding,
+) -> Result<(), StorageSerdeError> {
+    match encoding {
+        StorageEncoding::Legacy => Ok(res.write_all(raw)?),
+        StorageEncoding::Compact => compress_if_large(raw, res),
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////
 // Starknet API structs.
 ////////////////////////////////////////////////////////////////////////
 impl StorageSerde for ContractAddress {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        self.0.serialize_into(res)
-    }
-
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        self.0.serialize_into(res, encoding)
+    }
+
+    fn deserialize_from(
+        bytes: &mut impl std::io::Read,
+        _encoding: StorageEncoding,
+    ) -> Option<Self> {
         ContractAddress::try_from(StarkHash::deserialize(bytes)?).ok()
     }
 }
 
+// Patricia keys (contract addresses and storage keys) are hashes and are used as table keys, so
+// they are written in full.
 impl StorageSerde for PatriciaKey {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        self.key().serialize_into(res)
-    }
-
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        _encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        Ok(self.key().serialize(res)?)
+    }
+
+    fn deserialize_from(
+        bytes: &mut impl std::io::Read,
+        _encoding: StorageEncoding,
+    ) -> Option<Self> {
         Self::try_from(StarkHash::deserialize(bytes)?).ok()
     }
 }
 
-impl StorageSerde for StarkHash {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        Ok(self.serialize(res)?)
+// Most felts are small numbers, so they are written without their leading zeros, prefixed by the
+// number of remaining bytes. Hashes are written in full, see the hash structs of the macro.
+impl StorageSerde for StarkFelt {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        if encoding == StorageEncoding::Legacy {
+            return Ok(self.serialize(res)?);
+        }
+        let bytes = self.bytes();
+        let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+        res.write_all(&[(bytes.len() - leading_zeros) as u8])?;
+        Ok(res.write_all(&bytes[leading_zeros..])?)
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        Self::deserialize(bytes)
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        if encoding == StorageEncoding::Legacy {
+            return Self::deserialize(bytes);
+        }
+        let mut felt_bytes = [0u8; 32];
+        let len = usize::from(u8::deserialize_from(bytes, encoding)?);
+        if len > felt_bytes.len() {
+            return None;
+        }
+        bytes.read_exact(&mut felt_bytes[32 - len..]).ok()?;
+        Self::new(felt_bytes).ok()
     }
 }
 
 impl StorageSerde for StorageKey {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        self.0.serialize_into(res)
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        self.0.serialize_into(res, encoding)
+    }
+
+    fn deserialize_from(
+        bytes: &mut impl std::io::Read,
+        _encoding: StorageEncoding,
+    ) -> Option<Self> {
+        StorageKey::try_from(StarkHash::deserialize(bytes)?).ok()
     }
+}
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        StorageKey::try_from(StarkHash::deserialize(bytes)?).ok()
+// The compiled classes are large and are only read to be executed or served whole, so they are
+// kept as their compressed JSON.
+impl StorageSerde for CasmContractClass {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        _encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        compress_if_large(&serde_json::to_vec(self)?, res)
+    }
+
+    fn deserialize_from(
+        bytes: &mut impl std::io::Read,
+        _encoding: StorageEncoding,
+    ) -> Option<Self> {
+        serde_json::from_slice(decompress_if_needed(bytes)?.as_slice()).ok()
     }
 }
 
@@ -541,115 +764,150 @@ impl StorageSerde for StorageKey {
 //  Primitive types.
 ////////////////////////////////////////////////////////////////////////
 impl StorageSerde for bool {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        u8::from(*self).serialize_into(res)
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        u8::from(*self).serialize_into(res, encoding)
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        Some((u8::deserialize_from(bytes)?) != 0)
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        Some((u8::deserialize_from(bytes, encoding)?) != 0)
     }
 }
 
 // TODO(spapini): Perhaps compress this textual data.
 impl StorageSerde for serde_json::Value {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         let bytes = serde_json::to_vec(self)?;
-        bytes.serialize_into(res)
+        bytes.serialize_into(res, encoding)
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        let buf = Vec::deserialize_from(bytes)?;
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        let buf = Vec::deserialize_from(bytes, encoding)?;
         serde_json::from_slice(buf.as_slice()).ok()
     }
 }
 
 impl StorageSerde for String {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        (self.as_bytes().to_vec()).serialize_into(res)
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        (self.as_bytes().to_vec()).serialize_into(res, encoding)
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        Self::from_utf8(Vec::deserialize_from(bytes)?).ok()
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        Self::from_utf8(Vec::deserialize_from(bytes, encoding)?).ok()
     }
 }
 
 impl<T: StorageSerde> StorageSerde for Option<T> {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         match self {
             Some(value) => {
                 res.write_all(&[1])?;
-                value.serialize_into(res)
+                value.serialize_into(res, encoding)
             }
             None => Ok(res.write_all(&[0])?),
         }
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
         let mut exists = [0u8; 1];
         bytes.read_exact(&mut exists).ok()?;
         match exists[0] {
             0 => Some(None),
-            1 => Some(Some(T::deserialize_from(bytes)?)),
+            1 => Some(Some(T::deserialize_from(bytes, encoding)?)),
             _ => None,
         }
     }
 }
 
 impl StorageSerde for u8 {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        _encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         Ok(byteorder::WriteBytesExt::write_u8(res, *self)?)
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(
+        bytes: &mut impl std::io::Read,
+        _encoding: StorageEncoding,
+    ) -> Option<Self> {
         byteorder::ReadBytesExt::read_u8(bytes).ok()
     }
 }
 
 // TODO(dan): get rid of usize.
 impl StorageSerde for usize {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        (*self as u64).serialize_into(res)
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        (*self as u64).serialize_into(res, encoding)
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        usize::try_from(u64::deserialize_from(bytes)?).ok()
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        usize::try_from(u64::deserialize_from(bytes, encoding)?).ok()
     }
 }
 
 impl<T: StorageSerde> StorageSerde for Vec<T> {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         res.write_varint(self.len())?;
         for x in self {
-            x.serialize_into(res)?
+            x.serialize_into(res, encoding)?
         }
         Ok(())
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
         let n: usize = bytes.read_varint().ok()?;
         let mut res = Vec::with_capacity(n);
         for _i in 0..n {
-            res.push(T::deserialize_from(bytes)?);
+            res.push(T::deserialize_from(bytes, encoding)?);
         }
         Some(res)
     }
 }
 impl<K: StorageSerde + Eq + Hash, V: StorageSerde> StorageSerde for HashMap<K, V> {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         res.write_varint(self.len())?;
         for (k, v) in self.iter() {
-            k.serialize_into(res)?;
-            v.serialize_into(res)?;
+            k.serialize_into(res, encoding)?;
+            v.serialize_into(res, encoding)?;
         }
         Ok(())
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
         let n: usize = bytes.read_varint().ok()?;
         let mut res = HashMap::with_capacity(n);
         for _i in 0..n {
-            let k = K::deserialize_from(bytes)?;
-            let v = V::deserialize_from(bytes)?;
+            let k = K::deserialize_from(bytes, encoding)?;
+            let v = V::deserialize_from(bytes, encoding)?;
             if res.insert(k, v).is_some() {
                 return None;
             }
@@ -659,21 +917,52 @@ impl<K: StorageSerde + Eq + Hash, V: StorageSerde> StorageSerde for HashMap<K, V
 }
 // TODO(anatg): Find a way to share code with StorageSerde for HashMap.
 impl<K: StorageSerde + Eq + Hash, V: StorageSerde> StorageSerde for IndexMap<K, V> {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         res.write_varint(self.len())?;
         for (k, v) in self.iter() {
-            k.serialize_into(res)?;
-            v.serialize_into(res)?;
+            k.serialize_into(res, encoding)?;
+            v.serialize_into(res, encoding)?;
         }
         Ok(())
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
         let n: usize = bytes.read_varint().ok()?;
         let mut res = IndexMap::with_capacity(n);
         for _i in 0..n {
-            let k = K::deserialize_from(bytes)?;
-            let v = V::deserialize_from(bytes)?;
+            let k = K::deserialize_from(bytes, encoding)?;
+            let v = V::deserialize_from(bytes, encoding)?;
+            if res.insert(k, v).is_some() {
+                return None;
+            }
+        }
+        Some(res)
+    }
+}
+impl<K: StorageSerde + Ord, V: StorageSerde> StorageSerde for BTreeMap<K, V> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        res.write_varint(self.len())?;
+        for (k, v) in self.iter() {
+            k.serialize_into(res, encoding)?;
+            v.serialize_into(res, encoding)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        let n: usize = bytes.read_varint().ok()?;
+        let mut res = BTreeMap::new();
+        for _i in 0..n {
+            let k = K::deserialize_from(bytes, encoding)?;
+            let v = V::deserialize_from(bytes, encoding)?;
             if res.insert(k, v).is_some() {
                 return None;
             }
@@ -682,29 +971,37 @@ impl<K: StorageSerde + Eq + Hash, V: StorageSerde> StorageSerde for IndexMap<K,
     }
 }
 impl<T: StorageSerde + Default + Copy, const N: usize> StorageSerde for [T; N] {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
         for x in self {
-            x.serialize_into(res)?;
+            x.serialize_into(res, encoding)?;
         }
         Ok(())
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
         let mut res = [T::default(); N];
         for elm in res.iter_mut() {
-            *elm = T::deserialize_from(bytes)?;
+            *elm = T::deserialize_from(bytes, encoding)?;
         }
         Some(res)
     }
 }
 impl<T: StorageSerde> StorageSerde for Arc<T> {
-    fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
-        self.deref().serialize_into(res)?;
+    fn serialize_into(
+        &self,
+        res: &mut impl std::io::Write,
+        encoding: StorageEncoding,
+    ) -> Result<(), StorageSerdeError> {
+        self.deref().serialize_into(res, encoding)?;
         Ok(())
     }
 
-    fn deserialize_from(bytes: &mut impl std::io::Read) -> Option<Self> {
-        let res = T::deserialize_from(bytes)?;
+    fn deserialize_from(bytes: &mut impl std::io::Read, encoding: StorageEncoding) -> Option<Self> {
+        let res = T::deserialize_from(bytes, encoding)?;
         Some(Arc::new(res))
     }
 }
@@ -1,12 +1,16 @@
 use std::fmt::Debug;
+use std::sync::Arc;
 
-use starknet_api::block::BlockNumber;
+use starknet_api::block::{BlockHash, BlockNumber};
 use starknet_api::core::ContractAddress;
-use starknet_api::hash::StarkHash;
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::stark_felt;
 use starknet_api::state::StorageKey;
+use starknet_api::transaction::Calldata;
 use test_utils::{get_rng, GetTestInstance};
 
-use crate::db::serialization::StorageSerde;
+use crate::compression_utils::COMPRESSION_THRESHOLD;
+use crate::db::serialization::{StorageEncoding, StorageSerde};
 
 pub trait StorageSerdeTest: StorageSerde {
     fn storage_serde_test();
@@ -19,9 +23,9 @@ impl<T: StorageSerde + GetTestInstance + Eq + Debug> StorageSerdeTest for T {
         let mut rng = get_rng(None);
         let item = T::get_test_instance(&mut rng);
         let mut serialized: Vec<u8> = Vec::new();
-        item.serialize_into(&mut serialized).unwrap();
+        item.serialize_into(&mut serialized, StorageEncoding::Compact).unwrap();
         let bytes = serialized.into_boxed_slice();
-        let deserialized = T::deserialize_from(&mut bytes.as_ref());
+        let deserialized = T::deserialize_from(&mut bytes.as_ref(), StorageEncoding::Compact);
         assert_eq!(item, deserialized.unwrap());
     }
 }
@@ -56,17 +60,65 @@ create_storage_serde_test!(usize);
 fn block_number_endianness() {
     let bn_255 = BlockNumber(255);
     let mut serialized: Vec<u8> = Vec::new();
-    bn_255.serialize_into(&mut serialized).unwrap();
+    bn_255.serialize_into(&mut serialized, StorageEncoding::Compact).unwrap();
     let bytes_255 = serialized.into_boxed_slice();
-    let deserialized = BlockNumber::deserialize_from(&mut bytes_255.as_ref());
+    let deserialized =
+        BlockNumber::deserialize_from(&mut bytes_255.as_ref(), StorageEncoding::Compact);
     assert_eq!(bn_255, deserialized.unwrap());
 
     let bn_256 = BlockNumber(256);
     let mut serialized: Vec<u8> = Vec::new();
-    bn_256.serialize_into(&mut serialized).unwrap();
+    bn_256.serialize_into(&mut serialized, StorageEncoding::Compact).unwrap();
     let bytes_256 = serialized.into_boxed_slice();
-    let deserialized = BlockNumber::deserialize_from(&mut bytes_256.as_ref());
+    let deserialized =
+        BlockNumber::deserialize_from(&mut bytes_256.as_ref(), StorageEncoding::Compact);
     assert_eq!(bn_256, deserialized.unwrap());
 
     assert!(bytes_255 < bytes_256);
 }
+
+#[test]
+fn stark_felt_compact_encoding() {
+    for (felt, expected_len) in
+        [(stark_felt!("0x0"), 1), (stark_felt!("0x1"), 2), (stark_felt!("0x1234"), 3)]
+    {
+        let mut serialized: Vec<u8> = Vec::new();
+        felt.serialize_into(&mut serialized, StorageEncoding::Compact).unwrap();
+        assert_eq!(serialized.len(), expected_len);
+        assert_eq!(
+            StarkFelt::deserialize_from(&mut serialized.as_slice(), StorageEncoding::Compact)
+                .unwrap(),
+            felt
+        );
+    }
+
+    // Hashes are written in full.
+    let mut serialized: Vec<u8> = Vec::new();
+    BlockHash(stark_felt!("0x1"))
+        .serialize_into(&mut serialized, StorageEncoding::Compact)
+        .unwrap();
+    assert_eq!(serialized.len(), 32);
+}
+
+#[test]
+fn legacy_encoding() {
+    let felt = stark_felt!("0x1");
+    let mut serialized: Vec<u8> = Vec::new();
+    felt.serialize_into(&mut serialized, StorageEncoding::Legacy).unwrap();
+    assert_eq!(serialized.len(), 32);
+    let deserialized =
+        StarkFelt::deserialize_from(&mut serialized.as_slice(), StorageEncoding::Legacy);
+    assert_eq!(deserialized.unwrap(), felt);
+}
+
+#[test]
+fn large_calldata_is_compressed() {
+    let calldata = Calldata(Arc::new(vec![stark_felt!("0x1234"); COMPRESSION_THRESHOLD]));
+    let mut serialized: Vec<u8> = Vec::new();
+    calldata.serialize_into(&mut serialized, StorageEncoding::Compact).unwrap();
+    assert!(serialized.len() < COMPRESSION_THRESHOLD);
+    assert_eq!(
+        Calldata::deserialize_from(&mut serialized.as_slice(), StorageEncoding::Compact).unwrap(),
+        calldata
+    );
+}
@@ -1,7 +1,7 @@
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use starknet_api::block::BlockNumber;
-use starknet_api::core::ClassHash;
+use starknet_api::core::{ClassHash, CompiledClassHash};
 use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
 use starknet_api::state::ContractClass;
 
@@ -16,6 +16,12 @@ pub(crate) struct IndexedDeployedContract {
     pub class_hash: ClassHash,
 }
 
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub(crate) struct IndexedCompiledClassHash {
+    pub block_number: BlockNumber,
+    pub compiled_class_hash: CompiledClassHash,
+}
+
 #[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub(crate) struct IndexedContractClass {
     pub block_number: BlockNumber,
@@ -5,9 +5,10 @@ mod state_test;
 
 use std::collections::HashSet;
 
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use indexmap::IndexMap;
 use starknet_api::block::BlockNumber;
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::{ContractClass, StateDiff, StateNumber, StorageKey, ThinStateDiff};
@@ -15,10 +16,13 @@ use tracing::debug;
 
 use crate::db::{DbError, DbTransaction, TableHandle, TransactionKind, RW};
 use crate::state::data::{
-    IndexedContractClass, IndexedDeployedContract, IndexedDeprecatedContractClass,
+    IndexedCompiledClassHash, IndexedContractClass, IndexedDeployedContract,
+    IndexedDeprecatedContractClass,
 };
 use crate::{MarkerKind, MarkersTable, StorageError, StorageResult, StorageTxn};
 
+type CasmsTable<'env> = TableHandle<'env, ClassHash, CasmContractClass>;
+type CompiledClassHashesTable<'env> = TableHandle<'env, ClassHash, IndexedCompiledClassHash>;
 type DeclaredClassesTable<'env> = TableHandle<'env, ClassHash, IndexedContractClass>;
 type DeprecatedDeclaredClassesTable<'env> =
     TableHandle<'env, ClassHash, IndexedDeprecatedContractClass>;
@@ -29,6 +33,10 @@ type NoncesTable<'env> = TableHandle<'env, (ContractAddress, BlockNumber), Nonce
 type ReplacedClassesTable<'env> = TableHandle<'env, (ContractAddress, BlockNumber), ClassHash>;
 
 // Structure of state data:
+// * compiled_class_hashes_table: (class_hash) -> (block_num, compiled_class_hash). Each entry
+//   specifies at which block was this Cairo 1 class declared and with what compiled class hash. It
+//   is written with the rest of the state diff, so it has the classes whose definitions weren't
+//   stored yet.
 // * declared_classes_table: (class_hash) -> (block_num, contract_class). Each entry specifies at
 //   which block was this class declared and with what class definition. For Cairo 1 class
 //   definitions.
@@ -48,9 +56,15 @@ type ReplacedClassesTable<'env> = TableHandle<'env, (ContractAddress, BlockNumbe
 //   nonce of `contract_address` was changed to `nonce`.
 // * replaced_classes_table: (contract_address, block_num) -> (class_hash). Specifies that at
 //   `block_num`, the class of `contract_address` was changed to the class with `class_hash`.
+//
+// The definitions of the declared classes may be stored after the rest of the state diff (see
+// `append_thin_state_diff` and `append_classes`). The class marker is the first block whose classes
+// weren't stored yet. A storage without a class marker stores the classes with the state diffs, so
+// its class marker is the state marker.
 
 pub trait StateStorageReader<Mode: TransactionKind> {
     fn get_state_marker(&self) -> StorageResult<BlockNumber>;
+    fn get_class_marker(&self) -> StorageResult<BlockNumber>;
     fn get_state_diff(&self, block_number: BlockNumber) -> StorageResult<Option<ThinStateDiff>>;
     fn get_state_reader(&self) -> StorageResult<StateReader<'_, Mode>>;
 }
@@ -79,6 +93,24 @@ where
         deployed_contract_class_definitions: IndexMap<ClassHash, DeprecatedContractClass>,
     ) -> StorageResult<Self>;
 
+    // Like append_state_diff, without the definitions of the declared classes, which are stored
+    // later with append_classes.
+    fn append_thin_state_diff(
+        self,
+        block_number: BlockNumber,
+        thin_state_diff: ThinStateDiff,
+    ) -> StorageResult<Self>;
+
+    // Stores the definitions of the classes of a block whose state diff was appended with
+    // append_thin_state_diff. `deprecated_classes` also contains the classes of the contracts that
+    // were deployed in the block without being declared.
+    fn append_classes(
+        self,
+        block_number: BlockNumber,
+        classes: IndexMap<ClassHash, ContractClass>,
+        deprecated_classes: IndexMap<ClassHash, DeprecatedContractClass>,
+    ) -> StorageResult<Self>;
+
     fn revert_state_diff(
         self,
         block_number: BlockNumber,
@@ -91,6 +123,13 @@ impl<'env, Mode: TransactionKind> StateStorageReader<Mode> for StorageTxn<'env,
         let markers_table = self.txn.open_table(&self.tables.markers)?;
         Ok(markers_table.get(&self.txn, &MarkerKind::State)?.unwrap_or_default())
     }
+    fn get_class_marker(&self) -> StorageResult<BlockNumber> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        match markers_table.get(&self.txn, &MarkerKind::Class)? {
+            Some(class_marker) => Ok(class_marker),
+            None => self.get_state_marker(),
+        }
+    }
     fn get_state_diff(&self, block_number: BlockNumber) -> StorageResult<Option<ThinStateDiff>> {
         let state_diffs_table = self.txn.open_table(&self.tables.state_diffs)?;
         let state_diff = state_diffs_table.get(&self.txn, &block_number)?;
@@ -104,6 +143,7 @@ impl<'env, Mode: TransactionKind> StateStorageReader<Mode> for StorageTxn<'env,
 /// A single coherent state at a single point in time,
 pub struct StateReader<'env, Mode: TransactionKind> {
     txn: &'env DbTransaction<'env, Mode>,
+    compiled_class_hashes_table: CompiledClassHashesTable<'env>,
     declared_classes_table: DeclaredClassesTable<'env>,
     deprecated_declared_classes_table: DeprecatedDeclaredClassesTable<'env>,
     deployed_contracts_table: DeployedContractsTable<'env>,
@@ -115,6 +155,7 @@ pub struct StateReader<'env, Mode: TransactionKind> {
 #[allow(dead_code)]
 impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
     fn new(txn: &'env StorageTxn<'env, Mode>) -> StorageResult<Self> {
+        let compiled_class_hashes_table = txn.txn.open_table(&txn.tables.compiled_class_hashes)?;
         let declared_classes_table = txn.txn.open_table(&txn.tables.declared_classes)?;
         let deprecated_declared_classes_table =
             txn.txn.open_table(&txn.tables.deprecated_declared_classes)?;
@@ -124,6 +165,7 @@ impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
         let replaced_classes_table = txn.txn.open_table(&txn.tables.replaced_classes)?;
         Ok(StateReader {
             txn: &txn.txn,
+            compiled_class_hashes_table,
             declared_classes_table,
             deprecated_declared_classes_table,
             deployed_contracts_table,
@@ -247,6 +289,20 @@ impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
         Ok(None)
     }
 
+    pub fn get_compiled_class_hash_at(
+        &self,
+        state_number: StateNumber,
+        class_hash: &ClassHash,
+    ) -> StorageResult<Option<CompiledClassHash>> {
+        let value = self.compiled_class_hashes_table.get(self.txn, class_hash)?;
+        if let Some(value) = value {
+            if state_number.is_after(value.block_number) {
+                return Ok(Some(value.compiled_class_hash));
+            }
+        }
+        Ok(None)
+    }
+
     pub fn get_deprecated_class_definition_at(
         &self,
         state_number: StateNumber,
@@ -270,38 +326,20 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
         mut deployed_contract_class_definitions: IndexMap<ClassHash, DeprecatedContractClass>,
     ) -> StorageResult<Self> {
         let markers_table = self.txn.open_table(&self.tables.markers)?;
-        let nonces_table = self.txn.open_table(&self.tables.nonces)?;
-        let deployed_contracts_table = self.txn.open_table(&self.tables.deployed_contracts)?;
         let declared_classes_table = self.txn.open_table(&self.tables.declared_classes)?;
         let deprecated_declared_classes_table =
             self.txn.open_table(&self.tables.deprecated_declared_classes)?;
-        let storage_table = self.txn.open_table(&self.tables.contract_storage)?;
-        let replaced_classes_table = self.txn.open_table(&self.tables.replaced_classes)?;
-        let state_diffs_table = self.txn.open_table(&self.tables.state_diffs)?;
-
-        update_marker(&self.txn, &markers_table, block_number)?;
-
-        // Write state except declared classes.
-        write_deployed_contracts(
-            &state_diff.deployed_contracts,
-            &self.txn,
-            block_number,
-            &deployed_contracts_table,
-            &nonces_table,
-        )?;
-        write_storage_diffs(&state_diff.storage_diffs, &self.txn, block_number, &storage_table)?;
-        write_nonces(&state_diff.nonces, &self.txn, block_number, &nonces_table)?;
-        write_replaced_classes(
-            &state_diff.replaced_classes,
-            &self.txn,
-            block_number,
-            &replaced_classes_table,
-        )?;
 
         // Write state diff.
         let (thin_state_diff, declared_classes, deprecated_declared_classes) =
             ThinStateDiff::from_state_diff(state_diff);
-        state_diffs_table.insert(&self.txn, &block_number, &thin_state_diff)?;
+        self.write_thin_state_diff(block_number, thin_state_diff)?;
+
+        // Advance the class marker, if the classes are stored separately and are in step with the
+        // state diffs.
+        if markers_table.get(&self.txn, &MarkerKind::Class)? == Some(block_number) {
+            markers_table.upsert(&self.txn, &MarkerKind::Class, &block_number.next())?;
+        }
 
         // Write declared classes.
         write_declared_classes(declared_classes, &self.txn, block_number, &declared_classes_table)?;
@@ -332,11 +370,62 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
         Ok(self)
     }
 
+    fn append_thin_state_diff(
+        self,
+        block_number: BlockNumber,
+        thin_state_diff: ThinStateDiff,
+    ) -> StorageResult<Self> {
+        // The classes of the previous blocks are stored, unless the class marker says otherwise.
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        if markers_table.get(&self.txn, &MarkerKind::Class)?.is_none() {
+            markers_table.upsert(&self.txn, &MarkerKind::Class, &block_number)?;
+        }
+        self.write_thin_state_diff(block_number, thin_state_diff)?;
+        Ok(self)
+    }
+
+    fn append_classes(
+        self,
+        block_number: BlockNumber,
+        classes: IndexMap<ClassHash, ContractClass>,
+        deprecated_classes: IndexMap<ClassHash, DeprecatedContractClass>,
+    ) -> StorageResult<Self> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        let declared_classes_table = self.txn.open_table(&self.tables.declared_classes)?;
+        let deprecated_declared_classes_table =
+            self.txn.open_table(&self.tables.deprecated_declared_classes)?;
+
+        // Make sure the marker is consistent and the state diff of the block is stored.
+        let class_marker = self.get_class_marker()?;
+        if class_marker != block_number {
+            return Err(StorageError::MarkerMismatch {
+                expected: class_marker,
+                found: block_number,
+            });
+        }
+        if block_number >= self.get_state_marker()? {
+            return Err(StorageError::ClassesWithoutStateDiff { block_number });
+        }
+
+        write_declared_classes(classes, &self.txn, block_number, &declared_classes_table)?;
+        write_deprecated_declared_classes(
+            deprecated_classes,
+            &self.txn,
+            block_number,
+            &deprecated_declared_classes_table,
+        )?;
+        markers_table.upsert(&self.txn, &MarkerKind::Class, &block_number.next())?;
+        Ok(self)
+    }
+
     fn revert_state_diff(
         self,
         block_number: BlockNumber,
     ) -> StorageResult<(Self, Option<RevertedStateDiff>)> {
         let markers_table = self.txn.open_table(&self.tables.markers)?;
+        let casms_table = self.txn.open_table(&self.tables.casms)?;
+        let compiled_class_hashes_table =
+            self.txn.open_table(&self.tables.compiled_class_hashes)?;
         let declared_classes_table = self.txn.open_table(&self.tables.declared_classes)?;
         let deprecated_declared_classes_table =
             self.txn.open_table(&self.tables.deprecated_declared_classes)?;
@@ -362,8 +451,22 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
             .get_state_diff(block_number)?
             .expect("Missing state diff for block {block_number}.");
         markers_table.upsert(&self.txn, &MarkerKind::State, &block_number)?;
+        if let Some(class_marker) = markers_table.get(&self.txn, &MarkerKind::Class)? {
+            if class_marker > block_number {
+                markers_table.upsert(&self.txn, &MarkerKind::Class, &block_number)?;
+            }
+        }
+        if let Some(compiled_class_marker) =
+            markers_table.get(&self.txn, &MarkerKind::CompiledClass)?
+        {
+            if compiled_class_marker > block_number {
+                markers_table.upsert(&self.txn, &MarkerKind::CompiledClass, &block_number)?;
+            }
+        }
         let deleted_classes =
             delete_declared_classes(&self.txn, &thin_state_diff, &declared_classes_table)?;
+        delete_compiled_class_hashes(&self.txn, &thin_state_diff, &compiled_class_hashes_table)?;
+        delete_casms(&self.txn, &thin_state_diff, &casms_table)?;
         let deleted_deprecated_classes = delete_deprecated_declared_classes(
             &self.txn,
             block_number,
@@ -391,6 +494,56 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
     }
 }
 
+impl<'env> StorageTxn<'env, RW> {
+    // Writes the state diff, except for the definitions of the declared classes, and advances the
+    // state marker.
+    fn write_thin_state_diff(
+        &self,
+        block_number: BlockNumber,
+        thin_state_diff: ThinStateDiff,
+    ) -> StorageResult<()> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        let compiled_class_hashes_table =
+            self.txn.open_table(&self.tables.compiled_class_hashes)?;
+        let nonces_table = self.txn.open_table(&self.tables.nonces)?;
+        let deployed_contracts_table = self.txn.open_table(&self.tables.deployed_contracts)?;
+        let storage_table = self.txn.open_table(&self.tables.contract_storage)?;
+        let replaced_classes_table = self.txn.open_table(&self.tables.replaced_classes)?;
+        let state_diffs_table = self.txn.open_table(&self.tables.state_diffs)?;
+
+        update_marker(&self.txn, &markers_table, block_number)?;
+
+        write_deployed_contracts(
+            &thin_state_diff.deployed_contracts,
+            &self.txn,
+            block_number,
+            &deployed_contracts_table,
+            &nonces_table,
+        )?;
+        write_storage_diffs(
+            &thin_state_diff.storage_diffs,
+            &self.txn,
+            block_number,
+            &storage_table,
+        )?;
+        write_nonces(&thin_state_diff.nonces, &self.txn, block_number, &nonces_table)?;
+        write_compiled_class_hashes(
+            &thin_state_diff.declared_classes,
+            &self.txn,
+            block_number,
+            &compiled_class_hashes_table,
+        )?;
+        write_replaced_classes(
+            &thin_state_diff.replaced_classes,
+            &self.txn,
+            block_number,
+            &replaced_classes_table,
+        )?;
+        state_diffs_table.insert(&self.txn, &block_number, &thin_state_diff)?;
+        Ok(())
+    }
+}
+
 fn update_marker<'env>(
     txn: &DbTransaction<'env, RW>,
     markers_table: &'env MarkersTable<'env>,
@@ -424,6 +577,20 @@ fn write_declared_classes<'env>(
     Ok(())
 }
 
+pub(crate) fn write_compiled_class_hashes<'env>(
+    declared_classes: &IndexMap<ClassHash, CompiledClassHash>,
+    txn: &DbTransaction<'env, RW>,
+    block_number: BlockNumber,
+    compiled_class_hashes_table: &'env CompiledClassHashesTable<'env>,
+) -> StorageResult<()> {
+    for (class_hash, compiled_class_hash) in declared_classes {
+        let value =
+            IndexedCompiledClassHash { block_number, compiled_class_hash: *compiled_class_hash };
+        compiled_class_hashes_table.insert(txn, class_hash, &value)?;
+    }
+    Ok(())
+}
+
 fn write_deprecated_declared_classes<'env>(
     deprecated_declared_classes: IndexMap<ClassHash, DeprecatedContractClass>,
     txn: &DbTransaction<'env, RW>,
@@ -528,9 +695,12 @@ fn delete_declared_classes<'env>(
 ) -> StorageResult<IndexMap<ClassHash, ContractClass>> {
     let mut deleted_data = IndexMap::new();
     for class_hash in thin_state_diff.declared_classes.keys() {
-        let IndexedContractClass { block_number: _, contract_class } = declared_classes_table
-            .get(txn, class_hash)?
-            .expect("Missing declared class {class_hash:#?}.");
+        // The definition is missing if the classes of the block weren't stored yet.
+        let Some(IndexedContractClass { block_number: _, contract_class }) =
+            declared_classes_table.get(txn, class_hash)?
+        else {
+            continue;
+        };
         deleted_data.insert(*class_hash, contract_class);
         declared_classes_table.delete(txn, class_hash)?;
     }
@@ -538,6 +708,29 @@ fn delete_declared_classes<'env>(
     Ok(deleted_data)
 }
 
+fn delete_compiled_class_hashes<'env>(
+    txn: &'env DbTransaction<'env, RW>,
+    thin_state_diff: &ThinStateDiff,
+    compiled_class_hashes_table: &'env CompiledClassHashesTable<'env>,
+) -> StorageResult<()> {
+    for class_hash in thin_state_diff.declared_classes.keys() {
+        compiled_class_hashes_table.delete(txn, class_hash)?;
+    }
+    Ok(())
+}
+
+// The compiled classes are missing if they weren't synced yet.
+fn delete_casms<'env>(
+    txn: &'env DbTransaction<'env, RW>,
+    thin_state_diff: &ThinStateDiff,
+    casms_table: &'env CasmsTable<'env>,
+) -> StorageResult<()> {
+    for class_hash in thin_state_diff.declared_classes.keys() {
+        casms_table.delete(txn, class_hash)?;
+    }
+    Ok(())
+}
+
 fn delete_deprecated_declared_classes<'env>(
     txn: &'env DbTransaction<'env, RW>,
     block_number: BlockNumber,
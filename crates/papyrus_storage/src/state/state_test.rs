@@ -643,3 +643,121 @@ fn replace_class() {
 
     assert_eq!(current_class_hash, class_hash0);
 }
+
+#[test]
+fn append_classes_after_thin_state_diff() {
+    let (reader, mut writer) = get_test_storage();
+    let class_hash = ClassHash(stark_felt!("0x10"));
+    let deprecated_class_hash = ClassHash(stark_felt!("0x11"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x12"));
+    let thin_state_diff = ThinStateDiff {
+        declared_classes: indexmap! { class_hash => compiled_class_hash },
+        deprecated_declared_classes: vec![deprecated_class_hash],
+        ..ThinStateDiff::default()
+    };
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(BlockNumber(0), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .append_thin_state_diff(BlockNumber(1), thin_state_diff)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // The state diff is stored, the classes aren't.
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_state_marker().unwrap(), BlockNumber(2));
+    assert_eq!(txn.get_class_marker().unwrap(), BlockNumber(1));
+    let state_number = StateNumber::right_after_block(BlockNumber(1));
+    let state_reader = txn.get_state_reader().unwrap();
+    assert!(state_reader.get_class_definition_at(state_number, &class_hash).unwrap().is_none());
+    // The compiled class hash is part of the state diff.
+    assert_eq!(
+        state_reader.get_compiled_class_hash_at(state_number, &class_hash).unwrap(),
+        Some(compiled_class_hash)
+    );
+    let state_number = StateNumber::right_after_block(BlockNumber(0));
+    assert!(state_reader.get_compiled_class_hash_at(state_number, &class_hash).unwrap().is_none());
+    drop(txn);
+    let state_number = StateNumber::right_after_block(BlockNumber(1));
+
+    // The classes can't be stored before the state diff.
+    let res = writer.begin_rw_txn().unwrap().append_classes(
+        BlockNumber(2),
+        IndexMap::new(),
+        IndexMap::new(),
+    );
+    assert_matches!(
+        res,
+        Err(StorageError::MarkerMismatch { expected: BlockNumber(1), found: BlockNumber(2) })
+    );
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_classes(
+            BlockNumber(1),
+            indexmap! { class_hash => ContractClass::default() },
+            indexmap! { deprecated_class_hash => DeprecatedContractClass::default() },
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_class_marker().unwrap(), BlockNumber(2));
+    let state_reader = txn.get_state_reader().unwrap();
+    assert_eq!(
+        state_reader.get_class_definition_at(state_number, &class_hash).unwrap(),
+        Some(ContractClass::default())
+    );
+    assert_eq!(
+        state_reader
+            .get_deprecated_class_definition_at(state_number, &deprecated_class_hash)
+            .unwrap(),
+        Some(DeprecatedContractClass::default())
+    );
+    drop(txn);
+
+    // Once the classes are in step with the state diffs, they are stored together again.
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(BlockNumber(2), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    assert_eq!(reader.begin_ro_txn().unwrap().get_class_marker().unwrap(), BlockNumber(3));
+}
+
+#[test]
+fn revert_state_diff_with_missing_classes() {
+    let (reader, mut writer) = get_test_storage();
+    let class_hash = ClassHash(stark_felt!("0x10"));
+    let thin_state_diff = ThinStateDiff {
+        declared_classes: indexmap! { class_hash => CompiledClassHash::default() },
+        ..ThinStateDiff::default()
+    };
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_thin_state_diff(BlockNumber(0), ThinStateDiff::default())
+        .unwrap()
+        .append_thin_state_diff(BlockNumber(1), thin_state_diff)
+        .unwrap()
+        .commit()
+        .unwrap();
+    assert_eq!(reader.begin_ro_txn().unwrap().get_class_marker().unwrap(), BlockNumber(0));
+
+    let (txn, deleted_data) =
+        writer.begin_rw_txn().unwrap().revert_state_diff(BlockNumber(1)).unwrap();
+    txn.commit().unwrap();
+    let (_, declared_classes, _) = deleted_data.unwrap();
+    assert!(declared_classes.is_empty());
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_state_marker().unwrap(), BlockNumber(1));
+    assert_eq!(txn.get_class_marker().unwrap(), BlockNumber(0));
+    let state_number = StateNumber::right_after_block(BlockNumber(1));
+    let state_reader = txn.get_state_reader().unwrap();
+    assert!(state_reader.get_compiled_class_hash_at(state_number, &class_hash).unwrap().is_none());
+}
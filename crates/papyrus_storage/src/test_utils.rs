@@ -1,23 +1,37 @@
+use std::collections::BTreeMap;
+
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use starknet_api::block::{BlockHash, BlockNumber};
-use starknet_api::core::{ClassHash, ContractAddress};
-use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, EntryPointSelector};
+use starknet_api::deprecated_contract_class::{
+    ContractClass as DeprecatedContractClass, EntryPointType,
+};
+use starknet_api::hash::StarkFelt;
 use starknet_api::state::ContractClass;
 use starknet_api::transaction::{
-    EventIndexInTransactionOutput, Fee, MessageToL1, TransactionOffsetInBlock,
+    Calldata, EthAddress, EventData, EventIndexInTransactionOutput, EventKey, Fee, L2ToL1Payload,
+    MessageToL1, TransactionOffsetInBlock, TransactionSignature,
 };
 use tempfile::tempdir;
 use test_utils::{auto_impl_get_test_instance, get_number_of_variants, GetTestInstance};
+use web3::types::H256;
 
 use crate::body::events::{
-    ThinDeclareTransactionOutput, ThinDeployAccountTransactionOutput, ThinDeployTransactionOutput,
-    ThinInvokeTransactionOutput, ThinL1HandlerTransactionOutput, ThinTransactionOutput,
+    ExecutionResources, ThinDeclareTransactionOutput, ThinDeployAccountTransactionOutput,
+    ThinDeployTransactionOutput, ThinInvokeTransactionOutput, ThinL1HandlerTransactionOutput,
+    ThinTransactionOutput, TransactionExecutionInfo, TransactionExecutionStatus,
 };
-use crate::body::TransactionIndex;
+use crate::body::messages::{L1ToL2MessageHash, MessageToL1Index};
+use crate::body::{StarknetVersion, TransactionIndex};
 use crate::db::DbConfig;
 use crate::state::data::{
-    IndexedContractClass, IndexedDeployedContract, IndexedDeprecatedContractClass,
+    IndexedCompiledClassHash, IndexedContractClass, IndexedDeployedContract,
+    IndexedDeprecatedContractClass,
+};
+use crate::trace::{
+    CallType, FunctionInvocation, OrderedEvent, OrderedL2ToL1Message, TransactionTrace,
 };
 use crate::version::Version;
 use crate::{
@@ -40,8 +54,33 @@ pub fn get_test_storage() -> (StorageReader, StorageWriter) {
     open_storage(config).unwrap()
 }
 
+/// Returns a compiled class without code, whose compiler version is `compiler_version`.
+pub fn get_test_casm(compiler_version: &str) -> CasmContractClass {
+    serde_json::from_value(serde_json::json!({
+        "prime": "0x800000000000011000000000000000000000000000000000000000000000001",
+        "compiler_version": compiler_version,
+        "bytecode": [],
+        "hints": [],
+        "entry_points_by_type": { "EXTERNAL": [], "L1_HANDLER": [], "CONSTRUCTOR": [] },
+    }))
+    .unwrap()
+}
+
 auto_impl_get_test_instance! {
+    pub enum CallType {
+        Call = 0,
+        Delegate = 1,
+    }
     struct EventIndex(pub TransactionIndex, pub EventIndexInTransactionOutput);
+    pub struct ExecutionResources {
+        pub n_steps: u64,
+        pub builtin_instance_counter: BTreeMap<String, u64>,
+        pub n_memory_holes: u64,
+    }
+    pub struct IndexedCompiledClassHash {
+        pub block_number: BlockNumber,
+        pub compiled_class_hash: CompiledClassHash,
+    }
     pub struct IndexedDeprecatedContractClass {
         pub block_number: BlockNumber,
         pub contract_class: DeprecatedContractClass,
@@ -54,37 +93,59 @@ auto_impl_get_test_instance! {
         pub block_number: BlockNumber,
         pub class_hash: ClassHash,
     }
+    pub struct L1ToL2MessageHash(pub H256);
     enum MarkerKind {
         Header = 0,
         Body = 1,
         State = 2,
+        Checkpoint = 3,
+        Class = 4,
+        Trace = 5,
+        CompiledClass = 6,
     }
+    struct MessageToL1Index(pub TransactionIndex, pub usize);
     struct OmmerTransactionKey(pub BlockHash, pub TransactionOffsetInBlock);
     struct OmmerEventKey(pub OmmerTransactionKey, pub EventIndexInTransactionOutput);
+    pub struct OrderedEvent {
+        pub order: usize,
+        pub keys: Vec<EventKey>,
+        pub data: EventData,
+    }
+    pub struct OrderedL2ToL1Message {
+        pub order: usize,
+        pub to_address: EthAddress,
+        pub payload: L2ToL1Payload,
+    }
+    pub struct StarknetVersion(pub String);
     pub struct ThinDeclareTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinDeployTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinDeployAccountTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinInvokeTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub struct ThinL1HandlerTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
         pub events_contract_addresses: Vec<ContractAddress>,
+        pub execution_info: Option<TransactionExecutionInfo>,
     }
     pub enum ThinTransactionOutput {
         Declare(ThinDeclareTransactionOutput) = 0,
@@ -93,6 +154,40 @@ auto_impl_get_test_instance! {
         Invoke(ThinInvokeTransactionOutput) = 3,
         L1Handler(ThinL1HandlerTransactionOutput) = 4,
     }
+    pub struct TransactionExecutionInfo {
+        pub execution_status: TransactionExecutionStatus,
+        pub execution_resources: ExecutionResources,
+    }
+    pub enum TransactionExecutionStatus {
+        Succeeded = 0,
+        Reverted(String) = 1,
+    }
     struct TransactionIndex(pub BlockNumber, pub TransactionOffsetInBlock);
+    pub struct TransactionTrace {
+        pub validate_invocation: Option<FunctionInvocation>,
+        pub function_invocation: Option<FunctionInvocation>,
+        pub fee_transfer_invocation: Option<FunctionInvocation>,
+        pub signature: TransactionSignature,
+    }
     pub struct Version(pub u32);
 }
+
+// Implemented manually, since an invocation with an internal call of the same type would recurse
+// forever.
+impl GetTestInstance for FunctionInvocation {
+    fn get_test_instance(rng: &mut ChaCha8Rng) -> Self {
+        Self {
+            caller_address: ContractAddress::get_test_instance(rng),
+            contract_address: ContractAddress::get_test_instance(rng),
+            calldata: Calldata::get_test_instance(rng),
+            call_type: Option::<CallType>::get_test_instance(rng),
+            class_hash: Option::<ClassHash>::get_test_instance(rng),
+            selector: Option::<EntryPointSelector>::get_test_instance(rng),
+            entry_point_type: Option::<EntryPointType>::get_test_instance(rng),
+            result: Vec::<StarkFelt>::get_test_instance(rng),
+            internal_calls: Vec::new(),
+            events: Vec::<OrderedEvent>::get_test_instance(rng),
+            messages: Vec::<OrderedL2ToL1Message>::get_test_instance(rng),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+#[path = "trace_test.rs"]
+mod trace_test;
+
+use serde::{Deserialize, Serialize};
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector};
+use starknet_api::deprecated_contract_class::EntryPointType;
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{
+    Calldata, EthAddress, EventData, EventKey, L2ToL1Payload, TransactionOffsetInBlock,
+    TransactionSignature,
+};
+
+use crate::body::{BodyStorageReader, TransactionIndex};
+use crate::checkpoint::CheckpointStorageReader;
+use crate::db::{TransactionKind, RW};
+use crate::{MarkerKind, StorageError, StorageResult, StorageTxn};
+
+// The traces are synced separately from the bodies, so the trace marker advances independently of
+// the body marker, but never beyond it.
+
+/// The execution trace of a transaction, as returned by the starknet gateway.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct TransactionTrace {
+    pub validate_invocation: Option<FunctionInvocation>,
+    /// The invocation of the transaction itself, i.e., the execution of an invoke or an L1 handler
+    /// transaction, or the constructor of a deployed account.
+    pub function_invocation: Option<FunctionInvocation>,
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+    pub signature: TransactionSignature,
+}
+
+/// A call of a contract function during the execution of a transaction, with the calls it made.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FunctionInvocation {
+    pub caller_address: ContractAddress,
+    pub contract_address: ContractAddress,
+    pub calldata: Calldata,
+    // The following fields are missing from the traces of old blocks.
+    pub call_type: Option<CallType>,
+    pub class_hash: Option<ClassHash>,
+    pub selector: Option<EntryPointSelector>,
+    pub entry_point_type: Option<EntryPointType>,
+    pub result: Vec<StarkFelt>,
+    pub internal_calls: Vec<FunctionInvocation>,
+    pub events: Vec<OrderedEvent>,
+    pub messages: Vec<OrderedL2ToL1Message>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub enum CallType {
+    Call,
+    Delegate,
+}
+
+/// An event emitted by a function invocation, with its order within the transaction.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct OrderedEvent {
+    pub order: usize,
+    pub keys: Vec<EventKey>,
+    pub data: EventData,
+}
+
+/// A message sent to L1 by a function invocation, with its order within the transaction.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct OrderedL2ToL1Message {
+    pub order: usize,
+    pub to_address: EthAddress,
+    pub payload: L2ToL1Payload,
+}
+
+pub trait TraceStorageReader {
+    // The trace marker is the first block number whose traces weren't stored yet.
+    fn get_trace_marker(&self) -> StorageResult<BlockNumber>;
+
+    fn get_transaction_trace(
+        &self,
+        transaction_index: TransactionIndex,
+    ) -> StorageResult<Option<TransactionTrace>>;
+
+    fn get_block_traces(
+        &self,
+        block_number: BlockNumber,
+    ) -> StorageResult<Option<Vec<TransactionTrace>>>;
+}
+
+pub trait TraceStorageWriter
+where
+    Self: Sized,
+{
+    // To enforce that no commit happen after a failure, we consume and return Self on success.
+    fn append_traces(
+        self,
+        block_number: BlockNumber,
+        traces: Vec<TransactionTrace>,
+    ) -> StorageResult<Self>;
+}
+
+impl<'env, Mode: TransactionKind> TraceStorageReader for StorageTxn<'env, Mode> {
+    fn get_trace_marker(&self) -> StorageResult<BlockNumber> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        match markers_table.get(&self.txn, &MarkerKind::Trace)? {
+            Some(trace_marker) => Ok(trace_marker),
+            None => Ok(self.get_checkpoint()?.unwrap_or_default()),
+        }
+    }
+
+    fn get_transaction_trace(
+        &self,
+        transaction_index: TransactionIndex,
+    ) -> StorageResult<Option<TransactionTrace>> {
+        let transaction_traces_table = self.txn.open_table(&self.tables.transaction_traces)?;
+        Ok(transaction_traces_table.get(&self.txn, &transaction_index)?)
+    }
+
+    fn get_block_traces(
+        &self,
+        block_number: BlockNumber,
+    ) -> StorageResult<Option<Vec<TransactionTrace>>> {
+        if self.get_trace_marker()? <= block_number {
+            return Ok(None);
+        }
+        let transaction_traces_table = self.txn.open_table(&self.tables.transaction_traces)?;
+        let mut cursor = transaction_traces_table.cursor(&self.txn)?;
+        let mut current =
+            cursor.lower_bound(&TransactionIndex(block_number, TransactionOffsetInBlock(0)))?;
+        let mut res = Vec::new();
+        while let Some((TransactionIndex(current_block_number, _), trace)) = current {
+            if current_block_number != block_number {
+                break;
+            }
+            res.push(trace);
+            current = cursor.next()?;
+        }
+        Ok(Some(res))
+    }
+}
+
+impl<'env> TraceStorageWriter for StorageTxn<'env, RW> {
+    fn append_traces(
+        self,
+        block_number: BlockNumber,
+        traces: Vec<TransactionTrace>,
+    ) -> StorageResult<Self> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        let transaction_traces_table = self.txn.open_table(&self.tables.transaction_traces)?;
+
+        // Make sure the marker is consistent and the transactions of the block are stored.
+        let trace_marker = self.get_trace_marker()?;
+        if trace_marker != block_number {
+            return Err(StorageError::MarkerMismatch {
+                expected: trace_marker,
+                found: block_number,
+            });
+        }
+        let n_transactions = self
+            .get_block_transactions(block_number)?
+            .ok_or(StorageError::TracesWithoutBody { block_number })?
+            .len();
+        if traces.len() != n_transactions {
+            return Err(StorageError::TracesMismatchTransactions {
+                block_number,
+                n_traces: traces.len(),
+                n_transactions,
+            });
+        }
+
+        for (offset, trace) in traces.iter().enumerate() {
+            let transaction_index =
+                TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+            transaction_traces_table.insert(&self.txn, &transaction_index, trace)?;
+        }
+        markers_table.upsert(&self.txn, &MarkerKind::Trace, &block_number.next())?;
+        Ok(self)
+    }
+}
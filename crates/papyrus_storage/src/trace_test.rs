@@ -0,0 +1,84 @@
+use assert_matches::assert_matches;
+use starknet_api::block::BlockNumber;
+use starknet_api::transaction::TransactionOffsetInBlock;
+use test_utils::{get_rng, get_test_body, GetTestInstance};
+
+use crate::body::{BodyStorageWriter, TransactionIndex};
+use crate::test_utils::get_test_storage;
+use crate::trace::{TraceStorageReader, TraceStorageWriter, TransactionTrace};
+use crate::StorageError;
+
+fn get_test_traces(n_traces: usize) -> Vec<TransactionTrace> {
+    let mut rng = get_rng(None);
+    (0..n_traces).map(|_| TransactionTrace::get_test_instance(&mut rng)).collect()
+}
+
+#[test]
+fn append_traces() {
+    let (reader, mut writer) = get_test_storage();
+    let traces = get_test_traces(2);
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_body(BlockNumber(0), get_test_body(Some(0), 2, None, None, None))
+        .unwrap()
+        .append_traces(BlockNumber(0), traces.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_trace_marker().unwrap(), BlockNumber(1));
+    assert_eq!(txn.get_block_traces(BlockNumber(0)).unwrap(), Some(traces.clone()));
+    assert_eq!(txn.get_block_traces(BlockNumber(1)).unwrap(), None);
+    let transaction_index = TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(1));
+    assert_eq!(txn.get_transaction_trace(transaction_index).unwrap(), Some(traces[1].clone()));
+}
+
+#[test]
+fn append_traces_errors() {
+    let (_, mut writer) = get_test_storage();
+
+    let res = writer.begin_rw_txn().unwrap().append_traces(BlockNumber(0), get_test_traces(2));
+    assert_matches!(res, Err(StorageError::TracesWithoutBody { block_number: BlockNumber(0) }));
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_body(BlockNumber(0), get_test_body(Some(0), 2, None, None, None))
+        .unwrap()
+        .commit()
+        .unwrap();
+    let res = writer.begin_rw_txn().unwrap().append_traces(BlockNumber(0), get_test_traces(1));
+    assert_matches!(
+        res,
+        Err(StorageError::TracesMismatchTransactions { n_traces: 1, n_transactions: 2, .. })
+    );
+    let res = writer.begin_rw_txn().unwrap().append_traces(BlockNumber(1), get_test_traces(2));
+    assert_matches!(
+        res,
+        Err(StorageError::MarkerMismatch { expected: BlockNumber(0), found: BlockNumber(1) })
+    );
+}
+
+#[test]
+fn revert_body_reverts_traces() {
+    let (reader, mut writer) = get_test_storage();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_body(BlockNumber(0), get_test_body(Some(0), 2, None, None, None))
+        .unwrap()
+        .append_traces(BlockNumber(0), get_test_traces(2))
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let (txn, _) = writer.begin_rw_txn().unwrap().revert_body(BlockNumber(0)).unwrap();
+    txn.commit().unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_trace_marker().unwrap(), BlockNumber(0));
+    let transaction_index = TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0));
+    assert_eq!(txn.get_transaction_trace(transaction_index).unwrap(), None);
+}
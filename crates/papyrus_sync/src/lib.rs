@@ -5,34 +5,91 @@ mod sync_test;
 mod sources;
 
 use std::cmp::min;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_stream::try_stream;
-use futures_util::{pin_mut, select, Stream, StreamExt};
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use futures_util::{pin_mut, select, FutureExt, Stream, StreamExt};
 use indexmap::IndexMap;
-use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::body::{BlockExecutionInfo, BodyStorageReader, BodyStorageWriter};
+use papyrus_storage::checkpoint::{CheckpointStorageReader, CheckpointStorageWriter};
+use papyrus_storage::compiled_class::{CasmStorageReader, CasmStorageWriter};
+use papyrus_storage::db::TransactionKind;
 use papyrus_storage::header::{HeaderStorageReader, HeaderStorageWriter};
-use papyrus_storage::ommer::{OmmerStorageReader, OmmerStorageWriter};
+use papyrus_storage::ommer::OmmerStorageReader;
+use papyrus_storage::revert::RevertStorageWriter;
 use papyrus_storage::state::{StateStorageReader, StateStorageWriter};
-use papyrus_storage::{StorageError, StorageReader, StorageWriter};
+use papyrus_storage::trace::{TraceStorageReader, TraceStorageWriter, TransactionTrace};
+use papyrus_storage::{StorageError, StorageReader, StorageResult, StorageTxn, StorageWriter};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use starknet_api::block::{Block, BlockHash, BlockNumber};
+use starknet_api::block::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber};
 use starknet_api::core::ClassHash;
 use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
-use starknet_api::state::StateDiff;
+use starknet_api::state::{ContractClass, StateDiff, ThinStateDiff};
+use starknet_api::transaction::TransactionHash;
 use starknet_client::ClientError;
+use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, error, info, instrument, trace, warn};
 
-pub use self::sources::{CentralError, CentralSource, CentralSourceConfig, CentralSourceTrait};
+pub use self::sources::{
+    AdaptiveConcurrencyConfig, CentralError, CentralSource, CentralSourceConfig,
+    CentralSourceTrait, FileSource, FileSourceError, GenericCentralSource, PeerSource,
+    PeerSourceConfig,
+};
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SyncConfig {
     pub block_propagation_sleep_duration: Duration,
     pub recoverable_error_sleep_duration: Duration,
     pub blocks_max_stream_size: u32,
     pub state_updates_max_stream_size: u32,
+    /// If set, the sync stops once the given block and its state diff are stored.
+    pub stop_at: Option<StopAt>,
+    /// If set (and `stop_at` isn't), the sync stops once it reaches the last block the central had
+    /// when the sync started.
+    pub one_shot: bool,
+    /// If set, an empty storage starts at the checkpoint instead of the genesis.
+    pub checkpoint: Option<CheckpointConfig>,
+    /// If set, the state diffs are stored as soon as they are downloaded and the definitions of
+    /// their classes are downloaded and stored separately, so slow class downloads don't hold back
+    /// the state. Only the central source supports it.
+    pub separate_class_sync: bool,
+    /// If set, the headers are synced ahead of the bodies, which are downloaded behind them, so
+    /// the state diffs don't wait for the bodies. Only sources that serve the headers without the
+    /// bodies, such as a peer node, support it.
+    pub header_first_sync: bool,
+    /// If set, the execution traces of the transactions are downloaded and stored behind the
+    /// bodies. Only the central source supports it.
+    pub trace_sync: bool,
+    /// If set, the compiled classes (CASM) of the declared Cairo 1 classes are downloaded and
+    /// stored behind the state diffs, so they are executed as the central compiled them. Only the
+    /// central source supports it.
+    pub compiled_class_sync: bool,
+}
+
+/// A trusted block to start syncing from. The blocks before it aren't synced.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    pub block_number: BlockNumber,
+    pub block_hash: BlockHash,
+    /// A JSON file with the state right before the checkpoint, as a state diff from the genesis.
+    /// Without it, the state of the contracts that weren't changed since the checkpoint is
+    /// missing.
+    pub state_snapshot: Option<PathBuf>,
+}
+
+/// A block to stop the sync at (inclusive).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopAt {
+    BlockNumber(BlockNumber),
+    BlockHash(BlockHash),
 }
 
 // Orchestrates specific network interfaces (e.g. central, p2p, l1) and writes to Storage.
@@ -41,6 +98,126 @@ pub struct GenericStateSync<TCentralSource: CentralSourceTrait + Sync + Send> {
     central_source: Arc<TCentralSource>,
     reader: StorageReader,
     writer: StorageWriter,
+    subscribers: Vec<Arc<dyn StateSyncSubscriber>>,
+    control: SyncControl,
+    commands: mpsc::UnboundedReceiver<SyncCommand>,
+}
+
+/// A handle for controlling a running sync from other tasks, such as the admin server of the node.
+/// Clones control the same sync.
+#[derive(Clone)]
+pub struct SyncControl {
+    paused: Arc<AtomicBool>,
+    commands: mpsc::UnboundedSender<SyncCommand>,
+}
+
+// The commands of the control handle, applied by the sync between its writes to the storage.
+enum SyncCommand {
+    // Wakes the sync up to check whether it is paused.
+    CheckPaused,
+    Revert { block_number: BlockNumber, reply: oneshot::Sender<Result<(), SyncControlError>> },
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum SyncControlError {
+    #[error("The sync isn't running.")]
+    NotRunning,
+    #[error(
+        "Can't revert block {block_number}, the blocks up to the checkpoint {checkpoint} are \
+         trusted."
+    )]
+    RevertBeforeCheckpoint { block_number: BlockNumber, checkpoint: BlockNumber },
+    #[error("Failed to revert the blocks: {0}")]
+    RevertFailed(String),
+}
+
+impl SyncControl {
+    /// Stops the sync from downloading and storing blocks until it is resumed. Reverts are still
+    /// applied while the sync is paused.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+        let _ = self.commands.send(SyncCommand::CheckPaused);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+        let _ = self.commands.send(SyncCommand::CheckPaused);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Reverts the blocks from the last one down to `block_number` (inclusive), moving them to the
+    /// ommer tables. Unless the sync is paused, it then syncs them again from its source.
+    pub async fn revert(&self, block_number: BlockNumber) -> Result<(), SyncControlError> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(SyncCommand::Revert { block_number, reply })
+            .map_err(|_| SyncControlError::NotRunning)?;
+        response.await.map_err(|_| SyncControlError::NotRunning)?
+    }
+}
+
+/// Notified by the sync after each commit to the storage, in the order of the commits, so it stays
+/// in step with the storage (reverts included). The notifications run on the sync's task and delay
+/// it, so they should return quickly.
+pub trait StateSyncSubscriber: Send + Sync {
+    /// Called after the header and the body of a block were stored.
+    fn on_block_stored(&self, _block_number: BlockNumber, _block: &Block) {}
+
+    /// Called after the header of a block was stored without its body, when the headers are synced
+    /// first.
+    fn on_header_stored(&self, _block_number: BlockNumber, _header: &BlockHeader) {}
+
+    /// Called after the body of a block whose header was stored earlier was stored, when the
+    /// headers are synced first.
+    fn on_body_stored(
+        &self,
+        _block_number: BlockNumber,
+        _block_hash: BlockHash,
+        _body: &BlockBody,
+    ) {
+    }
+
+    /// Called after the state diff of a block was stored.
+    fn on_state_diff_stored(
+        &self,
+        _block_number: BlockNumber,
+        _block_hash: BlockHash,
+        _state_diff: &StateDiff,
+        _deployed_contract_class_definitions: &IndexMap<ClassHash, DeprecatedContractClass>,
+    ) {
+    }
+
+    /// Called after the state diff of a block was stored without the definitions of its classes,
+    /// when the classes are synced separately.
+    fn on_thin_state_diff_stored(
+        &self,
+        _block_number: BlockNumber,
+        _block_hash: BlockHash,
+        _thin_state_diff: &ThinStateDiff,
+    ) {
+    }
+
+    /// Called after the definitions of the classes of a block were stored, when the classes are
+    /// synced separately. `deprecated_classes` also contains the classes of the contracts that were
+    /// deployed in the block without being declared.
+    fn on_classes_stored(
+        &self,
+        _block_number: BlockNumber,
+        _classes: &IndexMap<ClassHash, ContractClass>,
+        _deprecated_classes: &IndexMap<ClassHash, DeprecatedContractClass>,
+    ) {
+    }
+
+    /// Called after the execution traces of the transactions of a block were stored, when the
+    /// traces are synced.
+    fn on_traces_stored(&self, _block_number: BlockNumber, _traces: &[TransactionTrace]) {}
+
+    /// Called after a block was reverted, i.e., its data was removed from the storage. Blocks are
+    /// reverted from the last one down.
+    fn on_block_reverted(&self, _block_number: BlockNumber, _block_hash: BlockHash) {}
 }
 
 pub type StateSyncResult = Result<(), StateSyncError>;
@@ -65,6 +242,36 @@ pub enum StateSyncError {
          matching header (neither in the ommer headers)."
     )]
     StateDiffWithoutMatchingHeader { block_number: BlockNumber, block_hash: BlockHash },
+    #[error(
+        "Block hash of the checkpoint block {block_number} is {block_hash}, expected \
+         {expected_block_hash}."
+    )]
+    CheckpointBlockHashMismatch {
+        block_number: BlockNumber,
+        expected_block_hash: BlockHash,
+        block_hash: BlockHash,
+    },
+    #[error("Failed to read the state snapshot {path:?}: {message}.")]
+    BadStateSnapshot { path: PathBuf, message: String },
+    #[error(
+        "Received traces of block {block_number} with block hash {block_hash} that don't match \
+         the transactions of the stored body."
+    )]
+    TracesMismatch { block_number: BlockNumber, block_hash: BlockHash },
+}
+
+// Why the sync loop returned without an error.
+enum SyncLoopExit {
+    ReachedStopMarker,
+    // By a command of the control handle. The loop is started again, since its streams may be
+    // ahead of the storage after a revert.
+    Interrupted,
+}
+
+// The input of an iteration of the sync loop.
+enum SyncInput {
+    Event(SyncEvent),
+    Command(SyncCommand),
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -72,6 +279,17 @@ pub enum SyncEvent {
     BlockAvailable {
         block_number: BlockNumber,
         block: Block,
+        execution_info: BlockExecutionInfo,
+    },
+    HeaderAvailable {
+        block_number: BlockNumber,
+        header: BlockHeader,
+    },
+    BodyAvailable {
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        body: BlockBody,
+        execution_info: BlockExecutionInfo,
     },
     StateDiffAvailable {
         block_number: BlockNumber,
@@ -83,12 +301,38 @@ pub enum SyncEvent {
         // Note: Since 0.11 new classes can not be implicitly declared.
         deployed_contract_class_definitions: IndexMap<ClassHash, DeprecatedContractClass>,
     },
+    ThinStateDiffAvailable {
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        thin_state_diff: ThinStateDiff,
+    },
+    ClassesAvailable {
+        block_number: BlockNumber,
+        classes: IndexMap<ClassHash, ContractClass>,
+        deprecated_classes: IndexMap<ClassHash, DeprecatedContractClass>,
+    },
+    TracesAvailable {
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        transaction_hashes: Vec<TransactionHash>,
+        traces: Vec<TransactionTrace>,
+    },
+    CompiledClassesAvailable {
+        block_number: BlockNumber,
+        casms: IndexMap<ClassHash, CasmContractClass>,
+    },
 }
 
 impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSync<TCentralSource> {
+    /// Runs the sync until it reaches the block it should stop at, if any, or until encountering an
+    /// unrecoverable error.
     pub async fn run(&mut self) -> StateSyncResult {
         info!("State sync started.");
+        self.set_checkpoint_if_needed()?;
+        let start_time = Instant::now();
+        let initial_state_marker = self.reader.begin_ro_txn()?.get_state_marker()?;
         loop {
+            self.wait_while_paused().await;
             match self.sync_while_ok().await {
                 Err(StateSyncError::ParentBlockHashMismatch {
                     block_number,
@@ -114,8 +358,27 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                     error!("{}", err);
                     return Err(err);
                 }
-                Ok(_) => {
-                    unreachable!("Sync should either return with an error or continue forever.")
+                Ok(SyncLoopExit::Interrupted) => continue,
+                // Reached the block to stop at.
+                Ok(SyncLoopExit::ReachedStopMarker) => {
+                    let state_marker = self.reader.begin_ro_txn()?.get_state_marker()?;
+                    let last_block_hash = match state_marker.prev() {
+                        Some(block_number) => self
+                            .reader
+                            .begin_ro_txn()?
+                            .get_block_header(block_number)?
+                            .map(|header| header.block_hash),
+                        None => None,
+                    };
+                    info!(
+                        "State sync stopped. Synced {} blocks in {:?}, the storage is at block \
+                         marker {} (last block hash: {:?}).",
+                        state_marker.0.saturating_sub(initial_state_marker.0),
+                        start_time.elapsed(),
+                        state_marker,
+                        last_block_hash,
+                    );
+                    return Ok(());
                 }
             }
         }
@@ -124,6 +387,22 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         fn is_recoverable(err: &StateSyncError) -> bool {
             match err {
                 StateSyncError::CentralSourceError(central_err) => {
+                    // Retrying won't change the content of the files.
+                    if let CentralError::FileSourceError(_) = central_err {
+                        return false;
+                    }
+                    if let CentralError::SeparateClassSyncNotSupported = central_err {
+                        return false;
+                    }
+                    if let CentralError::SeparateHeaderSyncNotSupported = central_err {
+                        return false;
+                    }
+                    if let CentralError::TraceSyncNotSupported = central_err {
+                        return false;
+                    }
+                    if let CentralError::CompiledClassSyncNotSupported = central_err {
+                        return false;
+                    }
                     if let CentralError::ClientError(client_err) = central_err {
                         match **client_err {
                             // In case of non existing url this error will occur.
@@ -155,52 +434,226 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                     block_number: _,
                     block_hash: _,
                 } => true,
+                // The central may have reverted the block after its body was stored. The traces
+                // are downloaded again, after the revert is detected if there was one.
+                StateSyncError::TracesMismatch { block_number: _, block_hash: _ } => true,
                 _ => false,
             }
         }
     }
 
-    // Sync until encountering an error:
+    // Starts an empty storage at the configured checkpoint.
+    fn set_checkpoint_if_needed(&mut self) -> StateSyncResult {
+        let Some(checkpoint) = &self.config.checkpoint else {
+            return Ok(());
+        };
+        let txn = self.reader.begin_ro_txn()?;
+        let stored_checkpoint = txn.get_checkpoint()?;
+        let header_marker = txn.get_header_marker()?;
+        drop(txn);
+        if stored_checkpoint.is_some() || header_marker > BlockNumber(0) {
+            if stored_checkpoint != Some(checkpoint.block_number) {
+                warn!(
+                    "Ignoring the checkpoint at block {}, the storage isn't empty.",
+                    checkpoint.block_number
+                );
+            }
+            return Ok(());
+        }
+
+        let state_snapshot =
+            checkpoint.state_snapshot.as_deref().map(read_state_snapshot).transpose()?;
+        info!(
+            "Starting the sync from the checkpoint at block {} with hash {}.",
+            checkpoint.block_number, checkpoint.block_hash
+        );
+        self.writer
+            .begin_rw_txn()?
+            .set_checkpoint(checkpoint.block_number, state_snapshot)?
+            .commit()?;
+        Ok(())
+    }
+
+    // Sync until encountering an error or reaching the block to stop at:
     //  1. If needed, revert blocks from the end of the chain.
     //  2. Create infinite block and state diff streams to fetch data from the central source.
     //  3. Fetch data from the streams with unblocking wait while there is no new data.
-    async fn sync_while_ok(&mut self) -> StateSyncResult {
+    async fn sync_while_ok(&mut self) -> Result<SyncLoopExit, StateSyncError> {
+        if self.config.one_shot && self.config.stop_at.is_none() {
+            let central_block_marker = self.central_source.get_block_marker().await?;
+            let Some(last_block_number) = central_block_marker.prev() else {
+                info!("The central has no blocks, nothing to sync.");
+                return Ok(SyncLoopExit::ReachedStopMarker);
+            };
+            info!("Syncing up to block {last_block_number}.");
+            self.config.stop_at = Some(StopAt::BlockNumber(last_block_number));
+        }
         self.handle_block_reverts().await?;
-        let block_stream = stream_new_blocks(
-            self.reader.clone(),
-            self.central_source.clone(),
-            self.config.block_propagation_sleep_duration,
-            self.config.blocks_max_stream_size,
-        )
-        .fuse();
-        let state_diff_stream = stream_new_state_diffs(
-            self.reader.clone(),
-            self.central_source.clone(),
-            self.config.block_propagation_sleep_duration,
-            self.config.state_updates_max_stream_size,
-        )
-        .fuse();
-        pin_mut!(block_stream, state_diff_stream);
+        if self.reached_stop_marker()? {
+            return Ok(SyncLoopExit::ReachedStopMarker);
+        }
+        let (block_stream, body_stream) = if self.config.header_first_sync {
+            (
+                stream_new_headers(
+                    self.reader.clone(),
+                    self.central_source.clone(),
+                    self.config.block_propagation_sleep_duration,
+                    self.config.blocks_max_stream_size,
+                    self.config.stop_at,
+                )
+                .boxed(),
+                stream_new_bodies(
+                    self.reader.clone(),
+                    self.central_source.clone(),
+                    self.config.block_propagation_sleep_duration,
+                    self.config.blocks_max_stream_size,
+                )
+                .boxed(),
+            )
+        } else {
+            (
+                stream_new_blocks(
+                    self.reader.clone(),
+                    self.central_source.clone(),
+                    self.config.block_propagation_sleep_duration,
+                    self.config.blocks_max_stream_size,
+                    self.config.stop_at,
+                )
+                .boxed(),
+                futures_util::stream::pending().boxed(),
+            )
+        };
+        let block_stream = block_stream.fuse();
+        let body_stream = body_stream.fuse();
+        let (state_diff_stream, class_stream) = if self.config.separate_class_sync {
+            (
+                stream_new_thin_state_diffs(
+                    self.reader.clone(),
+                    self.central_source.clone(),
+                    self.config.block_propagation_sleep_duration,
+                    self.config.state_updates_max_stream_size,
+                )
+                .boxed(),
+                stream_new_classes(
+                    self.reader.clone(),
+                    self.central_source.clone(),
+                    self.config.block_propagation_sleep_duration,
+                    self.config.state_updates_max_stream_size,
+                )
+                .boxed(),
+            )
+        } else {
+            (
+                stream_new_state_diffs(
+                    self.reader.clone(),
+                    self.central_source.clone(),
+                    self.config.block_propagation_sleep_duration,
+                    self.config.state_updates_max_stream_size,
+                )
+                .boxed(),
+                futures_util::stream::pending().boxed(),
+            )
+        };
+        let state_diff_stream = state_diff_stream.fuse();
+        let class_stream = class_stream.fuse();
+        let trace_stream = if self.config.trace_sync {
+            stream_new_traces(
+                self.reader.clone(),
+                self.central_source.clone(),
+                self.config.block_propagation_sleep_duration,
+                self.config.blocks_max_stream_size,
+            )
+            .boxed()
+        } else {
+            futures_util::stream::pending().boxed()
+        };
+        let trace_stream = trace_stream.fuse();
+        let compiled_class_stream = if self.config.compiled_class_sync {
+            stream_new_compiled_classes(
+                self.reader.clone(),
+                self.central_source.clone(),
+                self.config.block_propagation_sleep_duration,
+                self.config.state_updates_max_stream_size,
+            )
+            .boxed()
+        } else {
+            futures_util::stream::pending().boxed()
+        };
+        let compiled_class_stream = compiled_class_stream.fuse();
+        pin_mut!(
+            block_stream,
+            body_stream,
+            state_diff_stream,
+            class_stream,
+            trace_stream,
+            compiled_class_stream
+        );
 
         loop {
-            debug!("Selecting between block sync and state diff sync.");
-            let sync_event = select! {
-              res = block_stream.next() => res,
-              res = state_diff_stream.next() => res,
+            debug!(
+                "Selecting between block sync, body sync, state diff sync, class sync, trace sync \
+                 and compiled class sync."
+            );
+            let sync_input = select! {
+              res = block_stream.next() => res.map(|res| res.map(SyncInput::Event)),
+              res = body_stream.next() => res.map(|res| res.map(SyncInput::Event)),
+              res = state_diff_stream.next() => res.map(|res| res.map(SyncInput::Event)),
+              res = class_stream.next() => res.map(|res| res.map(SyncInput::Event)),
+              res = trace_stream.next() => res.map(|res| res.map(SyncInput::Event)),
+              res = compiled_class_stream.next() => res.map(|res| res.map(SyncInput::Event)),
+              command = self.commands.recv().fuse() => {
+                  command.map(|command| Ok(SyncInput::Command(command)))
+              }
               complete => break,
             }
             .expect("Received None as a sync event.")?;
+            let sync_event = match sync_input {
+                SyncInput::Event(sync_event) => sync_event,
+                SyncInput::Command(command) => {
+                    self.apply_command(command);
+                    return Ok(SyncLoopExit::Interrupted);
+                }
+            };
             self.process_sync_event(sync_event).await?;
             debug!("Finished processing sync event.");
+            if self.reached_stop_marker()? {
+                return Ok(SyncLoopExit::ReachedStopMarker);
+            }
         }
         unreachable!("Fetching data loop should never return.");
     }
 
+    // Returns whether the blocks and the state diffs are synced up to the block to stop at.
+    fn reached_stop_marker(&self) -> Result<bool, StateSyncError> {
+        let txn = self.reader.begin_ro_txn()?;
+        let Some(stop_marker) = get_stop_marker(&txn, self.config.stop_at)? else {
+            return Ok(false);
+        };
+        if self.config.separate_class_sync && txn.get_class_marker()? < stop_marker {
+            return Ok(false);
+        }
+        if self.config.trace_sync && txn.get_trace_marker()? < stop_marker {
+            return Ok(false);
+        }
+        if self.config.compiled_class_sync && txn.get_compiled_class_marker()? < stop_marker {
+            return Ok(false);
+        }
+        Ok(txn.get_header_marker()? >= stop_marker
+            && txn.get_body_marker()? >= stop_marker
+            && txn.get_state_marker()? >= stop_marker)
+    }
+
     // Tries to store the incoming data.
     async fn process_sync_event(&mut self, sync_event: SyncEvent) -> StateSyncResult {
         match sync_event {
-            SyncEvent::BlockAvailable { block_number, block } => {
-                self.store_block(block_number, block)
+            SyncEvent::BlockAvailable { block_number, block, execution_info } => {
+                self.store_block(block_number, block, execution_info)
+            }
+            SyncEvent::HeaderAvailable { block_number, header } => {
+                self.store_header(block_number, header)
+            }
+            SyncEvent::BodyAvailable { block_number, block_hash, body, execution_info } => {
+                self.store_body(block_number, block_hash, body, execution_info)
             }
             SyncEvent::StateDiffAvailable {
                 block_number,
@@ -213,22 +666,92 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                 state_diff,
                 deployed_contract_class_definitions,
             ),
+            SyncEvent::ThinStateDiffAvailable { block_number, block_hash, thin_state_diff } => {
+                self.store_thin_state_diff(block_number, block_hash, thin_state_diff)
+            }
+            SyncEvent::ClassesAvailable { block_number, classes, deprecated_classes } => {
+                self.store_classes(block_number, classes, deprecated_classes)
+            }
+            SyncEvent::TracesAvailable { block_number, block_hash, transaction_hashes, traces } => {
+                self.store_traces(block_number, block_hash, transaction_hashes, traces)
+            }
+            SyncEvent::CompiledClassesAvailable { block_number, casms } => {
+                self.store_compiled_classes(block_number, casms)
+            }
         }
     }
 
-    #[instrument(skip(self, block), level = "debug", fields(block_hash = %block.header.block_hash), err)]
-    fn store_block(&mut self, block_number: BlockNumber, block: Block) -> StateSyncResult {
+    #[instrument(skip(self, block, execution_info), level = "debug", fields(block_hash = %block.header.block_hash), err)]
+    fn store_block(
+        &mut self,
+        block_number: BlockNumber,
+        block: Block,
+        execution_info: BlockExecutionInfo,
+    ) -> StateSyncResult {
         // Assuming the central source is trusted, detect reverts by comparing the incoming block's
         // parent hash to the current hash.
-        self.verify_parent_block_hash(block_number, &block)?;
+        self.verify_parent_block_hash(block_number, &block.header)?;
 
         debug!("Storing block.");
         trace!("Block data: {block:#?}");
+        // The data is cloned only if someone is notified about it.
+        let notified_block = (!self.subscribers.is_empty()).then(|| block.clone());
         self.writer
             .begin_rw_txn()?
             .append_header(block_number, &block.header)?
-            .append_body(block_number, block.body)?
+            .append_body_with_execution_info(block_number, block.body, execution_info)?
+            .commit()?;
+        if let Some(block) = notified_block {
+            for subscriber in &self.subscribers {
+                subscriber.on_block_stored(block_number, &block);
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, header), level = "debug", fields(block_hash = %header.block_hash), err)]
+    fn store_header(&mut self, block_number: BlockNumber, header: BlockHeader) -> StateSyncResult {
+        self.verify_parent_block_hash(block_number, &header)?;
+
+        debug!("Storing header.");
+        trace!("Header data: {header:#?}");
+        self.writer.begin_rw_txn()?.append_header(block_number, &header)?.commit()?;
+        for subscriber in &self.subscribers {
+            subscriber.on_header_stored(block_number, &header);
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, body, execution_info), level = "debug", err)]
+    fn store_body(
+        &mut self,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        body: BlockBody,
+        execution_info: BlockExecutionInfo,
+    ) -> StateSyncResult {
+        // The body was downloaded before its block (or the blocks before it) got reverted.
+        let txn = self.reader.begin_ro_txn()?;
+        let stored_block_hash = txn.get_block_header(block_number)?.map(|header| header.block_hash);
+        if txn.get_body_marker()? != block_number || stored_block_hash != Some(block_hash) {
+            debug!("Ignoring the body of a reverted block.");
+            return Ok(());
+        }
+        drop(txn);
+
+        debug!("Storing body.");
+        trace!("Body data: {body:#?}");
+        // The data is cloned only if someone is notified about it.
+        let notified_body = (!self.subscribers.is_empty()).then(|| body.clone());
+        self.writer
+            .begin_rw_txn()?
+            .append_body_with_execution_info(block_number, body, execution_info)?
             .commit()?;
+        if let Some(body) = notified_body {
+            for subscriber in &self.subscribers {
+                subscriber.on_body_stored(block_number, block_hash, &body);
+            }
+        }
         Ok(())
     }
 
@@ -243,10 +766,23 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         if !self.is_reverted_state_diff(block_number, block_hash)? {
             debug!("Storing state diff.");
             trace!("StateDiff data: {state_diff:#?}");
+            // The data is cloned only if someone is notified about it.
+            let notified_data = (!self.subscribers.is_empty())
+                .then(|| (state_diff.clone(), deployed_contract_class_definitions.clone()));
             self.writer
                 .begin_rw_txn()?
                 .append_state_diff(block_number, state_diff, deployed_contract_class_definitions)?
                 .commit()?;
+            if let Some((state_diff, deployed_contract_class_definitions)) = notified_data {
+                for subscriber in &self.subscribers {
+                    subscriber.on_state_diff_stored(
+                        block_number,
+                        block_hash,
+                        &state_diff,
+                        &deployed_contract_class_definitions,
+                    );
+                }
+            }
 
             // Info the user on syncing the block once all the data is stored.
             info!("Added block {} with hash {}.", block_number, block_hash);
@@ -256,12 +792,151 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         Ok(())
     }
 
+    #[instrument(skip(self, thin_state_diff), level = "debug", err)]
+    fn store_thin_state_diff(
+        &mut self,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        thin_state_diff: ThinStateDiff,
+    ) -> StateSyncResult {
+        // The block was reverted after its state diff was requested. The state diff of the block
+        // that replaced it is downloaded once the stream restarts from the state marker.
+        if self.is_reverted_state_diff(block_number, block_hash)? {
+            debug!("Dropping the thin state diff of a reverted block.");
+            return Ok(());
+        }
+        debug!("Storing thin state diff.");
+        trace!("ThinStateDiff data: {thin_state_diff:#?}");
+        // The data is cloned only if someone is notified about it.
+        let notified_thin_state_diff =
+            (!self.subscribers.is_empty()).then(|| thin_state_diff.clone());
+        self.writer
+            .begin_rw_txn()?
+            .append_thin_state_diff(block_number, thin_state_diff)?
+            .commit()?;
+        if let Some(thin_state_diff) = notified_thin_state_diff {
+            for subscriber in &self.subscribers {
+                subscriber.on_thin_state_diff_stored(block_number, block_hash, &thin_state_diff);
+            }
+        }
+        info!("Added block {} with hash {}.", block_number, block_hash);
+        Ok(())
+    }
+
+    #[instrument(skip(self, classes, deprecated_classes), level = "debug", err)]
+    fn store_classes(
+        &mut self,
+        block_number: BlockNumber,
+        classes: IndexMap<ClassHash, ContractClass>,
+        deprecated_classes: IndexMap<ClassHash, DeprecatedContractClass>,
+    ) -> StateSyncResult {
+        // The classes were downloaded for a state diff that may have been reverted since. They are
+        // downloaded again once the class stream restarts from the class marker.
+        let txn = self.reader.begin_ro_txn()?;
+        let is_outdated = txn.get_class_marker()? != block_number
+            || txn.get_state_diff(block_number)?.map_or(true, |thin_state_diff| {
+                !thin_state_diff.declared_classes.keys().eq(classes.keys())
+                    || !thin_state_diff
+                        .deprecated_declared_classes
+                        .iter()
+                        .all(|class_hash| deprecated_classes.contains_key(class_hash))
+            });
+        drop(txn);
+        if is_outdated {
+            debug!("Ignoring outdated classes.");
+            return Ok(());
+        }
+
+        debug!("Storing classes.");
+        // The data is cloned only if someone is notified about it.
+        let notified_classes =
+            (!self.subscribers.is_empty()).then(|| (classes.clone(), deprecated_classes.clone()));
+        self.writer
+            .begin_rw_txn()?
+            .append_classes(block_number, classes, deprecated_classes)?
+            .commit()?;
+        if let Some((classes, deprecated_classes)) = notified_classes {
+            for subscriber in &self.subscribers {
+                subscriber.on_classes_stored(block_number, &classes, &deprecated_classes);
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, transaction_hashes, traces), level = "debug", err)]
+    fn store_traces(
+        &mut self,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        transaction_hashes: Vec<TransactionHash>,
+        traces: Vec<TransactionTrace>,
+    ) -> StateSyncResult {
+        // The traces were downloaded for a body that may have been reverted since. They are
+        // downloaded again once the trace stream restarts from the trace marker.
+        let txn = self.reader.begin_ro_txn()?;
+        let stored_block_hash = txn.get_block_header(block_number)?.map(|header| header.block_hash);
+        if txn.get_trace_marker()? != block_number || stored_block_hash != Some(block_hash) {
+            debug!("Ignoring the traces of a reverted block.");
+            return Ok(());
+        }
+        let matches_body =
+            txn.get_block_transactions(block_number)?.map_or(false, |transactions| {
+                transactions
+                    .iter()
+                    .map(|transaction| transaction.transaction_hash())
+                    .eq(transaction_hashes.into_iter())
+            });
+        drop(txn);
+        if !matches_body {
+            return Err(StateSyncError::TracesMismatch { block_number, block_hash });
+        }
+
+        debug!("Storing traces.");
+        // The data is cloned only if someone is notified about it.
+        let notified_traces = (!self.subscribers.is_empty()).then(|| traces.clone());
+        self.writer.begin_rw_txn()?.append_traces(block_number, traces)?.commit()?;
+        if let Some(traces) = notified_traces {
+            for subscriber in &self.subscribers {
+                subscriber.on_traces_stored(block_number, &traces);
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, casms), level = "debug", err)]
+    fn store_compiled_classes(
+        &mut self,
+        block_number: BlockNumber,
+        casms: IndexMap<ClassHash, CasmContractClass>,
+    ) -> StateSyncResult {
+        // The compiled classes were downloaded for a state diff that may have been reverted since.
+        // They are downloaded again once the compiled class stream restarts from its marker.
+        let txn = self.reader.begin_ro_txn()?;
+        let is_outdated = txn.get_compiled_class_marker()? != block_number
+            || txn.get_state_diff(block_number)?.map_or(true, |thin_state_diff| {
+                !thin_state_diff.declared_classes.keys().eq(casms.keys())
+            });
+        drop(txn);
+        if is_outdated {
+            debug!("Ignoring outdated compiled classes.");
+            return Ok(());
+        }
+
+        debug!("Storing compiled classes.");
+        self.writer.begin_rw_txn()?.append_casms(block_number, casms)?.commit()?;
+        Ok(())
+    }
+
     // Compares the block's parent hash to the stored block.
     fn verify_parent_block_hash(
         &self,
         block_number: BlockNumber,
-        block: &Block,
+        header: &BlockHeader,
     ) -> StateSyncResult {
+        // The parent of the checkpoint block isn't stored, the block itself is verified instead.
+        if self.reader.begin_ro_txn()?.get_checkpoint()? == Some(block_number) {
+            return self.verify_checkpoint_block_hash(block_number, header);
+        }
         let prev_block_number = match block_number.prev() {
             None => return Ok(()),
             Some(bn) => bn,
@@ -278,10 +953,10 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
             })?
             .block_hash;
 
-        if prev_hash != block.header.parent_hash {
+        if prev_hash != header.parent_hash {
             return Err(StateSyncError::ParentBlockHashMismatch {
                 block_number,
-                expected_parent_block_hash: block.header.parent_hash,
+                expected_parent_block_hash: header.parent_hash,
                 stored_parent_block_hash: prev_hash,
             });
         }
@@ -289,14 +964,90 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         Ok(())
     }
 
+    fn verify_checkpoint_block_hash(
+        &self,
+        block_number: BlockNumber,
+        header: &BlockHeader,
+    ) -> StateSyncResult {
+        // The checkpoint may have been removed from the configuration after the storage started.
+        let Some(checkpoint) = &self.config.checkpoint else {
+            return Ok(());
+        };
+        if checkpoint.block_number == block_number && checkpoint.block_hash != header.block_hash {
+            return Err(StateSyncError::CheckpointBlockHashMismatch {
+                block_number,
+                expected_block_hash: checkpoint.block_hash,
+                block_hash: header.block_hash,
+            });
+        }
+        Ok(())
+    }
+
+    // Applies the commands of the control handle until the sync isn't paused.
+    async fn wait_while_paused(&mut self) {
+        if !self.control.is_paused() {
+            return;
+        }
+        info!("State sync paused.");
+        while self.control.is_paused() {
+            let command =
+                self.commands.recv().await.expect("The sync should keep a sender of its commands.");
+            self.apply_command(command);
+        }
+        info!("State sync resumed.");
+    }
+
+    fn apply_command(&mut self, command: SyncCommand) {
+        match command {
+            // Checked by the caller.
+            SyncCommand::CheckPaused => {}
+            SyncCommand::Revert { block_number, reply } => {
+                let result = self.revert_to(block_number);
+                if let Err(err) = &result {
+                    warn!("{err}");
+                }
+                // The requester may have given up on the reply.
+                let _ = reply.send(result);
+            }
+        }
+    }
+
+    // Reverts the blocks from the last one down to `target_block_number` (inclusive).
+    fn revert_to(&mut self, target_block_number: BlockNumber) -> Result<(), SyncControlError> {
+        let revert_failed = |err: StateSyncError| SyncControlError::RevertFailed(err.to_string());
+        let txn = self.reader.begin_ro_txn().map_err(|err| revert_failed(err.into()))?;
+        let header_marker = txn.get_header_marker().map_err(|err| revert_failed(err.into()))?;
+        let checkpoint = txn.get_checkpoint().map_err(|err| revert_failed(err.into()))?;
+        drop(txn);
+        if let Some(checkpoint) = checkpoint.filter(|checkpoint| target_block_number <= *checkpoint)
+        {
+            return Err(SyncControlError::RevertBeforeCheckpoint {
+                block_number: target_block_number,
+                checkpoint,
+            });
+        }
+
+        info!("Reverting the blocks from {target_block_number} on by request.");
+        for block_number in (target_block_number.0..header_marker.0).rev() {
+            self.revert_block(BlockNumber(block_number)).map_err(revert_failed)?;
+        }
+        Ok(())
+    }
+
     // Reverts data if needed.
     async fn handle_block_reverts(&mut self) -> Result<(), StateSyncError> {
         debug!("Handling block reverts.");
-        let header_marker = self.reader.begin_ro_txn()?.get_header_marker()?;
+        let txn = self.reader.begin_ro_txn()?;
+        let header_marker = txn.get_header_marker()?;
+        let checkpoint = txn.get_checkpoint()?;
+        drop(txn);
 
-        // Revert last blocks if needed.
+        // Revert last blocks if needed. The blocks up to the checkpoint are trusted.
         let mut last_block_in_storage = header_marker.prev();
         while let Some(block_number) = last_block_in_storage {
+            if checkpoint.map_or(false, |checkpoint| block_number <= checkpoint) {
+                break;
+            }
             if self.should_revert_block(block_number).await? {
                 self.revert_block(block_number)?;
                 last_block_in_storage = block_number.prev();
@@ -312,41 +1063,14 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
     #[instrument(skip(self), level = "debug", err)]
     fn revert_block(&mut self, block_number: BlockNumber) -> StateSyncResult {
         debug!("Reverting block.");
-        let mut txn = self.writer.begin_rw_txn()?;
-
-        let res = txn.revert_header(block_number)?;
-        txn = res.0;
-        let mut reverted_block_hash: Option<BlockHash> = None;
-        if let Some(header) = res.1 {
-            reverted_block_hash = Some(header.block_hash);
-            txn = txn.insert_ommer_header(header.block_hash, &header)?;
-
-            let res = txn.revert_body(block_number)?;
-            txn = res.0;
-            if let Some((transactions, transaction_outputs, events)) = res.1 {
-                txn = txn.insert_ommer_body(
-                    header.block_hash,
-                    &transactions,
-                    &transaction_outputs,
-                    events.as_slice(),
-                )?;
-            }
-
-            let res = txn.revert_state_diff(block_number)?;
-            txn = res.0;
-            // TODO(yair): consider inserting to ommer the deprecated_declared_classes.
-            if let Some((thin_state_diff, declared_classes, _deprecated_declared_classes)) = res.1 {
-                txn = txn.insert_ommer_state_diff(
-                    header.block_hash,
-                    &thin_state_diff,
-                    &declared_classes,
-                )?;
-            }
-        }
-
+        let (txn, reverted_block_hash) =
+            self.writer.begin_rw_txn()?.revert_block(block_number, true)?;
         txn.commit()?;
         if let Some(hash) = reverted_block_hash {
             info!(%hash, "Reverted block.");
+            for subscriber in &self.subscribers {
+                subscriber.on_block_reverted(block_number, hash);
+            }
         }
         Ok(())
     }
@@ -391,29 +1115,144 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
     }
 }
 
+fn read_state_snapshot(path: &Path) -> Result<StateDiff, StateSyncError> {
+    let bad_state_snapshot =
+        |message: String| StateSyncError::BadStateSnapshot { path: path.to_path_buf(), message };
+    let file = File::open(path).map_err(|err| bad_state_snapshot(err.to_string()))?;
+    let mut state_diff: StateDiff = serde_json::from_reader(BufReader::new(file))
+        .map_err(|err| bad_state_snapshot(err.to_string()))?;
+    sort_state_diff(&mut state_diff);
+    Ok(state_diff)
+}
+
+// Returns the first block number that shouldn't be synced, or None if it isn't known (yet).
+fn get_stop_marker<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    stop_at: Option<StopAt>,
+) -> StorageResult<Option<BlockNumber>> {
+    match stop_at {
+        Some(StopAt::BlockNumber(block_number)) => Ok(Some(block_number.next())),
+        // The block number is known once the block is stored.
+        Some(StopAt::BlockHash(block_hash)) => {
+            Ok(txn.get_block_number_by_hash(&block_hash)?.map(|block_number| block_number.next()))
+        }
+        None => Ok(None),
+    }
+}
+
 fn stream_new_blocks<TCentralSource: CentralSourceTrait + Sync + Send>(
     reader: StorageReader,
     central_source: Arc<TCentralSource>,
     block_propation_sleep_duration: Duration,
     max_stream_size: u32,
+    stop_at: Option<StopAt>,
 ) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
     try_stream! {
         loop {
-            let header_marker = reader.begin_ro_txn()?.get_header_marker()?;
+            let txn = reader.begin_ro_txn()?;
+            let header_marker = txn.get_header_marker()?;
+            let stop_marker = get_stop_marker(&txn, stop_at)?;
+            drop(txn);
+            if stop_marker.map_or(false, |stop_marker| header_marker >= stop_marker) {
+                debug!("Blocks syncing reached the block to stop at.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
             let last_block_number = central_source.get_block_marker().await?;
             if header_marker == last_block_number {
                 debug!("Blocks syncing reached the last known block, waiting for blockchain to advance.");
                 tokio::time::sleep(block_propation_sleep_duration).await;
                 continue;
             }
-            let up_to = min(last_block_number, BlockNumber(header_marker.0 + max_stream_size as u64));
+            let mut up_to = min(last_block_number, BlockNumber(header_marker.0 + max_stream_size as u64));
+            if let Some(stop_marker) = stop_marker {
+                up_to = min(up_to, stop_marker);
+            }
             debug!("Downloading blocks [{} - {}).", header_marker, up_to);
             let block_stream =
                 central_source.stream_new_blocks(header_marker, up_to).fuse();
             pin_mut!(block_stream);
             while let Some(maybe_block) = block_stream.next().await {
-                let (block_number, block) = maybe_block?;
-                yield SyncEvent::BlockAvailable { block_number, block };
+                let (block_number, block, execution_info) = maybe_block?;
+                let block_hash = block.header.block_hash;
+                yield SyncEvent::BlockAvailable { block_number, block, execution_info };
+                // Once the block to stop at is stored, its number is known.
+                if stop_at == Some(StopAt::BlockHash(block_hash)) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn stream_new_headers<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    block_propation_sleep_duration: Duration,
+    max_stream_size: u32,
+    stop_at: Option<StopAt>,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let header_marker = txn.get_header_marker()?;
+            let stop_marker = get_stop_marker(&txn, stop_at)?;
+            drop(txn);
+            if stop_marker.map_or(false, |stop_marker| header_marker >= stop_marker) {
+                debug!("Headers syncing reached the block to stop at.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let last_block_number = central_source.get_block_marker().await?;
+            if header_marker == last_block_number {
+                debug!("Headers syncing reached the last known block, waiting for blockchain to advance.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let mut up_to = min(last_block_number, BlockNumber(header_marker.0 + max_stream_size as u64));
+            if let Some(stop_marker) = stop_marker {
+                up_to = min(up_to, stop_marker);
+            }
+            debug!("Downloading headers [{} - {}).", header_marker, up_to);
+            let header_stream = central_source.stream_headers(header_marker, up_to).fuse();
+            pin_mut!(header_stream);
+            while let Some(maybe_header) = header_stream.next().await {
+                let (block_number, header) = maybe_header?;
+                let block_hash = header.block_hash;
+                yield SyncEvent::HeaderAvailable { block_number, header };
+                // Once the block to stop at is stored, its number is known.
+                if stop_at == Some(StopAt::BlockHash(block_hash)) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn stream_new_bodies<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    block_propation_sleep_duration: Duration,
+    max_stream_size: u32,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let body_marker = txn.get_body_marker()?;
+            let header_marker = txn.get_header_marker()?;
+            drop(txn);
+            if body_marker >= header_marker {
+                debug!("Bodies syncing reached the last stored header, waiting for more headers.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let up_to = min(header_marker, BlockNumber(body_marker.0 + max_stream_size as u64));
+            debug!("Downloading bodies [{} - {}).", body_marker, up_to);
+            let body_stream = central_source.stream_bodies(body_marker, up_to).fuse();
+            pin_mut!(body_stream);
+            while let Some(maybe_body) = body_stream.next().await {
+                let (block_number, block_hash, body, execution_info) = maybe_body?;
+                yield SyncEvent::BodyAvailable { block_number, block_hash, body, execution_info };
             }
         }
     }
@@ -461,6 +1300,167 @@ fn stream_new_state_diffs<TCentralSource: CentralSourceTrait + Sync + Send>(
     }
 }
 
+fn stream_new_thin_state_diffs<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    block_propation_sleep_duration: Duration,
+    max_stream_size: u32,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let state_marker = txn.get_state_marker()?;
+            let last_block_number = txn.get_header_marker()?;
+            drop(txn);
+            if state_marker == last_block_number {
+                debug!("State updates syncing reached the last downloaded block, waiting for more blocks.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let up_to = min(last_block_number, BlockNumber(state_marker.0 + max_stream_size as u64));
+            debug!("Downloading thin state diffs [{} - {}).", state_marker, up_to);
+            let thin_state_diff_stream =
+                central_source.stream_thin_state_updates(state_marker, up_to).fuse();
+            pin_mut!(thin_state_diff_stream);
+
+            while let Some(maybe_thin_state_diff) = thin_state_diff_stream.next().await {
+                let (block_number, block_hash, mut thin_state_diff) = maybe_thin_state_diff?;
+                sort_thin_state_diff(&mut thin_state_diff);
+                yield SyncEvent::ThinStateDiffAvailable {
+                    block_number,
+                    block_hash,
+                    thin_state_diff,
+                };
+            }
+        }
+    }
+}
+
+fn stream_new_classes<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    block_propation_sleep_duration: Duration,
+    max_stream_size: u32,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let class_marker = txn.get_class_marker()?;
+            let state_marker = txn.get_state_marker()?;
+            drop(txn);
+            if class_marker >= state_marker {
+                debug!("Classes syncing reached the last stored state diff, waiting for more state diffs.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let up_to = min(state_marker, BlockNumber(class_marker.0 + max_stream_size as u64));
+            debug!("Downloading classes [{} - {}).", class_marker, up_to);
+            let classes_stream = central_source.stream_classes(class_marker, up_to).fuse();
+            pin_mut!(classes_stream);
+
+            while let Some(maybe_classes) = classes_stream.next().await {
+                let (block_number, mut classes, mut deprecated_classes) = maybe_classes?;
+                classes.sort_unstable_keys();
+                deprecated_classes.sort_unstable_keys();
+                yield SyncEvent::ClassesAvailable { block_number, classes, deprecated_classes };
+            }
+        }
+    }
+}
+
+fn stream_new_traces<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    block_propation_sleep_duration: Duration,
+    max_stream_size: u32,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let trace_marker = txn.get_trace_marker()?;
+            let body_marker = txn.get_body_marker()?;
+            if trace_marker >= body_marker {
+                drop(txn);
+                debug!("Traces syncing reached the last stored body, waiting for more bodies.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let up_to = min(body_marker, BlockNumber(trace_marker.0 + max_stream_size as u64));
+            // The hashes of the blocks whose bodies are traced, to tell when storing the traces
+            // whether the bodies were reverted meanwhile.
+            let mut block_hashes = Vec::new();
+            for block_number in trace_marker.iter_up_to(up_to) {
+                let header = txn.get_block_header(block_number)?.ok_or(
+                    StorageError::DBInconsistency {
+                        msg: format!("Missing the header of the body of block {block_number}."),
+                    },
+                )?;
+                block_hashes.push(header.block_hash);
+            }
+            drop(txn);
+            debug!("Downloading traces [{} - {}).", trace_marker, up_to);
+            let traces_stream = central_source.stream_traces(trace_marker, up_to).fuse();
+            pin_mut!(traces_stream);
+
+            while let Some(maybe_traces) = traces_stream.next().await {
+                let (block_number, transaction_hashes, traces) = maybe_traces?;
+                let block_hash = block_hashes[(block_number.0 - trace_marker.0) as usize];
+                yield SyncEvent::TracesAvailable {
+                    block_number,
+                    block_hash,
+                    transaction_hashes,
+                    traces,
+                };
+            }
+        }
+    }
+}
+
+fn stream_new_compiled_classes<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    block_propation_sleep_duration: Duration,
+    max_stream_size: u32,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let compiled_class_marker = txn.get_compiled_class_marker()?;
+            let state_marker = txn.get_state_marker()?;
+            drop(txn);
+            if compiled_class_marker >= state_marker {
+                debug!("Compiled classes syncing reached the last stored state diff, waiting for more state diffs.");
+                tokio::time::sleep(block_propation_sleep_duration).await;
+                continue;
+            }
+            let up_to =
+                min(state_marker, BlockNumber(compiled_class_marker.0 + max_stream_size as u64));
+            debug!("Downloading compiled classes [{} - {}).", compiled_class_marker, up_to);
+            let compiled_classes_stream =
+                central_source.stream_compiled_classes(compiled_class_marker, up_to).fuse();
+            pin_mut!(compiled_classes_stream);
+
+            while let Some(maybe_compiled_classes) = compiled_classes_stream.next().await {
+                let (block_number, mut casms) = maybe_compiled_classes?;
+                casms.sort_unstable_keys();
+                yield SyncEvent::CompiledClassesAvailable { block_number, casms };
+            }
+        }
+    }
+}
+
+fn sort_thin_state_diff(diff: &mut ThinStateDiff) {
+    diff.declared_classes.sort_unstable_keys();
+    diff.deprecated_declared_classes.sort_unstable();
+    diff.deployed_contracts.sort_unstable_keys();
+    diff.nonces.sort_unstable_keys();
+    diff.replaced_classes.sort_unstable_keys();
+    diff.storage_diffs.sort_unstable_keys();
+    for storage_entries in diff.storage_diffs.values_mut() {
+        storage_entries.sort_unstable_keys();
+    }
+}
+
 pub fn sort_state_diff(diff: &mut StateDiff) {
     diff.declared_classes.sort_unstable_keys();
     diff.deprecated_declared_classes.sort_unstable_keys();
@@ -475,13 +1475,33 @@ pub fn sort_state_diff(diff: &mut StateDiff) {
 
 pub type StateSync = GenericStateSync<CentralSource>;
 
-impl StateSync {
+impl<TCentralSource: CentralSourceTrait + Sync + Send> GenericStateSync<TCentralSource> {
     pub fn new(
         config: SyncConfig,
-        central_source: CentralSource,
+        central_source: TCentralSource,
         reader: StorageReader,
         writer: StorageWriter,
     ) -> Self {
-        Self { config, central_source: Arc::new(central_source), reader, writer }
+        let (sender, commands) = mpsc::unbounded_channel();
+        let control = SyncControl { paused: Arc::new(AtomicBool::new(false)), commands: sender };
+        Self {
+            config,
+            central_source: Arc::new(central_source),
+            reader,
+            writer,
+            subscribers: Vec::new(),
+            control,
+            commands,
+        }
+    }
+
+    /// Returns a handle for controlling the sync while it runs.
+    pub fn control(&self) -> SyncControl {
+        self.control.clone()
+    }
+
+    /// Adds a subscriber to notify about the data the sync commits to the storage.
+    pub fn add_subscriber(&mut self, subscriber: Arc<dyn StateSyncSubscriber>) {
+        self.subscribers.push(subscriber);
     }
 }
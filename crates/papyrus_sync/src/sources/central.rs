@@ -1,40 +1,69 @@
+mod concurrency_limiter;
 mod state_update_stream;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use async_stream::stream;
 use async_trait::async_trait;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use futures::stream::BoxStream;
 use futures_util::StreamExt;
 use indexmap::IndexMap;
 #[cfg(test)]
 use mockall::automock;
+use papyrus_storage::body::events::{
+    ExecutionResources, TransactionExecutionInfo, TransactionExecutionStatus,
+};
+use papyrus_storage::body::{BlockExecutionInfo, StarknetVersion};
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::trace::{
+    CallType, FunctionInvocation, OrderedEvent, OrderedL2ToL1Message, TransactionTrace,
+};
 use papyrus_storage::{StorageError, StorageReader};
 use serde::{Deserialize, Serialize};
-use starknet_api::block::{Block, BlockHash, BlockNumber};
+use starknet_api::block::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber};
 use starknet_api::core::ClassHash;
 use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
-use starknet_api::state::{ContractClass, StateDiff};
+use starknet_api::state::{ContractClass, StateDiff, StateNumber, ThinStateDiff};
+use starknet_api::transaction::TransactionHash;
 use starknet_api::StarknetApiError;
 use starknet_client::{
-    ClientCreationError, ClientError, GenericContractClass, RetryConfig, StarknetClient,
+    BuiltinInstanceCounter, ClientCreationError, ClientError, EndpointConfig, FailoverConfig,
+    FailoverMonitor, FailoverStarknetClient, GenericContractClass, RateLimitConfig, RetryConfig,
     StarknetClientTrait,
 };
 use tracing::{debug, trace};
 
-use self::state_update_stream::StateUpdateStream;
+pub use self::concurrency_limiter::AdaptiveConcurrencyConfig;
+pub(crate) use self::concurrency_limiter::ConcurrencyLimiter;
+pub(crate) use self::state_update_stream::client_to_central_state_update;
+use self::state_update_stream::{
+    client_to_thin_state_update, download_class_if_necessary, StateUpdateStream,
+};
+use super::file::FileSourceError;
 
 pub type CentralResult<T> = Result<T, CentralError>;
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CentralSourceConfig {
+    // The maximal number of concurrent requests to the central.
     pub concurrent_requests: usize,
+    // The maximal size (estimated) of the downloaded state updates and classes that weren't
+    // processed yet.
+    pub max_bytes_in_flight: usize,
+    pub adaptive_concurrency: AdaptiveConcurrencyConfig,
     pub url: String,
     pub http_headers: Option<HashMap<String, String>>,
+    // Endpoints to fail over to when `url` is unhealthy, by priority.
+    pub fallback_endpoints: Vec<EndpointConfig>,
+    pub failover: FailoverConfig,
     pub retry_config: RetryConfig,
+    // If set, limits the rate of the requests to each of the endpoints.
+    pub rate_limit: Option<RateLimitConfig>,
 }
 pub struct GenericCentralSource<TStarknetClient: StarknetClientTrait + Send + Sync> {
-    pub concurrent_requests: usize,
+    pub(crate) concurrency_limiter: Arc<ConcurrencyLimiter>,
+    pub max_bytes_in_flight: usize,
     pub starknet_client: Arc<TStarknetClient>,
     pub storage_reader: StorageReader,
 }
@@ -72,6 +101,22 @@ impl ApiContractClass {
     }
 }
 
+// Returns the definition of the class if it was already synced into the storage.
+pub(crate) fn get_stored_class(
+    storage_reader: &StorageReader,
+    class_hash: &ClassHash,
+) -> CentralResult<Option<ApiContractClass>> {
+    let txn = storage_reader.begin_ro_txn()?;
+    let state_reader = txn.get_state_reader()?;
+    let state_number = StateNumber::right_after_block(txn.get_state_marker()?);
+    if let Some(class) = state_reader.get_class_definition_at(state_number, class_hash)? {
+        return Ok(Some(ApiContractClass::ContractClass(class)));
+    }
+    Ok(state_reader
+        .get_deprecated_class_definition_at(state_number, class_hash)?
+        .map(ApiContractClass::DeprecatedContractClass))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum CentralError {
     #[error(transparent)]
@@ -90,6 +135,22 @@ pub enum CentralError {
     StorageError(#[from] StorageError),
     #[error("Wrong type of contract class")]
     BadContractClassType,
+    #[error(transparent)]
+    PeerError(#[from] Arc<jsonrpsee::core::Error>),
+    #[error("Bad response from the peer node: {message}")]
+    BadPeerResponse { message: String },
+    #[error(transparent)]
+    FileSourceError(#[from] Arc<FileSourceError>),
+    #[error("The source doesn't support syncing the classes separately from the state diffs.")]
+    SeparateClassSyncNotSupported,
+    #[error("The source doesn't support syncing the headers separately from the bodies.")]
+    SeparateHeaderSyncNotSupported,
+    #[error("The source doesn't support syncing the transaction traces.")]
+    TraceSyncNotSupported,
+    #[error("Could not find the traces of block {}.", block_number)]
+    TracesNotFound { block_number: BlockNumber },
+    #[error("The source doesn't support syncing the compiled classes.")]
+    CompiledClassSyncNotSupported,
 }
 
 #[cfg_attr(test, automock)]
@@ -111,20 +172,102 @@ pub trait CentralSourceTrait {
         &self,
         block_number: BlockNumber,
     ) -> Result<Option<BlockHash>, CentralError>;
+
+    // Streams the headers of the blocks, so they can be synced ahead of the bodies. Only sources
+    // that serve the headers without the bodies support it, since otherwise every block would be
+    // downloaded twice.
+    fn stream_headers(
+        &self,
+        _initial_block_number: BlockNumber,
+        _up_to_block_number: BlockNumber,
+    ) -> HeadersStream<'_> {
+        futures::stream::once(async { Err(CentralError::SeparateHeaderSyncNotSupported) }).boxed()
+    }
+
+    // Streams the bodies of blocks whose headers are already stored, along with the hashes of the
+    // blocks.
+    fn stream_bodies(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> BodiesStream<'_> {
+        self.stream_new_blocks(initial_block_number, up_to_block_number)
+            .map(|maybe_block| {
+                maybe_block.map(|(block_number, block, execution_info)| {
+                    (block_number, block.header.block_hash, block.body, execution_info)
+                })
+            })
+            .boxed()
+    }
+
+    // Streams the state updates without the definitions of the declared classes, which are
+    // streamed by `stream_classes` once the state diffs are stored.
+    fn stream_thin_state_updates(
+        &self,
+        _initial_block_number: BlockNumber,
+        _up_to_block_number: BlockNumber,
+    ) -> ThinStateUpdatesStream<'_> {
+        futures::stream::once(async { Err(CentralError::SeparateClassSyncNotSupported) }).boxed()
+    }
+
+    // Streams the definitions of the classes of blocks whose state diffs are already stored.
+    fn stream_classes(
+        &self,
+        _initial_block_number: BlockNumber,
+        _up_to_block_number: BlockNumber,
+    ) -> ClassesStream<'_> {
+        futures::stream::once(async { Err(CentralError::SeparateClassSyncNotSupported) }).boxed()
+    }
+
+    // Streams the execution traces of the transactions of blocks whose bodies are already stored.
+    fn stream_traces(
+        &self,
+        _initial_block_number: BlockNumber,
+        _up_to_block_number: BlockNumber,
+    ) -> TracesStream<'_> {
+        futures::stream::once(async { Err(CentralError::TraceSyncNotSupported) }).boxed()
+    }
+
+    // Streams the compiled classes of the classes that were declared in blocks whose state diffs
+    // are already stored.
+    fn stream_compiled_classes(
+        &self,
+        _initial_block_number: BlockNumber,
+        _up_to_block_number: BlockNumber,
+    ) -> CompiledClassesStream<'_> {
+        futures::stream::once(async { Err(CentralError::CompiledClassSyncNotSupported) }).boxed()
+    }
 }
 
-pub(crate) type BlocksStream<'a> = BoxStream<'a, Result<(BlockNumber, Block), CentralError>>;
-type CentralStateUpdate =
+pub(crate) type BlocksStream<'a> =
+    BoxStream<'a, Result<(BlockNumber, Block, BlockExecutionInfo), CentralError>>;
+pub(crate) type HeadersStream<'a> = BoxStream<'a, CentralResult<(BlockNumber, BlockHeader)>>;
+pub(crate) type CentralBody = (BlockNumber, BlockHash, BlockBody, BlockExecutionInfo);
+pub(crate) type BodiesStream<'a> = BoxStream<'a, CentralResult<CentralBody>>;
+pub(crate) type CentralStateUpdate =
     (BlockNumber, BlockHash, StateDiff, IndexMap<ClassHash, DeprecatedContractClass>);
 pub(crate) type StateUpdatesStream<'a> = BoxStream<'a, CentralResult<CentralStateUpdate>>;
+pub(crate) type CentralThinStateUpdate = (BlockNumber, BlockHash, ThinStateDiff);
+pub(crate) type ThinStateUpdatesStream<'a> = BoxStream<'a, CentralResult<CentralThinStateUpdate>>;
+// The classes of a block: the declared classes and the deprecated classes, including the classes of
+// the contracts that were deployed without being declared.
+pub(crate) type CentralClasses =
+    (BlockNumber, IndexMap<ClassHash, ContractClass>, IndexMap<ClassHash, DeprecatedContractClass>);
+pub(crate) type ClassesStream<'a> = BoxStream<'a, CentralResult<CentralClasses>>;
+// The traces of the transactions of a block, along with the hashes of the traced transactions.
+pub(crate) type CentralTraces = (BlockNumber, Vec<TransactionHash>, Vec<TransactionTrace>);
+pub(crate) type TracesStream<'a> = BoxStream<'a, CentralResult<CentralTraces>>;
+// The compiled classes of the classes that were declared in a block.
+pub(crate) type CentralCompiledClasses = (BlockNumber, IndexMap<ClassHash, CasmContractClass>);
+pub(crate) type CompiledClassesStream<'a> = BoxStream<'a, CentralResult<CentralCompiledClasses>>;
 
 #[async_trait]
 impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> CentralSourceTrait
     for GenericCentralSource<TStarknetClient>
 {
     async fn get_block_marker(&self) -> Result<BlockNumber, CentralError> {
-        self.starknet_client
-            .block_number()
+        self.concurrency_limiter
+            .run(self.starknet_client.block_number())
             .await
             .map_err(Arc::new)?
             .map_or(Ok(BlockNumber::default()), |block_number| Ok(block_number.next()))
@@ -134,8 +277,8 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> CentralSource
         &self,
         block_number: BlockNumber,
     ) -> Result<Option<BlockHash>, CentralError> {
-        self.starknet_client
-            .block(block_number)
+        self.concurrency_limiter
+            .run(self.starknet_client.block(block_number))
             .await
             .map_err(Arc::new)?
             .map_or(Ok(None), |block| Ok(Some(block.block_hash)))
@@ -151,6 +294,8 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> CentralSource
             up_to_block_number,
             self.starknet_client.clone(),
             self.storage_reader.clone(),
+            self.concurrency_limiter.clone(),
+            self.max_bytes_in_flight,
         )
         .boxed()
     }
@@ -165,14 +310,122 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> CentralSource
             // TODO(dan): add explanation.
             let mut res =
                 futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
-                    .map(|bn| async move { (bn, self.starknet_client.block(bn).await) })
-                    .buffered(self.concurrent_requests);
+                    .map(|bn| async move {
+                        (bn, self.concurrency_limiter.run(self.starknet_client.block(bn)).await)
+                    })
+                    .buffered(self.concurrency_limiter.max_limit());
             while let Some((current_block_number, maybe_client_block)) = res.next().await {
                 let maybe_central_block =
                     client_to_central_block(current_block_number, maybe_client_block);
                 match maybe_central_block {
-                    Ok(block) => {
-                        yield Ok((current_block_number, block));
+                    Ok((block, execution_info)) => {
+                        yield Ok((current_block_number, block, execution_info));
+                    }
+                    Err(err) => {
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_thin_state_updates(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> ThinStateUpdatesStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| async move {
+                        let state_update = self.starknet_client.state_update(bn);
+                        (bn, self.concurrency_limiter.run(state_update).await)
+                    })
+                    .buffered(self.concurrency_limiter.max_limit());
+            while let Some((current_block_number, maybe_client_state_update)) = res.next().await {
+                match client_to_thin_state_update(current_block_number, maybe_client_state_update) {
+                    Ok(thin_state_update) => {
+                        yield Ok(thin_state_update);
+                    }
+                    Err(err) => {
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_classes(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> ClassesStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| self.download_block_classes(bn))
+                    .buffered(self.concurrency_limiter.max_limit());
+            while let Some(maybe_classes) = res.next().await {
+                match maybe_classes {
+                    Ok(classes) => {
+                        yield Ok(classes);
+                    }
+                    Err(err) => {
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_traces(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> TracesStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| async move {
+                        let block_traces = self.starknet_client.block_traces(bn);
+                        (bn, self.concurrency_limiter.run(block_traces).await)
+                    })
+                    .buffered(self.concurrency_limiter.max_limit());
+            while let Some((current_block_number, maybe_client_traces)) = res.next().await {
+                match client_to_central_traces(current_block_number, maybe_client_traces) {
+                    Ok(traces) => {
+                        yield Ok(traces);
+                    }
+                    Err(err) => {
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_compiled_classes(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> CompiledClassesStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| self.download_block_compiled_classes(bn))
+                    .buffered(self.concurrency_limiter.max_limit());
+            while let Some(maybe_compiled_classes) = res.next().await {
+                match maybe_compiled_classes {
+                    Ok(compiled_classes) => {
+                        yield Ok(compiled_classes);
                     }
                     Err(err) => {
                         yield (Err(err));
@@ -185,15 +438,102 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> CentralSource
     }
 }
 
-fn client_to_central_block(
+impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
+    GenericCentralSource<TStarknetClient>
+{
+    // Downloads the classes of a block whose state diff is stored.
+    async fn download_block_classes(
+        &self,
+        block_number: BlockNumber,
+    ) -> CentralResult<CentralClasses> {
+        let thin_state_diff = self
+            .storage_reader
+            .begin_ro_txn()?
+            .get_state_diff(block_number)?
+            .ok_or(CentralError::StateUpdateNotFound)?;
+        let mut class_hashes: Vec<ClassHash> =
+            thin_state_diff.declared_classes.keys().copied().collect();
+        class_hashes.extend(thin_state_diff.deprecated_declared_classes.iter().copied());
+        for class_hash in thin_state_diff.deployed_contracts.values() {
+            if !class_hashes.contains(class_hash) {
+                class_hashes.push(*class_hash);
+            }
+        }
+
+        let downloaded_classes =
+            futures::future::try_join_all(class_hashes.iter().map(|class_hash| {
+                download_class_if_necessary(
+                    *class_hash,
+                    self.starknet_client.clone(),
+                    self.storage_reader.clone(),
+                    self.concurrency_limiter.clone(),
+                )
+            }))
+            .await?;
+
+        let mut classes = IndexMap::new();
+        let mut deprecated_classes = IndexMap::new();
+        for (class_hash, class) in class_hashes.into_iter().zip(downloaded_classes) {
+            match class.ok_or(CentralError::ClassNotFound)? {
+                ApiContractClass::DeprecatedContractClass(class) => {
+                    deprecated_classes.insert(class_hash, class);
+                }
+                // Since 0.11 new classes can not be implicitly declared by deployment.
+                ApiContractClass::ContractClass(class) => {
+                    if thin_state_diff.declared_classes.contains_key(&class_hash) {
+                        classes.insert(class_hash, class);
+                    }
+                }
+            }
+        }
+        debug!("Received the classes of block {block_number}.");
+        Ok((block_number, classes, deprecated_classes))
+    }
+
+    // Downloads the compiled classes of the classes that were declared in a block whose state diff
+    // is stored.
+    async fn download_block_compiled_classes(
+        &self,
+        block_number: BlockNumber,
+    ) -> CentralResult<CentralCompiledClasses> {
+        let class_hashes: Vec<ClassHash> = self
+            .storage_reader
+            .begin_ro_txn()?
+            .get_state_diff(block_number)?
+            .ok_or(CentralError::StateUpdateNotFound)?
+            .declared_classes
+            .into_keys()
+            .collect();
+
+        let downloaded_casms =
+            futures::future::try_join_all(class_hashes.iter().map(|class_hash| {
+                let casm = self.starknet_client.compiled_class_by_hash(*class_hash);
+                self.concurrency_limiter.run(casm)
+            }))
+            .await
+            .map_err(|err| CentralError::ClientError(Arc::new(err)))?;
+
+        let mut casms = IndexMap::new();
+        for (class_hash, casm) in class_hashes.into_iter().zip(downloaded_casms) {
+            casms.insert(class_hash, casm.ok_or(CentralError::ClassNotFound)?);
+        }
+        debug!("Received the compiled classes of block {block_number}.");
+        Ok((block_number, casms))
+    }
+}
+
+pub(crate) fn client_to_central_block(
     current_block_number: BlockNumber,
     maybe_client_block: Result<Option<starknet_client::Block>, ClientError>,
-) -> CentralResult<Block> {
+) -> CentralResult<(Block, BlockExecutionInfo)> {
     let res = match maybe_client_block {
         Ok(Some(block)) => {
             debug!("Received new block {current_block_number} with hash {}.", block.block_hash);
             trace!("Block: {block:#?}.");
-            Block::try_from(block).map_err(|err| CentralError::ClientError(Arc::new(err)))
+            let execution_info = client_to_central_execution_info(&block);
+            Block::try_from(block)
+                .map(|block| (block, execution_info))
+                .map_err(|err| CentralError::ClientError(Arc::new(err)))
         }
         Ok(None) => Err(CentralError::BlockNotFound { block_number: current_block_number }),
         Err(err) => Err(CentralError::ClientError(Arc::new(err))),
@@ -207,7 +547,139 @@ fn client_to_central_block(
     }
 }
 
-pub type CentralSource = GenericCentralSource<StarknetClient>;
+// The data of the block that starknet_api doesn't hold: the version of starknet the block was
+// created in and how its transactions were executed. The execution info of the transactions is
+// stored only if all of their receipts have execution resources.
+fn client_to_central_execution_info(block: &starknet_client::Block) -> BlockExecutionInfo {
+    let transaction_execution_infos = block
+        .transaction_receipts
+        .iter()
+        .map(|receipt| {
+            let resources = receipt.execution_resources.as_ref()?;
+            // Transactions of blocks from before transactions could revert have no status.
+            let execution_status = match receipt.execution_status {
+                None | Some(starknet_client::TransactionExecutionStatus::Succeeded) => {
+                    TransactionExecutionStatus::Succeeded
+                }
+                Some(starknet_client::TransactionExecutionStatus::Reverted) => {
+                    TransactionExecutionStatus::Reverted(
+                        receipt.revert_error.clone().unwrap_or_default(),
+                    )
+                }
+            };
+            let builtin_instance_counter = match &resources.builtin_instance_counter {
+                BuiltinInstanceCounter::NonEmpty(counter) => {
+                    counter.iter().map(|(name, count)| (name.clone(), *count)).collect()
+                }
+                BuiltinInstanceCounter::Empty(_) => BTreeMap::new(),
+            };
+            Some(TransactionExecutionInfo {
+                execution_status,
+                execution_resources: ExecutionResources {
+                    n_steps: resources.n_steps,
+                    builtin_instance_counter,
+                    n_memory_holes: resources.n_memory_holes,
+                },
+            })
+        })
+        .collect();
+    BlockExecutionInfo {
+        starknet_version: block.starknet_version.clone().map(StarknetVersion),
+        transaction_execution_infos,
+    }
+}
+
+fn client_to_central_traces(
+    current_block_number: BlockNumber,
+    maybe_client_traces: Result<Option<starknet_client::BlockTraces>, ClientError>,
+) -> CentralResult<CentralTraces> {
+    match maybe_client_traces {
+        Ok(Some(block_traces)) => {
+            debug!("Received the traces of block {current_block_number}.");
+            let (transaction_hashes, traces) = block_traces
+                .traces
+                .into_iter()
+                .map(|trace| (trace.transaction_hash, client_to_central_trace(trace)))
+                .unzip();
+            Ok((current_block_number, transaction_hashes, traces))
+        }
+        Ok(None) => Err(CentralError::TracesNotFound { block_number: current_block_number }),
+        Err(err) => {
+            debug!("Received error for the traces of block {}: {:?}.", current_block_number, err);
+            Err(CentralError::ClientError(Arc::new(err)))
+        }
+    }
+}
+
+fn client_to_central_trace(trace: starknet_client::TransactionTrace) -> TransactionTrace {
+    TransactionTrace {
+        validate_invocation: trace.validate_invocation.map(client_to_central_invocation),
+        function_invocation: trace.function_invocation.map(client_to_central_invocation),
+        fee_transfer_invocation: trace.fee_transfer_invocation.map(client_to_central_invocation),
+        signature: trace.signature,
+    }
+}
+
+fn client_to_central_invocation(
+    invocation: starknet_client::FunctionInvocation,
+) -> FunctionInvocation {
+    FunctionInvocation {
+        caller_address: invocation.caller_address,
+        contract_address: invocation.contract_address,
+        calldata: invocation.calldata,
+        call_type: invocation.call_type.map(|call_type| match call_type {
+            starknet_client::CallType::Call => CallType::Call,
+            starknet_client::CallType::Delegate => CallType::Delegate,
+        }),
+        class_hash: invocation.class_hash,
+        selector: invocation.selector,
+        entry_point_type: invocation.entry_point_type,
+        result: invocation.result,
+        internal_calls: invocation
+            .internal_calls
+            .into_iter()
+            .map(client_to_central_invocation)
+            .collect(),
+        events: invocation
+            .events
+            .into_iter()
+            .map(|event| OrderedEvent { order: event.order, keys: event.keys, data: event.data })
+            .collect(),
+        messages: invocation
+            .messages
+            .into_iter()
+            .map(|message| OrderedL2ToL1Message {
+                order: message.order,
+                to_address: message.to_address,
+                payload: message.payload,
+            })
+            .collect(),
+    }
+}
+
+impl<TStarknetClient: StarknetClientTrait + Send + Sync> GenericCentralSource<TStarknetClient> {
+    /// Creates a source that downloads through the given client, e.g., a
+    /// [`ReplayStarknetClient`](starknet_client::ReplayStarknetClient) serving recorded responses.
+    pub fn with_starknet_client(
+        concurrent_requests: usize,
+        adaptive_concurrency: AdaptiveConcurrencyConfig,
+        max_bytes_in_flight: usize,
+        starknet_client: TStarknetClient,
+        storage_reader: StorageReader,
+    ) -> Self {
+        GenericCentralSource {
+            concurrency_limiter: Arc::new(ConcurrencyLimiter::new(
+                concurrent_requests,
+                adaptive_concurrency,
+            )),
+            max_bytes_in_flight,
+            starknet_client: Arc::new(starknet_client),
+            storage_reader,
+        }
+    }
+}
+
+pub type CentralSource = GenericCentralSource<FailoverStarknetClient>;
 
 impl CentralSource {
     pub fn new(
@@ -215,17 +687,28 @@ impl CentralSource {
         node_version: &'static str,
         storage_reader: StorageReader,
     ) -> Result<CentralSource, ClientCreationError> {
-        let starknet_client = StarknetClient::new(
-            &config.url,
-            config.http_headers,
+        let mut endpoints =
+            vec![EndpointConfig { url: config.url, http_headers: config.http_headers }];
+        endpoints.extend(config.fallback_endpoints);
+        let starknet_client = FailoverStarknetClient::new(
+            endpoints,
             node_version,
             config.retry_config,
+            config.rate_limit,
+            config.failover,
         )?;
 
-        Ok(CentralSource {
-            concurrent_requests: config.concurrent_requests,
-            starknet_client: Arc::new(starknet_client),
+        Ok(CentralSource::with_starknet_client(
+            config.concurrent_requests,
+            config.adaptive_concurrency,
+            config.max_bytes_in_flight,
+            starknet_client,
             storage_reader,
-        })
+        ))
+    }
+
+    /// Returns a handle to the health and the request counters of the central's endpoints.
+    pub fn failover_monitor(&self) -> FailoverMonitor {
+        self.starknet_client.monitor()
     }
 }
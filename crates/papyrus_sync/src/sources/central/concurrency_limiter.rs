@@ -0,0 +1,162 @@
+#[cfg(test)]
+#[path = "concurrency_limiter_test.rs"]
+mod concurrency_limiter_test;
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use starknet_client::{ClientError, ClientResult, RetryErrorCode};
+use tokio::sync::Notify;
+use tracing::debug;
+
+/// Configuration of the adaptive limit on the number of concurrent requests to the central. The
+/// limit stays between `min_concurrent_requests` and the `concurrent_requests` of the source. It
+/// grows while the requests are answered within the target latency, and shrinks when they are
+/// slower or when the central throttles the node (429 and 503 responses).
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct AdaptiveConcurrencyConfig {
+    pub min_concurrent_requests: usize,
+    pub target_latency_millis: u64,
+}
+
+struct LimiterState {
+    limit: usize,
+    in_flight: usize,
+    // The number of requests that were answered within the target latency since the last change
+    // of the limit.
+    fast_responses: usize,
+    last_decrease: Option<Instant>,
+}
+
+/// Limits the number of concurrent requests to the central, adapting the limit to the observed
+/// latency and throttling.
+/// Note: requests that are throttled are retried by the client with a backoff, so most of the
+/// throttling is observed as latency.
+pub(crate) struct ConcurrencyLimiter {
+    min_limit: usize,
+    max_limit: usize,
+    target_latency: Duration,
+    state: Mutex<LimiterState>,
+    notify: Notify,
+}
+
+// Marks a request in flight until dropped.
+struct Permit<'a>(&'a ConcurrencyLimiter);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.state.lock().expect("Limiter lock should not be poisoned.").in_flight -= 1;
+        self.0.notify.notify_one();
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(max_concurrent_requests: usize, config: AdaptiveConcurrencyConfig) -> Self {
+        let max_limit = max_concurrent_requests.max(1);
+        ConcurrencyLimiter {
+            min_limit: config.min_concurrent_requests.clamp(1, max_limit),
+            max_limit,
+            target_latency: Duration::from_millis(config.target_latency_millis),
+            state: Mutex::new(LimiterState {
+                limit: max_limit,
+                in_flight: 0,
+                fast_responses: 0,
+                last_decrease: None,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// A limiter that always allows `concurrent_requests` requests.
+    pub fn fixed(concurrent_requests: usize) -> Self {
+        Self::new(
+            concurrent_requests,
+            AdaptiveConcurrencyConfig {
+                min_concurrent_requests: concurrent_requests,
+                target_latency_millis: 0,
+            },
+        )
+    }
+
+    pub fn max_limit(&self) -> usize {
+        self.max_limit
+    }
+
+    pub fn limit(&self) -> usize {
+        self.state.lock().expect("Limiter lock should not be poisoned.").limit
+    }
+
+    /// Sends the request once the number of requests in flight is below the limit, and adapts the
+    /// limit according to the response.
+    pub async fn run<T>(&self, request: impl Future<Output = ClientResult<T>>) -> ClientResult<T> {
+        let _permit = self.acquire().await;
+        let start = Instant::now();
+        let res = request.await;
+        self.record(start.elapsed(), matches!(&res, Err(err) if is_throttling(err)));
+        res
+    }
+
+    async fn acquire(&self) -> Permit<'_> {
+        loop {
+            // Created before checking the state so that a release in between isn't missed.
+            let notified = self.notify.notified();
+            {
+                let mut state = self.state.lock().expect("Limiter lock should not be poisoned.");
+                if state.in_flight < state.limit {
+                    state.in_flight += 1;
+                    return Permit(self);
+                }
+            }
+            notified.await;
+        }
+    }
+
+    fn record(&self, latency: Duration, throttled: bool) {
+        let mut state = self.state.lock().expect("Limiter lock should not be poisoned.");
+        if !throttled && latency <= self.target_latency {
+            // Additive increase, by one request per a full window of fast responses.
+            state.fast_responses += 1;
+            if state.fast_responses >= state.limit && state.limit < self.max_limit {
+                state.limit += 1;
+                state.fast_responses = 0;
+                self.notify.notify_one();
+            }
+            return;
+        }
+
+        // Multiplicative decrease. Requests that were sent together tend to be answered together,
+        // so the limit is decreased at most once per target latency.
+        let now = Instant::now();
+        if state.last_decrease.map_or(false, |last| now - last < self.target_latency) {
+            return;
+        }
+        let decreased_limit = if throttled { state.limit / 2 } else { state.limit * 3 / 4 };
+        let new_limit = decreased_limit.max(self.min_limit);
+        if new_limit != state.limit {
+            debug!(
+                "Decreasing the concurrent requests limit from {} to {new_limit} (latency: \
+                 {latency:?}, throttled: {throttled}).",
+                state.limit
+            );
+        }
+        state.limit = new_limit;
+        state.fast_responses = 0;
+        state.last_decrease = Some(now);
+    }
+}
+
+// Whether the error indicates that the central throttles the node.
+fn is_throttling(err: &ClientError) -> bool {
+    match err {
+        ClientError::BadResponseStatus { code, message: _ } => {
+            [StatusCode::TOO_MANY_REQUESTS, StatusCode::SERVICE_UNAVAILABLE].contains(code)
+        }
+        ClientError::RetryError { code, message: _ } => {
+            matches!(code, RetryErrorCode::TooManyRequests | RetryErrorCode::ServiceUnavailable)
+        }
+        _ => false,
+    }
+}
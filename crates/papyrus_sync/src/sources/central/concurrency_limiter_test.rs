@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use futures::future::join_all;
+use reqwest::StatusCode;
+use starknet_client::ClientError;
+
+use super::{AdaptiveConcurrencyConfig, ConcurrencyLimiter};
+
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+const MIN_CONCURRENT_REQUESTS: usize = 2;
+const TARGET_LATENCY: Duration = Duration::from_millis(50);
+
+fn get_limiter() -> ConcurrencyLimiter {
+    ConcurrencyLimiter::new(
+        MAX_CONCURRENT_REQUESTS,
+        AdaptiveConcurrencyConfig {
+            min_concurrent_requests: MIN_CONCURRENT_REQUESTS,
+            target_latency_millis: TARGET_LATENCY.as_millis() as u64,
+        },
+    )
+}
+
+fn throttled() -> ClientError {
+    ClientError::BadResponseStatus {
+        code: StatusCode::TOO_MANY_REQUESTS,
+        message: "Too many requests".to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn limits_requests_in_flight() {
+    let limiter = ConcurrencyLimiter::fixed(3);
+    let in_flight = &AtomicUsize::new(0);
+    let max_in_flight = &AtomicUsize::new(0);
+    join_all((0..10).map(|_| {
+        limiter.run(async move {
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        })
+    }))
+    .await;
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    assert_eq!(limiter.limit(), 3);
+}
+
+#[tokio::test]
+async fn throttling_halves_the_limit() {
+    let limiter = get_limiter();
+    assert_eq!(limiter.limit(), MAX_CONCURRENT_REQUESTS);
+
+    limiter.run(async { Err::<(), _>(throttled()) }).await.unwrap_err();
+    assert_eq!(limiter.limit(), MAX_CONCURRENT_REQUESTS / 2);
+
+    // Decreases at most once per target latency.
+    limiter.run(async { Err::<(), _>(throttled()) }).await.unwrap_err();
+    assert_eq!(limiter.limit(), MAX_CONCURRENT_REQUESTS / 2);
+
+    // Never below the minimum.
+    for _ in 0..3 {
+        tokio::time::sleep(TARGET_LATENCY).await;
+        limiter.run(async { Err::<(), _>(throttled()) }).await.unwrap_err();
+    }
+    assert_eq!(limiter.limit(), MIN_CONCURRENT_REQUESTS);
+}
+
+#[tokio::test]
+async fn slow_responses_decrease_the_limit() {
+    let limiter = get_limiter();
+    limiter
+        .run(async {
+            tokio::time::sleep(TARGET_LATENCY * 2).await;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(limiter.limit(), MAX_CONCURRENT_REQUESTS * 3 / 4);
+}
+
+#[tokio::test]
+async fn fast_responses_increase_the_limit() {
+    let limiter = get_limiter();
+    limiter.run(async { Err::<(), _>(throttled()) }).await.unwrap_err();
+    let decreased_limit = limiter.limit();
+
+    // A full window of fast responses increases the limit by one.
+    for _ in 0..decreased_limit {
+        limiter.run(async { Ok(()) }).await.unwrap();
+    }
+    assert_eq!(limiter.limit(), decreased_limit + 1);
+
+    // Never above the maximum.
+    for _ in 0..MAX_CONCURRENT_REQUESTS * MAX_CONCURRENT_REQUESTS {
+        limiter.run(async { Ok(()) }).await.unwrap();
+    }
+    assert_eq!(limiter.limit(), MAX_CONCURRENT_REQUESTS);
+}
@@ -8,36 +8,44 @@ use futures_util::{Future, Stream, StreamExt};
 use indexmap::IndexMap;
 use papyrus_storage::state::StateStorageReader;
 use papyrus_storage::StorageReader;
+use serde_json::Value;
 use starknet_api::block::BlockNumber;
 use starknet_api::core::ClassHash;
-use starknet_api::state::{StateDiff, StateNumber};
+use starknet_api::state::{StateDiff, StateNumber, ThinStateDiff};
 use starknet_client::{ClientResult, StarknetClientTrait, StateUpdate};
 use tracing::log::trace;
 use tracing::{debug, instrument};
 
-use super::{ApiContractClass, CentralResult, CentralStateUpdate};
+use super::concurrency_limiter::ConcurrencyLimiter;
+use super::{ApiContractClass, CentralResult, CentralStateUpdate, CentralThinStateUpdate};
 use crate::CentralError;
 
-// TODO (dan): take from config.
-const MAX_STATE_UPDATES_TO_DOWNLOAD: usize = 100;
-const MAX_STATE_UPDATES_TO_STORE_IN_MEMORY: usize = 100;
-const MAX_CLASSES_TO_DOWNLOAD: usize = 100;
-const CLASSES_INITIAL_CAPACITY: usize = MAX_STATE_UPDATES_TO_STORE_IN_MEMORY * 5;
-
 type TasksQueue<T> = FuturesOrdered<Pin<Box<dyn Future<Output = T> + Send>>>;
 type NumberOfClasses = usize;
+type NumberOfBytes = usize;
+
+// The sizes the memory held by the downloaded data is estimated with, without serializing it.
+const FELT_SIZE: NumberOfBytes = 32;
+// The size of an entry point or an ABI entry of a class, or a node of a JSON value.
+const ENTRY_SIZE: NumberOfBytes = 64;
 
+// The memory held by the stream is bounded by the size of the downloaded state updates and classes:
+// no new state updates are downloaded while it exceeds `max_bytes_in_flight`. The classes of the
+// downloaded state updates are always downloaded, so that the stream can advance.
 pub(crate) struct StateUpdateStream<TStarknetClient: StarknetClientTrait + Send + 'static> {
     initial_block_number: BlockNumber,
     up_to_block_number: BlockNumber,
     starknet_client: Arc<TStarknetClient>,
     storage_reader: StorageReader,
+    concurrency_limiter: Arc<ConcurrencyLimiter>,
+    max_bytes_in_flight: NumberOfBytes,
+    bytes_in_flight: NumberOfBytes,
     download_state_update_tasks: TasksQueue<(BlockNumber, ClientResult<Option<StateUpdate>>)>,
     // Contains NumberOfClasses so we don't need to calculate it from the StateUpdate.
-    downloaded_state_updates: VecDeque<(BlockNumber, NumberOfClasses, StateUpdate)>,
+    downloaded_state_updates: VecDeque<(BlockNumber, NumberOfClasses, NumberOfBytes, StateUpdate)>,
     classes_to_download: VecDeque<ClassHash>,
     download_class_tasks: TasksQueue<CentralResult<Option<ApiContractClass>>>,
-    downloaded_classes: VecDeque<ApiContractClass>,
+    downloaded_classes: VecDeque<(NumberOfBytes, ApiContractClass)>,
 }
 
 impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> Stream
@@ -88,32 +96,43 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
         up_to_block_number: BlockNumber,
         starknet_client: Arc<TStarknetClient>,
         storage_reader: StorageReader,
+        concurrency_limiter: Arc<ConcurrencyLimiter>,
+        max_bytes_in_flight: usize,
     ) -> Self {
         StateUpdateStream {
             initial_block_number,
             up_to_block_number,
             starknet_client,
             storage_reader,
+            concurrency_limiter,
+            max_bytes_in_flight,
+            bytes_in_flight: 0,
             download_state_update_tasks: futures::stream::FuturesOrdered::new(),
-            downloaded_state_updates: VecDeque::with_capacity(MAX_STATE_UPDATES_TO_STORE_IN_MEMORY),
-            classes_to_download: VecDeque::with_capacity(CLASSES_INITIAL_CAPACITY),
+            downloaded_state_updates: VecDeque::new(),
+            classes_to_download: VecDeque::new(),
             download_class_tasks: futures::stream::FuturesOrdered::new(),
-            downloaded_classes: VecDeque::with_capacity(CLASSES_INITIAL_CAPACITY),
+            downloaded_classes: VecDeque::new(),
         }
     }
 
     // Returns data needed for the next block CentralStateUpdate, or None if it is not yet ready.
     fn next_output(&mut self) -> Option<CentralResult<CentralStateUpdate>> {
-        let (_, n_classes, _) = self.downloaded_state_updates.front()?;
+        let (_, n_classes, _, _) = self.downloaded_state_updates.front()?;
         if self.downloaded_classes.len() < *n_classes {
             return None;
         }
-        let (block_number, n_classes, state_update) =
+        let (block_number, n_classes, state_update_size, state_update) =
             self.downloaded_state_updates.pop_front().expect("Should have a value");
+        self.bytes_in_flight -= state_update_size;
         let class_hashes = state_update.state_diff.class_hashes();
-        let classes = self.downloaded_classes.drain(..n_classes);
+        let mut classes_size = 0;
+        let classes = self.downloaded_classes.drain(..n_classes).map(|(class_size, class)| {
+            classes_size += class_size;
+            class
+        });
         let classes: IndexMap<ClassHash, ApiContractClass> =
             class_hashes.into_iter().zip(classes).collect();
+        self.bytes_in_flight -= classes_size;
         Some(client_to_central_state_update(block_number, Ok((state_update, classes))))
     }
 
@@ -137,14 +156,16 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
 
     // Adds more class downloading tasks.
     fn schedule_class_downloads(self: &mut std::pin::Pin<&mut Self>, should_poll_again: &mut bool) {
-        while self.download_class_tasks.len() < MAX_CLASSES_TO_DOWNLOAD {
+        while self.download_class_tasks.len() < self.concurrency_limiter.max_limit() {
             let Some(class_hash) = self.classes_to_download.pop_front() else { break; };
             let starknet_client = self.starknet_client.clone();
             let storage_reader = self.storage_reader.clone();
+            let concurrency_limiter = self.concurrency_limiter.clone();
             self.download_class_tasks.push_back(Box::pin(download_class_if_necessary(
                 class_hash,
                 starknet_client,
                 storage_reader,
+                concurrency_limiter,
             )));
             *should_poll_again = true;
         }
@@ -165,7 +186,9 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
         match maybe_class {
             // Add to downloaded classes.
             Ok(Some(class)) => {
-                self.downloaded_classes.push_back(class);
+                let class_size = class_size(&class);
+                self.bytes_in_flight += class_size;
+                self.downloaded_classes.push_back((class_size, class));
                 Ok(())
             }
             // Class was not found.
@@ -181,13 +204,18 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
         should_poll_again: &mut bool,
     ) {
         while self.initial_block_number < self.up_to_block_number
-            && self.download_state_update_tasks.len() < MAX_STATE_UPDATES_TO_DOWNLOAD
+            && self.download_state_update_tasks.len() < self.concurrency_limiter.max_limit()
+            && self.bytes_in_flight < self.max_bytes_in_flight
         {
             let current_block_number = self.initial_block_number;
             let starknet_client = self.starknet_client.clone();
+            let concurrency_limiter = self.concurrency_limiter.clone();
             *should_poll_again = true;
             self.download_state_update_tasks.push_back(Box::pin(async move {
-                (current_block_number, starknet_client.state_update(current_block_number).await)
+                let state_update = concurrency_limiter
+                    .run(starknet_client.state_update(current_block_number))
+                    .await;
+                (current_block_number, state_update)
             }));
             self.initial_block_number = self.initial_block_number.next();
         }
@@ -200,10 +228,6 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
         cx: &mut std::task::Context<'_>,
         should_poll_again: &mut bool,
     ) -> CentralResult<()> {
-        if self.downloaded_state_updates.len() >= MAX_STATE_UPDATES_TO_STORE_IN_MEMORY {
-            return Ok(());
-        }
-
         let Poll::Ready(Some((block_number, maybe_state_update))) =
             self.download_state_update_tasks.poll_next_unpin(cx) else {
             return Ok(());
@@ -216,8 +240,15 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
             Ok(Some(state_update)) => {
                 let hashes = state_update.state_diff.class_hashes();
                 let n_classes = hashes.len();
+                let state_update_size = state_update_size(&state_update);
+                self.bytes_in_flight += state_update_size;
                 self.classes_to_download.append(&mut VecDeque::from(hashes));
-                self.downloaded_state_updates.push_back((block_number, n_classes, state_update));
+                self.downloaded_state_updates.push_back((
+                    block_number,
+                    n_classes,
+                    state_update_size,
+                    state_update,
+                ));
                 Ok(())
             }
             // Class was not found.
@@ -228,7 +259,7 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
     }
 }
 
-fn client_to_central_state_update(
+pub(crate) fn client_to_central_state_update(
     current_block_number: BlockNumber,
     maybe_client_state_update: CentralResult<(StateUpdate, IndexMap<ClassHash, ApiContractClass>)>,
 ) -> CentralResult<CentralStateUpdate> {
@@ -315,14 +346,60 @@ fn client_to_central_state_update(
     }
 }
 
+pub(crate) fn client_to_thin_state_update(
+    current_block_number: BlockNumber,
+    maybe_client_state_update: ClientResult<Option<StateUpdate>>,
+) -> CentralResult<CentralThinStateUpdate> {
+    let state_update = match maybe_client_state_update {
+        Ok(Some(state_update)) => state_update,
+        Ok(None) => return Err(CentralError::StateUpdateNotFound),
+        Err(err) => {
+            debug!("Received error for state diff {}: {:?}.", current_block_number, err);
+            return Err(CentralError::ClientError(Arc::new(err)));
+        }
+    };
+    let state_diff = state_update.state_diff;
+    let thin_state_diff = ThinStateDiff {
+        deployed_contracts: state_diff
+            .deployed_contracts
+            .into_iter()
+            .map(|deployed_contract| (deployed_contract.address, deployed_contract.class_hash))
+            .collect(),
+        storage_diffs: state_diff
+            .storage_diffs
+            .into_iter()
+            .map(|(address, entries)| {
+                (address, entries.into_iter().map(|se| (se.key, se.value)).collect())
+            })
+            .collect(),
+        declared_classes: state_diff
+            .declared_classes
+            .into_iter()
+            .map(|hash_entry| (hash_entry.class_hash, hash_entry.compiled_class_hash))
+            .collect(),
+        deprecated_declared_classes: state_diff.old_declared_contracts,
+        nonces: state_diff.nonces,
+        replaced_classes: state_diff
+            .replaced_classes
+            .into_iter()
+            .map(|replaced_class| (replaced_class.address, replaced_class.class_hash))
+            .collect(),
+    };
+    let block_hash = state_update.block_hash;
+    debug!("Received new state update of block {current_block_number} with hash {block_hash}.");
+    trace!("Thin state diff: {thin_state_diff:?}.");
+    Ok((current_block_number, block_hash, thin_state_diff))
+}
+
 // Given a class hash, returns the corresponding class definition.
 // First tries to retrieve the class from the storage.
 // If not found in the storage, the class is downloaded.
-#[instrument(skip(starknet_client, storage_reader), level = "debug", err)]
-async fn download_class_if_necessary<TStarknetClient: StarknetClientTrait>(
+#[instrument(skip(starknet_client, storage_reader, concurrency_limiter), level = "debug", err)]
+pub(super) async fn download_class_if_necessary<TStarknetClient: StarknetClientTrait>(
     class_hash: ClassHash,
     starknet_client: Arc<TStarknetClient>,
     storage_reader: StorageReader,
+    concurrency_limiter: Arc<ConcurrencyLimiter>,
 ) -> CentralResult<Option<ApiContractClass>> {
     let txn = storage_reader.begin_ro_txn()?;
     let state_reader = txn.get_state_reader()?;
@@ -345,9 +422,73 @@ async fn download_class_if_necessary<TStarknetClient: StarknetClientTrait>(
 
     // Class not found in storage - download.
     trace!("Downloading class {:?}.", class_hash);
-    let client_class = starknet_client.class_by_hash(class_hash).await.map_err(Arc::new)?;
+    let client_class = concurrency_limiter
+        .run(starknet_client.class_by_hash(class_hash))
+        .await
+        .map_err(Arc::new)?;
     match client_class {
         None => Ok(None),
         Some(class) => Ok(Some(class.into())),
     }
 }
+
+// Estimates the size of the state update in memory by the number of felts it holds.
+fn state_update_size(state_update: &StateUpdate) -> NumberOfBytes {
+    let diff = &state_update.state_diff;
+    let n_storage_entries: usize = diff.storage_diffs.values().map(Vec::len).sum();
+    // The block hash and the roots.
+    let n_felts = 3
+        + diff.storage_diffs.len()
+        + 2 * n_storage_entries
+        + 2 * diff.deployed_contracts.len()
+        + 2 * diff.declared_classes.len()
+        + diff.old_declared_contracts.len()
+        + 2 * diff.nonces.len()
+        + 2 * diff.replaced_classes.len();
+    n_felts * FELT_SIZE
+}
+
+// Estimates the size of the class in memory by the number of its entries, and the lengths of the
+// strings of its ABI or program.
+fn class_size(class: &ApiContractClass) -> NumberOfBytes {
+    match class {
+        ApiContractClass::DeprecatedContractClass(class) => {
+            let n_abi_entries = class.abi.as_ref().map_or(0, Vec::len);
+            let n_entry_points: usize = class.entry_points_by_type.values().map(Vec::len).sum();
+            let program = &class.program;
+            let program_size: NumberOfBytes = [
+                &program.attributes,
+                &program.builtins,
+                &program.compiler_version,
+                &program.data,
+                &program.debug_info,
+                &program.hints,
+                &program.identifiers,
+                &program.main_scope,
+                &program.prime,
+                &program.reference_manager,
+            ]
+            .into_iter()
+            .map(json_size)
+            .sum();
+            (n_abi_entries + n_entry_points) * ENTRY_SIZE + program_size
+        }
+        ApiContractClass::ContractClass(class) => {
+            let n_entry_points: usize = class.entry_point_by_type.values().map(Vec::len).sum();
+            class.sierra_program.len() * FELT_SIZE + n_entry_points * ENTRY_SIZE + class.abi.len()
+        }
+    }
+}
+
+// Estimates the size of the JSON value by the number of its nodes and the lengths of its strings.
+fn json_size(value: &Value) -> NumberOfBytes {
+    match value {
+        Value::String(string) => ENTRY_SIZE + string.len(),
+        Value::Array(values) => ENTRY_SIZE + values.iter().map(json_size).sum::<NumberOfBytes>(),
+        Value::Object(map) => {
+            ENTRY_SIZE
+                + map.iter().map(|(key, value)| key.len() + json_size(value)).sum::<NumberOfBytes>()
+        }
+        _ => ENTRY_SIZE,
+    }
+}
@@ -1,24 +1,43 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use assert_matches::assert_matches;
 use async_stream::stream;
 use async_trait::async_trait;
 use futures::StreamExt;
-use indexmap::IndexMap;
-use papyrus_storage::header::HeaderStorageReader;
+use indexmap::{indexmap, IndexMap};
+use papyrus_storage::body::{BlockExecutionInfo, BodyStorageReader};
+use papyrus_storage::checkpoint::CheckpointStorageReader;
+use papyrus_storage::compiled_class::CasmStorageReader;
+use papyrus_storage::header::{HeaderStorageReader, HeaderStorageWriter};
+use papyrus_storage::revert::RevertStorageWriter;
 use papyrus_storage::state::StateStorageReader;
 use papyrus_storage::test_utils::get_test_storage;
+use papyrus_storage::trace::TraceStorageReader;
 use papyrus_storage::{StorageReader, StorageWriter};
 use starknet_api::block::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber};
-use starknet_api::hash::StarkFelt;
-use starknet_api::stark_felt;
-use starknet_api::state::StateDiff;
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{StateDiff, StateNumber, ThinStateDiff};
+use starknet_api::transaction::TransactionHash;
+use starknet_api::{patricia_key, stark_felt};
+use starknet_client::ReplayStarknetClient;
+use tempfile::NamedTempFile;
 use tokio::sync::Mutex;
 use tracing::{debug, error};
 
 use super::central::BlocksStream;
-use crate::sources::central::{MockCentralSourceTrait, StateUpdatesStream};
-use crate::{CentralError, CentralSourceTrait, GenericStateSync, StateSyncResult, SyncConfig};
+use crate::sources::central::{
+    BodiesStream, ClassesStream, CompiledClassesStream, HeadersStream, MockCentralSourceTrait,
+    StateUpdatesStream, ThinStateUpdatesStream, TracesStream,
+};
+use crate::{
+    AdaptiveConcurrencyConfig, CentralError, CentralSourceTrait, CheckpointConfig,
+    GenericCentralSource, GenericStateSync, StateSyncError, StateSyncResult, StateSyncSubscriber,
+    StopAt, SyncConfig, SyncControlError,
+};
 
 const SYNC_SLEEP_DURATION: Duration = Duration::new(0, 1000 * 1000 * 100); // 100ms
 const DURATION_BEFORE_CHECKING_STORAGE: Duration = Duration::new(0, 1000 * 1000 * 100); // 100ms
@@ -68,22 +87,115 @@ async fn run_sync(
     writer: StorageWriter,
     central: impl CentralSourceTrait + Send + Sync + 'static,
 ) -> StateSyncResult {
-    let mut state_sync = GenericStateSync {
-        config: SyncConfig {
-            block_propagation_sleep_duration: SYNC_SLEEP_DURATION,
-            recoverable_error_sleep_duration: SYNC_SLEEP_DURATION,
-            blocks_max_stream_size: STREAM_SIZE,
-            state_updates_max_stream_size: STREAM_SIZE,
-        },
-        central_source: Arc::new(central),
-        reader,
-        writer,
-    };
+    run_sync_until(reader, writer, central, None, false).await
+}
+
+// Runs sync loop with a mocked central until reaching the block to stop at.
+async fn run_sync_until(
+    reader: StorageReader,
+    writer: StorageWriter,
+    central: impl CentralSourceTrait + Send + Sync + 'static,
+    stop_at: Option<StopAt>,
+    one_shot: bool,
+) -> StateSyncResult {
+    let config = SyncConfig { stop_at, one_shot, ..get_test_sync_config() };
+    run_sync_with_config(reader, writer, central, config).await
+}
+
+fn get_test_sync_config() -> SyncConfig {
+    SyncConfig {
+        block_propagation_sleep_duration: SYNC_SLEEP_DURATION,
+        recoverable_error_sleep_duration: SYNC_SLEEP_DURATION,
+        blocks_max_stream_size: STREAM_SIZE,
+        state_updates_max_stream_size: STREAM_SIZE,
+        stop_at: None,
+        one_shot: false,
+        checkpoint: None,
+        separate_class_sync: false,
+        header_first_sync: false,
+        trace_sync: false,
+        compiled_class_sync: false,
+    }
+}
+
+async fn run_sync_with_config(
+    reader: StorageReader,
+    writer: StorageWriter,
+    central: impl CentralSourceTrait + Send + Sync + 'static,
+    config: SyncConfig,
+) -> StateSyncResult {
+    run_sync_with_subscriber(reader, writer, central, config, None).await
+}
+
+async fn run_sync_with_subscriber(
+    reader: StorageReader,
+    writer: StorageWriter,
+    central: impl CentralSourceTrait + Send + Sync + 'static,
+    config: SyncConfig,
+    subscriber: Option<Arc<dyn StateSyncSubscriber>>,
+) -> StateSyncResult {
+    let mut state_sync = GenericStateSync::new(config, central, reader, writer);
+    if let Some(subscriber) = subscriber {
+        state_sync.add_subscriber(subscriber);
+    }
 
     state_sync.run().await?;
     Ok(())
 }
 
+// Mocks a central with a chain of n_blocks blocks.
+fn mock_chain(n_blocks: u64) -> MockCentralSourceTrait {
+    let mut mock = MockCentralSourceTrait::new();
+    mock.expect_get_block_marker().returning(move || Ok(BlockNumber(n_blocks)));
+    mock.expect_stream_new_blocks().returning(move |initial, up_to| {
+        let blocks_stream: BlocksStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                if block_number.0 >= n_blocks {
+                    yield Err(CentralError::BlockNotFound { block_number });
+                }
+                let header = BlockHeader {
+                    block_number,
+                    block_hash: create_block_hash(block_number, false),
+                    parent_hash: create_block_hash(block_number.prev().unwrap_or_default(), false),
+                    ..BlockHeader::default()
+                };
+                yield Ok((
+                    block_number,
+                    Block { header, body: BlockBody::default() },
+                    BlockExecutionInfo::default(),
+                ));
+            }
+        }
+        .boxed();
+        blocks_stream
+    });
+    mock.expect_stream_state_updates().returning(move |initial, up_to| {
+        let state_stream: StateUpdatesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                if block_number.0 >= n_blocks {
+                    yield Err(CentralError::BlockNotFound { block_number })
+                }
+                yield Ok((
+                    block_number,
+                    create_block_hash(block_number, false),
+                    StateDiff::default(),
+                    IndexMap::new(),
+                ));
+            }
+        }
+        .boxed();
+        state_stream
+    });
+    mock.expect_get_block_hash().returning(|bn| Ok(Some(create_block_hash(bn, false))));
+    mock
+}
+
+fn assert_markers(reader: &StorageReader, expected_marker: BlockNumber) {
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), expected_marker);
+    assert_eq!(txn.get_state_marker().unwrap(), expected_marker);
+}
+
 #[tokio::test]
 async fn sync_empty_chain() {
     let _ = simple_logger::init_with_env();
@@ -130,7 +242,11 @@ async fn sync_happy_flow() {
                     parent_hash: create_block_hash(block_number.prev().unwrap_or_default(), false),
                     ..BlockHeader::default()
                 };
-                yield Ok((block_number, Block { header, body: BlockBody::default() }));
+                yield Ok((
+                    block_number,
+                    Block { header, body: BlockBody::default() },
+                    BlockExecutionInfo::default(),
+                ));
             }
         }
         .boxed();
@@ -187,6 +303,436 @@ async fn sync_happy_flow() {
     }
 }
 
+#[tokio::test]
+async fn sync_stops_at_block_number() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let stop_at = Some(StopAt::BlockNumber(BlockNumber(2)));
+    run_sync_until(reader.clone(), writer, mock_chain(5), stop_at, false).await.unwrap();
+    assert_markers(&reader, BlockNumber(3));
+}
+
+#[tokio::test]
+async fn sync_stops_at_block_hash() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let stop_at = Some(StopAt::BlockHash(create_block_hash(BlockNumber(3), false)));
+    run_sync_until(reader.clone(), writer, mock_chain(5), stop_at, false).await.unwrap();
+    assert_markers(&reader, BlockNumber(4));
+}
+
+#[tokio::test]
+async fn sync_one_shot() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    run_sync_until(reader.clone(), writer, mock_chain(5), None, true).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+}
+
+// Checks that the headers and the state diffs are synced up to the marker.
+async fn reaches_markers(reader: StorageReader, expected_marker: BlockNumber) -> bool {
+    check_storage(reader, Duration::from_millis(500), move |reader| {
+        let txn = reader.begin_ro_txn().unwrap();
+        if txn.get_header_marker().unwrap() == expected_marker
+            && txn.get_state_marker().unwrap() == expected_marker
+        {
+            return CheckStoragePredicateResult::Passed;
+        }
+        CheckStoragePredicateResult::InProgress
+    })
+    .await
+}
+
+#[tokio::test]
+async fn sync_control_pause_revert_and_resume() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let mut sync =
+        GenericStateSync::new(get_test_sync_config(), mock_chain(5), reader.clone(), writer);
+    let control = sync.control();
+
+    let control_future = async {
+        assert!(reaches_markers(reader.clone(), BlockNumber(5)).await);
+        control.pause();
+        assert!(control.is_paused());
+        control.revert(BlockNumber(2)).await.unwrap();
+        // The reverted blocks aren't synced again while the sync is paused.
+        tokio::time::sleep(SYNC_SLEEP_DURATION * 2).await;
+        assert_markers(&reader, BlockNumber(2));
+        control.resume();
+        assert!(reaches_markers(reader.clone(), BlockNumber(5)).await);
+    };
+    tokio::select! {
+        sync_result = sync.run() => panic!("The sync stopped: {sync_result:?}."),
+        () = control_future => {}
+    }
+
+    drop(sync);
+    assert_eq!(control.revert(BlockNumber(2)).await, Err(SyncControlError::NotRunning));
+}
+
+#[tokio::test]
+async fn sync_replayed_chain() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let fixtures_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources").join("replay");
+    let central = GenericCentralSource::with_starknet_client(
+        10,
+        AdaptiveConcurrencyConfig { min_concurrent_requests: 10, target_latency_millis: 1000 },
+        usize::MAX,
+        ReplayStarknetClient::new(fixtures_dir).unwrap(),
+        reader.clone(),
+    );
+    run_sync_until(reader.clone(), writer, central, None, true).await.unwrap();
+    assert_markers(&reader, BlockNumber(2));
+
+    let header = reader.begin_ro_txn().unwrap().get_block_header(BlockNumber(1)).unwrap().unwrap();
+    assert_eq!(header.block_hash, BlockHash(stark_felt!("0x11")));
+    assert_eq!(header.parent_hash, BlockHash(stark_felt!("0x10")));
+}
+
+#[tokio::test]
+async fn sync_classes_separately() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let class_hash = ClassHash(stark_felt!("0x1"));
+    let mut mock = mock_chain(5);
+    mock.expect_stream_thin_state_updates().returning(move |initial, up_to| {
+        let thin_state_stream: ThinStateUpdatesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let mut thin_state_diff = ThinStateDiff::default();
+                if block_number == BlockNumber(1) {
+                    thin_state_diff.deprecated_declared_classes.push(class_hash);
+                }
+                yield Ok((block_number, create_block_hash(block_number, false), thin_state_diff));
+            }
+        }
+        .boxed();
+        thin_state_stream
+    });
+    mock.expect_stream_classes().returning(move |initial, up_to| {
+        let classes_stream: ClassesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let mut deprecated_classes = IndexMap::new();
+                if block_number == BlockNumber(1) {
+                    deprecated_classes.insert(class_hash, DeprecatedContractClass::default());
+                }
+                yield Ok((block_number, IndexMap::new(), deprecated_classes));
+            }
+        }
+        .boxed();
+        classes_stream
+    });
+    let config = SyncConfig { one_shot: true, separate_class_sync: true, ..get_test_sync_config() };
+    run_sync_with_config(reader.clone(), writer, mock, config).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_class_marker().unwrap(), BlockNumber(5));
+    let state_reader = txn.get_state_reader().unwrap();
+    let state_number = StateNumber::right_after_block(BlockNumber(1));
+    assert_eq!(
+        state_reader.get_deprecated_class_definition_at(state_number, &class_hash).unwrap(),
+        Some(DeprecatedContractClass::default())
+    );
+}
+
+#[tokio::test]
+async fn thin_state_diff_of_reverted_block_is_dropped() {
+    let _ = simple_logger::init_with_env();
+    let (reader, mut writer) = get_test_storage();
+    // The header the thin state diff was requested for is replaced by the header of a new block.
+    let reverted_header = BlockHeader {
+        block_hash: create_block_hash(BlockNumber(0), true),
+        ..BlockHeader::default()
+    };
+    let header = BlockHeader {
+        block_hash: create_block_hash(BlockNumber(0), false),
+        ..BlockHeader::default()
+    };
+    let (txn, _) = writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &reverted_header)
+        .unwrap()
+        .revert_block(BlockNumber(0), true)
+        .unwrap();
+    txn.append_header(BlockNumber(0), &header).unwrap().commit().unwrap();
+
+    let mut sync =
+        GenericStateSync::new(get_test_sync_config(), mock_chain(1), reader.clone(), writer);
+    sync.store_thin_state_diff(
+        BlockNumber(0),
+        reverted_header.block_hash,
+        ThinStateDiff::default(),
+    )
+    .unwrap();
+    assert_eq!(reader.begin_ro_txn().unwrap().get_state_marker().unwrap(), BlockNumber(0));
+
+    sync.store_thin_state_diff(BlockNumber(0), header.block_hash, ThinStateDiff::default())
+        .unwrap();
+    assert_eq!(reader.begin_ro_txn().unwrap().get_state_marker().unwrap(), BlockNumber(1));
+}
+
+#[tokio::test]
+async fn sync_headers_first() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let mut mock = mock_chain(5);
+    mock.expect_stream_headers().returning(move |initial, up_to| {
+        let headers_stream: HeadersStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let header = BlockHeader {
+                    block_number,
+                    block_hash: create_block_hash(block_number, false),
+                    parent_hash: create_block_hash(block_number.prev().unwrap_or_default(), false),
+                    ..BlockHeader::default()
+                };
+                yield Ok((block_number, header));
+            }
+        }
+        .boxed();
+        headers_stream
+    });
+    mock.expect_stream_bodies().returning(move |initial, up_to| {
+        let bodies_stream: BodiesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let block_hash = create_block_hash(block_number, false);
+                let execution_info = BlockExecutionInfo::default();
+                yield Ok((block_number, block_hash, BlockBody::default(), execution_info));
+            }
+        }
+        .boxed();
+        bodies_stream
+    });
+    let config = SyncConfig { one_shot: true, header_first_sync: true, ..get_test_sync_config() };
+    run_sync_with_config(reader.clone(), writer, mock, config).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+    assert_eq!(reader.begin_ro_txn().unwrap().get_body_marker().unwrap(), BlockNumber(5));
+}
+
+#[tokio::test]
+async fn sync_traces() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let mut mock = mock_chain(5);
+    mock.expect_stream_traces().returning(move |initial, up_to| {
+        let traces_stream: TracesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                yield Ok((block_number, vec![], vec![]));
+            }
+        }
+        .boxed();
+        traces_stream
+    });
+    let config = SyncConfig { one_shot: true, trace_sync: true, ..get_test_sync_config() };
+    run_sync_with_config(reader.clone(), writer, mock, config).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_trace_marker().unwrap(), BlockNumber(5));
+    assert_eq!(txn.get_block_traces(BlockNumber(4)).unwrap(), Some(vec![]));
+}
+
+#[tokio::test]
+async fn sync_traces_mismatching_the_body() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let mut mock = mock_chain(5);
+    // The first traces of block 0 trace a transaction the stored body doesn't have.
+    let mut is_first_stream = true;
+    mock.expect_stream_traces().returning(move |initial, up_to| {
+        let mismatch = std::mem::replace(&mut is_first_stream, false);
+        let traces_stream: TracesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let transaction_hashes =
+                    if mismatch { vec![TransactionHash::default()] } else { vec![] };
+                yield Ok((block_number, transaction_hashes, vec![]));
+            }
+        }
+        .boxed();
+        traces_stream
+    });
+    let config = SyncConfig { one_shot: true, trace_sync: true, ..get_test_sync_config() };
+    run_sync_with_config(reader.clone(), writer, mock, config).await.unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_trace_marker().unwrap(), BlockNumber(5));
+    assert_eq!(txn.get_block_traces(BlockNumber(0)).unwrap(), Some(vec![]));
+}
+
+#[tokio::test]
+async fn sync_compiled_classes() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let mut mock = mock_chain(5);
+    mock.expect_stream_compiled_classes().returning(move |initial, up_to| {
+        let compiled_classes_stream: CompiledClassesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                yield Ok((block_number, IndexMap::new()));
+            }
+        }
+        .boxed();
+        compiled_classes_stream
+    });
+    let config = SyncConfig { one_shot: true, compiled_class_sync: true, ..get_test_sync_config() };
+    run_sync_with_config(reader.clone(), writer, mock, config).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+    assert_eq!(reader.begin_ro_txn().unwrap().get_compiled_class_marker().unwrap(), BlockNumber(5));
+}
+
+fn checkpoint_config(block_hash: BlockHash, state_snapshot: Option<PathBuf>) -> SyncConfig {
+    let checkpoint = CheckpointConfig { block_number: BlockNumber(2), block_hash, state_snapshot };
+    SyncConfig { one_shot: true, checkpoint: Some(checkpoint), ..get_test_sync_config() }
+}
+
+#[tokio::test]
+async fn sync_from_checkpoint() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let config = checkpoint_config(create_block_hash(BlockNumber(2), false), None);
+    run_sync_with_config(reader.clone(), writer, mock_chain(5), config).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_checkpoint().unwrap(), Some(BlockNumber(2)));
+    assert!(txn.get_block_header(BlockNumber(1)).unwrap().is_none());
+    assert!(txn.get_block_header(BlockNumber(2)).unwrap().is_some());
+}
+
+#[tokio::test]
+async fn sync_from_checkpoint_with_state_snapshot() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x11"));
+    let nonce = Nonce(stark_felt!("0x12"));
+    let state_snapshot =
+        StateDiff { nonces: indexmap! { address => nonce }, ..StateDiff::default() };
+    let mut snapshot_file = NamedTempFile::new().unwrap();
+    serde_json::to_writer(&mut snapshot_file, &state_snapshot).unwrap();
+
+    let config = checkpoint_config(
+        create_block_hash(BlockNumber(2), false),
+        Some(snapshot_file.path().to_path_buf()),
+    );
+    run_sync_with_config(reader.clone(), writer, mock_chain(5), config).await.unwrap();
+    assert_markers(&reader, BlockNumber(5));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    let state_number = StateNumber::right_after_block(BlockNumber(4));
+    assert_eq!(
+        txn.get_state_reader().unwrap().get_nonce_at(state_number, &address).unwrap(),
+        Some(nonce)
+    );
+}
+
+#[tokio::test]
+async fn sync_from_checkpoint_with_wrong_block_hash() {
+    let _ = simple_logger::init_with_env();
+    let (reader, writer) = get_test_storage();
+    let config = checkpoint_config(create_block_hash(BlockNumber(2), true), None);
+    let res = run_sync_with_config(reader.clone(), writer, mock_chain(5), config).await;
+    assert_matches!(
+        res,
+        Err(StateSyncError::CheckpointBlockHashMismatch { block_number: BlockNumber(2), .. })
+    );
+    assert_eq!(reader.begin_ro_txn().unwrap().get_header_marker().unwrap(), BlockNumber(2));
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Notification {
+    BlockStored(BlockNumber, BlockHash),
+    StateDiffStored(BlockNumber, BlockHash),
+    BlockReverted(BlockNumber, BlockHash),
+}
+
+// Records the notifications of the sync.
+#[derive(Default)]
+struct RecordingSubscriber {
+    notifications: std::sync::Mutex<Vec<Notification>>,
+}
+
+impl StateSyncSubscriber for RecordingSubscriber {
+    fn on_block_stored(&self, block_number: BlockNumber, block: &Block) {
+        assert_eq!(block.header.block_number, block_number);
+        let notification = Notification::BlockStored(block_number, block.header.block_hash);
+        self.notifications.lock().unwrap().push(notification);
+    }
+
+    fn on_state_diff_stored(
+        &self,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        _state_diff: &StateDiff,
+        _deployed_contract_class_definitions: &IndexMap<ClassHash, DeprecatedContractClass>,
+    ) {
+        let notification = Notification::StateDiffStored(block_number, block_hash);
+        self.notifications.lock().unwrap().push(notification);
+    }
+
+    fn on_block_reverted(&self, block_number: BlockNumber, block_hash: BlockHash) {
+        let notification = Notification::BlockReverted(block_number, block_hash);
+        self.notifications.lock().unwrap().push(notification);
+    }
+}
+
+#[tokio::test]
+async fn sync_notifies_subscribers() {
+    let _ = simple_logger::init_with_env();
+    let (reader, mut writer) = get_test_storage();
+
+    // Blocks that the central doesn't have, so they are reverted.
+    let mut txn = writer.begin_rw_txn().unwrap();
+    for block_number in BlockNumber(0).iter_up_to(BlockNumber(2)) {
+        let header = BlockHeader {
+            block_number,
+            block_hash: create_block_hash(block_number, true),
+            ..BlockHeader::default()
+        };
+        txn = txn.append_header(block_number, &header).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let subscriber = Arc::new(RecordingSubscriber::default());
+    let config = SyncConfig { one_shot: true, ..get_test_sync_config() };
+    run_sync_with_subscriber(reader, writer, mock_chain(3), config, Some(subscriber.clone()))
+        .await
+        .unwrap();
+
+    let notifications = subscriber.notifications.lock().unwrap();
+    // The reverts precede the stored data, from the last block down.
+    let reverted = (0..3)
+        .rev()
+        .map(|i| {
+            Notification::BlockReverted(BlockNumber(i), create_block_hash(BlockNumber(i), true))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(notifications[..3], reverted[..]);
+
+    // Blocks and state diffs are stored concurrently, so their notifications are interleaved.
+    let stored_blocks = notifications[3..]
+        .iter()
+        .filter(|notification| matches!(notification, Notification::BlockStored(..)))
+        .collect::<Vec<_>>();
+    let expected_stored_blocks = (0..3)
+        .map(|i| {
+            Notification::BlockStored(BlockNumber(i), create_block_hash(BlockNumber(i), false))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(stored_blocks, expected_stored_blocks.iter().collect::<Vec<_>>());
+    let stored_state_diffs = notifications[3..]
+        .iter()
+        .filter(|notification| matches!(notification, Notification::StateDiffStored(..)))
+        .collect::<Vec<_>>();
+    let expected_stored_state_diffs = (0..3)
+        .map(|i| {
+            Notification::StateDiffStored(BlockNumber(i), create_block_hash(BlockNumber(i), false))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(stored_state_diffs, expected_stored_state_diffs.iter().collect::<Vec<_>>());
+    assert_eq!(notifications.len(), 9);
+}
+
 #[tokio::test]
 async fn sync_with_revert() {
     let _ = simple_logger::init_with_env();
@@ -373,7 +919,8 @@ async fn sync_with_revert() {
                             block_hash: create_block_hash(i, false),
                             parent_hash: create_block_hash(i.prev().unwrap_or_default(), false),
                             ..BlockHeader::default()};
-                        yield Ok((i,Block{header, body: BlockBody::default()}));
+                        let execution_info = BlockExecutionInfo::default();
+                        yield Ok((i, Block{header, body: BlockBody::default()}, execution_info));
                     }
                 }
                 .boxed(),
@@ -387,7 +934,8 @@ async fn sync_with_revert() {
                             block_hash: create_block_hash(i, i.0 >= CHAIN_FORK_BLOCK_NUMBER),
                             parent_hash: create_block_hash(i.prev().unwrap_or_default(), i.0 > CHAIN_FORK_BLOCK_NUMBER),
                             ..BlockHeader::default()};
-                        yield Ok((i, Block{header, body: BlockBody::default()}));
+                        let execution_info = BlockExecutionInfo::default();
+                        yield Ok((i, Block{header, body: BlockBody::default()}, execution_info));
                     }
                 }
                 .boxed(),
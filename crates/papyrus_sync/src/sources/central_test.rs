@@ -18,9 +18,12 @@ use starknet_client::{
 };
 use tokio_stream::StreamExt;
 
-use crate::sources::central::{CentralError, CentralSourceTrait, GenericCentralSource};
+use crate::sources::central::{
+    CentralError, CentralSourceTrait, ConcurrencyLimiter, GenericCentralSource,
+};
 
 const TEST_CONCURRENT_REQUESTS: usize = 300;
+const TEST_MAX_BYTES_IN_FLIGHT: usize = 1 << 20;
 
 #[tokio::test]
 async fn last_block_number() {
@@ -32,7 +35,8 @@ async fn last_block_number() {
 
     let central_source = GenericCentralSource {
         starknet_client: Arc::new(mock),
-        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: TEST_MAX_BYTES_IN_FLIGHT,
         storage_reader: get_test_storage().0,
     };
 
@@ -54,7 +58,8 @@ async fn stream_block_headers() {
             .returning(|_block_number| Ok(Some(Block::default())));
     }
     let central_source = GenericCentralSource {
-        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: TEST_MAX_BYTES_IN_FLIGHT,
         starknet_client: Arc::new(mock),
         storage_reader: get_test_storage().0,
     };
@@ -63,7 +68,7 @@ async fn stream_block_headers() {
     let stream =
         central_source.stream_new_blocks(expected_block_num, BlockNumber(END_BLOCK_NUMBER));
     pin_mut!(stream);
-    while let Some(Ok((block_number, _block))) = stream.next().await {
+    while let Some(Ok((block_number, _block, _execution_info))) = stream.next().await {
         assert_eq!(expected_block_num, block_number);
         expected_block_num = expected_block_num.next();
     }
@@ -89,7 +94,8 @@ async fn stream_block_headers_some_are_missing() {
         .times(1)
         .returning(|_| Ok(None));
     let central_source = GenericCentralSource {
-        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: TEST_MAX_BYTES_IN_FLIGHT,
         starknet_client: Arc::new(mock),
         storage_reader: get_test_storage().0,
     };
@@ -136,7 +142,8 @@ async fn stream_block_headers_error() {
         },
     );
     let central_source = GenericCentralSource {
-        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: TEST_MAX_BYTES_IN_FLIGHT,
         starknet_client: Arc::new(mock),
         storage_reader: get_test_storage().0,
     };
@@ -165,6 +172,24 @@ async fn stream_block_headers_error() {
     assert_eq!(expected_block_num, BlockNumber(ERROR_BLOCK_NUMBER + 1));
 }
 
+// The feeder gateway serves the headers only with the bodies, so they aren't downloaded twice.
+#[tokio::test]
+async fn stream_headers_not_supported() {
+    let mut mock = MockStarknetClientTrait::new();
+    mock.expect_block().never();
+    let central_source = GenericCentralSource {
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: TEST_MAX_BYTES_IN_FLIGHT,
+        starknet_client: Arc::new(mock),
+        storage_reader: get_test_storage().0,
+    };
+
+    let stream = central_source.stream_headers(BlockNumber(0), BlockNumber(1));
+    pin_mut!(stream);
+    assert_matches!(stream.next().await, Some(Err(CentralError::SeparateHeaderSyncNotSupported)));
+    assert!(stream.next().await.is_none());
+}
+
 #[tokio::test]
 async fn stream_state_updates() {
     const START_BLOCK_NUMBER: u64 = 5;
@@ -271,7 +296,8 @@ async fn stream_state_updates() {
     });
 
     let central_source = GenericCentralSource {
-        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: TEST_MAX_BYTES_IN_FLIGHT,
         starknet_client: Arc::new(mock),
         storage_reader: get_test_storage().0,
     };
@@ -352,3 +378,35 @@ async fn stream_state_updates() {
 
     assert!(stream.next().await.is_none());
 }
+
+#[tokio::test]
+async fn stream_state_updates_with_small_bytes_budget() {
+    const START_BLOCK_NUMBER: u64 = 5;
+    const END_BLOCK_NUMBER: u64 = 9;
+
+    let mut mock = MockStarknetClientTrait::new();
+    for i in START_BLOCK_NUMBER..END_BLOCK_NUMBER {
+        mock.expect_state_update()
+            .with(predicate::eq(BlockNumber(i)))
+            .times(1)
+            .returning(|_x| Ok(Some(StateUpdate::default())));
+    }
+    // Every downloaded state update exceeds the budget, so they are downloaded one at a time.
+    let central_source = GenericCentralSource {
+        concurrency_limiter: Arc::new(ConcurrencyLimiter::fixed(TEST_CONCURRENT_REQUESTS)),
+        max_bytes_in_flight: 1,
+        starknet_client: Arc::new(mock),
+        storage_reader: get_test_storage().0,
+    };
+
+    let mut expected_block_num = BlockNumber(START_BLOCK_NUMBER);
+    let stream =
+        central_source.stream_state_updates(expected_block_num, BlockNumber(END_BLOCK_NUMBER));
+    pin_mut!(stream);
+    while let Some(state_update) = stream.next().await {
+        let (block_number, _, _, _) = state_update.unwrap();
+        assert_eq!(expected_block_num, block_number);
+        expected_block_num = expected_block_num.next();
+    }
+    assert_eq!(expected_block_num, BlockNumber(END_BLOCK_NUMBER));
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use futures_util::StreamExt;
+use indexmap::IndexMap;
+use papyrus_storage::StorageReader;
+use serde::de::DeserializeOwned;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkHash;
+use starknet_client::{GenericContractClass, StateUpdate};
+use tempfile::TempDir;
+use tracing::{debug, trace};
+
+use super::central::{
+    client_to_central_block, client_to_central_state_update, get_stored_class, ApiContractClass,
+    BlocksStream, CentralResult, CentralStateUpdate, StateUpdatesStream,
+};
+use crate::{CentralError, CentralSourceTrait};
+
+const BLOCKS_DIR: &str = "blocks";
+const STATE_UPDATES_DIR: &str = "state_updates";
+const CLASSES_DIR: &str = "classes";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(thiserror::Error, Debug)]
+pub enum FileSourceError {
+    #[error("Failed to read {}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Failed to parse {}: {source}", path.display())]
+    Serde { path: PathBuf, source: serde_json::Error },
+    #[error("Unexpected file name {}.", path.display())]
+    BadFileName { path: PathBuf },
+    #[error("Block {block_number} is missing from the archive.")]
+    MissingBlock { block_number: BlockNumber },
+    #[error("The state update of block {block_number} is missing from the archive.")]
+    MissingStateUpdate { block_number: BlockNumber },
+    #[error("Class {class_hash:?} is neither in the archive nor in the storage.")]
+    MissingClass { class_hash: ClassHash },
+}
+
+impl From<FileSourceError> for CentralError {
+    fn from(err: FileSourceError) -> Self {
+        CentralError::FileSourceError(Arc::new(err))
+    }
+}
+
+/// A source that reads feeder gateway responses that were dumped to files, instead of requesting
+/// them from the feeder gateway. The files are read from a directory, or from a tar archive
+/// (optionally gzipped) of such a directory, with the following layout:
+/// - `blocks/<block number>.json`: responses of `get_block`.
+/// - `state_updates/<block number>.json`: responses of `get_state_update`.
+/// - `classes/<class hash>.json`: responses of `get_class_by_hash`. Classes that are already in the
+///   storage may be omitted.
+pub struct FileSource {
+    root: PathBuf,
+    block_marker: BlockNumber,
+    classes: HashMap<ClassHash, PathBuf>,
+    storage_reader: StorageReader,
+    // Holds the extracted archive, which is deleted when the source is dropped.
+    _extracted_archive: Option<TempDir>,
+}
+
+impl FileSource {
+    pub fn new(path: &Path, storage_reader: StorageReader) -> Result<Self, FileSourceError> {
+        let (root, extracted_archive) = if path.is_dir() {
+            (path.to_path_buf(), None)
+        } else {
+            let extracted_archive = extract_archive(path)?;
+            let root = find_root(extracted_archive.path())?;
+            (root, Some(extracted_archive))
+        };
+
+        let mut block_marker = BlockNumber::default();
+        for (stem, _) in list_dir(&root.join(BLOCKS_DIR))? {
+            if let Ok(block_number) = stem.parse::<u64>() {
+                block_marker = block_marker.max(BlockNumber(block_number).next());
+            }
+        }
+        let mut classes = HashMap::new();
+        for (stem, path) in list_dir(&root.join(CLASSES_DIR))? {
+            let class_hash = StarkHash::try_from(stem.as_str())
+                .map_err(|_| FileSourceError::BadFileName { path: path.clone() })?;
+            classes.insert(ClassHash(class_hash), path);
+        }
+        debug!(
+            "Opened {} with blocks up to {} and {} classes.",
+            root.display(),
+            block_marker,
+            classes.len()
+        );
+
+        Ok(FileSource {
+            root,
+            block_marker,
+            classes,
+            storage_reader,
+            _extracted_archive: extracted_archive,
+        })
+    }
+
+    async fn block(&self, block_number: BlockNumber) -> CentralResult<starknet_client::Block> {
+        let path = self.root.join(BLOCKS_DIR).join(format!("{block_number}.json"));
+        Ok(read_json(path).await?.ok_or(FileSourceError::MissingBlock { block_number })?)
+    }
+
+    async fn state_update(&self, block_number: BlockNumber) -> CentralResult<CentralStateUpdate> {
+        let path = self.root.join(STATE_UPDATES_DIR).join(format!("{block_number}.json"));
+        let state_update: StateUpdate =
+            read_json(path).await?.ok_or(FileSourceError::MissingStateUpdate { block_number })?;
+        let mut classes = IndexMap::new();
+        for class_hash in state_update.state_diff.class_hashes() {
+            let class = self.class(class_hash).await?;
+            classes.insert(class_hash, class);
+        }
+        client_to_central_state_update(block_number, Ok((state_update, classes)))
+    }
+
+    async fn class(&self, class_hash: ClassHash) -> CentralResult<ApiContractClass> {
+        if let Some(class) = get_stored_class(&self.storage_reader, &class_hash)? {
+            trace!("Class {:?} retrieved from storage.", class_hash);
+            return Ok(class);
+        }
+        let missing_class = || FileSourceError::MissingClass { class_hash };
+        let path = self.classes.get(&class_hash).ok_or_else(missing_class)?;
+        let class: GenericContractClass =
+            read_json(path.clone()).await?.ok_or_else(missing_class)?;
+        Ok(class.into())
+    }
+}
+
+#[async_trait]
+impl CentralSourceTrait for FileSource {
+    async fn get_block_marker(&self) -> Result<BlockNumber, CentralError> {
+        Ok(self.block_marker)
+    }
+
+    async fn get_block_hash(
+        &self,
+        block_number: BlockNumber,
+    ) -> Result<Option<BlockHash>, CentralError> {
+        match self.block(block_number).await {
+            Ok(block) => Ok(Some(block.block_hash)),
+            Err(CentralError::FileSourceError(err))
+                if matches!(*err, FileSourceError::MissingBlock { .. }) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn stream_new_blocks(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> BlocksStream<'_> {
+        stream! {
+            for block_number in initial_block_number.iter_up_to(up_to_block_number) {
+                let maybe_block = self
+                    .block(block_number)
+                    .await
+                    .and_then(|block| client_to_central_block(block_number, Ok(Some(block))));
+                match maybe_block {
+                    Ok((block, execution_info)) => {
+                        yield Ok((block_number, block, execution_info));
+                    }
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_state_updates(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> StateUpdatesStream<'_> {
+        stream! {
+            for block_number in initial_block_number.iter_up_to(up_to_block_number) {
+                match self.state_update(block_number).await {
+                    Ok(state_update) => {
+                        yield Ok(state_update);
+                    }
+                    Err(err) => {
+                        debug!("Received error for state diff {}: {:?}.", block_number, err);
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+// Reads and parses the file on a blocking thread, so large files don't hold up the runtime. Returns
+// None if the file doesn't exist.
+async fn read_json<T: DeserializeOwned + Send + 'static>(
+    path: PathBuf,
+) -> Result<Option<T>, FileSourceError> {
+    let join_path = path.clone();
+    tokio::task::spawn_blocking(move || {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(FileSourceError::Io { path, source }),
+        };
+        serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .map_err(|source| FileSourceError::Serde { path, source })
+    })
+    .await
+    .map_err(|err| FileSourceError::Io {
+        path: join_path,
+        source: io::Error::new(ErrorKind::Other, err),
+    })?
+}
+
+// Returns the stems and paths of the files in the directory, or nothing if it doesn't exist.
+fn list_dir(dir: &Path) -> Result<Vec<(String, PathBuf)>, FileSourceError> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let io_err = |source| FileSourceError::Io { path: dir.into(), source };
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).map_err(io_err)? {
+        let path = entry.map_err(io_err)?.path();
+        let stem = path.file_stem().and_then(|stem| stem.to_str());
+        match stem {
+            Some(stem) if path.is_file() => files.push((stem.to_owned(), path.clone())),
+            _ => trace!("Skipping {}.", path.display()),
+        }
+    }
+    Ok(files)
+}
+
+// Extracts a tar archive, which may be gzipped, into a temporary directory.
+fn extract_archive(path: &Path) -> Result<TempDir, FileSourceError> {
+    let io_err = |source| FileSourceError::Io { path: path.into(), source };
+    let mut file = File::open(path).map_err(io_err)?;
+    let mut magic = [0; 2];
+    let is_gzipped = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.seek(SeekFrom::Start(0)).map_err(io_err)?;
+
+    let extracted_archive = TempDir::new().map_err(io_err)?;
+    debug!("Extracting {} into {}.", path.display(), extracted_archive.path().display());
+    let reader: Box<dyn Read> =
+        if is_gzipped { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+    tar::Archive::new(reader).unpack(extracted_archive.path()).map_err(io_err)?;
+    Ok(extracted_archive)
+}
+
+// Archives are usually created from a directory, so the data may be nested in a single top-level
+// directory.
+fn find_root(extracted_archive: &Path) -> Result<PathBuf, FileSourceError> {
+    if extracted_archive.join(BLOCKS_DIR).exists() {
+        return Ok(extracted_archive.to_path_buf());
+    }
+    let io_err = |source| FileSourceError::Io { path: extracted_archive.into(), source };
+    let entries = fs::read_dir(extracted_archive)
+        .map_err(io_err)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err)?;
+    match entries.as_slice() {
+        [single_dir] if single_dir.is_dir() => Ok(single_dir.clone()),
+        _ => Ok(extracted_archive.to_path_buf()),
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use assert_matches::assert_matches;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::StreamExt;
+use indexmap::IndexMap;
+use papyrus_storage::test_utils::get_test_storage;
+use serde::Serialize;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::deprecated_contract_class::ContractClass as ApiDeprecatedContractClass;
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::{patricia_key, stark_felt};
+use starknet_client::{Block, DeployedContract, DeprecatedContractClass, StateUpdate};
+use tempfile::{tempdir, NamedTempFile, TempDir};
+
+use crate::sources::file::{FileSource, FileSourceError};
+use crate::sources::{CentralError, CentralSourceTrait};
+
+const BLOCK_HASH: &str = "0x1";
+const CLASS_HASH: &str = "0x2";
+
+fn write_json(path: &Path, value: &impl Serialize) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    serde_json::to_writer(File::create(path).unwrap(), value).unwrap();
+}
+
+// Dumps block 0, with a contract whose class is implicitly declared by the deployment.
+fn dump_block(with_class: bool) -> TempDir {
+    let dir = tempdir().unwrap();
+    let block = Block {
+        block_hash: BlockHash(stark_felt!(BLOCK_HASH)),
+        block_number: BlockNumber(0),
+        ..Block::default()
+    };
+    write_json(&dir.path().join("blocks/0.json"), &block);
+    let mut state_update =
+        StateUpdate { block_hash: BlockHash(stark_felt!(BLOCK_HASH)), ..StateUpdate::default() };
+    state_update.state_diff.deployed_contracts = vec![DeployedContract {
+        address: ContractAddress(patricia_key!("0x10")),
+        class_hash: ClassHash(stark_felt!(CLASS_HASH)),
+    }];
+    write_json(&dir.path().join("state_updates/0.json"), &state_update);
+    if with_class {
+        write_json(
+            &dir.path().join(format!("classes/{CLASS_HASH}.json")),
+            &DeprecatedContractClass::default(),
+        );
+    }
+    dir
+}
+
+async fn assert_block_is_read(file_source: &FileSource) {
+    assert_eq!(file_source.get_block_marker().await.unwrap(), BlockNumber(1));
+    assert_eq!(
+        file_source.get_block_hash(BlockNumber(0)).await.unwrap(),
+        Some(BlockHash(stark_felt!(BLOCK_HASH)))
+    );
+    assert_eq!(file_source.get_block_hash(BlockNumber(1)).await.unwrap(), None);
+
+    let blocks: Vec<_> =
+        file_source.stream_new_blocks(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_eq!(blocks.len(), 1);
+    let (block_number, block, _execution_info) = blocks.into_iter().next().unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(block.header.block_hash, BlockHash(stark_felt!(BLOCK_HASH)));
+
+    let state_updates: Vec<_> =
+        file_source.stream_state_updates(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_eq!(state_updates.len(), 1);
+    let (block_number, block_hash, state_diff, deployed_contract_class_definitions) =
+        state_updates.into_iter().next().unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(block_hash, BlockHash(stark_felt!(BLOCK_HASH)));
+    assert_eq!(
+        state_diff.deployed_contracts,
+        IndexMap::from([(
+            ContractAddress(patricia_key!("0x10")),
+            ClassHash(stark_felt!(CLASS_HASH))
+        )])
+    );
+    assert_eq!(
+        deployed_contract_class_definitions,
+        IndexMap::from([(
+            ClassHash(stark_felt!(CLASS_HASH)),
+            ApiDeprecatedContractClass::from(DeprecatedContractClass::default())
+        )])
+    );
+}
+
+#[tokio::test]
+async fn read_from_directory() {
+    let dir = dump_block(true);
+    let file_source = FileSource::new(dir.path(), get_test_storage().0).unwrap();
+    assert_block_is_read(&file_source).await;
+}
+
+#[tokio::test]
+async fn read_from_archive() {
+    let dir = dump_block(true);
+    let archive = NamedTempFile::new().unwrap();
+    let mut builder =
+        tar::Builder::new(GzEncoder::new(archive.reopen().unwrap(), Compression::default()));
+    builder.append_dir_all("dump", dir.path()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let file_source = FileSource::new(archive.path(), get_test_storage().0).unwrap();
+    assert_block_is_read(&file_source).await;
+}
+
+#[tokio::test]
+async fn missing_class() {
+    let dir = dump_block(false);
+    let file_source = FileSource::new(dir.path(), get_test_storage().0).unwrap();
+
+    let state_updates: Vec<_> =
+        file_source.stream_state_updates(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_eq!(state_updates.len(), 1);
+    assert_matches!(
+        state_updates.into_iter().next().unwrap(),
+        Err(CentralError::FileSourceError(err))
+            if matches!(*err, FileSourceError::MissingClass { .. })
+    );
+}
@@ -3,7 +3,16 @@ mod central;
 mod central_sync_test;
 #[cfg(test)]
 mod central_test;
+mod file;
+#[cfg(test)]
+mod file_test;
+mod peer;
+#[cfg(test)]
+mod peer_test;
 
 pub use central::{
-    CentralError, CentralResult, CentralSource, CentralSourceConfig, CentralSourceTrait,
+    AdaptiveConcurrencyConfig, CentralError, CentralResult, CentralSource, CentralSourceConfig,
+    CentralSourceTrait, GenericCentralSource,
 };
+pub use file::{FileSource, FileSourceError};
+pub use peer::{PeerSource, PeerSourceConfig};
@@ -0,0 +1,326 @@
+mod objects;
+
+use std::sync::Arc;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use futures_util::StreamExt;
+use indexmap::{IndexMap, IndexSet};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use jsonrpsee::types::error::CallError;
+use jsonrpsee::types::ParamsSer;
+use papyrus_storage::body::events::TransactionExecutionInfo;
+use papyrus_storage::body::{BlockExecutionInfo, StarknetVersion};
+use papyrus_storage::StorageReader;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{Block, BlockHash, BlockHeader, BlockNumber};
+use starknet_api::core::ClassHash;
+use starknet_api::state::StateDiff;
+use starknet_api::transaction::{TransactionHash, TransactionOutput};
+use tracing::{debug, trace};
+
+use self::objects::{BlockHashAndNumber, BlockId, TransactionReceipt};
+use super::central::{
+    get_stored_class, ApiContractClass, BlocksStream, CentralResult, CentralStateUpdate,
+    HeadersStream, StateUpdatesStream,
+};
+use crate::{CentralError, CentralSourceTrait};
+
+// Error codes of the JSON-RPC API that indicate that the requested object doesn't exist.
+const BLOCK_NOT_FOUND: i32 = 24;
+const TRANSACTION_HASH_NOT_FOUND: i32 = 25;
+const CLASS_HASH_NOT_FOUND: i32 = 28;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PeerSourceConfig {
+    /// The URL of the JSON-RPC server of the peer node, which should serve version 0.4 of the API.
+    pub url: String,
+    pub concurrent_requests: usize,
+}
+
+/// A source that syncs from the JSON-RPC server of another Papyrus node instead of from the
+/// feeder gateway. Blocks without a gas price, which versions of the API before 0.4 don't serve,
+/// are refused.
+pub struct PeerSource {
+    concurrent_requests: usize,
+    client: HttpClient,
+    storage_reader: StorageReader,
+}
+
+impl PeerSource {
+    pub fn new(config: PeerSourceConfig, storage_reader: StorageReader) -> Result<Self, Error> {
+        let client = HttpClientBuilder::default().build(&config.url)?;
+        Ok(PeerSource { concurrent_requests: config.concurrent_requests, client, storage_reader })
+    }
+
+    // Sends a request to the peer. Returns None if the requested object doesn't exist.
+    async fn request<'a, T: DeserializeOwned>(
+        &self,
+        method: &'a str,
+        params: Option<ParamsSer<'a>>,
+    ) -> CentralResult<Option<T>> {
+        match self.client.request(method, params).await {
+            Ok(response) => Ok(Some(response)),
+            Err(Error::Call(CallError::Custom(err)))
+                if [BLOCK_NOT_FOUND, TRANSACTION_HASH_NOT_FOUND, CLASS_HASH_NOT_FOUND]
+                    .contains(&err.code()) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(CentralError::PeerError(Arc::new(err))),
+        }
+    }
+
+    async fn block(&self, block_number: BlockNumber) -> CentralResult<(Block, BlockExecutionInfo)> {
+        let block: objects::Block = self
+            .request("starknet_getBlockWithTxs", rpc_params![BlockId::Number(block_number)])
+            .await?
+            .ok_or(CentralError::BlockNotFound { block_number })?;
+        let (transaction_outputs, transaction_execution_infos): (Vec<_>, Vec<_>) = try_join_all(
+            block.transactions.iter().map(|tx| self.transaction_output(tx.transaction_hash())),
+        )
+        .await?
+        .into_iter()
+        .unzip();
+        debug!("Received new block {block_number} with hash {}.", block.header.block_hash);
+        let execution_info = BlockExecutionInfo {
+            starknet_version: block.header.starknet_version.clone().map(StarknetVersion),
+            // The execution info of the block is stored only if the peer has it for all of its
+            // transactions.
+            transaction_execution_infos: transaction_execution_infos.into_iter().collect(),
+        };
+        Ok((block.into_starknet_api_block(transaction_outputs)?, execution_info))
+    }
+
+    async fn header(&self, block_number: BlockNumber) -> CentralResult<BlockHeader> {
+        let header: objects::BlockHeader = self
+            .request("starknet_getBlockWithTxHashes", rpc_params![BlockId::Number(block_number)])
+            .await?
+            .ok_or(CentralError::BlockNotFound { block_number })?;
+        debug!("Received new header {block_number} with hash {}.", header.block_hash);
+        header.try_into()
+    }
+
+    async fn transaction_output(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> CentralResult<(TransactionOutput, Option<TransactionExecutionInfo>)> {
+        let receipt: TransactionReceipt = self
+            .request("starknet_getTransactionReceipt", rpc_params![transaction_hash])
+            .await?
+            .ok_or_else(|| CentralError::BadPeerResponse {
+                message: format!("Missing receipt of transaction {transaction_hash:?}."),
+            })?;
+        if receipt.transaction_hash != transaction_hash {
+            return Err(CentralError::BadPeerResponse {
+                message: format!(
+                    "Received the receipt of transaction {:?} instead of {transaction_hash:?}.",
+                    receipt.transaction_hash
+                ),
+            });
+        }
+        let execution_info = receipt.execution_info()?;
+        Ok((receipt.into(), execution_info))
+    }
+
+    async fn state_update(&self, block_number: BlockNumber) -> CentralResult<CentralStateUpdate> {
+        let state_update: objects::StateUpdate = self
+            .request("starknet_getStateUpdate", rpc_params![BlockId::Number(block_number)])
+            .await?
+            .ok_or(CentralError::StateUpdateNotFound)?;
+        let diff = state_update.state_diff;
+
+        let declared_classes = try_join_all(diff.declared_classes.iter().map(|entry| async move {
+            let class = self.class(block_number, entry.class_hash).await?.into_cairo1()?;
+            Ok::<_, CentralError>((entry.class_hash, (entry.compiled_class_hash, class)))
+        }))
+        .await?;
+        let deprecated_declared_classes =
+            try_join_all(diff.deprecated_declared_classes.iter().map(|class_hash| async move {
+                let class = self.class(block_number, *class_hash).await?.into_cairo0()?;
+                Ok::<_, CentralError>((*class_hash, class))
+            }))
+            .await?;
+        let state_diff = StateDiff {
+            deployed_contracts: diff
+                .deployed_contracts
+                .iter()
+                .map(|contract| (contract.address, contract.class_hash))
+                .collect(),
+            storage_diffs: diff
+                .storage_diffs
+                .into_iter()
+                .map(|storage_diff| (storage_diff.address, storage_diff.into_entries().collect()))
+                .collect(),
+            declared_classes: declared_classes.into_iter().collect(),
+            deprecated_declared_classes: deprecated_declared_classes.into_iter().collect(),
+            nonces: diff
+                .nonces
+                .into_iter()
+                .map(|contract_nonce| (contract_nonce.contract_address, contract_nonce.nonce))
+                .collect(),
+            replaced_classes: diff
+                .replaced_classes
+                .into_iter()
+                .map(|replaced_class| (replaced_class.contract_address, replaced_class.class_hash))
+                .collect(),
+        };
+
+        // Classes of deployed contracts that were not declared in this state diff. Classes of
+        // Cairo1 contracts are filtered out because since 0.11 new classes can not be implicitly
+        // declared by deployment.
+        let undeclared_class_hashes: IndexSet<ClassHash> = state_diff
+            .deployed_contracts
+            .values()
+            .filter(|class_hash| {
+                !state_diff.declared_classes.contains_key(*class_hash)
+                    && !state_diff.deprecated_declared_classes.contains_key(*class_hash)
+            })
+            .copied()
+            .collect();
+        let deployed_contract_class_definitions =
+            try_join_all(undeclared_class_hashes.into_iter().map(|class_hash| async move {
+                Ok::<_, CentralError>((class_hash, self.class(block_number, class_hash).await?))
+            }))
+            .await?
+            .into_iter()
+            .filter_map(|(class_hash, class)| match class {
+                ApiContractClass::DeprecatedContractClass(class) => Some((class_hash, class)),
+                ApiContractClass::ContractClass(_) => None,
+            })
+            .collect::<IndexMap<_, _>>();
+
+        let block_hash = state_update.block_hash;
+        debug!("Received new state update of block {block_number} with hash {block_hash}.");
+        trace!(
+            "State diff: {state_diff:?}, deployed_contract_class_definitions: \
+             {deployed_contract_class_definitions:?}."
+        );
+        Ok((block_number, block_hash, state_diff, deployed_contract_class_definitions))
+    }
+
+    // Returns the class from the storage if it was already synced, and otherwise downloads its
+    // definition as of the given block from the peer.
+    async fn class(
+        &self,
+        block_number: BlockNumber,
+        class_hash: ClassHash,
+    ) -> CentralResult<ApiContractClass> {
+        if let Some(class) = get_stored_class(&self.storage_reader, &class_hash)? {
+            trace!("Class {:?} retrieved from storage.", class_hash);
+            return Ok(class);
+        }
+        trace!("Downloading class {:?}.", class_hash);
+        let class: objects::ContractClass = self
+            .request("starknet_getClass", rpc_params![BlockId::Number(block_number), class_hash])
+            .await?
+            .ok_or(CentralError::ClassNotFound)?;
+        class.try_into()
+    }
+}
+
+#[async_trait]
+impl CentralSourceTrait for PeerSource {
+    async fn get_block_marker(&self) -> Result<BlockNumber, CentralError> {
+        let latest: Option<BlockHashAndNumber> =
+            self.request("starknet_getBlockWithTxHashes", rpc_params![BlockId::Latest]).await?;
+        Ok(latest.map_or(BlockNumber::default(), |block| block.block_number.next()))
+    }
+
+    async fn get_block_hash(
+        &self,
+        block_number: BlockNumber,
+    ) -> Result<Option<BlockHash>, CentralError> {
+        let block: Option<BlockHashAndNumber> = self
+            .request("starknet_getBlockWithTxHashes", rpc_params![BlockId::Number(block_number)])
+            .await?;
+        Ok(block.map(|block| block.block_hash))
+    }
+
+    fn stream_new_blocks(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> BlocksStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| async move { (bn, self.block(bn).await) })
+                    .buffered(self.concurrent_requests);
+            while let Some((current_block_number, maybe_block)) = res.next().await {
+                match maybe_block {
+                    Ok((block, execution_info)) => {
+                        yield Ok((current_block_number, block, execution_info));
+                    }
+                    Err(err) => {
+                        debug!("Received error for block {}: {:?}.", current_block_number, err);
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    // The headers are requested without the transactions and their receipts, so they are synced
+    // much faster than the full blocks.
+    fn stream_headers(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> HeadersStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| async move { (bn, self.header(bn).await) })
+                    .buffered(self.concurrent_requests);
+            while let Some((current_block_number, maybe_header)) = res.next().await {
+                match maybe_header {
+                    Ok(header) => {
+                        yield Ok((current_block_number, header));
+                    }
+                    Err(err) => {
+                        debug!("Received error for header {}: {:?}.", current_block_number, err);
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_state_updates(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> StateUpdatesStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|bn| async move { (bn, self.state_update(bn).await) })
+                    .buffered(self.concurrent_requests);
+            while let Some((current_block_number, maybe_state_update)) = res.next().await {
+                match maybe_state_update {
+                    Ok(state_update) => {
+                        yield Ok(state_update);
+                    }
+                    Err(err) => {
+                        debug!(
+                            "Received error for state diff {}: {:?}.", current_block_number, err
+                        );
+                        yield (Err(err));
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::{BTreeMap, HashMap};
+
+use flate2::read::GzDecoder;
+use papyrus_storage::body::events::{
+    ExecutionResources as StorageExecutionResources, TransactionExecutionInfo,
+    TransactionExecutionStatus as StorageTransactionExecutionStatus,
+};
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockBody, BlockHash, BlockNumber, BlockTimestamp, GasPrice};
+use starknet_api::core::{
+    ClassHash, CompiledClassHash, ContractAddress, EntryPointSelector, GlobalRoot, Nonce,
+};
+use starknet_api::deprecated_contract_class::{EntryPoint, EntryPointType, Program};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{
+    Calldata, DeclareTransactionOutput, DeployAccountTransaction, DeployAccountTransactionOutput,
+    DeployTransaction, DeployTransactionOutput, Event, Fee, InvokeTransactionOutput,
+    L1HandlerTransaction, L1HandlerTransactionOutput, MessageToL1, TransactionHash,
+    TransactionOutput, TransactionSignature, TransactionVersion,
+};
+use starknet_client::{DeclaredClassHashEntry, DeployedContract, StorageEntry};
+
+use crate::sources::central::{ApiContractClass, CentralResult};
+use crate::CentralError;
+
+// The objects in this module follow the JSON-RPC specification, as served by papyrus_gateway.
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) enum BlockId {
+    #[serde(rename = "latest")]
+    Latest,
+    #[serde(rename = "block_number")]
+    Number(BlockNumber),
+}
+
+// The header fields of a block that are needed without its transactions.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BlockHashAndNumber {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BlockHeader {
+    pub block_hash: BlockHash,
+    pub parent_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub sequencer_address: ContractAddress,
+    pub new_root: GlobalRoot,
+    pub timestamp: BlockTimestamp,
+    // Served from version 0.4 of the specification.
+    pub l1_gas_price: Option<ResourcePrice>,
+    pub starknet_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ResourcePrice {
+    pub price_in_wei: StarkFelt,
+}
+
+impl TryFrom<BlockHeader> for starknet_api::block::BlockHeader {
+    type Error = CentralError;
+
+    fn try_from(header: BlockHeader) -> CentralResult<Self> {
+        // The fees of the transactions that are executed on top of the block are computed with its
+        // gas price, so a header without it isn't stored.
+        let price_in_wei = header
+            .l1_gas_price
+            .ok_or_else(|| CentralError::BadPeerResponse {
+                message: format!(
+                    "Block {} has no L1 gas price. The peer should serve version 0.4 of the API.",
+                    header.block_number
+                ),
+            })?
+            .price_in_wei;
+        let gas_price =
+            felt_to_u128(&price_in_wei).ok_or_else(|| CentralError::BadPeerResponse {
+                message: format!(
+                    "Invalid L1 gas price {price_in_wei:?} of block {}.",
+                    header.block_number
+                ),
+            })?;
+        Ok(starknet_api::block::BlockHeader {
+            block_hash: header.block_hash,
+            parent_hash: header.parent_hash,
+            block_number: header.block_number,
+            gas_price: GasPrice(gas_price),
+            state_root: header.new_root,
+            sequencer: header.sequencer_address,
+            timestamp: header.timestamp,
+        })
+    }
+}
+
+// Numbers that the specification gives as hex strings, like gas prices, that should fit in a u128.
+fn felt_to_u128(felt: &StarkFelt) -> Option<u128> {
+    let (high, low) = felt.bytes().split_at(16);
+    if high.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    Some(u128::from_be_bytes(low.try_into().expect("The low half of a felt should be 16 bytes.")))
+}
+
+fn felt_to_u64(felt: &StarkFelt) -> Option<u64> {
+    felt_to_u128(felt).and_then(|number| u64::try_from(number).ok())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Block {
+    #[serde(flatten)]
+    pub header: BlockHeader,
+    pub transactions: Vec<Transaction>,
+}
+
+impl Block {
+    pub fn into_starknet_api_block(
+        self,
+        transaction_outputs: Vec<TransactionOutput>,
+    ) -> CentralResult<starknet_api::block::Block> {
+        let transactions = self
+            .transactions
+            .into_iter()
+            .map(starknet_api::transaction::Transaction::try_from)
+            .collect::<CentralResult<_>>()?;
+        Ok(starknet_api::block::Block {
+            header: self.header.try_into()?,
+            body: BlockBody { transactions, transaction_outputs },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub(crate) enum Transaction {
+    #[serde(rename = "DECLARE")]
+    Declare(IntermediateDeclareTransaction),
+    #[serde(rename = "DEPLOY")]
+    Deploy(DeployTransaction),
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount(DeployAccountTransaction),
+    #[serde(rename = "INVOKE")]
+    Invoke(IntermediateInvokeTransaction),
+    #[serde(rename = "L1_HANDLER")]
+    L1Handler(L1HandlerTransaction),
+}
+
+impl Transaction {
+    pub fn transaction_hash(&self) -> TransactionHash {
+        match self {
+            Transaction::Declare(tx) => tx.transaction_hash,
+            Transaction::Deploy(tx) => tx.transaction_hash,
+            Transaction::DeployAccount(tx) => tx.transaction_hash,
+            Transaction::Invoke(tx) => tx.transaction_hash,
+            Transaction::L1Handler(tx) => tx.transaction_hash,
+        }
+    }
+}
+
+impl TryFrom<Transaction> for starknet_api::transaction::Transaction {
+    type Error = CentralError;
+
+    fn try_from(tx: Transaction) -> CentralResult<Self> {
+        match tx {
+            Transaction::Declare(declare_tx) => Ok(Self::Declare(declare_tx.try_into()?)),
+            Transaction::Deploy(deploy_tx) => Ok(Self::Deploy(deploy_tx)),
+            Transaction::DeployAccount(deploy_tx) => Ok(Self::DeployAccount(deploy_tx)),
+            Transaction::Invoke(invoke_tx) => Ok(Self::Invoke(invoke_tx.try_into()?)),
+            Transaction::L1Handler(l1_handler_tx) => Ok(Self::L1Handler(l1_handler_tx)),
+        }
+    }
+}
+
+fn tx_version(version: u64) -> TransactionVersion {
+    TransactionVersion(StarkFelt::from(version))
+}
+
+fn bad_transaction(tx_hash: TransactionHash, msg: &str) -> CentralError {
+    CentralError::BadPeerResponse { message: format!("Transaction {tx_hash:?}: {msg}") }
+}
+
+// A declare transaction of any version.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct IntermediateDeclareTransaction {
+    pub class_hash: ClassHash,
+    pub compiled_class_hash: Option<CompiledClassHash>,
+    pub sender_address: ContractAddress,
+    pub nonce: Nonce,
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub transaction_hash: TransactionHash,
+    pub signature: TransactionSignature,
+}
+
+impl TryFrom<IntermediateDeclareTransaction> for starknet_api::transaction::DeclareTransaction {
+    type Error = CentralError;
+
+    fn try_from(tx: IntermediateDeclareTransaction) -> CentralResult<Self> {
+        let v0_v1 = starknet_api::transaction::DeclareTransactionV0V1 {
+            transaction_hash: tx.transaction_hash,
+            max_fee: tx.max_fee,
+            signature: tx.signature.clone(),
+            nonce: tx.nonce,
+            class_hash: tx.class_hash,
+            sender_address: tx.sender_address,
+        };
+        match tx.version {
+            v if v == tx_version(0) => Ok(Self::V0(v0_v1)),
+            v if v == tx_version(1) => Ok(Self::V1(v0_v1)),
+            v if v == tx_version(2) => {
+                Ok(Self::V2(starknet_api::transaction::DeclareTransactionV2 {
+                    transaction_hash: tx.transaction_hash,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    class_hash: tx.class_hash,
+                    compiled_class_hash: tx.compiled_class_hash.ok_or_else(|| {
+                        bad_transaction(
+                            tx.transaction_hash,
+                            "Declare V2 must contain compiled_class_hash field.",
+                        )
+                    })?,
+                    sender_address: tx.sender_address,
+                }))
+            }
+            _ => Err(bad_transaction(
+                tx.transaction_hash,
+                &format!("Declare version {:?} is not supported.", tx.version),
+            )),
+        }
+    }
+}
+
+// An invoke transaction of any version. Version 0 transactions name the sender
+// `contract_address`.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct IntermediateInvokeTransaction {
+    pub transaction_hash: TransactionHash,
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    #[serde(alias = "contract_address")]
+    pub sender_address: ContractAddress,
+    pub entry_point_selector: Option<EntryPointSelector>,
+    pub calldata: Calldata,
+}
+
+impl TryFrom<IntermediateInvokeTransaction> for starknet_api::transaction::InvokeTransaction {
+    type Error = CentralError;
+
+    fn try_from(tx: IntermediateInvokeTransaction) -> CentralResult<Self> {
+        match tx.version {
+            v if v == tx_version(0) => {
+                Ok(Self::V0(starknet_api::transaction::InvokeTransactionV0 {
+                    transaction_hash: tx.transaction_hash,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    sender_address: tx.sender_address,
+                    entry_point_selector: tx.entry_point_selector.ok_or_else(|| {
+                        bad_transaction(
+                            tx.transaction_hash,
+                            "Invoke V0 must contain entry_point_selector field.",
+                        )
+                    })?,
+                    calldata: tx.calldata,
+                }))
+            }
+            v if v == tx_version(1) => {
+                Ok(Self::V1(starknet_api::transaction::InvokeTransactionV1 {
+                    transaction_hash: tx.transaction_hash,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    sender_address: tx.sender_address,
+                    calldata: tx.calldata,
+                }))
+            }
+            _ => Err(bad_transaction(
+                tx.transaction_hash,
+                &format!("Invoke version {:?} is not supported.", tx.version),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+pub(crate) enum TransactionType {
+    #[serde(rename = "DECLARE")]
+    Declare,
+    #[serde(rename = "DEPLOY")]
+    Deploy,
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount,
+    #[serde(rename = "INVOKE")]
+    Invoke,
+    #[serde(rename = "L1_HANDLER")]
+    L1Handler,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct TransactionReceipt {
+    pub transaction_hash: TransactionHash,
+    pub r#type: TransactionType,
+    pub actual_fee: Fee,
+    pub messages_sent: Vec<MessageToL1>,
+    pub events: Vec<Event>,
+    // Served from version 0.4 of the specification, for transactions that the peer synced with
+    // their execution info.
+    pub execution_status: Option<TransactionExecutionStatus>,
+    pub revert_reason: Option<String>,
+    pub execution_resources: Option<ExecutionResources>,
+}
+
+impl TransactionReceipt {
+    // Returns None if the peer doesn't have the execution info of the transaction.
+    pub fn execution_info(&self) -> CentralResult<Option<TransactionExecutionInfo>> {
+        let (Some(status), Some(resources)) = (self.execution_status, &self.execution_resources)
+        else {
+            return Ok(None);
+        };
+        let execution_status = match status {
+            TransactionExecutionStatus::Succeeded => StorageTransactionExecutionStatus::Succeeded,
+            TransactionExecutionStatus::Reverted => {
+                let revert_reason = self.revert_reason.clone().ok_or_else(|| {
+                    bad_transaction(self.transaction_hash, "Reverted without a revert reason.")
+                })?;
+                StorageTransactionExecutionStatus::Reverted(revert_reason)
+            }
+        };
+        let execution_resources = resources.to_storage_resources().ok_or_else(|| {
+            bad_transaction(
+                self.transaction_hash,
+                &format!("Invalid execution resources {resources:?}."),
+            )
+        })?;
+        Ok(Some(TransactionExecutionInfo { execution_status, execution_resources }))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+pub(crate) enum TransactionExecutionStatus {
+    #[serde(rename = "SUCCEEDED")]
+    Succeeded,
+    #[serde(rename = "REVERTED")]
+    Reverted,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ExecutionResources {
+    pub steps: StarkFelt,
+    pub memory_holes: StarkFelt,
+    pub range_check_builtin_applications: StarkFelt,
+    pub pedersen_builtin_applications: StarkFelt,
+    pub poseidon_builtin_applications: StarkFelt,
+    pub ec_op_builtin_applications: StarkFelt,
+    pub ecdsa_builtin_applications: StarkFelt,
+    pub bitwise_builtin_applications: StarkFelt,
+    pub keccak_builtin_applications: StarkFelt,
+}
+
+impl ExecutionResources {
+    // Returns None if one of the numbers doesn't fit in a u64.
+    fn to_storage_resources(&self) -> Option<StorageExecutionResources> {
+        // The specification gives every builtin, while the feeder gateway only lists the builtins
+        // that were used, so unused builtins are left out.
+        let mut builtin_instance_counter = BTreeMap::new();
+        for (name, applications) in [
+            ("range_check_builtin", &self.range_check_builtin_applications),
+            ("pedersen_builtin", &self.pedersen_builtin_applications),
+            ("poseidon_builtin", &self.poseidon_builtin_applications),
+            ("ec_op_builtin", &self.ec_op_builtin_applications),
+            ("ecdsa_builtin", &self.ecdsa_builtin_applications),
+            ("bitwise_builtin", &self.bitwise_builtin_applications),
+            ("keccak_builtin", &self.keccak_builtin_applications),
+        ] {
+            let count = felt_to_u64(applications)?;
+            if count > 0 {
+                builtin_instance_counter.insert(name.to_owned(), count);
+            }
+        }
+        Some(StorageExecutionResources {
+            n_steps: felt_to_u64(&self.steps)?,
+            builtin_instance_counter,
+            n_memory_holes: felt_to_u64(&self.memory_holes)?,
+        })
+    }
+}
+
+impl From<TransactionReceipt> for TransactionOutput {
+    fn from(receipt: TransactionReceipt) -> Self {
+        let TransactionReceipt { r#type, actual_fee, messages_sent, events, .. } = receipt;
+        match r#type {
+            TransactionType::Declare => {
+                Self::Declare(DeclareTransactionOutput { actual_fee, messages_sent, events })
+            }
+            TransactionType::Deploy => {
+                Self::Deploy(DeployTransactionOutput { actual_fee, messages_sent, events })
+            }
+            TransactionType::DeployAccount => Self::DeployAccount(DeployAccountTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events,
+            }),
+            TransactionType::Invoke => {
+                Self::Invoke(InvokeTransactionOutput { actual_fee, messages_sent, events })
+            }
+            TransactionType::L1Handler => {
+                Self::L1Handler(L1HandlerTransactionOutput { actual_fee, messages_sent, events })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct StateUpdate {
+    pub block_hash: BlockHash,
+    pub state_diff: ThinStateDiff,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ThinStateDiff {
+    pub deployed_contracts: Vec<DeployedContract>,
+    pub storage_diffs: Vec<StorageDiff>,
+    pub declared_classes: Vec<DeclaredClassHashEntry>,
+    pub deprecated_declared_classes: Vec<ClassHash>,
+    pub nonces: Vec<ContractNonce>,
+    pub replaced_classes: Vec<ReplacedClass>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct StorageDiff {
+    pub address: ContractAddress,
+    pub storage_entries: Vec<StorageEntry>,
+}
+
+impl StorageDiff {
+    pub fn into_entries(self) -> impl Iterator<Item = (StorageKey, StarkFelt)> {
+        self.storage_entries.into_iter().map(|entry| (entry.key, entry.value))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ContractNonce {
+    pub contract_address: ContractAddress,
+    pub nonce: Nonce,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ReplacedClass {
+    pub contract_address: ContractAddress,
+    pub class_hash: ClassHash,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ContractClass {
+    Cairo0(DeprecatedContractClass),
+    Sierra(starknet_client::ContractClass),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DeprecatedContractClass {
+    pub abi: serde_json::Value,
+    /// A base64 encoding of the gzip-compressed JSON representation of program.
+    pub program: String,
+    pub entry_points_by_type: HashMap<EntryPointType, Vec<EntryPoint>>,
+}
+
+impl TryFrom<ContractClass> for ApiContractClass {
+    type Error = CentralError;
+
+    fn try_from(class: ContractClass) -> CentralResult<Self> {
+        match class {
+            ContractClass::Cairo0(class) => {
+                let program = decode_program(&class.program).map_err(|message| {
+                    CentralError::BadPeerResponse {
+                        message: format!("Invalid program of a deprecated class: {message}"),
+                    }
+                })?;
+                // The abi entries are in the same format as in the feeder gateway.
+                let class = starknet_client::DeprecatedContractClass {
+                    abi: class.abi,
+                    program,
+                    entry_points_by_type: class.entry_points_by_type,
+                };
+                Ok(Self::DeprecatedContractClass(class.into()))
+            }
+            ContractClass::Sierra(class) => Ok(Self::ContractClass(class.into())),
+        }
+    }
+}
+
+fn decode_program(encoded_program: &str) -> Result<Program, String> {
+    let compressed_program = base64::decode(encoded_program).map_err(|err| err.to_string())?;
+    serde_json::from_reader(GzDecoder::new(compressed_program.as_slice()))
+        .map_err(|err| err.to_string())
+}
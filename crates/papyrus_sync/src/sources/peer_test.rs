@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+
+use assert_matches::assert_matches;
+use futures_util::StreamExt;
+use indexmap::IndexMap;
+use papyrus_execution::ExecutionConfig;
+use papyrus_gateway::{run_server, ApiVersion, GatewayConfig, GatewayMonitor, LimitsConfig};
+use papyrus_storage::body::events::{
+    ExecutionResources, TransactionExecutionInfo, TransactionExecutionStatus,
+};
+use papyrus_storage::body::{BlockExecutionInfo, BodyStorageWriter, StarknetVersion};
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::get_test_storage;
+use papyrus_storage::StorageReader;
+use starknet_api::block::{Block, BlockHash, BlockHeader, BlockNumber, GasPrice};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress};
+use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use test_utils::{get_rng, get_test_body, get_test_state_diff, GetTestInstance};
+
+use crate::sources::peer::{PeerSource, PeerSourceConfig};
+use crate::sources::CentralSourceTrait;
+use crate::{sort_state_diff, CentralError};
+
+const TEST_CONCURRENT_REQUESTS: usize = 10;
+
+// The server keeps running after its handle is dropped.
+async fn run_peer(storage_reader: StorageReader) -> SocketAddr {
+    let config = GatewayConfig {
+        chain_id: ChainId("SN_GOERLI".to_string()),
+        server_address: String::from("127.0.0.1:0"),
+        max_events_chunk_size: 10,
+        max_events_keys: 10,
+        max_events_scanned: 10_000,
+        max_events_scan_millis: 10_000,
+        execution_config: ExecutionConfig {
+            fee_contract_address: ContractAddress::default(),
+            invoke_tx_max_n_steps: 1_000_000,
+            validate_tx_max_n_steps: 1_000_000,
+            max_recursion_depth: 50,
+        },
+        default_version: ApiVersion::V0_4,
+        limits: LimitsConfig {
+            max_request_body_size: 10 * 1024 * 1024,
+            max_response_body_size: 10 * 1024 * 1024,
+            max_concurrent_calls: 100,
+            max_concurrent_batch_calls: 10,
+            ip_rate_limit: None,
+            api_key_rate_limits: HashMap::new(),
+            method_allowlist: None,
+            method_denylist: vec![],
+        },
+        serve_feeder_gateway: false,
+        continuation_token_secret: None,
+    };
+    run_server(&config, storage_reader, GatewayMonitor::default()).await.unwrap().0
+}
+
+fn get_peer_source(addr: SocketAddr) -> PeerSource {
+    get_peer_source_at_path(addr, "")
+}
+
+fn get_peer_source_at_path(addr: SocketAddr, path: &str) -> PeerSource {
+    let config = PeerSourceConfig {
+        url: format!("http://{addr:?}{path}"),
+        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+    };
+    PeerSource::new(config, get_test_storage().0).unwrap()
+}
+
+#[tokio::test]
+async fn empty_peer() {
+    let (peer_reader, _) = get_test_storage();
+    let addr = run_peer(peer_reader).await;
+    let peer_source = get_peer_source(addr);
+
+    assert_eq!(peer_source.get_block_marker().await.unwrap(), BlockNumber(0));
+    assert_eq!(peer_source.get_block_hash(BlockNumber(0)).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn sync_from_peer() {
+    let (peer_reader, mut peer_writer) = get_test_storage();
+    let block = Block {
+        header: BlockHeader {
+            block_hash: BlockHash(stark_felt!("0x1")),
+            block_number: BlockNumber(0),
+            gas_price: GasPrice(0x3b9aca00),
+            ..BlockHeader::default()
+        },
+        body: get_test_body(None, 5, Some(2), None, None),
+    };
+    // A contract whose class was implicitly declared by the deployment.
+    let mut state_diff = get_test_state_diff();
+    let deployed_class_hash = ClassHash(stark_felt!("0x2"));
+    let deployed_class = DeprecatedContractClass::get_test_instance(&mut get_rng(None));
+    let (_, class_hash) = state_diff.deployed_contracts.iter_mut().next().unwrap();
+    *class_hash = deployed_class_hash;
+    state_diff.replaced_classes.clear();
+    // The first transaction was reverted.
+    let execution_info = BlockExecutionInfo {
+        starknet_version: Some(StarknetVersion("0.12.1".to_owned())),
+        transaction_execution_infos: Some(
+            (0..block.body.transactions.len())
+                .map(|i| TransactionExecutionInfo {
+                    execution_status: if i == 0 {
+                        TransactionExecutionStatus::Reverted("Out of gas.".to_owned())
+                    } else {
+                        TransactionExecutionStatus::Succeeded
+                    },
+                    execution_resources: ExecutionResources {
+                        n_steps: 100 + i as u64,
+                        builtin_instance_counter: BTreeMap::from([
+                            ("pedersen_builtin".to_owned(), 2),
+                            ("range_check_builtin".to_owned(), 3),
+                        ]),
+                        n_memory_holes: 4,
+                    },
+                })
+                .collect(),
+        ),
+    };
+    peer_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &block.header)
+        .unwrap()
+        .append_body_with_execution_info(BlockNumber(0), block.body.clone(), execution_info.clone())
+        .unwrap()
+        .append_state_diff(
+            BlockNumber(0),
+            state_diff.clone(),
+            IndexMap::from([(deployed_class_hash, deployed_class.clone())]),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let addr = run_peer(peer_reader).await;
+    let peer_source = get_peer_source(addr);
+
+    assert_eq!(peer_source.get_block_marker().await.unwrap(), BlockNumber(1));
+    assert_eq!(
+        peer_source.get_block_hash(BlockNumber(0)).await.unwrap(),
+        Some(block.header.block_hash)
+    );
+
+    let blocks: Vec<_> =
+        peer_source.stream_new_blocks(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_eq!(blocks.len(), 1);
+    let (block_number, synced_block, synced_execution_info) =
+        blocks.into_iter().next().unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(synced_block, block);
+    assert_eq!(synced_execution_info, execution_info);
+
+    let headers: Vec<_> =
+        peer_source.stream_headers(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_eq!(headers.len(), 1);
+    let (block_number, synced_header) = headers.into_iter().next().unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(synced_header, block.header);
+
+    let state_updates: Vec<_> =
+        peer_source.stream_state_updates(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_eq!(state_updates.len(), 1);
+    let (block_number, block_hash, mut synced_state_diff, deployed_contract_class_definitions) =
+        state_updates.into_iter().next().unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(block_hash, block.header.block_hash);
+    sort_state_diff(&mut state_diff);
+    sort_state_diff(&mut synced_state_diff);
+    assert_eq!(synced_state_diff, state_diff);
+    assert_eq!(
+        deployed_contract_class_definitions,
+        IndexMap::from([(deployed_class_hash, deployed_class)])
+    );
+}
+
+#[tokio::test]
+async fn refuse_blocks_without_gas_price() {
+    let (peer_reader, mut peer_writer) = get_test_storage();
+    peer_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let addr = run_peer(peer_reader).await;
+    // Version 0.3 of the API doesn't serve the gas price of blocks.
+    let peer_source = get_peer_source_at_path(addr, ApiVersion::V0_3.path());
+
+    let headers: Vec<_> =
+        peer_source.stream_headers(BlockNumber(0), BlockNumber(1)).collect().await;
+    assert_matches!(headers.as_slice(), [Err(CentralError::BadPeerResponse { .. })]);
+}
@@ -0,0 +1,366 @@
+#[cfg(test)]
+#[path = "failover_test.rs"]
+mod failover_test;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+use tracing::{debug, info, warn};
+
+use crate::rate_limit::RateLimiter;
+use crate::{
+    Block, BlockTraces, ClientCreationError, ClientError, ClientResult, GenericContractClass,
+    RateLimitConfig, RetryConfig, StarknetClient, StarknetClientTrait, StateUpdate,
+};
+
+/// A starknet gateway to send requests to.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EndpointConfig {
+    pub url: String,
+    pub http_headers: Option<HashMap<String, String>>,
+}
+
+/// A configuration for the failover between several starknet gateways.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FailoverConfig {
+    /// The number of consecutive failed requests after which an endpoint is considered unhealthy.
+    pub max_consecutive_errors: usize,
+    /// The time in milliseconds an unhealthy endpoint is avoided before it is tried again.
+    pub failback_delay_millis: u64,
+    /// If set, a block request that isn't answered within this time in milliseconds is sent to
+    /// the next endpoint as well, and the first answer is used.
+    pub hedge_after_millis: Option<u64>,
+}
+
+/// The health and the request counters of an endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EndpointStats {
+    pub url: String,
+    pub healthy: bool,
+    pub requests: u64,
+    pub errors: u64,
+    pub average_latency_millis: u64,
+    /// The time requests to the endpoint were held back by the rate limit or by the endpoint
+    /// asking to retry later.
+    pub throttled_millis: u64,
+}
+
+type RequestFuture<'a, T> = Pin<Box<dyn Future<Output = ClientResult<T>> + Send + 'a>>;
+
+#[derive(Default)]
+struct EndpointHealth {
+    consecutive_errors: usize,
+    unhealthy_since: Option<Instant>,
+}
+
+struct EndpointState {
+    url: String,
+    health: Mutex<EndpointHealth>,
+    requests: AtomicU64,
+    errors: AtomicU64,
+    total_latency_millis: AtomicU64,
+    // Only known for the endpoints of a StarknetClient.
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl EndpointState {
+    fn new(url: String) -> Self {
+        EndpointState {
+            url,
+            health: Mutex::new(EndpointHealth::default()),
+            requests: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            total_latency_millis: AtomicU64::new(0),
+            rate_limiter: None,
+        }
+    }
+
+    fn health(&self) -> std::sync::MutexGuard<'_, EndpointHealth> {
+        self.health.lock().expect("Endpoint health lock should not be poisoned.")
+    }
+
+    // Whether requests should be sent to the endpoint. Unhealthy endpoints become available again
+    // after the failback delay, so that the next request checks whether they recovered.
+    fn is_available(&self, now: Instant, failback_delay: Duration) -> bool {
+        self.health().unhealthy_since.map_or(true, |since| now - since >= failback_delay)
+    }
+
+    fn record<T>(&self, res: &ClientResult<T>, latency: Duration, config: &FailoverConfig) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.total_latency_millis.fetch_add(latency.as_millis() as u64, Ordering::Relaxed);
+        let mut health = self.health();
+        match res {
+            Err(err) if is_endpoint_failure(err) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                health.consecutive_errors += 1;
+                if health.unhealthy_since.is_some() {
+                    // The endpoint didn't recover, wait for another failback delay.
+                    health.unhealthy_since = Some(Instant::now());
+                } else if health.consecutive_errors >= config.max_consecutive_errors {
+                    warn!("Endpoint {} is unhealthy: {err}.", self.url);
+                    health.unhealthy_since = Some(Instant::now());
+                }
+            }
+            _ => {
+                if health.unhealthy_since.is_some() {
+                    info!("Endpoint {} is healthy again.", self.url);
+                }
+                *health = EndpointHealth::default();
+            }
+        }
+    }
+
+    fn stats(&self) -> EndpointStats {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let total_latency_millis = self.total_latency_millis.load(Ordering::Relaxed);
+        EndpointStats {
+            url: self.url.clone(),
+            healthy: self.health().unhealthy_since.is_none(),
+            requests,
+            errors: self.errors.load(Ordering::Relaxed),
+            average_latency_millis: total_latency_millis.checked_div(requests).unwrap_or(0),
+            throttled_millis: self
+                .rate_limiter
+                .as_ref()
+                .map_or(0, |rate_limiter| rate_limiter.throttled_time().as_millis() as u64),
+        }
+    }
+}
+
+// Whether the error indicates a problem with the endpoint rather than an answer to the request,
+// so the request should be sent to another endpoint.
+fn is_endpoint_failure(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::BadResponseStatus { .. }
+            | ClientError::Throttled { .. }
+            | ClientError::RequestError(_)
+            | ClientError::RetryError { .. }
+            | ClientError::SerdeError(_)
+    )
+}
+
+/// A read only handle to the stats of the endpoints of a [`FailoverStarknetClient`].
+#[derive(Clone)]
+pub struct FailoverMonitor {
+    endpoints: Vec<Arc<EndpointState>>,
+}
+
+impl FailoverMonitor {
+    /// Returns the stats of the endpoints, by priority.
+    pub fn endpoints_stats(&self) -> Vec<EndpointStats> {
+        self.endpoints.iter().map(|endpoint| endpoint.stats()).collect()
+    }
+}
+
+struct Endpoint<TClient> {
+    client: TClient,
+    state: Arc<EndpointState>,
+}
+
+/// A client that sends each request to the first healthy endpoint out of a list ordered by
+/// priority, and fails over to the next endpoints when an endpoint fails. Unhealthy endpoints are
+/// retried after a delay, so the client fails back to an endpoint once it recovers.
+pub struct FailoverStarknetClient<TClient: StarknetClientTrait = StarknetClient> {
+    endpoints: Vec<Endpoint<TClient>>,
+    config: FailoverConfig,
+}
+
+impl FailoverStarknetClient<StarknetClient> {
+    /// Creates a client for the starknet gateways at `endpoints`, ordered by priority. If
+    /// `rate_limit` is set, the requests to each of the endpoints are limited according to it.
+    pub fn new(
+        endpoints: Vec<EndpointConfig>,
+        node_version: &'static str,
+        retry_config: RetryConfig,
+        rate_limit: Option<RateLimitConfig>,
+        config: FailoverConfig,
+    ) -> Result<Self, ClientCreationError> {
+        let clients = endpoints
+            .into_iter()
+            .map(|endpoint| {
+                let mut client = StarknetClient::new(
+                    &endpoint.url,
+                    endpoint.http_headers,
+                    node_version,
+                    retry_config,
+                )?;
+                if let Some(rate_limit) = rate_limit {
+                    client = client.with_rate_limit(rate_limit);
+                }
+                Ok((endpoint.url, client))
+            })
+            .collect::<Result<_, ClientCreationError>>()?;
+        let mut failover_client = Self::with_clients(clients, config);
+        for endpoint in &mut failover_client.endpoints {
+            Arc::get_mut(&mut endpoint.state)
+                .expect("The endpoint state should not be shared yet.")
+                .rate_limiter = Some(endpoint.client.rate_limiter.clone());
+        }
+        Ok(failover_client)
+    }
+}
+
+impl<TClient: StarknetClientTrait + Send + Sync> FailoverStarknetClient<TClient> {
+    /// Creates a client for the given clients, ordered by priority, identified by their URLs.
+    pub fn with_clients(clients: Vec<(String, TClient)>, config: FailoverConfig) -> Self {
+        assert!(!clients.is_empty(), "There should be at least one endpoint.");
+        let endpoints = clients
+            .into_iter()
+            .map(|(url, client)| Endpoint { client, state: Arc::new(EndpointState::new(url)) })
+            .collect();
+        FailoverStarknetClient { endpoints, config }
+    }
+
+    pub fn monitor(&self) -> FailoverMonitor {
+        FailoverMonitor {
+            endpoints: self.endpoints.iter().map(|endpoint| endpoint.state.clone()).collect(),
+        }
+    }
+
+    // The indices of the available endpoints by priority, followed by the unavailable ones.
+    fn endpoints_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let failback_delay = Duration::from_millis(self.config.failback_delay_millis);
+        let (mut available, unavailable): (Vec<_>, Vec<_>) = (0..self.endpoints.len())
+            .partition(|&index| self.endpoints[index].state.is_available(now, failback_delay));
+        available.extend(unavailable);
+        available
+    }
+
+    async fn send<T, F>(&self, index: usize, request: &F) -> ClientResult<T>
+    where
+        F: for<'a> Fn(&'a TClient) -> RequestFuture<'a, T>,
+    {
+        let endpoint = &self.endpoints[index];
+        let start = Instant::now();
+        let res = request(&endpoint.client).await;
+        endpoint.state.record(&res, start.elapsed(), &self.config);
+        res
+    }
+
+    // Sends the request to the endpoints in `order` until one of them answers.
+    async fn request_from<T, F>(&self, order: &[usize], request: &F) -> ClientResult<T>
+    where
+        F: for<'a> Fn(&'a TClient) -> RequestFuture<'a, T>,
+    {
+        let mut last_err = None;
+        for &index in order {
+            match self.send(index, request).await {
+                Err(err) if is_endpoint_failure(&err) => {
+                    debug!("Request to {} failed: {err}.", self.endpoints[index].state.url);
+                    last_err = Some(err);
+                }
+                res => return res,
+            }
+        }
+        Err(last_err.expect("There should be at least one endpoint."))
+    }
+
+    async fn request<T, F>(&self, request: F) -> ClientResult<T>
+    where
+        F: for<'a> Fn(&'a TClient) -> RequestFuture<'a, T>,
+    {
+        self.request_from(&self.endpoints_order(), &request).await
+    }
+
+    // Like `request`, but if the first endpoint doesn't answer within `hedge_after`, sends the
+    // request to the second endpoint as well and returns the first answer.
+    async fn hedged_request<T, F>(&self, request: F, hedge_after: Duration) -> ClientResult<T>
+    where
+        F: for<'a> Fn(&'a TClient) -> RequestFuture<'a, T>,
+    {
+        let order = self.endpoints_order();
+        let (first, second, rest) = match order[..] {
+            [first, second, ..] => (first, second, &order[2..]),
+            _ => return self.request_from(&order, &request).await,
+        };
+
+        let primary = self.send(first, &request);
+        tokio::pin!(primary);
+        tokio::select! {
+            res = &mut primary => {
+                if !matches!(&res, Err(err) if is_endpoint_failure(err)) {
+                    return res;
+                }
+                return self.request_from(&order[1..], &request).await;
+            }
+            _ = tokio::time::sleep(hedge_after) => {}
+        }
+
+        debug!("Hedging a request to {}.", self.endpoints[second].state.url);
+        let secondary = self.send(second, &request);
+        tokio::pin!(secondary);
+        let remaining = tokio::select! {
+            res = &mut primary => {
+                if !matches!(&res, Err(err) if is_endpoint_failure(err)) {
+                    return res;
+                }
+                secondary.await
+            }
+            res = &mut secondary => {
+                if !matches!(&res, Err(err) if is_endpoint_failure(err)) {
+                    return res;
+                }
+                primary.await
+            }
+        };
+        if !matches!(&remaining, Err(err) if is_endpoint_failure(err)) || rest.is_empty() {
+            return remaining;
+        }
+        self.request_from(rest, &request).await
+    }
+}
+
+#[async_trait]
+impl<TClient: StarknetClientTrait + Send + Sync> StarknetClientTrait
+    for FailoverStarknetClient<TClient>
+{
+    async fn block_number(&self) -> ClientResult<Option<BlockNumber>> {
+        self.request(|client| client.block_number()).await
+    }
+
+    async fn block(&self, block_number: BlockNumber) -> ClientResult<Option<Block>> {
+        match self.config.hedge_after_millis {
+            Some(hedge_after_millis) => {
+                self.hedged_request(
+                    move |client| client.block(block_number),
+                    Duration::from_millis(hedge_after_millis),
+                )
+                .await
+            }
+            None => self.request(move |client| client.block(block_number)).await,
+        }
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>> {
+        self.request(move |client| client.class_by_hash(class_hash)).await
+    }
+
+    async fn compiled_class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<CasmContractClass>> {
+        self.request(move |client| client.compiled_class_by_hash(class_hash)).await
+    }
+
+    async fn state_update(&self, block_number: BlockNumber) -> ClientResult<Option<StateUpdate>> {
+        self.request(move |client| client.state_update(block_number)).await
+    }
+
+    async fn block_traces(&self, block_number: BlockNumber) -> ClientResult<Option<BlockTraces>> {
+        self.request(move |client| client.block_traces(block_number)).await
+    }
+}
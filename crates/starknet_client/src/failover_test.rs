@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use async_trait::async_trait;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use reqwest::StatusCode;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+
+use super::{FailoverConfig, FailoverStarknetClient};
+use crate::{
+    Block, BlockTraces, ClientError, ClientResult, GenericContractClass, MockStarknetClientTrait,
+    StarknetClientTrait, StateUpdate,
+};
+
+const FAILBACK_DELAY: Duration = Duration::from_millis(100);
+
+fn get_config(hedge_after_millis: Option<u64>) -> FailoverConfig {
+    FailoverConfig {
+        max_consecutive_errors: 2,
+        failback_delay_millis: FAILBACK_DELAY.as_millis() as u64,
+        hedge_after_millis,
+    }
+}
+
+fn unavailable() -> ClientError {
+    ClientError::BadResponseStatus {
+        code: StatusCode::SERVICE_UNAVAILABLE,
+        message: "Service unavailable".to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn fails_over_to_the_next_endpoint() {
+    let mut primary = MockStarknetClientTrait::new();
+    primary.expect_block_number().times(1).returning(|| Err(unavailable()));
+    let mut secondary = MockStarknetClientTrait::new();
+    secondary.expect_block_number().times(1).returning(|| Ok(Some(BlockNumber(5))));
+    let client = FailoverStarknetClient::with_clients(
+        vec![("primary".to_owned(), primary), ("secondary".to_owned(), secondary)],
+        get_config(None),
+    );
+
+    assert_eq!(client.block_number().await.unwrap(), Some(BlockNumber(5)));
+
+    let stats = client.monitor().endpoints_stats();
+    assert_eq!((stats[0].requests, stats[0].errors), (1, 1));
+    assert_eq!((stats[1].requests, stats[1].errors), (1, 0));
+    // A single error isn't enough to mark the primary as unhealthy.
+    assert!(stats[0].healthy);
+}
+
+#[tokio::test]
+async fn answers_are_not_failed_over() {
+    let mut primary = MockStarknetClientTrait::new();
+    primary.expect_block().times(1).returning(|_| Ok(None));
+    let secondary = MockStarknetClientTrait::new();
+    let client = FailoverStarknetClient::with_clients(
+        vec![("primary".to_owned(), primary), ("secondary".to_owned(), secondary)],
+        get_config(None),
+    );
+
+    assert!(client.block(BlockNumber(0)).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn avoids_unhealthy_endpoints_and_fails_back() {
+    let mut primary = MockStarknetClientTrait::new();
+    primary.expect_block_number().times(2).returning(|| Err(unavailable()));
+    primary.expect_block_number().times(1).returning(|| Ok(Some(BlockNumber(1))));
+    let mut secondary = MockStarknetClientTrait::new();
+    secondary.expect_block_number().times(3).returning(|| Ok(Some(BlockNumber(2))));
+    let client = FailoverStarknetClient::with_clients(
+        vec![("primary".to_owned(), primary), ("secondary".to_owned(), secondary)],
+        get_config(None),
+    );
+    let monitor = client.monitor();
+
+    // Two consecutive errors mark the primary as unhealthy.
+    for _ in 0..2 {
+        assert_eq!(client.block_number().await.unwrap(), Some(BlockNumber(2)));
+    }
+    assert!(!monitor.endpoints_stats()[0].healthy);
+
+    // The primary isn't requested until the failback delay passes.
+    assert_eq!(client.block_number().await.unwrap(), Some(BlockNumber(2)));
+    tokio::time::sleep(FAILBACK_DELAY).await;
+    assert_eq!(client.block_number().await.unwrap(), Some(BlockNumber(1)));
+    assert!(monitor.endpoints_stats()[0].healthy);
+}
+
+#[tokio::test]
+async fn returns_the_last_error_when_all_endpoints_fail() {
+    let mut primary = MockStarknetClientTrait::new();
+    primary.expect_state_update().times(1).returning(|_| Err(unavailable()));
+    let mut secondary = MockStarknetClientTrait::new();
+    secondary.expect_state_update().times(1).returning(|_| {
+        Err(ClientError::BadResponseStatus {
+            code: StatusCode::BAD_GATEWAY,
+            message: "Bad gateway".to_owned(),
+        })
+    });
+    let client = FailoverStarknetClient::with_clients(
+        vec![("primary".to_owned(), primary), ("secondary".to_owned(), secondary)],
+        get_config(None),
+    );
+
+    let err = client.state_update(BlockNumber(0)).await.unwrap_err();
+    assert_matches!(
+        err,
+        ClientError::BadResponseStatus { code, message: _ } if code == StatusCode::BAD_GATEWAY
+    );
+}
+
+// A client that answers block requests after a delay.
+struct DelayedClient {
+    delay: Duration,
+    block_number: BlockNumber,
+}
+
+#[async_trait]
+impl StarknetClientTrait for DelayedClient {
+    async fn block_number(&self) -> ClientResult<Option<BlockNumber>> {
+        unimplemented!();
+    }
+
+    async fn block(&self, _block_number: BlockNumber) -> ClientResult<Option<Block>> {
+        tokio::time::sleep(self.delay).await;
+        Ok(Some(Block { block_number: self.block_number, ..Block::default() }))
+    }
+
+    async fn class_by_hash(
+        &self,
+        _class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>> {
+        unimplemented!();
+    }
+
+    async fn compiled_class_by_hash(
+        &self,
+        _class_hash: ClassHash,
+    ) -> ClientResult<Option<CasmContractClass>> {
+        unimplemented!();
+    }
+
+    async fn state_update(&self, _block_number: BlockNumber) -> ClientResult<Option<StateUpdate>> {
+        unimplemented!();
+    }
+
+    async fn block_traces(&self, _block_number: BlockNumber) -> ClientResult<Option<BlockTraces>> {
+        unimplemented!();
+    }
+}
+
+#[tokio::test]
+async fn hedges_slow_block_requests() {
+    let primary = DelayedClient { delay: Duration::from_secs(10), block_number: BlockNumber(0) };
+    let secondary =
+        DelayedClient { delay: Duration::from_millis(10), block_number: BlockNumber(1) };
+    let client = FailoverStarknetClient::with_clients(
+        vec![("primary".to_owned(), primary), ("secondary".to_owned(), secondary)],
+        get_config(Some(10)),
+    );
+
+    let block = client.block(BlockNumber(1)).await.unwrap().unwrap();
+    assert_eq!(block.block_number, BlockNumber(1));
+    let stats = client.monitor().endpoints_stats();
+    // The request to the primary was cancelled.
+    assert_eq!((stats[0].requests, stats[1].requests), (0, 1));
+}
+
+#[tokio::test]
+async fn fast_block_requests_are_not_hedged() {
+    let primary = DelayedClient { delay: Duration::from_millis(0), block_number: BlockNumber(0) };
+    let secondary = DelayedClient { delay: Duration::from_millis(0), block_number: BlockNumber(1) };
+    let client = FailoverStarknetClient::with_clients(
+        vec![("primary".to_owned(), primary), ("secondary".to_owned(), secondary)],
+        get_config(Some(1000)),
+    );
+
+    let block = client.block(BlockNumber(0)).await.unwrap().unwrap();
+    assert_eq!(block.block_number, BlockNumber(0));
+    let stats = client.monitor().endpoints_stats();
+    assert_eq!((stats[0].requests, stats[1].requests), (1, 0));
+}
@@ -0,0 +1,264 @@
+//! Recording of the responses of a starknet gateway into a fixture directory, and replaying them
+//! later without a gateway.
+//!
+//! Each response is written to its own file, named after the request and the order in which its
+//! responses were received, e.g., `get_block_5.0.json` and then `get_block_5.1.json` for the second
+//! request of block 5. Requests without a query, which return the latest data of the gateway,
+//! are named `latest`, e.g., `get_block_latest.0.json`. Successful responses are stored as returned
+//! by the gateway, and errors of the gateway are stored in files ending with `.error.json`. Other
+//! errors are not recorded. A replay serves the responses to a request in the same order.
+
+#[cfg(test)]
+#[path = "fixtures_test.rs"]
+mod fixtures_test;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    Block, BlockTraces, ClientCreationError, ClientError, ClientResult, GenericContractClass,
+    RetryConfig, StarknetClient, StarknetClientTrait, StarknetError, StateUpdate,
+};
+
+// The client of a replay doesn't send requests, it only needs a URL to build them.
+const REPLAY_URL: &str = "http://replay/";
+const REPLAY_NODE_VERSION: &str = "replay";
+
+/// Errors that may be returned while recording or replaying responses.
+#[derive(thiserror::Error, Debug)]
+pub enum FixtureError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("No recorded response for request {}.", request)]
+    MissingResponse { request: String },
+}
+
+// The responses of the client, either recorded or replayed.
+pub(crate) enum Fixtures {
+    Record(Recorder),
+    Replay(Replayer),
+}
+
+pub(crate) struct Recorder {
+    dir: PathBuf,
+    // The number of responses recorded for each request.
+    recorded: Mutex<HashMap<String, usize>>,
+}
+
+impl Recorder {
+    /// Creates a recorder into `dir`, which is created if needed. The responses recorded into
+    /// `dir` before are removed, so they are not replayed after the new ones.
+    pub(crate) fn new(dir: PathBuf) -> Result<Self, FixtureError> {
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.file_name().and_then(|name| name.to_str()).map_or(false, is_fixture_file_name) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(Recorder { dir, recorded: Mutex::new(HashMap::new()) })
+    }
+
+    /// Writes the response to the request to a new file.
+    pub(crate) fn record(&self, url: &Url, response: &ClientResult<String>) -> ClientResult<()> {
+        let (suffix, body) = match response {
+            Ok(body) => ("json", body.clone()),
+            Err(ClientError::StarknetError(err)) => ("error.json", serde_json::to_string(err)?),
+            Err(_) => return Ok(()),
+        };
+        let key = request_key(url);
+        let mut recorded = self.recorded.lock().expect("Recorder lock should not be poisoned.");
+        let index = recorded.entry(key.clone()).or_default();
+        let path = self.dir.join(format!("{key}.{index}.{suffix}"));
+        debug!("Recording the response to {key} in {path:?}.");
+        fs::write(path, body).map_err(FixtureError::from)?;
+        *index += 1;
+        Ok(())
+    }
+}
+
+pub(crate) struct Replayer {
+    dir: PathBuf,
+    // The number of responses served for each request.
+    served: Mutex<HashMap<String, usize>>,
+}
+
+impl Replayer {
+    /// Returns the next recorded response to the request. Once all of them were served, the last
+    /// one is returned again.
+    pub(crate) fn replay(&self, url: &Url) -> ClientResult<String> {
+        let key = request_key(url);
+        let mut served = self.served.lock().expect("Replayer lock should not be poisoned.");
+        let n_served = served.entry(key.clone()).or_default();
+        if let Some(response) = self.read(&key, *n_served)? {
+            *n_served += 1;
+            return response;
+        }
+        let last = n_served
+            .checked_sub(1)
+            .ok_or_else(|| FixtureError::MissingResponse { request: key.clone() })?;
+        self.read(&key, last)?.expect("A served response should exist.")
+    }
+
+    fn read(&self, key: &str, index: usize) -> Result<Option<ClientResult<String>>, FixtureError> {
+        if let Some(body) = read_if_exists(self.dir.join(format!("{key}.{index}.json")))? {
+            return Ok(Some(Ok(body)));
+        }
+        let Some(body) = read_if_exists(self.dir.join(format!("{key}.{index}.error.json")))? else {
+            return Ok(None);
+        };
+        Ok(Some(match serde_json::from_str::<StarknetError>(&body) {
+            Ok(err) => Err(ClientError::StarknetError(err)),
+            Err(err) => Err(ClientError::SerdeError(err)),
+        }))
+    }
+}
+
+fn read_if_exists(path: PathBuf) -> Result<Option<String>, FixtureError> {
+    match fs::read_to_string(path) {
+        Ok(body) => Ok(Some(body)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Whether the file is a recorded response, named `{key}.{index}.json` or
+// `{key}.{index}.error.json`.
+fn is_fixture_file_name(name: &str) -> bool {
+    let Some(name) = name.strip_suffix(".json") else {
+        return false;
+    };
+    let name = name.strip_suffix(".error").unwrap_or(name);
+    name.rsplit_once('.').map_or(false, |(key, index)| {
+        !key.is_empty() && !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit())
+    })
+}
+
+// Identifies a request by the name of the endpoint and the values of its query, e.g.,
+// `get_block_5`, or `get_block_latest` for a request without a query.
+fn request_key(url: &Url) -> String {
+    let mut key = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default()
+        .to_owned();
+    if url.query_pairs().next().is_none() {
+        key.push_str("_latest");
+    }
+    for (_, value) in url.query_pairs() {
+        key.push('_');
+        key.push_str(&value);
+    }
+    key
+}
+
+/// A [`StarknetClient`] that records the responses of the gateway into a fixture directory, to be
+/// replayed by a [`ReplayStarknetClient`].
+pub struct RecordingStarknetClient {
+    client: StarknetClient,
+}
+
+impl RecordingStarknetClient {
+    /// Wraps `client`, recording its responses into `dir`, which is created if needed. Responses
+    /// that were recorded into `dir` before are removed.
+    pub fn new(mut client: StarknetClient, dir: impl Into<PathBuf>) -> Result<Self, FixtureError> {
+        client.fixtures = Some(Fixtures::Record(Recorder::new(dir.into())?));
+        Ok(RecordingStarknetClient { client })
+    }
+}
+
+/// A [`StarknetClientTrait`] implementation that serves the responses recorded by a
+/// [`RecordingStarknetClient`], in the order they were recorded.
+pub struct ReplayStarknetClient {
+    client: StarknetClient,
+}
+
+impl ReplayStarknetClient {
+    /// Creates a client that serves the responses recorded in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, ClientCreationError> {
+        let retry_config =
+            RetryConfig { retry_base_millis: 0, retry_max_delay_millis: 0, max_retries: 0 };
+        let mut client = StarknetClient::new(REPLAY_URL, None, REPLAY_NODE_VERSION, retry_config)?;
+        client.fixtures = Some(Fixtures::Replay(Replayer {
+            dir: dir.into(),
+            served: Mutex::new(HashMap::new()),
+        }));
+        Ok(ReplayStarknetClient { client })
+    }
+}
+
+#[async_trait]
+impl StarknetClientTrait for RecordingStarknetClient {
+    async fn block_number(&self) -> ClientResult<Option<BlockNumber>> {
+        self.client.block_number().await
+    }
+
+    async fn block(&self, block_number: BlockNumber) -> ClientResult<Option<Block>> {
+        self.client.block(block_number).await
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>> {
+        self.client.class_by_hash(class_hash).await
+    }
+
+    async fn compiled_class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<CasmContractClass>> {
+        self.client.compiled_class_by_hash(class_hash).await
+    }
+
+    async fn state_update(&self, block_number: BlockNumber) -> ClientResult<Option<StateUpdate>> {
+        self.client.state_update(block_number).await
+    }
+
+    async fn block_traces(&self, block_number: BlockNumber) -> ClientResult<Option<BlockTraces>> {
+        self.client.block_traces(block_number).await
+    }
+}
+
+#[async_trait]
+impl StarknetClientTrait for ReplayStarknetClient {
+    async fn block_number(&self) -> ClientResult<Option<BlockNumber>> {
+        self.client.block_number().await
+    }
+
+    async fn block(&self, block_number: BlockNumber) -> ClientResult<Option<Block>> {
+        self.client.block(block_number).await
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>> {
+        self.client.class_by_hash(class_hash).await
+    }
+
+    async fn compiled_class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<CasmContractClass>> {
+        self.client.compiled_class_by_hash(class_hash).await
+    }
+
+    async fn state_update(&self, block_number: BlockNumber) -> ClientResult<Option<StateUpdate>> {
+        self.client.state_update(block_number).await
+    }
+
+    async fn block_traces(&self, block_number: BlockNumber) -> ClientResult<Option<BlockTraces>> {
+        self.client.block_traces(block_number).await
+    }
+}
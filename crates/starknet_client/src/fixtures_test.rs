@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use assert_matches::assert_matches;
+use mockito::mock;
+use starknet_api::block::BlockNumber;
+use tempfile::tempdir;
+use url::Url;
+
+use super::{
+    request_key, FixtureError, Recorder, RecordingStarknetClient, ReplayStarknetClient, Replayer,
+};
+use crate::test_utils::read_resource::read_resource_file;
+use crate::test_utils::retry::get_test_config;
+use crate::{Block, ClientError, StarknetClient, StarknetClientTrait};
+
+const NODE_VERSION: &str = "NODE VERSION";
+
+#[test]
+fn request_keys() {
+    let url = Url::parse("https://url/feeder_gateway/get_block").unwrap();
+    assert_eq!(request_key(&url), "get_block_latest");
+    let url = Url::parse("https://url/feeder_gateway/get_state_update?blockNumber=5").unwrap();
+    assert_eq!(request_key(&url), "get_state_update_5");
+}
+
+#[test]
+fn replay_in_recorded_order() {
+    let dir = tempdir().unwrap();
+    let url = Url::parse("https://url/feeder_gateway/get_block?blockNumber=5").unwrap();
+    let recorder = Recorder::new(dir.path().to_path_buf()).unwrap();
+    for body in ["old", "old", "new"] {
+        recorder.record(&url, &Ok(body.to_owned())).unwrap();
+    }
+    assert!(dir.path().join("get_block_5.2.json").exists());
+
+    let replayer = Replayer { dir: dir.path().to_path_buf(), served: Mutex::new(HashMap::new()) };
+    assert_eq!(replayer.replay(&url).unwrap(), "old");
+    assert_eq!(replayer.replay(&url).unwrap(), "old");
+    assert_eq!(replayer.replay(&url).unwrap(), "new");
+    // The last response is served again.
+    assert_eq!(replayer.replay(&url).unwrap(), "new");
+
+    let url = Url::parse("https://url/feeder_gateway/get_block?blockNumber=6").unwrap();
+    assert_matches!(
+        replayer.replay(&url),
+        Err(ClientError::FixtureError(FixtureError::MissingResponse { request }))
+        if request == "get_block_6"
+    );
+}
+
+#[test]
+fn recorder_removes_stale_responses() {
+    let dir = tempdir().unwrap();
+    let url = Url::parse("https://url/feeder_gateway/get_block?blockNumber=5").unwrap();
+    let recorder = Recorder::new(dir.path().to_path_buf()).unwrap();
+    for body in ["first", "second"] {
+        recorder.record(&url, &Ok(body.to_owned())).unwrap();
+    }
+    fs::write(dir.path().join("get_block_6.0.error.json"), "{}").unwrap();
+    fs::write(dir.path().join("README.json"), "{}").unwrap();
+
+    let recorder = Recorder::new(dir.path().to_path_buf()).unwrap();
+    recorder.record(&url, &Ok("third".to_owned())).unwrap();
+    assert!(!dir.path().join("get_block_5.1.json").exists());
+    assert!(!dir.path().join("get_block_6.0.error.json").exists());
+    assert!(dir.path().join("README.json").exists());
+
+    let replayer = Replayer { dir: dir.path().to_path_buf(), served: Mutex::new(HashMap::new()) };
+    assert_eq!(replayer.replay(&url).unwrap(), "third");
+    assert_eq!(replayer.replay(&url).unwrap(), "third");
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let dir = tempdir().unwrap();
+    let starknet_client =
+        StarknetClient::new(&mockito::server_url(), None, NODE_VERSION, get_test_config()).unwrap();
+    let recording_client = RecordingStarknetClient::new(starknet_client, dir.path()).unwrap();
+
+    let raw_block = read_resource_file("block.json");
+    let mock_block = mock("GET", "/feeder_gateway/get_block?blockNumber=1001")
+        .with_status(200)
+        .with_body(&raw_block)
+        .create();
+    let body =
+        r#"{"code": "StarknetErrorCode.BLOCK_NOT_FOUND", "message": "Block 1002 was not found."}"#;
+    let mock_no_block = mock("GET", "/feeder_gateway/get_block?blockNumber=1002")
+        .with_status(500)
+        .with_body(body)
+        .create();
+    let block = recording_client.block(BlockNumber(1001)).await.unwrap();
+    assert!(recording_client.block(BlockNumber(1002)).await.unwrap().is_none());
+    mock_block.assert();
+    mock_no_block.assert();
+    assert_eq!(block, Some(serde_json::from_str::<Block>(&raw_block).unwrap()));
+
+    let replay_client = ReplayStarknetClient::new(dir.path()).unwrap();
+    assert_eq!(replay_client.block(BlockNumber(1001)).await.unwrap(), block);
+    assert!(replay_client.block(BlockNumber(1002)).await.unwrap().is_none());
+    assert_matches!(
+        replay_client.block(BlockNumber(1003)).await,
+        Err(ClientError::FixtureError(FixtureError::MissingResponse { .. }))
+    );
+}
@@ -2,7 +2,10 @@
 //!
 //! [`starknet`]: https://starknet.io/
 
+pub mod failover;
+pub mod fixtures;
 mod objects;
+pub mod rate_limit;
 pub mod retry;
 #[cfg(test)]
 mod starknet_client_test;
@@ -10,12 +13,14 @@ mod starknet_client_test;
 mod test_utils;
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 #[cfg(any(feature = "testing", test))]
 use mockall::automock;
-use reqwest::header::HeaderMap;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use starknet_api::block::BlockNumber;
@@ -25,16 +30,30 @@ use starknet_api::StarknetApiError;
 use tracing::debug;
 use url::Url;
 
-pub use self::objects::block::{Block, GlobalRoot, TransactionReceiptsError};
+pub use self::failover::{
+    EndpointConfig, EndpointStats, FailoverConfig, FailoverMonitor, FailoverStarknetClient,
+};
+use self::fixtures::Fixtures;
+pub use self::fixtures::{FixtureError, RecordingStarknetClient, ReplayStarknetClient};
+pub use self::objects::block::{
+    Block, BlockStatus, GlobalRoot, ResourcePrice, TransactionReceiptsError,
+};
 pub use self::objects::deprecated_contract_class::DeprecatedContractClass;
 pub use self::objects::state::{
     ContractClass, DeclaredClassHashEntry, DeployedContract, ReplacedClass, StateDiff, StateUpdate,
     StorageEntry,
 };
+pub use self::objects::trace::{
+    BlockTraces, CallType, FunctionInvocation, OrderedEvent, OrderedL2ToL1Message, TransactionTrace,
+};
+pub use self::objects::transaction::{
+    BuiltinInstanceCounter, ExecutionResources, L1ToL2Message, L1ToL2Nonce, Transaction,
+    TransactionExecutionStatus, TransactionReceipt,
+};
+use self::rate_limit::RateLimiter;
+pub use self::rate_limit::{RateLimitConfig, TokenBucket};
 use self::retry::Retry;
 pub use self::retry::RetryConfig;
-#[cfg(doc)]
-pub use crate::objects::transaction::TransactionReceipt;
 
 /// A [`Result`] in which the error is a [`ClientError`].
 pub type ClientResult<T> = Result<T, ClientError>;
@@ -61,6 +80,9 @@ pub trait StarknetClientTrait {
     ) -> ClientResult<Option<CasmContractClass>>;
     /// Returns a [`starknet_client`][`StateUpdate`] corresponding to `block_number`.
     async fn state_update(&self, block_number: BlockNumber) -> ClientResult<Option<StateUpdate>>;
+    /// Returns the [`BlockTraces`] of the transactions of the block corresponding to
+    /// `block_number`, returning [`None`] in case no such block exists in the system.
+    async fn block_traces(&self, block_number: BlockNumber) -> ClientResult<Option<BlockTraces>>;
 }
 
 /// A starknet client.
@@ -69,6 +91,10 @@ pub struct StarknetClient {
     http_headers: HeaderMap,
     internal_client: Client,
     retry_config: RetryConfig,
+    // Shared by all the requests, so they are throttled together.
+    rate_limiter: Arc<RateLimiter>,
+    // If set, the responses are recorded into, or replayed from, a fixture directory.
+    fixtures: Option<Fixtures>,
 }
 
 #[derive(Clone, Debug)]
@@ -77,6 +103,7 @@ struct StarknetUrls {
     get_contract_by_hash: Url,
     get_compiled_class_by_class_hash: Url,
     get_state_update: Url,
+    get_block_traces: Url,
 }
 
 /// Error codes returned by the starknet gateway.
@@ -129,6 +156,9 @@ pub enum ClientError {
     /// A client error representing http request errors.
     #[error(transparent)]
     RequestError(#[from] reqwest::Error),
+    /// A client error representing a response asking to retry after the given delay.
+    #[error("Throttled with status code {:?}, retry after {:?}.", code, retry_after)]
+    Throttled { code: StatusCode, retry_after: Duration, message: String },
     /// A client error representing errors that might be solved by retrying mechanism.
     #[error("Retry error code: {:?}, message: {:?}.", code, message)]
     RetryError { code: RetryErrorCode, message: String },
@@ -146,6 +176,9 @@ pub enum ClientError {
     TransactionReceiptsError(#[from] TransactionReceiptsError),
     #[error("Invalid transaction: {:?}, error: {:?}.", tx_hash, msg)]
     BadTransaction { tx_hash: TransactionHash, msg: String },
+    /// A client error representing errors of recording or replaying responses.
+    #[error(transparent)]
+    FixtureError(#[from] FixtureError),
 }
 
 const GET_BLOCK_URL: &str = "feeder_gateway/get_block";
@@ -153,6 +186,7 @@ const GET_CONTRACT_BY_HASH_URL: &str = "feeder_gateway/get_class_by_hash";
 const GET_COMPILED_CLASS_BY_CLASS_HASH_URL: &str =
     "feeder_gateway/get_compiled_class_by_class_hash";
 const GET_STATE_UPDATE_URL: &str = "feeder_gateway/get_state_update";
+const GET_BLOCK_TRACES_URL: &str = "feeder_gateway/get_block_traces";
 const BLOCK_NUMBER_QUERY: &str = "blockNumber";
 const CLASS_HASH_QUERY: &str = "classHash";
 
@@ -165,6 +199,7 @@ impl StarknetUrls {
             get_compiled_class_by_class_hash: base_url
                 .join(GET_COMPILED_CLASS_BY_CLASS_HASH_URL)?,
             get_state_update: base_url.join(GET_STATE_UPDATE_URL)?,
+            get_block_traces: base_url.join(GET_BLOCK_TRACES_URL)?,
         })
     }
 }
@@ -201,9 +236,23 @@ impl StarknetClient {
             http_headers: header_map,
             internal_client: Client::builder().user_agent(app_user_agent).build()?,
             retry_config,
+            rate_limiter: Arc::new(RateLimiter::new(None)),
+            fixtures: None,
         })
     }
 
+    /// Limits the rate of the requests of the client according to `config`.
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limiter = Arc::new(RateLimiter::new(Some(config)));
+        self
+    }
+
+    /// Returns the total time the requests of the client were held back by the rate limit or by
+    /// the gateway asking to retry later.
+    pub fn throttled_time(&self) -> Duration {
+        self.rate_limiter.throttled_time()
+    }
+
     fn get_retry_error_code(err: &ClientError) -> Option<RetryErrorCode> {
         match err {
             ClientError::BadResponseStatus { code, message: _ } => match *code {
@@ -216,6 +265,14 @@ impl StarknetClient {
                 _ => None,
             },
 
+            ClientError::Throttled { code, retry_after: _, message: _ } => {
+                if *code == StatusCode::TOO_MANY_REQUESTS {
+                    Some(RetryErrorCode::TooManyRequests)
+                } else {
+                    Some(RetryErrorCode::ServiceUnavailable)
+                }
+            }
+
             ClientError::RequestError(internal_err) => {
                 if internal_err.is_timeout() {
                     Some(RetryErrorCode::Timeout)
@@ -238,21 +295,50 @@ impl StarknetClient {
         Self::get_retry_error_code(err).is_some()
     }
 
+    fn retry_after(err: &ClientError) -> Option<Duration> {
+        match err {
+            ClientError::Throttled { code: _, retry_after, message: _ } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
     async fn request_with_retry(&self, url: Url) -> Result<String, ClientError> {
-        Retry::new(&self.retry_config)
-            .start_with_condition(|| self.request(url.clone()), Self::should_retry)
+        if let Some(Fixtures::Replay(replayer)) = &self.fixtures {
+            return replayer.replay(&url);
+        }
+        let response = Retry::new(&self.retry_config)
+            .start_with_min_delay(
+                || self.request(url.clone()),
+                Self::should_retry,
+                Self::retry_after,
+            )
             .await
             .map_err(|err| {
                 Self::get_retry_error_code(&err)
                     .map(|code| ClientError::RetryError { code, message: err.to_string() })
                     .unwrap_or(err)
-            })
+            });
+        if let Some(Fixtures::Record(recorder)) = &self.fixtures {
+            recorder.record(&url, &response)?;
+        }
+        response
     }
 
     async fn request(&self, url: Url) -> ClientResult<String> {
+        self.rate_limiter.acquire().await;
         let res = self.internal_client.get(url).headers(self.http_headers.clone()).send().await;
         let (code, message) = match res {
-            Ok(response) => (response.status(), response.text().await?),
+            Ok(response) => {
+                let code = response.status();
+                let max_delay = Duration::from_millis(self.retry_config.retry_max_delay_millis);
+                if let Some(retry_after) = get_retry_after(code, response.headers(), max_delay) {
+                    // Hold back the other requests as well, they would be throttled too.
+                    self.rate_limiter.pause(retry_after);
+                    let message = response.text().await?;
+                    return Err(ClientError::Throttled { code, retry_after, message });
+                }
+                (code, response.text().await?)
+            }
             Err(err) => {
                 let msg = err.to_string();
                 (err.status().ok_or(err)?, msg)
@@ -295,6 +381,16 @@ impl StarknetClient {
     }
 }
 
+// Returns the delay a throttling response asks to wait before retrying. Only a delay in seconds is
+// supported, not an HTTP date. The delay is capped at `max_delay`.
+fn get_retry_after(code: StatusCode, headers: &HeaderMap, max_delay: Duration) -> Option<Duration> {
+    if ![StatusCode::TOO_MANY_REQUESTS, StatusCode::SERVICE_UNAVAILABLE].contains(&code) {
+        return None;
+    }
+    let seconds = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds).min(max_delay))
+}
+
 #[async_trait]
 impl StarknetClientTrait for StarknetClient {
     async fn block_number(&self) -> ClientResult<Option<BlockNumber>> {
@@ -373,6 +469,23 @@ impl StarknetClientTrait for StarknetClient {
             }
         }
     }
+
+    async fn block_traces(&self, block_number: BlockNumber) -> ClientResult<Option<BlockTraces>> {
+        let mut url = self.urls.get_block_traces.clone();
+        url.query_pairs_mut().append_pair(BLOCK_NUMBER_QUERY, &block_number.to_string());
+        let response = self.request_with_retry(url).await;
+        match response {
+            Ok(raw_block_traces) => Ok(Some(serde_json::from_str(&raw_block_traces)?)),
+            Err(ClientError::StarknetError(StarknetError {
+                code: StarknetErrorCode::BlockNotFound,
+                message: _,
+            })) => Ok(None),
+            Err(err) => {
+                debug!("Failed to get traces of block {} from starknet server.", block_number);
+                Err(err)
+            }
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
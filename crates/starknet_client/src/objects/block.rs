@@ -1,6 +1,6 @@
 use std::ops::Index;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 #[cfg(doc)]
 use starknet_api::block::Block as starknet_api_block;
 use starknet_api::block::{BlockHash, BlockNumber, BlockTimestamp, GasPrice};
@@ -17,9 +17,7 @@ use crate::objects::transaction::{
 };
 use crate::{ClientError, ClientResult};
 
-#[derive(
-    Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord,
-)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Deserialize, PartialOrd, Ord)]
 #[serde(try_from = "NonPrefixedBytesAsHex<32_usize>")]
 pub struct GlobalRoot(pub StarkHash);
 
@@ -31,6 +29,20 @@ impl TryFrom<NonPrefixedBytesAsHex<32_usize>> for GlobalRoot {
         Ok(Self(StarkHash::try_from(val)?))
     }
 }
+// Serialized the same way, so that the roots the node serves are parsed like the sequencer's.
+impl Serialize for GlobalRoot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = self.0.bytes().iter().map(|byte| format!("{byte:02x}")).collect();
+        serializer.serialize_str(&hex)
+    }
+}
+
+impl From<starknet_api::core::GlobalRoot> for GlobalRoot {
+    fn from(val: starknet_api::core::GlobalRoot) -> Self {
+        Self(val.0)
+    }
+}
+
 impl From<GlobalRoot> for starknet_api::core::GlobalRoot {
     fn from(val: GlobalRoot) -> Self {
         Self(val.0)
@@ -42,7 +54,11 @@ impl From<GlobalRoot> for starknet_api::core::GlobalRoot {
 pub struct Block {
     pub block_hash: BlockHash,
     pub block_number: BlockNumber,
+    // Replaced by l1_gas_price in newer blocks.
+    #[serde(default)]
     pub gas_price: GasPrice,
+    #[serde(default)]
+    pub l1_gas_price: Option<ResourcePrice>,
     pub parent_block_hash: BlockHash,
     #[serde(default)]
     pub sequencer_address: ContractAddress,
@@ -52,6 +68,20 @@ pub struct Block {
     pub timestamp: BlockTimestamp,
     pub transactions: Vec<Transaction>,
     pub transaction_receipts: Vec<TransactionReceipt>,
+    #[serde(default)]
+    pub starknet_version: Option<String>,
+}
+
+impl Block {
+    /// The price of a unit of L1 gas in the block, in wei.
+    pub fn l1_gas_price(&self) -> GasPrice {
+        self.l1_gas_price.map(|price| price.price_in_wei).unwrap_or(self.gas_price)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct ResourcePrice {
+    pub price_in_wei: GasPrice,
 }
 
 /// Errors that might be encountered while converting the client representation of a [`Block`] to a
@@ -116,6 +146,8 @@ impl TryFrom<Block> for starknet_api::block::Block {
     type Error = ClientError;
 
     fn try_from(block: Block) -> ClientResult<Self> {
+        let gas_price = block.l1_gas_price();
+
         // Check that the number of receipts is the same as the number of transactions.
         let num_of_txs = block.transactions.len();
         let num_of_receipts = block.transaction_receipts.len();
@@ -194,7 +226,7 @@ impl TryFrom<Block> for starknet_api::block::Block {
             block_hash: block.block_hash,
             parent_hash: block.parent_block_hash,
             block_number: block.block_number,
-            gas_price: block.gas_price,
+            gas_price,
             state_root: block.state_root.into(),
             sequencer: block.sequencer_address,
             timestamp: block.timestamp,
@@ -223,6 +255,17 @@ pub enum BlockStatus {
     Reverted,
 }
 
+impl From<starknet_api::block::BlockStatus> for BlockStatus {
+    fn from(status: starknet_api::block::BlockStatus) -> Self {
+        match status {
+            starknet_api::block::BlockStatus::AcceptedOnL1 => BlockStatus::AcceptedOnL1,
+            starknet_api::block::BlockStatus::AcceptedOnL2 => BlockStatus::AcceptedOnL2,
+            starknet_api::block::BlockStatus::Pending => BlockStatus::Pending,
+            starknet_api::block::BlockStatus::Rejected => BlockStatus::Aborted,
+        }
+    }
+}
+
 impl From<BlockStatus> for starknet_api::block::BlockStatus {
     fn from(status: BlockStatus) -> Self {
         match status {
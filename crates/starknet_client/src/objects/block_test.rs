@@ -1,7 +1,7 @@
 use assert::assert_ok;
 use assert_matches::assert_matches;
 use indexmap::IndexMap;
-use starknet_api::block::BlockHash;
+use starknet_api::block::{BlockHash, GasPrice};
 use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::serde_utils::bytes_from_hex_str;
@@ -24,6 +24,39 @@ fn load_block_succeeds() {
     assert_ok!(serde_json::from_str::<Block>(&read_resource_file("block.json")));
 }
 
+// The node serves blocks in the format of the sequencer, so they must parse back the same.
+#[test]
+fn serialize_block_and_back() {
+    let block: Block = serde_json::from_str(&read_resource_file("block.json")).unwrap();
+    let serialized = serde_json::to_string(&block).unwrap();
+    assert_eq!(serde_json::from_str::<Block>(&serialized).unwrap(), block);
+}
+
+#[test]
+fn serialize_global_root_without_prefix() {
+    let root = GlobalRoot(stark_felt!("0x1"));
+    assert_eq!(serde_json::to_value(root).unwrap(), serde_json::json!(format!("{:0>64}", "1")));
+}
+
+#[test]
+fn block_l1_gas_price() {
+    let mut raw_block: serde_json::Value =
+        serde_json::from_str(&read_resource_file("block.json")).unwrap();
+    let block: Block = serde_json::from_value(raw_block.clone()).unwrap();
+    assert_eq!(block.starknet_version, Some("0.9.1".to_string()));
+    assert_eq!(block.l1_gas_price(), GasPrice(0x59682f03));
+
+    // Newer blocks give the gas price in the l1_gas_price field instead.
+    let raw_block_object = raw_block.as_object_mut().unwrap();
+    raw_block_object.remove("gas_price");
+    raw_block_object
+        .insert("l1_gas_price".to_string(), serde_json::json!({"price_in_wei": "0x3b9aca00"}));
+    let block: Block = serde_json::from_value(raw_block).unwrap();
+    assert_eq!(block.l1_gas_price(), GasPrice(0x3b9aca00));
+    let block = starknet_api::block::Block::try_from(block).unwrap();
+    assert_eq!(block.header.gas_price, GasPrice(0x3b9aca00));
+}
+
 #[test]
 fn load_block_state_update_succeeds() {
     let expected_state_update = StateUpdate {
@@ -22,6 +22,17 @@ impl From<DeprecatedContractClass> for starknet_api::deprecated_contract_class::
     }
 }
 
+impl From<starknet_api::deprecated_contract_class::ContractClass> for DeprecatedContractClass {
+    fn from(class: starknet_api::deprecated_contract_class::ContractClass) -> Self {
+        // A class whose ABI couldn't be parsed has no ABI, which is parsed back as None.
+        let abi = class.abi.map_or(serde_json::Value::Null, |entries| {
+            let entries = entries.into_iter().map(ContractClassAbiEntry::from).collect::<Vec<_>>();
+            serde_json::to_value(entries).expect("ABI entries should serialize to JSON.")
+        });
+        Self { abi, program: class.program, entry_points_by_type: class.entry_points_by_type }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum ContractClassAbiEntry {
@@ -50,6 +61,38 @@ impl ContractClassAbiEntry {
     }
 }
 
+impl From<starknet_api::deprecated_contract_class::ContractClassAbiEntry>
+    for ContractClassAbiEntry
+{
+    fn from(entry: starknet_api::deprecated_contract_class::ContractClassAbiEntry) -> Self {
+        match entry {
+            starknet_api::deprecated_contract_class::ContractClassAbiEntry::Event(entry) => {
+                ContractClassAbiEntry::Event(EventAbiEntry { r#type: "event".to_owned(), entry })
+            }
+            starknet_api::deprecated_contract_class::ContractClassAbiEntry::Function(entry) => {
+                let r#type = match entry.r#type {
+                    starknet_api::deprecated_contract_class::FunctionAbiEntryType::Constructor => {
+                        "constructor"
+                    }
+                    starknet_api::deprecated_contract_class::FunctionAbiEntryType::Regular => {
+                        "function"
+                    }
+                    starknet_api::deprecated_contract_class::FunctionAbiEntryType::L1Handler => {
+                        "l1_handler"
+                    }
+                };
+                ContractClassAbiEntry::Function(FunctionAbiEntry {
+                    r#type: r#type.to_owned(),
+                    entry: entry.entry,
+                })
+            }
+            starknet_api::deprecated_contract_class::ContractClassAbiEntry::Struct(entry) => {
+                ContractClassAbiEntry::Struct(StructAbiEntry { r#type: "struct".to_owned(), entry })
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
 pub struct EventAbiEntry {
     pub r#type: String,
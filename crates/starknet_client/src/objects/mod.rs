@@ -3,6 +3,7 @@ pub mod block;
 mod block_test;
 pub mod deprecated_contract_class;
 pub mod state;
+pub mod trace;
 pub mod transaction;
 #[cfg(test)]
 mod transaction_test;
@@ -29,6 +29,42 @@ pub struct StateDiff {
     pub replaced_classes: Vec<ReplacedClass>,
 }
 
+impl From<starknet_api::state::ThinStateDiff> for StateDiff {
+    fn from(diff: starknet_api::state::ThinStateDiff) -> Self {
+        StateDiff {
+            storage_diffs: diff
+                .storage_diffs
+                .into_iter()
+                .map(|(address, entries)| {
+                    let entries =
+                        entries.into_iter().map(|(key, value)| StorageEntry { key, value });
+                    (address, entries.collect())
+                })
+                .collect(),
+            deployed_contracts: diff
+                .deployed_contracts
+                .into_iter()
+                .map(|(address, class_hash)| DeployedContract { address, class_hash })
+                .collect(),
+            declared_classes: diff
+                .declared_classes
+                .into_iter()
+                .map(|(class_hash, compiled_class_hash)| DeclaredClassHashEntry {
+                    class_hash,
+                    compiled_class_hash,
+                })
+                .collect(),
+            old_declared_contracts: diff.deprecated_declared_classes,
+            nonces: diff.nonces,
+            replaced_classes: diff
+                .replaced_classes
+                .into_iter()
+                .map(|(address, class_hash)| ReplacedClass { address, class_hash })
+                .collect(),
+        }
+    }
+}
+
 impl StateDiff {
     // Returns the declared class hashes in the following order:
     // [declared classes, deprecated declared class, class hashes of deployed contracts].
@@ -72,6 +108,20 @@ pub struct ContractClass {
     pub abi: String,
 }
 
+// The version of the Sierra classes, which starknet_api doesn't keep.
+const CONTRACT_CLASS_VERSION: &str = "0.1.0";
+
+impl From<starknet_api::state::ContractClass> for ContractClass {
+    fn from(class: starknet_api::state::ContractClass) -> Self {
+        Self {
+            sierra_program: class.sierra_program,
+            entry_points_by_type: class.entry_point_by_type,
+            contract_class_version: CONTRACT_CLASS_VERSION.to_owned(),
+            abi: class.abi,
+        }
+    }
+}
+
 impl From<ContractClass> for starknet_api::state::ContractClass {
     fn from(class: ContractClass) -> Self {
         Self {
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector};
+use starknet_api::deprecated_contract_class::EntryPointType;
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{
+    Calldata, EthAddress, EventData, EventKey, L2ToL1Payload, TransactionHash, TransactionSignature,
+};
+
+use crate::objects::transaction::ExecutionResources;
+
+/// The execution traces of the transactions of a block as returned by the starknet gateway, in the
+/// order of the transactions.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct BlockTraces {
+    pub traces: Vec<TransactionTrace>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct TransactionTrace {
+    pub transaction_hash: TransactionHash,
+    #[serde(default)]
+    pub validate_invocation: Option<FunctionInvocation>,
+    #[serde(default)]
+    pub function_invocation: Option<FunctionInvocation>,
+    #[serde(default)]
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+    #[serde(default)]
+    pub signature: TransactionSignature,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct FunctionInvocation {
+    pub caller_address: ContractAddress,
+    pub contract_address: ContractAddress,
+    pub calldata: Calldata,
+    // The following fields are missing from the traces of old blocks.
+    #[serde(default)]
+    pub call_type: Option<CallType>,
+    #[serde(default)]
+    pub class_hash: Option<ClassHash>,
+    #[serde(default)]
+    pub selector: Option<EntryPointSelector>,
+    #[serde(default)]
+    pub entry_point_type: Option<EntryPointType>,
+    pub result: Vec<StarkFelt>,
+    #[serde(default)]
+    pub execution_resources: ExecutionResources,
+    #[serde(default)]
+    pub internal_calls: Vec<FunctionInvocation>,
+    #[serde(default)]
+    pub events: Vec<OrderedEvent>,
+    #[serde(default)]
+    pub messages: Vec<OrderedL2ToL1Message>,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, Eq, PartialEq)]
+pub enum CallType {
+    #[serde(rename = "CALL")]
+    Call,
+    #[serde(rename = "DELEGATE")]
+    Delegate,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct OrderedEvent {
+    pub order: usize,
+    pub keys: Vec<EventKey>,
+    pub data: EventData,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct OrderedL2ToL1Message {
+    pub order: usize,
+    pub to_address: EthAddress,
+    pub payload: L2ToL1Payload,
+}
@@ -60,6 +60,28 @@ impl TryFrom<Transaction> for starknet_api::transaction::Transaction {
     }
 }
 
+impl From<starknet_api::transaction::Transaction> for Transaction {
+    fn from(tx: starknet_api::transaction::Transaction) -> Self {
+        match tx {
+            starknet_api::transaction::Transaction::Declare(declare_tx) => {
+                Transaction::Declare(declare_tx.into())
+            }
+            starknet_api::transaction::Transaction::Deploy(deploy_tx) => {
+                Transaction::Deploy(deploy_tx.into())
+            }
+            starknet_api::transaction::Transaction::DeployAccount(deploy_acc_tx) => {
+                Transaction::DeployAccount(deploy_acc_tx.into())
+            }
+            starknet_api::transaction::Transaction::Invoke(invoke_tx) => {
+                Transaction::Invoke(invoke_tx.into())
+            }
+            starknet_api::transaction::Transaction::L1Handler(l1_handler_tx) => {
+                Transaction::L1Handler(l1_handler_tx.into())
+            }
+        }
+    }
+}
+
 impl Transaction {
     pub fn transaction_hash(&self) -> TransactionHash {
         match self {
@@ -107,6 +129,20 @@ impl From<L1HandlerTransaction> for starknet_api::transaction::L1HandlerTransact
     }
 }
 
+impl From<starknet_api::transaction::L1HandlerTransaction> for L1HandlerTransaction {
+    fn from(l1_handler_tx: starknet_api::transaction::L1HandlerTransaction) -> Self {
+        L1HandlerTransaction {
+            transaction_hash: l1_handler_tx.transaction_hash,
+            version: l1_handler_tx.version,
+            nonce: l1_handler_tx.nonce,
+            contract_address: l1_handler_tx.contract_address,
+            entry_point_selector: l1_handler_tx.entry_point_selector,
+            calldata: l1_handler_tx.calldata,
+            r#type: TransactionType::L1Handler,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct IntermediateDeclareTransaction {
     pub class_hash: ClassHash,
@@ -136,6 +172,38 @@ impl TryFrom<IntermediateDeclareTransaction> for starknet_api::transaction::Decl
     }
 }
 
+impl From<starknet_api::transaction::DeclareTransaction> for IntermediateDeclareTransaction {
+    fn from(declare_tx: starknet_api::transaction::DeclareTransaction) -> Self {
+        let (version, tx, compiled_class_hash) = match declare_tx {
+            starknet_api::transaction::DeclareTransaction::V0(tx) => (tx_v0(), tx, None),
+            starknet_api::transaction::DeclareTransaction::V1(tx) => (tx_v1(), tx, None),
+            starknet_api::transaction::DeclareTransaction::V2(tx) => {
+                let compiled_class_hash = Some(tx.compiled_class_hash);
+                let tx = starknet_api::transaction::DeclareTransactionV0V1 {
+                    transaction_hash: tx.transaction_hash,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce: tx.nonce,
+                    class_hash: tx.class_hash,
+                    sender_address: tx.sender_address,
+                };
+                (tx_v2(), tx, compiled_class_hash)
+            }
+        };
+        IntermediateDeclareTransaction {
+            class_hash: tx.class_hash,
+            compiled_class_hash,
+            sender_address: tx.sender_address,
+            nonce: tx.nonce,
+            max_fee: tx.max_fee,
+            version,
+            transaction_hash: tx.transaction_hash,
+            signature: tx.signature,
+            r#type: TransactionType::Declare,
+        }
+    }
+}
+
 impl From<IntermediateDeclareTransaction> for starknet_api::transaction::DeclareTransactionV0V1 {
     fn from(declare_tx: IntermediateDeclareTransaction) -> Self {
         Self {
@@ -195,6 +263,20 @@ impl From<DeployTransaction> for starknet_api::transaction::DeployTransaction {
     }
 }
 
+impl From<starknet_api::transaction::DeployTransaction> for DeployTransaction {
+    fn from(deploy_tx: starknet_api::transaction::DeployTransaction) -> Self {
+        DeployTransaction {
+            contract_address: deploy_tx.contract_address,
+            contract_address_salt: deploy_tx.contract_address_salt,
+            class_hash: deploy_tx.class_hash,
+            constructor_calldata: deploy_tx.constructor_calldata,
+            transaction_hash: deploy_tx.transaction_hash,
+            version: deploy_tx.version,
+            r#type: TransactionType::Deploy,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct DeployAccountTransaction {
     pub contract_address: ContractAddress,
@@ -226,6 +308,23 @@ impl From<DeployAccountTransaction> for starknet_api::transaction::DeployAccount
     }
 }
 
+impl From<starknet_api::transaction::DeployAccountTransaction> for DeployAccountTransaction {
+    fn from(deploy_tx: starknet_api::transaction::DeployAccountTransaction) -> Self {
+        DeployAccountTransaction {
+            contract_address: deploy_tx.contract_address,
+            contract_address_salt: deploy_tx.contract_address_salt,
+            class_hash: deploy_tx.class_hash,
+            constructor_calldata: deploy_tx.constructor_calldata,
+            nonce: deploy_tx.nonce,
+            max_fee: deploy_tx.max_fee,
+            signature: deploy_tx.signature,
+            transaction_hash: deploy_tx.transaction_hash,
+            version: deploy_tx.version,
+            r#type: TransactionType::DeployAccount,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct IntermediateInvokeTransaction {
     pub calldata: Calldata,
@@ -255,6 +354,36 @@ impl TryFrom<IntermediateInvokeTransaction> for starknet_api::transaction::Invok
     }
 }
 
+impl From<starknet_api::transaction::InvokeTransaction> for IntermediateInvokeTransaction {
+    fn from(invoke_tx: starknet_api::transaction::InvokeTransaction) -> Self {
+        match invoke_tx {
+            // Invoke transactions of version 0 have no nonce.
+            starknet_api::transaction::InvokeTransaction::V0(tx) => IntermediateInvokeTransaction {
+                calldata: tx.calldata,
+                sender_address: tx.sender_address,
+                entry_point_selector: Some(tx.entry_point_selector),
+                nonce: None,
+                max_fee: tx.max_fee,
+                signature: tx.signature,
+                transaction_hash: tx.transaction_hash,
+                version: tx_v0(),
+                r#type: TransactionType::InvokeFunction,
+            },
+            starknet_api::transaction::InvokeTransaction::V1(tx) => IntermediateInvokeTransaction {
+                calldata: tx.calldata,
+                sender_address: tx.sender_address,
+                entry_point_selector: None,
+                nonce: Some(tx.nonce),
+                max_fee: tx.max_fee,
+                signature: tx.signature,
+                transaction_hash: tx.transaction_hash,
+                version: tx_v1(),
+                r#type: TransactionType::InvokeFunction,
+            },
+        }
+    }
+}
+
 impl TryFrom<IntermediateInvokeTransaction> for starknet_api::transaction::InvokeTransactionV0 {
     type Error = ClientError;
 
@@ -303,9 +432,17 @@ pub struct TransactionReceipt {
     pub l1_to_l2_consumed_message: L1ToL2Message,
     pub l2_to_l1_messages: Vec<L2ToL1Message>,
     pub events: Vec<Event>,
-    #[serde(default)]
-    pub execution_resources: ExecutionResources,
+    // Missing from the receipts served by nodes that don't have the execution info of the
+    // transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_resources: Option<ExecutionResources>,
     pub actual_fee: Fee,
+    // Missing from the receipts of blocks that were created before transactions could revert, and
+    // from the receipts that have no execution resources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_status: Option<TransactionExecutionStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_error: Option<String>,
 }
 
 impl TransactionReceipt {
@@ -348,6 +485,15 @@ impl TransactionReceipt {
     }
 }
 
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub enum TransactionExecutionStatus {
+    #[serde(rename = "SUCCEEDED")]
+    #[default]
+    Succeeded,
+    #[serde(rename = "REVERTED")]
+    Reverted,
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct ExecutionResources {
     pub n_steps: u64,
@@ -400,6 +546,16 @@ pub struct L2ToL1Message {
     pub payload: L2ToL1Payload,
 }
 
+impl From<starknet_api::transaction::MessageToL1> for L2ToL1Message {
+    fn from(message: starknet_api::transaction::MessageToL1) -> Self {
+        L2ToL1Message {
+            from_address: message.from_address,
+            to_address: message.to_address,
+            payload: message.payload,
+        }
+    }
+}
+
 impl From<L2ToL1Message> for starknet_api::transaction::MessageToL1 {
     fn from(message: L2ToL1Message) -> Self {
         starknet_api::transaction::MessageToL1 {
@@ -3,7 +3,7 @@ use assert::assert_ok;
 use super::super::test_utils::read_resource::read_resource_file;
 use super::transaction::{
     DeployTransaction, IntermediateDeclareTransaction, IntermediateInvokeTransaction,
-    L1HandlerTransaction, Transaction, TransactionReceipt,
+    L1HandlerTransaction, Transaction, TransactionExecutionStatus, TransactionReceipt,
 };
 
 #[test]
@@ -43,13 +43,41 @@ fn load_transaction_succeeds() {
     }
 }
 
+#[test]
+fn transaction_into_starknet_api_and_back() {
+    for file_name in
+        ["deploy_transaction.json", "invoke_transaction.json", "declare_transaction.json"]
+    {
+        let tx: Transaction = serde_json::from_str(&read_resource_file(file_name)).unwrap();
+        let starknet_api_tx = starknet_api::transaction::Transaction::try_from(tx.clone()).unwrap();
+        assert_eq!(Transaction::from(starknet_api_tx), tx);
+    }
+}
+
 #[test]
 fn load_transaction_receipt_succeeds() {
     for file_name in [
         "transaction_receipt.json",
         "transaction_receipt_without_l1_to_l2.json",
         "transaction_receipt_without_l1_to_l2_nonce.json",
+        "transaction_receipt_reverted.json",
     ] {
         assert_ok!(serde_json::from_str::<TransactionReceipt>(&read_resource_file(file_name)));
     }
 }
+
+#[test]
+fn load_reverted_transaction_receipt() {
+    let receipt: TransactionReceipt =
+        serde_json::from_str(&read_resource_file("transaction_receipt_reverted.json")).unwrap();
+    assert_eq!(receipt.execution_status, Some(TransactionExecutionStatus::Reverted));
+    assert!(receipt.revert_error.unwrap().starts_with("Error in the called contract"));
+    assert_eq!(receipt.execution_resources.unwrap().n_steps, 410);
+
+    // Receipts of blocks from before transactions could revert have no execution status.
+    let receipt: TransactionReceipt =
+        serde_json::from_str(&read_resource_file("transaction_receipt.json")).unwrap();
+    assert_eq!(receipt.execution_status, None);
+    assert_eq!(receipt.revert_error, None);
+    assert!(receipt.execution_resources.is_some());
+}
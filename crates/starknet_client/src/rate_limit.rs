@@ -0,0 +1,115 @@
+#[cfg(test)]
+#[path = "rate_limit_test.rs"]
+mod rate_limit_test;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+// The longest time the requests can be held back by a single pause.
+const MAX_PAUSE: Duration = Duration::from_secs(60 * 60);
+
+/// A configuration for the client-side rate limiting of the requests to a starknet gateway.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// The number of requests per second allowed in the long run. Must be positive.
+    pub requests_per_second: u32,
+    /// The number of requests that can be sent at once after a quiet period. Must be positive.
+    pub burst: u32,
+}
+
+/// A bucket of tokens that refills at the rate of a [`RateLimitConfig`], up to its burst. Each
+/// request takes a token.
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    config: RateLimitConfig,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket.
+    pub fn new(config: RateLimitConfig, now: Instant) -> Self {
+        TokenBucket { config, tokens: config.burst.into(), last_refill: now }
+    }
+
+    /// Takes a token if the request is allowed at `now`, and returns how long to wait otherwise.
+    pub fn try_acquire(&mut self, now: Instant) -> Option<Duration> {
+        let rate = f64::from(self.config.requests_per_second);
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.config.burst.into());
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        // Without a rate, the bucket never refills.
+        Some(Duration::try_from_secs_f64((1.0 - self.tokens) / rate).unwrap_or(Duration::MAX))
+    }
+
+    /// The last time a token was asked for.
+    pub fn last_refill(&self) -> Instant {
+        self.last_refill
+    }
+}
+
+struct RateLimiterState {
+    bucket: Option<TokenBucket>,
+    paused_until: Option<Instant>,
+}
+
+/// A token bucket shared by all the requests of a client. Requests wait for a token, and all of
+/// them wait while the limiter is paused, e.g., after the gateway asked to retry later.
+pub(crate) struct RateLimiter {
+    state: Mutex<RateLimiterState>,
+    throttled_millis: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Creates a limiter that only limits the requests while paused if `config` is None.
+    pub(crate) fn new(config: Option<RateLimitConfig>) -> Self {
+        RateLimiter {
+            state: Mutex::new(RateLimiterState {
+                bucket: config.map(|config| TokenBucket::new(config, Instant::now())),
+                paused_until: None,
+            }),
+            throttled_millis: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits until a request is allowed to be sent.
+    pub(crate) async fn acquire(&self) {
+        while let Some(wait) = self.try_acquire(Instant::now()) {
+            debug!("Throttling a request for {wait:?}.");
+            self.throttled_millis.fetch_add(wait.as_millis() as u64, Ordering::Relaxed);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back all the requests for the given duration, capped at an hour.
+    pub(crate) fn pause(&self, duration: Duration) {
+        let until = Instant::now() + duration.min(MAX_PAUSE);
+        let mut state = self.state.lock().expect("Rate limiter lock should not be poisoned.");
+        state.paused_until = Some(state.paused_until.map_or(until, |paused| paused.max(until)));
+    }
+
+    /// The total time the requests waited for the limiter.
+    pub(crate) fn throttled_time(&self) -> Duration {
+        Duration::from_millis(self.throttled_millis.load(Ordering::Relaxed))
+    }
+
+    // Takes a token if the request is allowed at `now`, and returns how long to wait otherwise.
+    fn try_acquire(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().expect("Rate limiter lock should not be poisoned.");
+        if let Some(paused_until) = state.paused_until {
+            if now < paused_until {
+                return Some(paused_until - now);
+            }
+            state.paused_until = None;
+        }
+        state.bucket.as_mut()?.try_acquire(now)
+    }
+}
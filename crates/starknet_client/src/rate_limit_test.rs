@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::{RateLimitConfig, RateLimiter, TokenBucket};
+use crate::retry::RetryConfig;
+use crate::test_utils::read_resource::read_resource_file;
+use crate::test_utils::retry::get_test_config;
+use crate::{StarknetClient, StarknetClientTrait};
+
+const NODE_VERSION: &str = "NODE VERSION";
+
+#[test]
+fn token_bucket() {
+    let limiter = RateLimiter::new(Some(RateLimitConfig { requests_per_second: 10, burst: 2 }));
+    let now = Instant::now();
+    assert_eq!(limiter.try_acquire(now), None);
+    assert_eq!(limiter.try_acquire(now), None);
+    let wait = limiter.try_acquire(now).unwrap();
+    assert!(wait <= Duration::from_millis(100));
+    assert_eq!(limiter.try_acquire(now + Duration::from_millis(100)), None);
+}
+
+#[test]
+fn token_bucket_without_rate() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(RateLimitConfig { requests_per_second: 0, burst: 1 }, now);
+    assert_eq!(bucket.try_acquire(now), None);
+    assert_eq!(bucket.try_acquire(now + Duration::from_secs(60)), Some(Duration::MAX));
+}
+
+#[test]
+fn pause() {
+    let limiter = RateLimiter::new(None);
+    assert_eq!(limiter.try_acquire(Instant::now()), None);
+    limiter.pause(Duration::from_secs(10));
+    assert!(limiter.try_acquire(Instant::now()).is_some());
+    assert_eq!(limiter.try_acquire(Instant::now() + Duration::from_secs(10)), None);
+}
+
+#[test]
+fn pause_is_capped() {
+    let limiter = RateLimiter::new(None);
+    limiter.pause(Duration::MAX);
+    let wait = limiter.try_acquire(Instant::now()).unwrap();
+    assert!(wait <= super::MAX_PAUSE);
+}
+
+// Serves blocks, rejecting requests that arrive less than `min_interval` after the previous one.
+// If `retry_after` is set, the first request is throttled with the given Retry-After value.
+// Returns the URL of the gateway and the arrival times of the requests.
+async fn run_limited_gateway(
+    min_interval: Duration,
+    retry_after: Option<u64>,
+) -> (String, Arc<Mutex<Vec<Instant>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let block = read_resource_file("block.json");
+    let arrivals = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            read_request(&mut socket).await;
+            let response = {
+                let mut arrivals = arrivals.lock().unwrap();
+                let now = Instant::now();
+                let response = match (arrivals.last(), retry_after) {
+                    (None, Some(retry_after)) => format!(
+                        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: {retry_after}\r\n\
+                         Content-Length: 0\r\nConnection: close\r\n\r\n"
+                    ),
+                    (Some(last), _) if now.duration_since(*last) < min_interval => String::from(
+                        "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\
+                         Connection: close\r\n\r\n",
+                    ),
+                    _ => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{block}",
+                        block.len()
+                    ),
+                };
+                arrivals.push(now);
+                response
+            };
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+        }
+    });
+    (url, requests)
+}
+
+async fn read_request(socket: &mut TcpStream) {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = socket.read(&mut buf).await.unwrap();
+        if n == 0 {
+            return;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[tokio::test]
+async fn requests_are_rate_limited() {
+    let (url, requests) = run_limited_gateway(Duration::from_millis(50), None).await;
+    let starknet_client = StarknetClient::new(&url, None, NODE_VERSION, get_test_config())
+        .unwrap()
+        .with_rate_limit(RateLimitConfig { requests_per_second: 10, burst: 1 });
+
+    let start = Instant::now();
+    let (first, second, third, fourth) = tokio::join!(
+        starknet_client.block_number(),
+        starknet_client.block_number(),
+        starknet_client.block_number(),
+        starknet_client.block_number(),
+    );
+    for res in [first, second, third, fourth] {
+        assert!(res.unwrap().is_some());
+    }
+    assert_eq!(requests.lock().unwrap().len(), 4);
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(starknet_client.throttled_time() > Duration::ZERO);
+}
+
+#[tokio::test]
+async fn retry_after_is_honoured() {
+    let (url, requests) = run_limited_gateway(Duration::ZERO, Some(1)).await;
+    let retry_config = RetryConfig { retry_max_delay_millis: 2000, ..get_test_config() };
+    let starknet_client = StarknetClient::new(&url, None, NODE_VERSION, retry_config).unwrap();
+
+    let start = Instant::now();
+    assert!(starknet_client.block_number().await.unwrap().is_some());
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn retry_after_is_capped_at_max_delay() {
+    let (url, requests) = run_limited_gateway(Duration::ZERO, Some(u64::MAX)).await;
+    let starknet_client = StarknetClient::new(&url, None, NODE_VERSION, get_test_config()).unwrap();
+
+    let start = Instant::now();
+    assert!(starknet_client.block_number().await.unwrap().is_some());
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
@@ -73,4 +73,35 @@ impl Retry {
             Box::new(|err| Self::log_condition(err, &mut condition));
         RetryIf::spawn(self.strategy.clone(), action, condition).await
     }
+
+    /// Like [`Retry::start_with_condition`], but waits at least the delay returned by `min_delay`
+    /// for the error before retrying, e.g., the delay the server asked for.
+    pub async fn start_with_min_delay<I, E, A, C, D>(
+        &self,
+        mut action: A,
+        mut condition: C,
+        min_delay: D,
+    ) -> Result<I, E>
+    where
+        E: Debug,
+        A: Action<Item = I, Error = E>,
+        C: Condition<E> + Send,
+        D: Fn(&E) -> Option<Duration>,
+    {
+        let mut strategy = self.strategy.clone();
+        loop {
+            let err = match action.run().await {
+                Ok(item) => return Ok(item),
+                Err(err) => err,
+            };
+            if !Self::log_condition(&err, &mut condition) {
+                return Err(err);
+            }
+            let Some(delay) = strategy.next() else {
+                return Err(err);
+            };
+            tokio::time::sleep(min_delay(&err).map_or(delay, |min_delay| min_delay.max(delay)))
+                .await;
+        }
+    }
 }
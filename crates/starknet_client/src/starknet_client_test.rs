@@ -24,7 +24,7 @@ use super::{
     Block, ClientError, RetryErrorCode, StarknetClient, StarknetClientTrait, BLOCK_NUMBER_QUERY,
     CLASS_HASH_QUERY, GET_BLOCK_URL, GET_STATE_UPDATE_URL,
 };
-use crate::{ContractClass, GenericContractClass};
+use crate::{BlockTraces, CallType, ContractClass, GenericContractClass};
 
 const NODE_VERSION: &str = "NODE VERSION";
 
@@ -103,6 +103,39 @@ async fn state_update() {
     assert_eq!(state_update.unwrap(), expected_state_update);
 }
 
+#[tokio::test]
+async fn block_traces() {
+    let starknet_client =
+        StarknetClient::new(&mockito::server_url(), None, NODE_VERSION, get_test_config()).unwrap();
+    let raw_block_traces = read_resource_file("block_traces.json");
+    let mock_traces =
+        mock("GET", &format!("/feeder_gateway/get_block_traces?{BLOCK_NUMBER_QUERY}=20")[..])
+            .with_status(200)
+            .with_body(&raw_block_traces)
+            .create();
+    let block_traces = starknet_client.block_traces(BlockNumber(20)).await.unwrap().unwrap();
+    mock_traces.assert();
+    let expected_block_traces: BlockTraces = serde_json::from_str(&raw_block_traces).unwrap();
+    assert_eq!(block_traces, expected_block_traces);
+    let internal_call =
+        &block_traces.traces[0].function_invocation.as_ref().unwrap().internal_calls[0];
+    assert_eq!(internal_call.call_type, Some(CallType::Delegate));
+    // Old traces lack some of the fields.
+    assert_eq!(block_traces.traces[1].function_invocation.as_ref().unwrap().class_hash, None);
+
+    // Non-existing block.
+    let body =
+        r#"{"code": "StarknetErrorCode.BLOCK_NOT_FOUND", "message": "Block 21 was not found."}"#;
+    let mock_no_block =
+        mock("GET", &format!("/feeder_gateway/get_block_traces?{BLOCK_NUMBER_QUERY}=21")[..])
+            .with_status(500)
+            .with_body(body)
+            .create();
+    let block_traces = starknet_client.block_traces(BlockNumber(21)).await.unwrap();
+    mock_no_block.assert();
+    assert!(block_traces.is_none());
+}
+
 #[tokio::test]
 async fn serialization_precision() {
     let input =
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs::read_to_string;
 use std::hash::Hash;
@@ -39,16 +39,26 @@ use starknet_api::transaction::{
     Transaction, TransactionHash, TransactionOffsetInBlock, TransactionOutput,
     TransactionSignature, TransactionVersion,
 };
-use web3::types::H160;
+use web3::types::{H160, H256};
 
 //////////////////////////////////////////////////////////////////////////
 // GENERIC TEST UTIL FUNCTIONS
 //////////////////////////////////////////////////////////////////////////
 
 pub async fn send_request(address: SocketAddr, method: &str, params: &str) -> serde_json::Value {
+    send_request_to_path(address, "", method, params).await
+}
+
+/// Sends a JSON-RPC request to the given path of the server, e.g. to a specific version of the API.
+pub async fn send_request_to_path(
+    address: SocketAddr,
+    path: &str,
+    method: &str,
+    params: &str,
+) -> serde_json::Value {
     let client = Client::new();
     let res_str = client
-        .post(format!("http://{address:?}"))
+        .post(format!("http://{address:?}{path}"))
         .header("Content-Type", "application/json")
         .body(format!(r#"{{"jsonrpc":"2.0","id":"1","method":"{method}","params":[{params}]}}"#))
         .send()
@@ -624,6 +634,15 @@ impl<K: GetTestInstance + Eq + Hash, V: GetTestInstance> GetTestInstance for Has
         res
     }
 }
+impl<K: GetTestInstance + Ord, V: GetTestInstance> GetTestInstance for BTreeMap<K, V> {
+    fn get_test_instance(rng: &mut ChaCha8Rng) -> Self {
+        let mut res = BTreeMap::new();
+        let k = K::get_test_instance(rng);
+        let v = V::get_test_instance(rng);
+        res.insert(k, v);
+        res
+    }
+}
 impl<K: GetTestInstance + Eq + Hash, V: GetTestInstance> GetTestInstance for IndexMap<K, V> {
     fn get_test_instance(rng: &mut ChaCha8Rng) -> Self {
         let mut res = IndexMap::with_capacity(1);
@@ -649,6 +668,7 @@ macro_rules! get_number_of_variants {
 // by the macro [`impl_get_test_instance`].
 ////////////////////////////////////////////////////////////////////////
 default_impl_get_test_instance!(H160);
+default_impl_get_test_instance!(H256);
 default_impl_get_test_instance!(ContractAddress);
 default_impl_get_test_instance!(StarkHash);
 default_impl_get_test_instance!(StorageKey);